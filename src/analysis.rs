@@ -0,0 +1,236 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use serde::Serialize;
+
+use crate::used_item::CrateMap;
+
+/// The sets of items which many files import together, each a candidate for a shared
+/// prelude, see `CrateMap::common_imports`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct CommonImports {
+    preludes: Vec<Prelude>,
+}
+
+/// Items imported together, with the files importing all of them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Prelude {
+    /// The paths of the items, the most imported one first.
+    items: Vec<String>,
+    files: Vec<String>,
+}
+
+impl CommonImports {
+    /// Finds the sets of at least two items which `min_files` files or more all import.
+    ///
+    /// Sets are grown greedily: starting from the most imported item which no set contains
+    /// yet, the item imported by the most files importing the whole set is added to it, as
+    /// long as `min_files` of them remain. The sets covering the most files come first.
+    pub(crate) fn new(crate_map: &CrateMap, min_files: usize) -> Self {
+        let min_files = min_files.max(1);
+        let mut all_importers: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for (file, _, item) in crate_map.iter() {
+            all_importers.entry(item.path()).or_default().insert(file);
+        }
+        let importers: BTreeMap<&str, BTreeSet<&str>> = all_importers
+            .into_iter()
+            .filter(|(_, files)| files.len() >= min_files)
+            .collect();
+
+        let mut seeds: Vec<(&str, usize)> = importers
+            .iter()
+            .map(|(path, files)| (*path, files.len()))
+            .collect();
+        seeds.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+        let mut taken: BTreeSet<&str> = BTreeSet::new();
+        let mut preludes = vec![];
+        for (seed, _) in seeds {
+            if taken.contains(seed) {
+                continue;
+            }
+            let mut items = vec![seed];
+            let mut files = importers[seed].clone();
+            loop {
+                let best = importers
+                    .iter()
+                    .filter(|(path, _)| !items.contains(path))
+                    .map(|(path, importers)| (*path, importers.intersection(&files).count()))
+                    .filter(|(_, count)| *count >= min_files)
+                    .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)));
+                let path = match best {
+                    Some((path, _)) => path,
+                    None => break,
+                };
+                files = files.intersection(&importers[path]).copied().collect();
+                items.push(path);
+            }
+            if items.len() < 2 {
+                continue;
+            }
+            taken.extend(items.iter().copied());
+            preludes.push(Prelude {
+                items: items.into_iter().map(str::to_owned).collect(),
+                files: files.into_iter().map(str::to_owned).collect(),
+            });
+        }
+        preludes.sort_by(|a, b| {
+            b.files
+                .len()
+                .cmp(&a.files.len())
+                .then_with(|| b.items.len().cmp(&a.items.len()))
+        });
+        CommonImports { preludes }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.preludes.is_empty()
+    }
+
+    pub fn preludes(&self) -> &[Prelude] {
+        &self.preludes
+    }
+}
+
+impl Prelude {
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    /// The files importing every item, which the prelude would cover.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+}
+
+impl fmt::Display for CommonImports {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, prelude) in self.preludes.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(
+                f,
+                "{} items imported together by {} files:",
+                prelude.items.len(),
+                prelude.files.len()
+            )?;
+            for item in &prelude.items {
+                writeln!(f, "  {}", item)?;
+            }
+            writeln!(f, "covering:")?;
+            for file in &prelude.files {
+                writeln!(f, "  {}", file)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Map, Value};
+
+    use super::*;
+
+    /// The map of the files importing the structs of the given paths.
+    fn map(files: &[(&str, &[&str])]) -> CrateMap {
+        let mut map = Map::new();
+        for (file, paths) in files {
+            let structs: Vec<Value> = paths
+                .iter()
+                .map(|path| {
+                    json!({
+                        "name": path.rsplit("::").next().unwrap(),
+                        "path": path,
+                        "kind": "struct",
+                        "count": 1
+                    })
+                })
+                .collect();
+            map.insert(file.to_string(), json!({ "structs": structs }));
+        }
+        CrateMap::from_json(Value::Object(map))
+    }
+
+    fn workspace() -> CrateMap {
+        let sync: &[&str] = &["std::sync::Arc", "std::sync::Mutex"];
+        let serde: &[&str] = &["serde::Deserialize", "serde::Serialize"];
+        map(&[
+            (
+                "app/src/a.rs",
+                &["std::rc::Rc", "std::sync::Arc", "std::sync::Mutex"],
+            ),
+            ("app/src/b.rs", sync),
+            ("app/src/c.rs", sync),
+            ("app/src/d.rs", serde),
+            (
+                "app/src/e.rs",
+                &["serde::Deserialize", "serde::Serialize", "std::sync::Arc"],
+            ),
+        ])
+    }
+
+    fn preludes(imports: &CommonImports) -> Vec<(Vec<&str>, Vec<&str>)> {
+        imports
+            .preludes()
+            .iter()
+            .map(|prelude| {
+                (
+                    prelude.items().iter().map(String::as_str).collect(),
+                    prelude.files().iter().map(String::as_str).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn items_imported_together_are_a_prelude() {
+        assert_eq!(
+            preludes(&workspace().common_imports(2)),
+            vec![
+                (
+                    vec!["std::sync::Arc", "std::sync::Mutex"],
+                    vec!["app/src/a.rs", "app/src/b.rs", "app/src/c.rs"]
+                ),
+                (
+                    vec!["serde::Deserialize", "serde::Serialize"],
+                    vec!["app/src/d.rs", "app/src/e.rs"]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn preludes_are_imported_by_min_files() {
+        assert_eq!(
+            preludes(&workspace().common_imports(3)),
+            vec![(
+                vec!["std::sync::Arc", "std::sync::Mutex"],
+                vec!["app/src/a.rs", "app/src/b.rs", "app/src/c.rs"]
+            )]
+        );
+        assert!(workspace().common_imports(4).is_empty());
+    }
+
+    #[test]
+    fn a_single_item_is_no_prelude() {
+        let map = map(&[
+            ("app/src/a.rs", &["std::sync::Arc", "std::rc::Rc"]),
+            ("app/src/b.rs", &["std::sync::Arc"]),
+        ]);
+        assert!(map.common_imports(2).is_empty());
+    }
+
+    #[test]
+    fn preludes_are_listed_with_their_files() {
+        assert_eq!(
+            workspace().common_imports(3).to_string(),
+            "2 items imported together by 3 files:\n  std::sync::Arc\n  std::sync::Mutex\n\
+             covering:\n  app/src/a.rs\n  app/src/b.rs\n  app/src/c.rs\n"
+        );
+    }
+}
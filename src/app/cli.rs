@@ -0,0 +1,705 @@
+use std::{env, ffi::OsString, io, path::PathBuf, process, str::FromStr};
+
+use structopt::StructOpt;
+
+use crate::{
+    output::OutputFormat, GeneratedMode, Keys, OriginKind, ResolveReexports, TargetKind,
+    UsedItemKind,
+};
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "usedump",
+    about = "Dumps the items imported by each file of a cargo workspace",
+    after_help = EXIT_CODES
+)]
+pub struct Opts {
+    /// Output format
+    #[structopt(long, global = true, default_value = "json", possible_values = OutputFormat::NAMES)]
+    pub format: OutputFormat,
+    /// Pretty-prints JSON output
+    #[structopt(long, global = true)]
+    pub pretty: bool,
+    /// Writes the output to FILE instead of stdout
+    #[structopt(long, short, global = true, name = "FILE", parse(from_os_str))]
+    pub output: Option<PathBuf>,
+    /// Suppresses everything but the output itself
+    #[structopt(long, short, global = true)]
+    pub quiet: bool,
+    /// Analyzes the package or workspace of the manifest PATH instead of the current
+    /// directory, when no project is given
+    #[structopt(long, global = true, name = "MANIFEST_PATH", parse(from_os_str))]
+    pub manifest_path: Option<PathBuf>,
+    /// Reads the defaults of the options from PATH instead of the `usedump.toml` of the
+    /// project or of one of its parent directories
+    #[structopt(long, global = true, name = "CONFIG_PATH", parse(from_os_str))]
+    pub config: Option<PathBuf>,
+    /// Ignores the value the configuration and the environment give KEY, e.g. `--unset
+    /// locked` for `locked = true`, or `--unset jobs`
+    #[structopt(long, global = true, name = "KEY", number_of_values = 1)]
+    pub unset: Vec<String>,
+    /// The project analyzed when none is given, the current directory unless
+    /// `cargo-usedump` locates the package.
+    #[structopt(skip)]
+    pub default_dir: Option<PathBuf>,
+    /// Whether `--format` was given, or set by the environment, which the configuration file
+    /// cannot then override.
+    #[structopt(skip)]
+    pub format_given: bool,
+    /// The keys a configuration set, which the configurations applied after it leave alone.
+    #[structopt(skip)]
+    pub configured: Vec<&'static str>,
+    #[structopt(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// The exit code of the findings of checks, e.g. unused imports, or of differing dumps.
+pub const FINDINGS: i32 = 1;
+/// The exit code of invalid command lines and of options which cannot be combined.
+pub const USAGE_ERROR: i32 = 2;
+/// The exit code of workspaces which fail to load, and of files which fail to analyze
+/// with `--strict`.
+pub const ANALYSIS_ERROR: i32 = 3;
+
+const EXIT_CODES: &str = "EXIT CODES:
+    0      Success
+    1      Findings or differences: unused imports with `unused`, misplaced dependencies \
+with `deps-audit`, differing dumps with `diff`, or unresolved imports with \
+`dump --check-unresolved`
+    2      Usage error
+    3      Analysis error, including files which fail to analyze with `dump --strict`, or \
+have syntax errors with `dump --deny-syntax-errors`
+    130    Interrupted, after writing the files analyzed so far";
+
+const SQLITE_EXAMPLES: &str = "EXAMPLE QUERIES:
+    With `--format sqlite --output usedump.db`, the files importing both tokio and async-std:
+
+        SELECT f.path FROM files f
+        JOIN imports i ON i.file_id = f.id JOIN items t ON t.id = i.item_id
+        WHERE t.origin IN ('tokio', 'async-std')
+        GROUP BY f.id HAVING COUNT(DISTINCT t.origin) = 2;
+
+    The ten items imported by the most files:
+
+        SELECT t.path, COUNT(DISTINCT i.file_id) AS files FROM imports i
+        JOIN items t ON t.id = i.item_id
+        GROUP BY t.path ORDER BY files DESC LIMIT 10;";
+
+#[derive(Debug, StructOpt)]
+pub enum Command {
+    /// Dumps the items imported by each file (the default), supports `--format dot`, `csv`,
+    /// `markdown`, `html` and `sqlite`
+    #[structopt(after_help = SQLITE_EXAMPLES)]
+    Dump(DumpArgs),
+    /// Lists the imports each file never refers to, supports `--format sarif`
+    Unused(UnusedArgs),
+    /// Compares two dumps of a single project, supports `--format text`
+    Diff(DiffArgs),
+    /// Lists the dependencies of each member which none of its files uses
+    #[structopt(name = "unused-deps")]
+    UnusedDeps(UnusedDepsArgs),
+    /// Checks that dev-dependencies are only imported by tests and that normal
+    /// dependencies are not, supports `--format text` and `--format sarif`
+    #[structopt(name = "deps-audit")]
+    DepsAudit(DepsAuditArgs),
+    /// Lists the files importing an item, given by name or full path
+    #[structopt(name = "who-uses")]
+    WhoUses(WhoUsesArgs),
+    /// Dumps the project again whenever one of its files changes, until interrupted
+    Watch(WatchArgs),
+    /// Loads the project once and answers JSON-RPC requests, one per line, on stdin or a
+    /// unix socket: `dump_file {path}`, `who_uses {item, regex}` and `reload`
+    Serve(ServeArgs),
+    /// Dumps the `pub` items and re-exports of each file instead of its imports
+    Exports(ExportsArgs),
+    /// Lists the exports of each member which no other member uses, supports
+    /// `--format text` and `--format sarif`
+    #[structopt(name = "dead-exports")]
+    DeadExports(DeadExportsArgs),
+    /// Lists how many items each member imports from the other crates and flags cycles
+    /// between members, supports `--format text` and `--format dot`
+    Graph(GraphArgs),
+    /// Lists how many files import each crate and how many of its items they use, most
+    /// used first, supports `--format text`
+    Crates(CratesArgs),
+    /// Lists the sets of items which many files import together, as candidates for a shared
+    /// prelude, supports `--format text`
+    #[structopt(name = "common-imports")]
+    CommonImports(CommonImportsArgs),
+    /// Counts how many of the public items of a dependency the workspace imports with `use`,
+    /// by module, supports `--format text`
+    Coverage(CoverageArgs),
+    /// Lists the glob imports with the names each file refers to through them, as explicit
+    /// replacements, supports `--format text`
+    Globs(GlobsArgs),
+    /// Suggests how to merge the use items sharing a path prefix, as patches of the files,
+    /// supports `--format text`
+    Consolidate(ConsolidateArgs),
+    /// Lists the items imported more than once, the names bound to several items in the
+    /// same scope and the unstable items of the standard library, supports `--format text`
+    Lints(LintsArgs),
+    /// Lists the imports of items marked `#[deprecated]`, with their locations and the
+    /// `since` and `note` of the attribute, supports `--format text`
+    Deprecated(DeprecatedArgs),
+    /// Lists the imports of a single Rust file outside of any Cargo project, resolving only
+    /// the items the file defines
+    File(FileArgs),
+    /// Dumps a crate of crates.io or a git repository on its own, fetched into a temporary
+    /// directory
+    Remote(RemoteArgs),
+    /// Combines dumps of a single project, e.g. of shards of a workspace, into one
+    Merge(MergeArgs),
+    /// Prints the JSON Schema of the output of `dump`, in JSON or YAML
+    Schema,
+}
+
+#[derive(Debug, Default, StructOpt)]
+pub struct DumpArgs {
+    /// Number of files to resolve in parallel, defaults to the number of CPUs
+    #[structopt(long, short)]
+    pub jobs: Option<usize>,
+    /// Gives up on the files which take longer than SECS seconds to resolve, reporting them
+    /// as failed
+    #[structopt(long, name = "SECS")]
+    pub file_timeout: Option<u64>,
+    /// Resolves every file again instead of reusing the results of the previous run
+    #[structopt(long)]
+    pub no_cache: bool,
+    /// Keeps the cache in DIR instead of `.usedump-cache` under the target directory
+    #[structopt(long, name = "DIR", parse(from_os_str), conflicts_with = "no-cache")]
+    pub cache_dir: Option<PathBuf>,
+    /// Loads the standard library from the sysroot SYSROOT, or from SYSROOT itself when it
+    /// holds its sources, instead of the sysroot of the toolchain of the workspace
+    #[structopt(long, name = "SYSROOT", parse(from_os_str))]
+    pub sysroot: Option<PathBuf>,
+    /// Loads no standard library, leaving the items of `std`, `core` and `alloc` unresolved
+    #[structopt(long, conflicts_with = "SYSROOT")]
+    pub no_sysroot: bool,
+    /// Analyzes the crates with the cfgs of the target TRIPLE instead of those of the host,
+    /// can be repeated to list the imports of every TRIPLE, annotated with the ones they are
+    /// for when not there for all
+    #[structopt(long, name = "TRIPLE", number_of_values = 1)]
+    pub target: Vec<String>,
+    /// Loads the workspace without accessing the network, failing when a dependency is not
+    /// available locally
+    #[structopt(long)]
+    pub offline: bool,
+    /// Loads the workspace without changing its Cargo.lock, failing when it is out of date
+    /// or missing
+    #[structopt(long)]
+    pub locked: bool,
+    /// Lists the use paths as written, parsing each file instead of loading the workspace,
+    /// much faster but leaving every item unresolved with a kind guessed from its casing
+    #[structopt(long)]
+    pub no_resolve: bool,
+    /// Reports the progress on stderr, the default when it is a terminal and the output
+    /// is not
+    #[structopt(long)]
+    pub progress: bool,
+    /// Logs every file on stderr as it is analyzed
+    #[structopt(long, short)]
+    pub verbose: bool,
+    /// Exits with code 3 if any file could not be analyzed
+    #[structopt(long)]
+    pub strict: bool,
+    /// Exits with code 1 if any use tree could not be resolved
+    #[structopt(long, visible_alias = "check-unresolved")]
+    pub fail_on_unresolved: bool,
+    /// Exits with code 3 if any file has syntax errors, whose imports are dumped regardless
+    #[structopt(long)]
+    pub deny_syntax_errors: bool,
+    /// Exits with code 1 if any item imported is deprecated
+    #[structopt(long)]
+    pub deny_deprecated: bool,
+    /// Only analyzes the workspace member SPEC, can be repeated
+    #[structopt(long, short, name = "SPEC", number_of_values = 1)]
+    pub package: Vec<String>,
+    /// Analyzes every workspace member (the default)
+    #[structopt(long, conflicts_with = "SPEC")]
+    pub workspace: bool,
+    /// Also analyzes the integration tests under `tests/`
+    #[structopt(long)]
+    pub tests: bool,
+    /// Also analyzes the benchmarks under `benches/`
+    #[structopt(long)]
+    pub benches: bool,
+    /// Also analyzes the examples under `examples/`
+    #[structopt(long)]
+    pub examples: bool,
+    /// Analyzes every target, like `--tests --benches --examples`
+    #[structopt(long)]
+    pub all_targets: bool,
+    /// Also analyzes the build scripts, keyed like `my-crate/build.rs`
+    #[structopt(long)]
+    pub build_scripts: bool,
+    /// Skips the files members `include!` from outside their directories, e.g. from
+    /// `OUT_DIR`, dumps them like the others, or apart under `generated`
+    #[structopt(long, default_value = "include", possible_values = GeneratedMode::NAMES)]
+    pub generated: GeneratedMode,
+    /// Comma separated list of features to activate
+    #[structopt(long, name = "FEATURES", use_delimiter = true)]
+    pub features: Vec<String>,
+    /// Activates all available features
+    #[structopt(long)]
+    pub all_features: bool,
+    /// Does not activate the `default` feature
+    #[structopt(long)]
+    pub no_default_features: bool,
+    /// Only analyzes files matching GLOB, a gitignore-style pattern such as `src/**`
+    #[structopt(long, name = "GLOB", number_of_values = 1)]
+    pub include: Vec<String>,
+    /// Skips files matching GLOB, even if they are included, e.g. `**/tests/**`
+    #[structopt(long, name = "EXCLUDE_GLOB", number_of_values = 1)]
+    pub exclude: Vec<String>,
+    /// Keeps only items imported under a `#[cfg(PREDICATE)]`, or only items which are not
+    /// when prefixed with `!`, e.g. `--cfg-filter '!test'`
+    #[structopt(long, name = "PREDICATE")]
+    pub cfg_filter: Option<String>,
+    /// Keeps only the items defined in the standard library, dependencies or members
+    #[structopt(long, name = "ORIGIN", possible_values = OriginKind::NAMES)]
+    pub only: Option<OriginKind>,
+    /// Drops the items defined in the standard library
+    #[structopt(long)]
+    pub exclude_std: bool,
+    /// Keeps only the given kinds of items, comma-separated, e.g. `--kind traits,structs`
+    #[structopt(long, name = "KINDS", use_delimiter = true)]
+    pub kind: Vec<UsedItemKind>,
+    /// Keeps the files which import nothing, or have nothing left to list after filtering
+    #[structopt(long)]
+    pub include_empty: bool,
+    /// Also lists the derives named in `#[derive(...)]` attributes, under `derives`
+    #[structopt(long)]
+    pub include_derives: bool,
+    /// Keeps only the items re-exported by `pub use`, not by restricted ones such as
+    /// `pub(crate) use`
+    #[structopt(long, conflicts_with = "no-reexports")]
+    pub only_reexports: bool,
+    /// Drops the items re-exported by `pub use`, keeping those of restricted ones
+    #[structopt(long)]
+    pub no_reexports: bool,
+    /// Drops the test code: the items imported under `#[cfg(test)]`, whether on the use
+    /// item, on an enclosing `mod tests` or on the `mod` declaration of the file, and the
+    /// integration tests
+    #[structopt(long, conflicts_with = "only-tests")]
+    pub exclude_tests: bool,
+    /// Keeps only the test code `--exclude-tests` drops, analyzing the integration tests too
+    #[structopt(long)]
+    pub only_tests: bool,
+    /// Keys the output by file, by workspace member, or by the crate the items come from
+    #[structopt(long, default_value = "file", possible_values = GroupBy::NAMES)]
+    pub group_by: GroupBy,
+    /// Records the line, column and byte range of each import
+    #[structopt(long)]
+    pub locations: bool,
+    /// Records the crate, version and file each imported item is defined in
+    #[structopt(long)]
+    pub definitions: bool,
+    /// Emits aggregate statistics instead of the used items of each file
+    #[structopt(long)]
+    pub summary: bool,
+    /// Prints timings and counters as a table on stderr, or embeds them as `stats` in the
+    /// output with `--stats=json`
+    #[structopt(long, name = "STATS_FORMAT", require_equals = true, possible_values = StatsFormat::NAMES)]
+    pub stats: Option<Option<StatsFormat>>,
+    /// Number of most imported items listed by `--summary`
+    #[structopt(long, name = "N", default_value = "10")]
+    pub top: usize,
+    /// Keys files by their path relative to their package, without the package name
+    #[structopt(long)]
+    pub flat_paths: bool,
+    /// Keys files by their absolute path, e.g. to open them without knowing the root
+    #[structopt(long)]
+    pub absolute_paths: bool,
+    /// Keys the files by their path, or by their module path such as `foo::parser::expr`
+    #[structopt(long, default_value = "file", possible_values = Keys::NAMES)]
+    pub keys: Keys,
+    /// Lists re-exported items under the path they are imported as, under the path of their
+    /// definition, or under both, adding `surface_path`
+    #[structopt(long, default_value = "definition", possible_values = ResolveReexports::NAMES)]
+    pub resolve_reexports: ResolveReexports,
+    /// With `--format dot`, draws the files of each member in a cluster
+    #[structopt(long)]
+    pub cluster_by_package: bool,
+    /// With `--format dot`, also draws the crates outside the workspace files import from
+    #[structopt(long)]
+    pub include_external: bool,
+    /// With `--format csv`, separates the fields with CHAR instead of a comma, e.g. `tab`
+    #[structopt(long, name = "CHAR", parse(try_from_str = parse_delimiter))]
+    pub csv_delimiter: Option<u8>,
+    /// With `--format markdown`, folds the long lists of items into `<details>` blocks
+    #[structopt(long)]
+    pub collapsible: bool,
+    /// Emits the bare map of files, as format version 1 did, instead of wrapping it with the
+    /// usedump and format versions
+    #[structopt(long)]
+    pub no_envelope: bool,
+    /// Emits used items as plain names, as older versions did
+    #[structopt(long)]
+    pub legacy_names: bool,
+    /// Projects to analyze, defaults to the current directory
+    #[structopt(parse(from_os_str))]
+    pub paths: Vec<PathBuf>,
+    /// The options with a default value which were given on the command line or set by the
+    /// environment, by name.
+    #[structopt(skip)]
+    pub given: Vec<&'static str>,
+}
+
+impl Command {
+    /// The options of `dump`, which the other commands dumping a project before looking at
+    /// it take as well.
+    pub fn dump_args(&mut self) -> Option<&mut DumpArgs> {
+        match self {
+            Command::Dump(args) => Some(args),
+            Command::Watch(WatchArgs { dump })
+            | Command::Serve(ServeArgs { dump, .. })
+            | Command::Exports(ExportsArgs { dump })
+            | Command::WhoUses(WhoUsesArgs { dump, .. })
+            | Command::Graph(GraphArgs { dump, .. })
+            | Command::Crates(CratesArgs { dump })
+            | Command::CommonImports(CommonImportsArgs { dump, .. })
+            | Command::Lints(LintsArgs { dump, .. })
+            | Command::Deprecated(DeprecatedArgs { dump, .. }) => Some(dump),
+            _ => None,
+        }
+    }
+}
+
+impl DumpArgs {
+    /// The names of the options with a default value, which `given` lists.
+    pub const WITH_DEFAULT_VALUE: &'static [&'static str] =
+        &["group-by", "N", "keys", "generated", "resolve-reexports"];
+
+    /// The kinds of targets to analyze, the libraries and binaries unless asked otherwise.
+    pub fn targets(&self) -> Vec<TargetKind> {
+        let mut targets = TargetKind::DEFAULT.to_vec();
+        for (kind, yes) in &[
+            (TargetKind::Test, self.tests),
+            (TargetKind::Bench, self.benches),
+            (TargetKind::Example, self.examples),
+        ] {
+            if *yes || self.all_targets {
+                targets.push(*kind);
+            }
+        }
+        if self.build_scripts {
+            targets.push(TargetKind::BuildScript);
+        }
+        targets
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    File,
+    Package,
+    Origin,
+}
+
+impl GroupBy {
+    pub const NAMES: &'static [&'static str] = &["file", "package", "origin"];
+}
+
+impl Default for GroupBy {
+    fn default() -> Self {
+        GroupBy::File
+    }
+}
+
+impl FromStr for GroupBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "file" => Ok(GroupBy::File),
+            "package" => Ok(GroupBy::Package),
+            "origin" => Ok(GroupBy::Origin),
+            _ => Err(format!(
+                "unknown grouping `{}`, expected one of: {}",
+                s,
+                GroupBy::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsFormat {
+    Table,
+    Json,
+}
+
+impl StatsFormat {
+    pub const NAMES: &'static [&'static str] = &["table", "json"];
+}
+
+impl FromStr for StatsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(StatsFormat::Table),
+            "json" => Ok(StatsFormat::Json),
+            _ => Err(format!(
+                "unknown stats format `{}`, expected one of: {}",
+                s,
+                StatsFormat::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Parses a single ASCII character, or `tab` or `\t` for a tab.
+fn parse_delimiter(s: &str) -> Result<u8, String> {
+    match s {
+        "tab" | "\\t" => Ok(b'\t'),
+        _ if s.len() == 1 && s.is_ascii() => Ok(s.as_bytes()[0]),
+        _ => Err(format!(
+            "invalid delimiter `{}`, expected a single ASCII character or `tab`",
+            s
+        )),
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub struct UnusedArgs {
+    /// Only fails on the unused imports which the baseline at PATH does not record, and
+    /// reports the recorded ones which no longer occur
+    #[structopt(long, name = "PATH", parse(from_os_str))]
+    pub baseline: Option<PathBuf>,
+    /// Records the unused imports in a baseline at PATH instead of failing on them
+    #[structopt(long, name = "OUT_PATH", parse(from_os_str), conflicts_with = "PATH")]
+    pub write_baseline: Option<PathBuf>,
+    /// Project to analyze, defaults to the current directory
+    #[structopt(parse(from_os_str))]
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct DiffArgs {
+    /// The older dump, in JSON, or YAML or TOML by extension
+    #[structopt(parse(from_os_str))]
+    pub old: PathBuf,
+    /// The newer dump
+    #[structopt(parse(from_os_str))]
+    pub new: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct MergeArgs {
+    /// Keeps the used items of the last dump for a file several dumps have, instead of
+    /// failing when they differ
+    #[structopt(long)]
+    pub prefer_last: bool,
+    /// Emits the bare map, without the version envelope
+    #[structopt(long)]
+    pub no_envelope: bool,
+    /// The dumps to merge, in JSON, or YAML or TOML by extension
+    #[structopt(parse(from_os_str), required = true)]
+    pub dumps: Vec<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct UnusedDepsArgs {
+    /// Never reports the dependency NAME, e.g. a proc-macro crate only used through
+    /// another crate's re-exports, can be repeated
+    #[structopt(long, name = "NAME", number_of_values = 1)]
+    pub ignore: Vec<String>,
+    /// Project to analyze, defaults to the current directory
+    #[structopt(parse(from_os_str))]
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct DepsAuditArgs {
+    /// Project to analyze, defaults to the current directory
+    #[structopt(parse(from_os_str))]
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct WhoUsesArgs {
+    /// Name or full path of the item, e.g. `Serialize` or `serde::ser::Serialize`
+    pub query: String,
+    /// Treats the query as a regular expression over names and paths
+    #[structopt(long)]
+    pub regex: bool,
+    #[structopt(flatten)]
+    pub dump: DumpArgs,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct WatchArgs {
+    #[structopt(flatten)]
+    pub dump: DumpArgs,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ServeArgs {
+    /// Listens on the unix socket PATH instead of stdin, answering one client at a time
+    #[structopt(long, name = "PATH", parse(from_os_str))]
+    pub socket: Option<PathBuf>,
+    #[structopt(flatten)]
+    pub dump: DumpArgs,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ExportsArgs {
+    #[structopt(flatten)]
+    pub dump: DumpArgs,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct DeadExportsArgs {
+    /// Exits with 1 when some export is not used by any other member
+    #[structopt(long)]
+    pub deny_dead_exports: bool,
+    /// Project to analyze, defaults to the current directory
+    #[structopt(parse(from_os_str))]
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct GraphArgs {
+    /// Leaves out the crates which are not members of the workspace
+    #[structopt(long)]
+    pub workspace_only: bool,
+    #[structopt(flatten)]
+    pub dump: DumpArgs,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct CratesArgs {
+    #[structopt(flatten)]
+    pub dump: DumpArgs,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct CommonImportsArgs {
+    /// Only lists the sets of items which at least MIN_FILES files import together
+    #[structopt(long, name = "MIN_FILES", default_value = "10")]
+    pub min_files: usize,
+    #[structopt(flatten)]
+    pub dump: DumpArgs,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct GlobsArgs {
+    /// Exits with 1 when some glob import is not allowed
+    #[structopt(long)]
+    pub deny: bool,
+    /// Allows the glob imports of PATH, e.g. `super::*` or `std::io::prelude`, can be
+    /// repeated, defaults to `allow` under `[globs]` in the configuration
+    #[structopt(long, name = "PATH", number_of_values = 1)]
+    pub allow: Vec<String>,
+    /// Project to analyze, defaults to the current directory
+    #[structopt(parse(from_os_str))]
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ConsolidateArgs {
+    /// Project to analyze, defaults to the current directory
+    #[structopt(parse(from_os_str))]
+    pub path: Option<PathBuf>,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct LintsArgs {
+    /// Also reports the imports of functions shadowing those of the enclosing scope
+    #[structopt(long)]
+    pub shadowing: bool,
+    /// Exits with 1 when there is some lint
+    #[structopt(long)]
+    pub deny: bool,
+    #[structopt(flatten)]
+    pub dump: DumpArgs,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct DeprecatedArgs {
+    /// Exits with 1 when some deprecated item is imported
+    #[structopt(long)]
+    pub deny: bool,
+    #[structopt(flatten)]
+    pub dump: DumpArgs,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct RemoteArgs {
+    /// The crate to fetch, as `name@version`, or `name` for its latest version
+    #[structopt(name = "CRATE", required_unless_one = &["URL", "DIR"])]
+    pub spec: Option<String>,
+    /// Clones the git repository at URL instead, with the system git
+    #[structopt(long, name = "URL", conflicts_with_all = &["CRATE", "DIR"])]
+    pub git: Option<String>,
+    /// Checks out REV, a commit, a tag or a branch, instead of the default branch
+    #[structopt(long, name = "REV", requires = "URL")]
+    pub rev: Option<String>,
+    /// Dumps an existing checkout in DIR instead of cloning it
+    #[structopt(long, name = "DIR", parse(from_os_str), conflicts_with = "CRATE")]
+    pub path: Option<PathBuf>,
+    /// Dumps the workspace in the directory SUBDIR of the repository, e.g. `crates/foo`
+    #[structopt(long, name = "SUBDIR", parse(from_os_str))]
+    pub subdir: Option<PathBuf>,
+    /// Only uses the crates and the index already in the cache of cargo
+    #[structopt(long)]
+    pub offline: bool,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct FileArgs {
+    /// The file to analyze, `-` for standard input
+    #[structopt(parse(from_os_str))]
+    pub file: PathBuf,
+}
+
+#[derive(Debug, StructOpt)]
+pub struct CoverageArgs {
+    /// The dependency whose public items are listed
+    #[structopt(long, short, name = "SPEC")]
+    pub package: String,
+    /// Project to analyze, defaults to the current directory
+    #[structopt(parse(from_os_str))]
+    pub path: Option<PathBuf>,
+}
+
+impl Opts {
+    /// Parses the command line `args`, exiting with code 2 on usage errors.
+    pub fn from_args_or_exit(args: Vec<OsString>) -> Self {
+        let matches = match Opts::clap().get_matches_from_safe(args) {
+            Ok(matches) => matches,
+            Err(e) if e.use_stderr() => {
+                eprintln!("{}", e.message);
+                process::exit(USAGE_ERROR);
+            }
+            Err(e) => e.exit(),
+        };
+        let mut opts = Opts::from_clap(&matches);
+        opts.format_given = matches.occurrences_of("format") > 0;
+        let (_, subcommand_matches) = matches.subcommand();
+        let args = opts.command.as_mut().and_then(Command::dump_args);
+        if let (Some(args), Some(subcommand_matches)) = (args, subcommand_matches) {
+            args.given = DumpArgs::WITH_DEFAULT_VALUE
+                .iter()
+                .cloned()
+                .filter(|name| subcommand_matches.occurrences_of(name) > 0)
+                .collect();
+        }
+        opts
+    }
+
+    /// The project to analyze when none is given, a directory or a manifest.
+    pub fn project_path(&self) -> io::Result<PathBuf> {
+        match (&self.manifest_path, &self.default_dir) {
+            (Some(path), _) | (None, Some(path)) => Ok(path.clone()),
+            (None, None) => env::current_dir(),
+        }
+    }
+
+    pub fn command(&mut self) -> Command {
+        self.command
+            .take()
+            .unwrap_or_else(|| Command::Dump(DumpArgs::default()))
+    }
+}
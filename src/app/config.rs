@@ -0,0 +1,763 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::Deserialize;
+
+use crate::output::{self, OutputFormat};
+
+use super::cli::{DumpArgs, GlobsArgs, Opts};
+
+/// The name of the configuration file looked up in the project and its parent directories.
+pub const CONFIG_FILE: &str = "usedump.toml";
+
+/// The prefix of the environment variables `Config::from_env` reads.
+const ENV_PREFIX: &str = "USEDUMP_";
+/// The keys outside of `[dump]`.
+const TOP_LEVEL_KEYS: &[&str] = &["format", "pretty", "quiet"];
+/// The keys whose environment variables are lists, separated by `:` or `,`.
+const LIST_KEYS: &[&str] = &[
+    "package", "target", "features", "include", "exclude", "kind",
+];
+/// The keys whose environment variables are numbers.
+const INTEGER_KEYS: &[&str] = &["jobs", "file-timeout", "top"];
+/// The keys of `[dump]`, which `--unset` takes as they are.
+const DUMP_KEYS: &[&str] = &[
+    "jobs",
+    "file-timeout",
+    "no-cache",
+    "cache-dir",
+    "no-resolve",
+    "sysroot",
+    "no-sysroot",
+    "offline",
+    "locked",
+    "target",
+    "strict",
+    "fail-on-unresolved",
+    "deny-syntax-errors",
+    "deny-deprecated",
+    "package",
+    "tests",
+    "benches",
+    "examples",
+    "all-targets",
+    "build-scripts",
+    "generated",
+    "features",
+    "all-features",
+    "no-default-features",
+    "include",
+    "exclude",
+    "cfg-filter",
+    "only",
+    "exclude-std",
+    "kind",
+    "include-empty",
+    "include-derives",
+    "only-reexports",
+    "no-reexports",
+    "exclude-tests",
+    "only-tests",
+    "group-by",
+    "locations",
+    "definitions",
+    "top",
+    "keys",
+    "resolve-reexports",
+    "flat-paths",
+    "absolute-paths",
+    "no-envelope",
+    "legacy-names",
+];
+/// The key of `[globs]`.
+const GLOBS_KEYS: &[&str] = &["allow"];
+/// The keys whose environment variables are `true` or `false`, the others being strings.
+const BOOLEAN_KEYS: &[&str] = &[
+    "pretty",
+    "quiet",
+    "no-cache",
+    "no-resolve",
+    "no-sysroot",
+    "offline",
+    "locked",
+    "strict",
+    "fail-on-unresolved",
+    "deny-syntax-errors",
+    "deny-deprecated",
+    "tests",
+    "benches",
+    "examples",
+    "all-targets",
+    "build-scripts",
+    "all-features",
+    "no-default-features",
+    "exclude-std",
+    "include-empty",
+    "include-derives",
+    "only-reexports",
+    "no-reexports",
+    "exclude-tests",
+    "only-tests",
+    "locations",
+    "definitions",
+    "flat-paths",
+    "absolute-paths",
+    "no-envelope",
+    "legacy-names",
+];
+
+/// The defaults of the options, read from `usedump.toml` or from the environment. Keys are named after the flags,
+/// e.g. `exclude-std`, the ones of `dump` going under `[dump]` and those of `globs` under
+/// `[globs]`.
+///
+/// The command line always wins: lists, values and flags only apply when the command line
+/// leaves them unset, and no key applies which it gives to `--unset`, e.g. `--unset locked`
+/// for `locked = true`. Of two conflicting flags, e.g. `only-tests` and `exclude-tests`,
+/// the one with the higher precedence is kept.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    format: Option<String>,
+    pretty: Option<bool>,
+    quiet: Option<bool>,
+    dump: DumpConfig,
+    globs: GlobsConfig,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, toml::Value>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct DumpConfig {
+    jobs: Option<usize>,
+    file_timeout: Option<u64>,
+    no_cache: Option<bool>,
+    cache_dir: Option<PathBuf>,
+    no_resolve: Option<bool>,
+    sysroot: Option<PathBuf>,
+    no_sysroot: Option<bool>,
+    offline: Option<bool>,
+    locked: Option<bool>,
+    target: Vec<String>,
+    strict: Option<bool>,
+    fail_on_unresolved: Option<bool>,
+    deny_syntax_errors: Option<bool>,
+    deny_deprecated: Option<bool>,
+    package: Vec<String>,
+    tests: Option<bool>,
+    benches: Option<bool>,
+    examples: Option<bool>,
+    all_targets: Option<bool>,
+    build_scripts: Option<bool>,
+    generated: Option<String>,
+    features: Vec<String>,
+    all_features: Option<bool>,
+    no_default_features: Option<bool>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    cfg_filter: Option<String>,
+    only: Option<String>,
+    exclude_std: Option<bool>,
+    kind: Vec<String>,
+    include_empty: Option<bool>,
+    include_derives: Option<bool>,
+    only_reexports: Option<bool>,
+    no_reexports: Option<bool>,
+    exclude_tests: Option<bool>,
+    only_tests: Option<bool>,
+    group_by: Option<String>,
+    locations: Option<bool>,
+    definitions: Option<bool>,
+    top: Option<usize>,
+    keys: Option<String>,
+    resolve_reexports: Option<String>,
+    flat_paths: Option<bool>,
+    absolute_paths: Option<bool>,
+    no_envelope: Option<bool>,
+    legacy_names: Option<bool>,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, toml::Value>,
+}
+
+/// The options of `globs`, under `[globs]`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+struct GlobsConfig {
+    allow: Vec<String>,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, toml::Value>,
+}
+
+impl Config {
+    /// The `usedump.toml` of `dir` or of the closest of its parent directories.
+    pub fn find(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|dir| dir.join(CONFIG_FILE))
+            .find(|path| path.is_file())
+    }
+
+    /// Reads the configuration at `path`, whose relative paths are resolved against its
+    /// directory.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let mut config: Config = output::read_value(path).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid configuration `{}`: {}", path.display(), e),
+            )
+        })?;
+        if let (Some(cache_dir), Some(dir)) = (&mut config.dump.cache_dir, path.parent()) {
+            *cache_dir = dir.join(&*cache_dir);
+        }
+        Ok(config)
+    }
+
+    /// The configuration the `USEDUMP_*` variables among `vars` make up, named after the keys
+    /// of the file, e.g. `USEDUMP_EXCLUDE_STD=true` for `exclude-std`. Lists are separated by
+    /// `:` or `,`, e.g. `USEDUMP_EXCLUDE=**/tests/**:benches/**`.
+    pub fn from_env<I: IntoIterator<Item = (String, String)>>(vars: I) -> io::Result<Self> {
+        let mut table = toml::value::Table::new();
+        let mut dump = toml::value::Table::new();
+        for (name, value) in vars {
+            if !name.starts_with(ENV_PREFIX) {
+                continue;
+            }
+            let key = name[ENV_PREFIX.len()..].to_lowercase().replace('_', "-");
+            let value = env_value(&name, &key, value)?;
+            if TOP_LEVEL_KEYS.contains(&key.as_str()) {
+                table.insert(key, value);
+            } else {
+                dump.insert(key, value);
+            }
+        }
+        table.insert("dump".to_owned(), toml::Value::Table(dump));
+        toml::Value::Table(table).try_into().map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid `{}*` variable: {}", ENV_PREFIX, e),
+            )
+        })
+    }
+
+    /// The keys which mean nothing to usedump, e.g. misspelled flags.
+    pub fn unknown_keys(&self) -> Vec<String> {
+        self.unknown
+            .keys()
+            .cloned()
+            .chain(self.dump.unknown.keys().map(|key| format!("dump.{}", key)))
+            .chain(
+                self.globs
+                    .unknown
+                    .keys()
+                    .map(|key| format!("globs.{}", key)),
+            )
+            .collect()
+    }
+
+    /// `unknown_keys` as the variables `from_env` read them from.
+    pub fn unknown_variables(&self) -> Vec<String> {
+        self.unknown_keys()
+            .iter()
+            .map(|key| {
+                let key = key.trim_start_matches("dump.");
+                format!("{}{}", ENV_PREFIX, key.to_uppercase().replace('-', "_"))
+            })
+            .collect()
+    }
+
+    /// Fills in the options of `opts` and `args` which the command line, or a configuration
+    /// applied before, leaves unset.
+    pub fn apply(&self, opts: &mut Opts, args: Option<&mut DumpArgs>) -> io::Result<()> {
+        let mut keys = Unsettled {
+            unset: &opts.unset,
+            settled: &mut opts.configured,
+        };
+        if let (Some(format), false) = (&self.format, opts.format_given) {
+            if keys.settle("format") {
+                opts.format = parse::<OutputFormat>("format", format)?;
+                opts.format_given = true;
+            }
+        }
+        keys.fill_flag(&mut opts.pretty, "pretty", self.pretty);
+        keys.fill_flag(&mut opts.quiet, "quiet", self.quiet);
+        if let Some(args) = args {
+            self.dump.apply(args, &mut keys)?;
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    /// Fills in the allowlist of `globs` when the command line gives none, nor unsets it.
+    pub fn apply_globs(&self, unset: &[String], args: &mut GlobsArgs) {
+        if !unset.iter().any(|key| key == "allow") {
+            fill_list(&mut args.allow, &self.globs.allow);
+        }
+    }
+}
+
+/// Fills in the options the command line leaves unset from the environment, then from the
+/// configuration file.
+pub fn resolve(
+    opts: &mut Opts,
+    mut args: Option<&mut DumpArgs>,
+    env: &Config,
+    file: &Config,
+) -> io::Result<()> {
+    check_unset(&opts.unset)?;
+    env.apply(opts, args.as_mut().map(|args| &mut **args))?;
+    file.apply(opts, args.as_mut().map(|args| &mut **args))?;
+    match args {
+        Some(args) => check_merged_conflicts(args),
+        None => Ok(()),
+    }
+}
+
+impl DumpConfig {
+    fn apply(&self, args: &mut DumpArgs, keys: &mut Unsettled) -> io::Result<()> {
+        self.check_conflicts()?;
+        keys.fill(&mut args.jobs, "jobs", &self.jobs);
+        keys.fill(&mut args.file_timeout, "file-timeout", &self.file_timeout);
+        // A cache directory, wherever it is set, asks for the cache.
+        if args.cache_dir.is_none() {
+            keys.fill_flag(&mut args.no_cache, "no-cache", self.no_cache);
+        }
+        if !args.no_cache {
+            keys.fill(&mut args.cache_dir, "cache-dir", &self.cache_dir);
+        }
+        keys.fill_flag(&mut args.no_resolve, "no-resolve", self.no_resolve);
+        if args.sysroot.is_none() {
+            keys.fill_flag(&mut args.no_sysroot, "no-sysroot", self.no_sysroot);
+        }
+        keys.fill_flag(&mut args.offline, "offline", self.offline);
+        keys.fill_flag(&mut args.locked, "locked", self.locked);
+        keys.fill_list(&mut args.target, "target", &self.target);
+        if !args.no_sysroot {
+            keys.fill(&mut args.sysroot, "sysroot", &self.sysroot);
+        }
+        keys.fill_flag(&mut args.strict, "strict", self.strict);
+        keys.fill_flag(
+            &mut args.fail_on_unresolved,
+            "fail-on-unresolved",
+            self.fail_on_unresolved,
+        );
+        keys.fill_flag(
+            &mut args.deny_syntax_errors,
+            "deny-syntax-errors",
+            self.deny_syntax_errors,
+        );
+        keys.fill_flag(
+            &mut args.deny_deprecated,
+            "deny-deprecated",
+            self.deny_deprecated,
+        );
+        keys.fill_list(&mut args.package, "package", &self.package);
+        keys.fill_flag(&mut args.tests, "tests", self.tests);
+        keys.fill_flag(&mut args.benches, "benches", self.benches);
+        keys.fill_flag(&mut args.examples, "examples", self.examples);
+        keys.fill_flag(&mut args.all_targets, "all-targets", self.all_targets);
+        keys.fill_flag(&mut args.build_scripts, "build-scripts", self.build_scripts);
+        if let (Some(generated), false) = (&self.generated, args.given.contains(&"generated")) {
+            if keys.settle("generated") {
+                args.generated = parse("dump.generated", generated)?;
+                args.given.push("generated");
+            }
+        }
+        keys.fill_list(&mut args.features, "features", &self.features);
+        keys.fill_flag(&mut args.all_features, "all-features", self.all_features);
+        keys.fill_flag(
+            &mut args.no_default_features,
+            "no-default-features",
+            self.no_default_features,
+        );
+        keys.fill_list(&mut args.include, "include", &self.include);
+        keys.fill_list(&mut args.exclude, "exclude", &self.exclude);
+        keys.fill(&mut args.cfg_filter, "cfg-filter", &self.cfg_filter);
+        if let (None, Some(only)) = (args.only, &self.only) {
+            if keys.settle("only") {
+                args.only = Some(parse("dump.only", only)?);
+            }
+        }
+        keys.fill_flag(&mut args.exclude_std, "exclude-std", self.exclude_std);
+        if let (true, false) = (args.kind.is_empty(), self.kind.is_empty()) {
+            if keys.settle("kind") {
+                args.kind = self
+                    .kind
+                    .iter()
+                    .map(|kind| parse("dump.kind", kind))
+                    .collect::<io::Result<_>>()?;
+            }
+        }
+        keys.fill_flag(&mut args.include_empty, "include-empty", self.include_empty);
+        keys.fill_flag(
+            &mut args.include_derives,
+            "include-derives",
+            self.include_derives,
+        );
+        // Of two conflicting flags, the one set with a higher precedence wins.
+        if !args.no_reexports {
+            keys.fill_flag(
+                &mut args.only_reexports,
+                "only-reexports",
+                self.only_reexports,
+            );
+        }
+        if !args.only_reexports {
+            keys.fill_flag(&mut args.no_reexports, "no-reexports", self.no_reexports);
+        }
+        if !args.only_tests {
+            keys.fill_flag(&mut args.exclude_tests, "exclude-tests", self.exclude_tests);
+        }
+        if !args.exclude_tests {
+            keys.fill_flag(&mut args.only_tests, "only-tests", self.only_tests);
+        }
+        if let (Some(group_by), false) = (&self.group_by, args.given.contains(&"group-by")) {
+            if keys.settle("group-by") {
+                args.group_by = parse("dump.group-by", group_by)?;
+                args.given.push("group-by");
+            }
+        }
+        keys.fill_flag(&mut args.locations, "locations", self.locations);
+        keys.fill_flag(&mut args.definitions, "definitions", self.definitions);
+        if let (Some(top), false) = (self.top, args.given.contains(&"N")) {
+            if keys.settle("top") {
+                args.top = top;
+                args.given.push("N");
+            }
+        }
+        if let (Some(value), false) = (&self.keys, args.given.contains(&"keys")) {
+            if keys.settle("keys") {
+                args.keys = parse("dump.keys", value)?;
+                args.given.push("keys");
+            }
+        }
+        if let Some(resolve_reexports) = &self.resolve_reexports {
+            if !args.given.contains(&"resolve-reexports") && keys.settle("resolve-reexports") {
+                args.resolve_reexports = parse("dump.resolve-reexports", resolve_reexports)?;
+                args.given.push("resolve-reexports");
+            }
+        }
+        keys.fill_flag(&mut args.flat_paths, "flat-paths", self.flat_paths);
+        keys.fill_flag(
+            &mut args.absolute_paths,
+            "absolute-paths",
+            self.absolute_paths,
+        );
+        keys.fill_flag(&mut args.no_envelope, "no-envelope", self.no_envelope);
+        keys.fill_flag(&mut args.legacy_names, "legacy-names", self.legacy_names);
+        Ok(())
+    }
+
+    /// Rejects the conflicting flags the configuration turns on together.
+    fn check_conflicts(&self) -> io::Result<()> {
+        let on = |flag: Option<bool>| flag == Some(true);
+        check_conflicts(&[
+            (
+                "dump.only-tests",
+                "dump.exclude-tests",
+                on(self.only_tests) && on(self.exclude_tests),
+            ),
+            (
+                "dump.only-reexports",
+                "dump.no-reexports",
+                on(self.only_reexports) && on(self.no_reexports),
+            ),
+        ])
+    }
+}
+
+/// Rejects the conflicting flags of `args` once every configuration is applied, as the
+/// command line checks them before.
+fn check_merged_conflicts(args: &DumpArgs) -> io::Result<()> {
+    check_conflicts(&[
+        (
+            "only-tests",
+            "exclude-tests",
+            args.only_tests && args.exclude_tests,
+        ),
+        (
+            "only-reexports",
+            "no-reexports",
+            args.only_reexports && args.no_reexports,
+        ),
+        (
+            "no-cache",
+            "cache-dir",
+            args.no_cache && args.cache_dir.is_some(),
+        ),
+        (
+            "no-sysroot",
+            "sysroot",
+            args.no_sysroot && args.sysroot.is_some(),
+        ),
+    ])
+}
+
+/// Fails on the first of `conflicts`, pairs of keys with whether both are set.
+fn check_conflicts(conflicts: &[(&str, &str, bool)]) -> io::Result<()> {
+    match conflicts.iter().find(|(_, _, both)| *both) {
+        Some((a, b, _)) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`{}` cannot be combined with `{}`", a, b),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// The keys the configurations may still set: those the command line gives to `--unset`
+/// never are, and each of the others only by the first configuration setting it, the one
+/// with the highest precedence.
+struct Unsettled<'a> {
+    unset: &'a [String],
+    settled: &'a mut Vec<&'static str>,
+}
+
+impl Unsettled<'_> {
+    /// Whether `key` may be set, in which case it is settled by the caller.
+    fn settle(&mut self, key: &'static str) -> bool {
+        if self.unset.iter().any(|unset| unset == key) || self.settled.contains(&key) {
+            return false;
+        }
+        self.settled.push(key);
+        true
+    }
+
+    /// Turns `flag` on or off per `value` unless the command line gives it, or `key` is
+    /// settled.
+    fn fill_flag(&mut self, flag: &mut bool, key: &'static str, value: Option<bool>) {
+        if let (false, Some(value)) = (*flag, value) {
+            if self.settle(key) {
+                *flag = value;
+            }
+        }
+    }
+
+    fn fill<T: Clone>(&mut self, arg: &mut Option<T>, key: &'static str, value: &Option<T>) {
+        if arg.is_none() && value.is_some() && self.settle(key) {
+            *arg = value.clone();
+        }
+    }
+
+    fn fill_list<T: Clone>(&mut self, arg: &mut Vec<T>, key: &'static str, values: &[T]) {
+        if arg.is_empty() && !values.is_empty() && self.settle(key) {
+            *arg = values.to_vec();
+        }
+    }
+}
+
+/// Rejects the keys given to `--unset` which no configuration has.
+fn check_unset(unset: &[String]) -> io::Result<()> {
+    let mut known = TOP_LEVEL_KEYS.iter().chain(DUMP_KEYS).chain(GLOBS_KEYS);
+    match unset.iter().find(|key| !known.any(|known| known == key)) {
+        Some(key) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unknown key `{}` for `--unset`", key),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// The value of the variable `name` for `key`, of the type of the key: a list, a number, a
+/// boolean or else a string, so that e.g. `USEDUMP_CFG_FILTER=true` is the predicate `true`.
+fn env_value(name: &str, key: &str, value: String) -> io::Result<toml::Value> {
+    let invalid = |expected: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid `{}={}`, expected {}", name, value, expected),
+        )
+    };
+    Ok(if LIST_KEYS.contains(&key) {
+        let values = value
+            .split(|c| c == ':' || c == ',')
+            .filter(|value| !value.is_empty())
+            .map(|value| toml::Value::String(value.to_owned()))
+            .collect();
+        toml::Value::Array(values)
+    } else if INTEGER_KEYS.contains(&key) {
+        toml::Value::Integer(value.parse().map_err(|_| invalid("a number"))?)
+    } else if BOOLEAN_KEYS.contains(&key) {
+        match value.as_str() {
+            "true" | "1" => toml::Value::Boolean(true),
+            "false" | "0" => toml::Value::Boolean(false),
+            _ => return Err(invalid("`true` or `false`")),
+        }
+    } else {
+        toml::Value::String(value)
+    })
+}
+
+fn fill_list<T: Clone>(arg: &mut Vec<T>, values: &[T]) {
+    if arg.is_empty() {
+        *arg = values.to_vec();
+    }
+}
+
+fn parse<T>(key: &str, value: &str) -> io::Result<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value.parse().map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid `{}` in the configuration: {}", key, e),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use structopt::StructOpt;
+
+    use super::*;
+
+    /// The options of the command line `args`, with the configurations resolved.
+    fn resolved(args: &[&str], env: &[(&str, &str)], file: &str) -> io::Result<(Opts, DumpArgs)> {
+        let mut opts = Opts::from_iter(Some("usedump").into_iter().chain(args.iter().cloned()));
+        let mut command = opts.command();
+        let vars = env
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()));
+        let env = Config::from_env(vars)?;
+        let file: Config = toml::from_str(file).unwrap();
+        resolve(&mut opts, command.dump_args(), &env, &file)?;
+        match command {
+            Command::Dump(args) => Ok((opts, args)),
+            command => panic!("not a dump: {:?}", command),
+        }
+    }
+
+    #[test]
+    fn command_line_wins_over_environment_and_file() {
+        let (_, args) = resolved(
+            &["dump", "--jobs", "8"],
+            &[("USEDUMP_JOBS", "2")],
+            "[dump]\njobs = 4",
+        )
+        .unwrap();
+        assert_eq!(args.jobs, Some(8));
+    }
+
+    #[test]
+    fn environment_wins_over_file() {
+        let (_, args) = resolved(&["dump"], &[("USEDUMP_JOBS", "2")], "[dump]\njobs = 4").unwrap();
+        assert_eq!(args.jobs, Some(2));
+        let (_, args) = resolved(&["dump"], &[], "[dump]\njobs = 4").unwrap();
+        assert_eq!(args.jobs, Some(4));
+    }
+
+    #[test]
+    fn unset_keys_of_the_configurations_are_ignored() {
+        let (_, args) = resolved(&["dump"], &[], "[dump]\nlocked = true").unwrap();
+        assert!(args.locked);
+        let (_, args) =
+            resolved(&["dump", "--unset", "locked"], &[], "[dump]\nlocked = true").unwrap();
+        assert!(!args.locked);
+        let (_, args) = resolved(
+            &["dump"],
+            &[("USEDUMP_LOCKED", "false")],
+            "[dump]\nlocked = true",
+        )
+        .unwrap();
+        assert!(!args.locked);
+        let (_, args) = resolved(
+            &["dump", "--unset", "jobs"],
+            &[("USEDUMP_JOBS", "2")],
+            "[dump]\njobs = 4",
+        )
+        .unwrap();
+        assert_eq!(args.jobs, None);
+        let (_, args) = resolved(
+            &["dump", "--unset", "no-cache"],
+            &[],
+            "[dump]\nno-cache = true",
+        )
+        .unwrap();
+        assert!(!args.no_cache);
+    }
+
+    #[test]
+    fn top_level_flags_are_turned_off() {
+        let (opts, _) = resolved(&["--unset", "pretty", "dump"], &[], "pretty = true").unwrap();
+        assert!(!opts.pretty);
+        let (opts, _) = resolved(&["dump"], &[("USEDUMP_PRETTY", "1")], "").unwrap();
+        assert!(opts.pretty);
+    }
+
+    #[test]
+    fn unknown_unset_keys_are_rejected() {
+        let error = resolved(&["dump", "--unset", "lockd"], &[], "").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn typed_keys_can_be_unset() {
+        for key in LIST_KEYS.iter().chain(INTEGER_KEYS).chain(BOOLEAN_KEYS) {
+            assert!(check_unset(&[key.to_string()]).is_ok(), "{}", key);
+        }
+    }
+
+    #[test]
+    fn conflicting_flag_of_higher_precedence_wins() {
+        let (_, args) = resolved(
+            &["dump", "--only-tests"],
+            &[],
+            "[dump]\nexclude-tests = true",
+        )
+        .unwrap();
+        assert!(args.only_tests);
+        assert!(!args.exclude_tests);
+        let (_, args) = resolved(
+            &["dump"],
+            &[("USEDUMP_NO_REEXPORTS", "true")],
+            "[dump]\nonly-reexports = true",
+        )
+        .unwrap();
+        assert!(args.no_reexports);
+        assert!(!args.only_reexports);
+    }
+
+    #[test]
+    fn conflicting_flags_of_a_configuration_are_rejected() {
+        let file = "[dump]\nonly-tests = true\nexclude-tests = true";
+        assert!(resolved(&["dump"], &[], file).is_err());
+    }
+
+    #[test]
+    fn environment_values_have_the_type_of_their_key() {
+        let (_, args) = resolved(&["dump"], &[("USEDUMP_CFG_FILTER", "true")], "").unwrap();
+        assert_eq!(args.cfg_filter.as_ref().map(String::as_str), Some("true"));
+        let (_, args) = resolved(&["dump"], &[("USEDUMP_TARGET", "wasm32-wasi")], "").unwrap();
+        assert_eq!(args.target, vec!["wasm32-wasi".to_owned()]);
+
+        let config = Config::from_env(vec![("USEDUMP_FORMAT".to_owned(), "1".to_owned())]);
+        assert_eq!(
+            config.unwrap().format.as_ref().map(String::as_str),
+            Some("1")
+        );
+        let config = Config::from_env(vec![("USEDUMP_LOCKED".to_owned(), "yes".to_owned())]);
+        assert!(config.is_err());
+        let config = Config::from_env(vec![("USEDUMP_JOBS".to_owned(), "many".to_owned())]);
+        assert!(config.is_err());
+    }
+
+    #[test]
+    fn finds_the_configuration_of_a_parent_directory() {
+        let root = env::temp_dir().join(format!("usedump-config-{}", std::process::id()));
+        let dir = root.join("crates").join("member");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(root.join(CONFIG_FILE), "[dump]\nexclude-std = true\n").unwrap();
+
+        let found = Config::find(&dir);
+        let config = found.as_ref().map(|path| Config::read(path));
+        fs::remove_dir_all(&root).unwrap();
+        assert_eq!(found, Some(root.join(CONFIG_FILE)));
+        assert_eq!(config.unwrap().unwrap().dump.exclude_std, Some(true));
+    }
+}
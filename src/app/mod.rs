@@ -0,0 +1,1172 @@
+//! The command line of the `usedump` and `cargo-usedump` binaries, which only differ in
+//! how they take their arguments and which project they analyze by default.
+
+mod cli;
+mod config;
+
+use std::{
+    collections::BTreeMap,
+    env,
+    ffi::OsString,
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use serde::Serialize;
+
+use crate::{
+    output::{self, DumpWriter, Envelope, OutputFormat},
+    Baseline, CrateMap, CrateMapDiff, Diagnostic, DumpOptions, FileEntry, FileGraph, ItemQuery,
+    Keys, MarkdownOptions, MemberGraph, OriginFilter, OriginKind, Progress, SarifLog, Server,
+    UsedItemMap, UsedumpError,
+};
+
+use self::{
+    cli::{
+        Command, CommonImportsArgs, ConsolidateArgs, CoverageArgs, CratesArgs, DeadExportsArgs,
+        DeprecatedArgs, DepsAuditArgs, DiffArgs, DumpArgs, ExportsArgs, FileArgs, GlobsArgs,
+        GraphArgs, GroupBy, LintsArgs, MergeArgs, Opts, RemoteArgs, ServeArgs, StatsFormat,
+        UnusedArgs, UnusedDepsArgs, WatchArgs, WhoUsesArgs,
+    },
+    config::Config,
+};
+
+/// Parses `args`, runs the command and exits with its code, as both `usedump` and
+/// `cargo-usedump` do. `default_dir` is the project to analyze when none is given, the
+/// current directory when `None`.
+pub fn run(args: Vec<OsString>, default_dir: Option<PathBuf>) -> ! {
+    let mut opts = Opts::from_args_or_exit(args);
+    opts.default_dir = default_dir;
+    let mut command = opts.command();
+    if let Err(e) = apply_config(&mut opts, &mut command) {
+        eprintln!("error: {}", e);
+        process::exit(cli::USAGE_ERROR);
+    }
+    let result = match command {
+        Command::Dump(args) => dump(&opts, args),
+        Command::Unused(args) => unused(&opts, args),
+        Command::Diff(args) => diff(&opts, args),
+        Command::UnusedDeps(args) => unused_deps(&opts, args),
+        Command::DepsAudit(args) => deps_audit(&opts, args),
+        Command::WhoUses(args) => who_uses(&opts, args),
+        Command::Watch(args) => watch(&opts, args),
+        Command::Serve(args) => serve(&opts, args),
+        Command::Exports(args) => exports(&opts, args),
+        Command::DeadExports(args) => dead_exports(&opts, args),
+        Command::Graph(args) => graph(&opts, args),
+        Command::Crates(args) => crates(&opts, args),
+        Command::CommonImports(args) => common_imports(&opts, args),
+        Command::Coverage(args) => coverage(&opts, args),
+        Command::Globs(args) => globs(&opts, args),
+        Command::Consolidate(args) => consolidate(&opts, args),
+        Command::Lints(args) => lints(&opts, args),
+        Command::Deprecated(args) => deprecated(&opts, args),
+        Command::File(args) => file(&opts, args),
+        Command::Remote(args) => remote(&opts, args),
+        Command::Merge(args) => merge(&opts, args),
+        Command::Schema => schema(&opts),
+    };
+
+    match result {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(exit_code_of(&e));
+        }
+    }
+}
+
+/// Applies the `USEDUMP_*` environment variables, then the file given with `--config` or the
+/// `usedump.toml` of the project when there is one, to the options the command line leaves
+/// unset.
+fn apply_config(opts: &mut Opts, command: &mut Command) -> io::Result<()> {
+    let args = command.dump_args();
+    // `env::vars` panics on the variables which are not valid unicode.
+    let vars = env::vars_os()
+        .filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)));
+    let env_config = Config::from_env(vars)?;
+    let path = match &opts.config {
+        Some(path) => Some(path.clone()),
+        None => {
+            let dir = match args.as_ref().and_then(|args| args.paths.first()) {
+                Some(dir) => dir.clone(),
+                None => opts.project_path()?,
+            };
+            Config::find(&dir)
+        }
+    };
+    let file_config = match &path {
+        Some(path) => Config::read(path)?,
+        None => Config::default(),
+    };
+    config::resolve(opts, args, &env_config, &file_config)?;
+    if let Command::Globs(args) = command {
+        file_config.apply_globs(&opts.unset, args);
+    }
+
+    if !opts.quiet {
+        for variable in env_config.unknown_variables() {
+            eprintln!("warning: unknown variable `{}`", variable);
+        }
+        if let Some(path) = &path {
+            for key in file_config.unknown_keys() {
+                eprintln!("warning: unknown key `{}` in `{}`", key, path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Tells usage errors apart from the failures of the analysis.
+fn exit_code_of(e: &UsedumpError) -> i32 {
+    match e {
+        UsedumpError::UnknownPackage { .. }
+        | UsedumpError::UnknownDependency { .. }
+        | UsedumpError::InvalidGlob { .. }
+        | UsedumpError::InvalidRegex { .. } => cli::USAGE_ERROR,
+        UsedumpError::Io(e) if e.kind() == io::ErrorKind::InvalidInput => cli::USAGE_ERROR,
+        _ => cli::ANALYSIS_ERROR,
+    }
+}
+
+/// Runs the dump subcommand, returning the exit code.
+fn dump(opts: &Opts, mut args: DumpArgs) -> crate::Result<i32> {
+    if args.paths.is_empty() {
+        args.paths.push(opts.project_path()?);
+    }
+
+    // Fail before spending time on loading any workspace.
+    for path in &args.paths {
+        if crate::find_manifest(path).is_none() {
+            return Err(UsedumpError::ManifestNotFound { path: path.clone() });
+        }
+        warn_without_lockfile(opts, &args, path);
+    }
+    if DumpWriter::new(opts.format).renders_single_maps() {
+        check_single_map_args(opts, &args)?;
+    }
+    if args.no_resolve && args.keys == Keys::Module {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`--no-resolve` cannot be combined with `--keys module`",
+        )
+        .into());
+    }
+    if args.no_resolve && !args.target.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`--no-resolve` cannot be combined with `--target`, as it evaluates no cfgs",
+        )
+        .into());
+    }
+    if opts.format == OutputFormat::Ndjson {
+        check_single_target("dump --format ndjson", &args)?;
+        return dump_ndjson(opts, &args);
+    }
+
+    let cancel = cancel_on_ctrl_c()?;
+    let mut maps = BTreeMap::new();
+    let mut stats = BTreeMap::new();
+    for path in &args.paths {
+        if cancel.load(Ordering::SeqCst) {
+            break;
+        }
+        let (mut map, project_stats) = dump_options(opts, &args, path)
+            .cancel_on(Arc::clone(&cancel))
+            .run_with_stats()?;
+        // Rather than an empty dump which looks like a workspace importing nothing.
+        if project_stats.files().is_empty() && !map.is_partial() && !opts.quiet {
+            eprintln!(
+                "warning: found no member files to analyze in `{}`, check the members of the \
+                 workspace and `--package`, `--include` and `--exclude`",
+                path.display()
+            );
+        }
+        if args.group_by == GroupBy::Package {
+            map = map.aggregate_by_package();
+        }
+        maps.insert(output::normalize_path(path), map);
+        stats.insert(output::normalize_path(path), project_stats);
+    }
+    warn_without_sysroot(opts, &args, &maps);
+    let embedded_stats = match args.stats {
+        Some(Some(StatsFormat::Json)) => Some(&stats),
+        _ => None,
+    };
+
+    let mut buf = vec![];
+    dump_writer(opts, &args).write(&maps, embedded_stats, &mut buf)?;
+    write_output(opts, &buf)?;
+
+    if let Some(None) | Some(Some(StatsFormat::Table)) = args.stats {
+        for (project, project_stats) in &stats {
+            if stats.len() > 1 {
+                eprintln!("{}:", project);
+            }
+            eprint!("{}", project_stats);
+        }
+    }
+
+    let mut code = 0;
+    for map in maps.values() {
+        code = code.max(report(
+            opts,
+            &args,
+            map.diagnostics(),
+            map.unresolved_count(),
+            map.files_with_syntax_errors().count(),
+            map.deprecated_imports().len(),
+        ));
+    }
+    if cancel.load(Ordering::SeqCst) {
+        return Ok(cancelled(opts));
+    }
+    Ok(code)
+}
+
+/// The exit code of a dump interrupted with Ctrl-C, as shells report for SIGINT.
+const CANCELLED: i32 = 130;
+
+/// Warns once when a project was loaded without the sources of the standard library,
+/// unless `--no-sysroot` or `--no-resolve` asked for that.
+fn warn_without_sysroot(opts: &Opts, args: &DumpArgs, maps: &BTreeMap<String, CrateMap>) {
+    let missing = maps.values().any(|map| map.sysroot().is_none());
+    if missing && !args.no_sysroot && !args.no_resolve && !opts.quiet {
+        eprintln!(
+            "warning: found no sources of the standard library, so the items of `std`, `core` \
+             and `alloc` are unresolved; install them with `rustup component add rust-src` or \
+             pass `--sysroot`"
+        );
+    }
+}
+
+/// Warns that `path` has no `Cargo.lock`, so that cargo resolves the dependencies anew,
+/// unless `--locked` is going to fail on that.
+fn warn_without_lockfile(opts: &Opts, args: &DumpArgs, path: &Path) {
+    if crate::find_lockfile(path).is_none() && !args.locked && !opts.quiet {
+        eprintln!(
+            "warning: `{}` has no Cargo.lock, so the dependencies resolve to the versions \
+             latest when usedump runs; add one or pass `--locked` to require it",
+            path.display()
+        );
+    }
+}
+
+/// Installs a Ctrl-C handler setting the returned flag.
+fn cancel_on_ctrl_c() -> io::Result<Arc<AtomicBool>> {
+    let cancel = Arc::new(AtomicBool::new(false));
+    let handler_cancel = Arc::clone(&cancel);
+    ctrlc::set_handler(move || handler_cancel.store(true, Ordering::SeqCst))
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    Ok(cancel)
+}
+
+/// Warns that the output is partial, returning the exit code for it.
+fn cancelled(opts: &Opts) -> i32 {
+    if !opts.quiet {
+        eprintln!("warning: cancelled, only the files analyzed so far were written");
+    }
+    CANCELLED
+}
+
+/// Runs the exports subcommand, which takes the options of `dump` but `--stats`.
+fn exports(opts: &Opts, mut args: ExportsArgs) -> crate::Result<i32> {
+    let args = &mut args.dump;
+    if args.stats.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`exports` cannot be combined with `--stats`",
+        )
+        .into());
+    }
+    check_resolved("exports", args)?;
+    check_single_target("exports", args)?;
+    if opts.format == OutputFormat::Dot {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`exports` does not support the dot format",
+        )
+        .into());
+    }
+    if DumpWriter::new(opts.format).renders_single_maps() {
+        check_single_map_args(opts, args)?;
+    }
+    if args.paths.is_empty() {
+        args.paths.push(opts.project_path()?);
+    }
+    for path in &args.paths {
+        if crate::find_manifest(path).is_none() {
+            return Err(UsedumpError::ManifestNotFound { path: path.clone() });
+        }
+        warn_without_lockfile(opts, args, path);
+    }
+
+    let mut maps = BTreeMap::new();
+    for path in &args.paths {
+        let mut map = dump_options(opts, args, path).run_exports()?;
+        if args.group_by == GroupBy::Package {
+            map = map.aggregate_by_package();
+        }
+        maps.insert(output::normalize_path(path), map);
+    }
+    warn_without_sysroot(opts, args, &maps);
+
+    let mut buf = vec![];
+    dump_writer(opts, args).write(&maps, None, &mut buf)?;
+    write_output(opts, &buf)?;
+
+    let mut code = 0;
+    for map in maps.values() {
+        code = code.max(report(
+            opts,
+            args,
+            map.diagnostics(),
+            map.unresolved_count(),
+            map.files_with_syntax_errors().count(),
+            map.deprecated_imports().len(),
+        ));
+    }
+    Ok(code)
+}
+
+/// The writer of the dump `args` asks for.
+fn dump_writer(opts: &Opts, args: &DumpArgs) -> DumpWriter {
+    let graph = FileGraph::new()
+        .cluster_by_package(args.cluster_by_package)
+        .include_external(args.include_external);
+    DumpWriter::new(opts.format)
+        .pretty(opts.pretty)
+        .envelope(!args.no_envelope)
+        .summary(args.summary)
+        .top(args.top)
+        .by_origin(args.group_by == GroupBy::Origin)
+        .legacy_names(args.legacy_names)
+        .csv_delimiter(args.csv_delimiter.unwrap_or(b','))
+        .graph(graph)
+        .markdown(MarkdownOptions::new().collapsible(args.collapsible))
+}
+
+/// The project of the commands which take a single one, the current one when none is
+/// given.
+fn single_project(opts: &Opts, command: &str, args: &mut DumpArgs) -> crate::Result<PathBuf> {
+    if args.paths.len() > 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`{}` takes a single project", command),
+        )
+        .into());
+    }
+    match args.paths.pop() {
+        Some(path) => Ok(path),
+        None => Ok(opts.project_path()?),
+    }
+}
+
+/// Rejects `--no-resolve` for the commands which need the analysis.
+fn check_resolved(command: &str, args: &DumpArgs) -> io::Result<()> {
+    if args.no_resolve {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`{}` cannot be combined with `--no-resolve`", command),
+        ));
+    }
+    Ok(())
+}
+
+/// Rejects a repeated `--target` for the commands which analyze a single target.
+fn check_single_target(command: &str, args: &DumpArgs) -> io::Result<()> {
+    if args.target.len() > 1 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`{}` takes a single `--target`", command),
+        ));
+    }
+    Ok(())
+}
+
+/// Rejects the options the formats of `DumpWriter::renders_single_maps` cannot render, as
+/// they cover the files of a single project, neither summarized nor keyed by origin.
+fn check_single_map_args(opts: &Opts, args: &DumpArgs) -> io::Result<()> {
+    if opts.format == OutputFormat::Sqlite
+        && opts.output.is_none()
+        && atty::is(atty::Stream::Stdout)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`--format sqlite` writes a database, pass `--output FILE`",
+        ));
+    }
+    let conflict = if args.paths.len() > 1 {
+        Some("several projects")
+    } else if args.summary {
+        Some("`--summary`")
+    } else if args.group_by == GroupBy::Origin {
+        Some("`--group-by origin`")
+    } else if args.stats == Some(Some(StatsFormat::Json)) {
+        Some("`--stats=json`")
+    } else {
+        None
+    };
+    match conflict {
+        Some(conflict) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`--format {}` does not support {}", opts.format, conflict),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Runs the watch subcommand until interrupted, emitting the dump again on every change.
+///
+/// With `--format ndjson` only the files resolved again are emitted, with empty items
+/// for those which no longer have any.
+fn watch(opts: &Opts, mut args: WatchArgs) -> crate::Result<i32> {
+    let args = &mut args.dump;
+    let path = single_project(opts, "watch", args)?;
+    check_resolved("watch", args)?;
+    check_single_target("watch", args)?;
+    if crate::find_manifest(&path).is_none() {
+        return Err(UsedumpError::ManifestNotFound { path });
+    }
+    if DumpWriter::new(opts.format).renders_single_maps() {
+        check_single_map_args(opts, args)?;
+    }
+
+    let stop = cancel_on_ctrl_c()?;
+
+    #[derive(Serialize)]
+    struct Entry<'a> {
+        file: &'a str,
+        items: &'a UsedItemMap,
+    }
+
+    let project = output::normalize_path(&path);
+    let empty = UsedItemMap::default();
+    dump_options(opts, args, &path).watch(&stop, |map, changed| {
+        let mut buf = vec![];
+        if opts.format == OutputFormat::Ndjson {
+            let files: Vec<&str> = match changed {
+                Some(changed) => changed.iter().map(String::as_str).collect(),
+                None => map.files().map(|(file, _)| file).collect(),
+            };
+            for file in files {
+                let items = map.get(file).unwrap_or(&empty);
+                output::write_value(&Entry { file, items }, opts.format, false, &mut buf)?;
+            }
+        } else {
+            let mut maps = BTreeMap::new();
+            let map = if args.group_by == GroupBy::Package {
+                map.aggregate_by_package()
+            } else {
+                map.clone()
+            };
+            maps.insert(project.clone(), map);
+            dump_writer(opts, args).write(&maps, None, &mut buf)?;
+        }
+        write_output(opts, &buf)?;
+        for diagnostic in map.diagnostics() {
+            if !opts.quiet {
+                eprintln!(
+                    "warning: failed to analyze `{}`: {}",
+                    diagnostic.file(),
+                    diagnostic.error()
+                );
+            }
+        }
+        Ok(())
+    })?;
+    Ok(0)
+}
+
+/// Runs the serve subcommand until stdin ends, or forever with `--socket`.
+fn serve(opts: &Opts, mut args: ServeArgs) -> crate::Result<i32> {
+    let path = single_project(opts, "serve", &mut args.dump)?;
+    check_resolved("serve", &args.dump)?;
+    check_single_target("serve", &args.dump)?;
+    let mut server = Server::new(dump_options(opts, &args.dump, &path))?;
+    match &args.socket {
+        Some(socket) => serve_socket(opts, &mut server, socket)?,
+        None => {
+            let stdin = io::stdin();
+            server.serve(stdin.lock(), io::stdout())?;
+        }
+    }
+    Ok(0)
+}
+
+#[cfg(unix)]
+fn serve_socket(opts: &Opts, server: &mut Server, socket: &Path) -> io::Result<()> {
+    let listener = std::os::unix::net::UnixListener::bind(socket)?;
+    if !opts.quiet {
+        eprintln!("listening on `{}`", socket.display());
+    }
+    for stream in listener.incoming() {
+        let stream = stream?;
+        // A client going away must not take the server down with it.
+        if let Err(e) = server.serve(io::BufReader::new(stream.try_clone()?), stream) {
+            if !opts.quiet {
+                eprintln!("warning: client disconnected: {}", e);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve_socket(_: &Opts, _: &mut Server, _: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Other,
+        "`--socket` is only supported on unix",
+    ))
+}
+
+/// Streams the dump with one line per file as soon as its workspace member is analyzed,
+/// never holding the whole `CrateMap`.
+fn dump_ndjson(opts: &Opts, args: &DumpArgs) -> crate::Result<i32> {
+    if args.summary || args.legacy_names || args.stats.is_some() || args.group_by != GroupBy::File {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "`--format ndjson` cannot be combined with `--summary`, `--legacy-names`, \
+             `--stats` or `--group-by`",
+        )
+        .into());
+    }
+
+    #[derive(Serialize)]
+    struct ProjectEntry<'a> {
+        project: &'a str,
+        #[serde(flatten)]
+        entry: &'a FileEntry,
+    }
+
+    // The lines go to a temporary file renamed into place at the end, so that a failed
+    // dump leaves the previous output alone.
+    let mut file = opts
+        .output
+        .as_ref()
+        .map(PathBuf::as_path)
+        .map(output::AtomicFile::create)
+        .transpose()?;
+    let mut w: Box<dyn Write> = match &mut file {
+        Some(file) => Box::new(io::BufWriter::new(file)),
+        None => Box::new(io::stdout()),
+    };
+    let cancel = cancel_on_ctrl_c()?;
+    let mut code = 0;
+    let mut partial = false;
+    for path in &args.paths {
+        if cancel.load(Ordering::SeqCst) {
+            partial = true;
+            break;
+        }
+        let project = output::normalize_path(path);
+        let mut unresolved_count = 0;
+        let mut syntax_error_files = 0;
+        let mut deprecated_count = 0;
+        let mut written = Ok(());
+        let options = dump_options(opts, args, path).cancel_on(Arc::clone(&cancel));
+        let dump = options.run_streaming(|entry| {
+            unresolved_count += entry.items().unresolved().len();
+            if entry.items().has_syntax_errors() {
+                syntax_error_files += 1;
+            }
+            deprecated_count += entry
+                .items()
+                .iter()
+                .filter(|item| item.deprecated())
+                .count();
+            if written.is_err() {
+                return;
+            }
+            // Several projects would share keys, so their lines say which one they are of.
+            written = if args.paths.len() == 1 {
+                output::write_value(&entry, OutputFormat::Ndjson, false, &mut w)
+            } else {
+                let entry = ProjectEntry {
+                    project: &project,
+                    entry: &entry,
+                };
+                output::write_value(&entry, OutputFormat::Ndjson, false, &mut w)
+            }
+            .and_then(|()| w.flush());
+        })?;
+        written?;
+        code = code.max(report(
+            opts,
+            args,
+            dump.diagnostics(),
+            unresolved_count,
+            syntax_error_files,
+            deprecated_count,
+        ));
+        if dump.is_partial() {
+            partial = true;
+            // A member lost to a panic only costs its files, which are diagnostics.
+            if cancel.load(Ordering::SeqCst) {
+                break;
+            }
+        }
+    }
+    if partial {
+        // The stream may well be complete otherwise, so its end is marked instead.
+        w.write_all(b"{\"partial\":true}\n")?;
+    }
+    w.flush()?;
+    drop(w);
+    if let Some(file) = file {
+        file.commit()?;
+    }
+    if let Some(path) = &opts.output {
+        if !opts.quiet {
+            eprintln!("wrote `{}`", path.display());
+        }
+    }
+    if cancel.load(Ordering::SeqCst) {
+        return Ok(cancelled(opts));
+    }
+    Ok(code)
+}
+
+/// Warns about the files which could not be analyzed, the imports which could not be
+/// resolved, the files with syntax errors and the imports of deprecated items, returning
+/// the exit code they call for.
+fn report(
+    opts: &Opts,
+    args: &DumpArgs,
+    diagnostics: &[Diagnostic],
+    unresolved_count: usize,
+    syntax_error_files: usize,
+    deprecated_count: usize,
+) -> i32 {
+    let mut code = 0;
+    for diagnostic in diagnostics {
+        if !opts.quiet {
+            eprintln!(
+                "warning: failed to analyze `{}`: {}",
+                diagnostic.file(),
+                diagnostic.error()
+            );
+        }
+        if args.strict {
+            code = cli::ANALYSIS_ERROR;
+        }
+    }
+    if args.fail_on_unresolved && unresolved_count > 0 {
+        if !opts.quiet {
+            eprintln!("error: {} imports could not be resolved", unresolved_count);
+        }
+        code = code.max(cli::FINDINGS);
+    }
+    if args.deny_syntax_errors && syntax_error_files > 0 {
+        if !opts.quiet {
+            eprintln!("error: {} files have syntax errors", syntax_error_files);
+        }
+        code = cli::ANALYSIS_ERROR;
+    }
+    if args.deny_deprecated && deprecated_count > 0 {
+        if !opts.quiet {
+            eprintln!(
+                "error: {} imports are of deprecated items, see `usedump deprecated`",
+                deprecated_count
+            );
+        }
+        code = code.max(cli::FINDINGS);
+    }
+    code
+}
+
+fn dump_options(opts: &Opts, args: &DumpArgs, path: &Path) -> DumpOptions {
+    let mut origin_filters = vec![];
+    if let Some(only) = args.only {
+        origin_filters.push(OriginFilter::Only(only));
+    }
+    if args.exclude_std {
+        origin_filters.push(OriginFilter::Exclude(OriginKind::Std));
+    }
+    let mut options = DumpOptions::new(path)
+        .packages(&args.package)
+        .targets(&args.targets())
+        .include(&args.include)
+        .exclude(&args.exclude)
+        .kinds(&args.kind)
+        .cfg_filter(args.cfg_filter.clone())
+        .origin_filters(&origin_filters)
+        .features(&args.features)
+        .all_features(args.all_features)
+        .no_default_features(args.no_default_features)
+        .include_derives(args.include_derives)
+        .only_reexports(args.only_reexports)
+        .no_reexports(args.no_reexports)
+        .exclude_tests(args.exclude_tests)
+        .only_tests(args.only_tests)
+        .include_empty(args.include_empty)
+        // SQLite databases have the line of every import.
+        .locations(args.locations || opts.format == OutputFormat::Sqlite)
+        // The file graph follows the definitions.
+        .definitions(args.definitions || opts.format == OutputFormat::Dot)
+        .flat_paths(args.flat_paths)
+        .absolute_paths(args.absolute_paths)
+        .keys(args.keys)
+        .generated(args.generated)
+        .resolve_reexports(args.resolve_reexports)
+        .jobs(args.jobs)
+        .file_timeout(args.file_timeout.map(Duration::from_secs))
+        .cache(!args.no_cache)
+        .no_resolve(args.no_resolve)
+        .no_sysroot(args.no_sysroot)
+        .offline(args.offline)
+        .locked(args.locked)
+        .target_triples(&args.target);
+    if let Some(dir) = &args.cache_dir {
+        options = options.cache_dir(dir);
+    }
+    if let Some(sysroot) = &args.sysroot {
+        options = options.sysroot(sysroot);
+    }
+    let progress = !opts.quiet
+        && (args.progress
+            || atty::is(atty::Stream::Stderr)
+                && (opts.output.is_some() || !atty::is(atty::Stream::Stdout)));
+    let verbose = !opts.quiet && args.verbose;
+    if progress || verbose {
+        options = options.progress(progress_printer(progress, verbose));
+    }
+    options
+}
+
+/// Prints the phases of a dump, and either the number of files analyzed so far or every
+/// file as it is done.
+fn progress_printer(progress: bool, verbose: bool) -> impl Fn(Progress<'_>) + Send + Sync {
+    // Overwriting the count in place only works on a terminal.
+    let in_place = progress && !verbose && atty::is(atty::Stream::Stderr);
+    move |event| match event {
+        Progress::LoadingWorkspace => eprintln!("loading workspace"),
+        Progress::Analyzing { total } => eprintln!("analyzing {} files", total),
+        Progress::Analyzed { file, done, total } => {
+            if verbose {
+                eprintln!("analyzed `{}` ({}/{})", file, done, total);
+            } else if in_place {
+                eprint!("\ranalyzing {}/{} files", done, total);
+                if done == total {
+                    eprintln!();
+                }
+            } else if progress {
+                eprintln!("analyzing {}/{} files", done, total);
+            }
+        }
+    }
+}
+
+/// Runs the unused subcommand, exiting with 1 when unused imports were found.
+fn unused(opts: &Opts, args: UnusedArgs) -> crate::Result<i32> {
+    let path = match args.path {
+        Some(path) => path,
+        None => opts.project_path()?,
+    };
+    let mut unused = crate::find_unused_imports_with_locations(&path)?;
+    if let Some(baseline_path) = &args.write_baseline {
+        Baseline::from_unused_imports(&unused).write(baseline_path)?;
+        return Ok(0);
+    }
+    if let Some(baseline_path) = &args.baseline {
+        let stale = Baseline::read(baseline_path)?.filter(&mut unused);
+        if !stale.is_empty() && !opts.quiet {
+            eprint!(
+                "warning: these entries of `{}` no longer occur and can be removed:\n{}",
+                baseline_path.display(),
+                stale
+            );
+        }
+    }
+
+    let mut buf = vec![];
+    match opts.format {
+        OutputFormat::Sarif => {
+            write_sarif(&SarifLog::from_unused_imports(&unused), opts, &mut buf)?
+        }
+        format => output::write_value(&unused.names(), format, opts.pretty, &mut buf)?,
+    }
+    write_output(opts, &buf)?;
+
+    Ok(if unused.is_empty() { 0 } else { cli::FINDINGS })
+}
+
+/// Runs the unused-deps subcommand, exiting with 1 when unused dependencies were found.
+fn unused_deps(opts: &Opts, args: UnusedDepsArgs) -> crate::Result<i32> {
+    let path = match args.path {
+        Some(path) => path,
+        None => opts.project_path()?,
+    };
+    let unused = crate::find_unused_dependencies(&path, &args.ignore)?;
+
+    let mut buf = vec![];
+    output::write_value(&unused, opts.format, opts.pretty, &mut buf)?;
+    write_output(opts, &buf)?;
+
+    Ok(if unused.is_empty() { 0 } else { cli::FINDINGS })
+}
+
+/// Runs the deps-audit subcommand, exiting with 1 when misplaced dependencies were found.
+fn deps_audit(opts: &Opts, args: DepsAuditArgs) -> crate::Result<i32> {
+    let path = match args.path {
+        Some(path) => path,
+        None => opts.project_path()?,
+    };
+    let audit = crate::audit_dependencies(&path)?;
+
+    let mut buf = vec![];
+    match opts.format {
+        OutputFormat::Text => write!(buf, "{}", audit)?,
+        OutputFormat::Sarif => write_sarif(&SarifLog::from_deps_audit(&audit), opts, &mut buf)?,
+        format => output::write_value(&audit, format, opts.pretty, &mut buf)?,
+    }
+    write_output(opts, &buf)?;
+
+    Ok(if audit.is_empty() { 0 } else { cli::FINDINGS })
+}
+
+/// Runs the dead-exports subcommand, exiting with 1 with `--deny-dead-exports` when dead
+/// exports were found.
+fn dead_exports(opts: &Opts, args: DeadExportsArgs) -> crate::Result<i32> {
+    let path = match args.path {
+        Some(path) => path,
+        None => opts.project_path()?,
+    };
+    let dead = crate::find_dead_exports(&path)?;
+
+    let mut buf = vec![];
+    match opts.format {
+        OutputFormat::Text => write!(buf, "{}", dead)?,
+        OutputFormat::Sarif => write_sarif(&SarifLog::from_dead_exports(&dead), opts, &mut buf)?,
+        format => output::write_value(&dead, format, opts.pretty, &mut buf)?,
+    }
+    write_output(opts, &buf)?;
+
+    Ok(if args.deny_dead_exports && !dead.is_empty() {
+        cli::FINDINGS
+    } else {
+        0
+    })
+}
+
+/// Runs the graph subcommand, warning about cycles between members.
+fn graph(opts: &Opts, mut args: GraphArgs) -> crate::Result<i32> {
+    let path = single_project(opts, "graph", &mut args.dump)?;
+    let map = dump_options(opts, &args.dump, &path).run()?;
+    let graph = MemberGraph::new(&map, args.workspace_only);
+    if !opts.quiet {
+        for cycle in graph.cycles() {
+            eprintln!("warning: members {} use each other", cycle.join(", "));
+        }
+    }
+
+    let mut buf = vec![];
+    match opts.format {
+        OutputFormat::Text => write!(buf, "{}", graph)?,
+        OutputFormat::Dot => buf.extend_from_slice(graph.to_dot().as_bytes()),
+        format => output::write_value(&graph, format, opts.pretty, &mut buf)?,
+    }
+    write_output(opts, &buf)?;
+
+    Ok(0)
+}
+
+/// Runs the crates subcommand.
+fn crates(opts: &Opts, mut args: CratesArgs) -> crate::Result<i32> {
+    let path = single_project(opts, "crates", &mut args.dump)?;
+    let usages = dump_options(opts, &args.dump, &path).run()?.crate_usages();
+
+    let mut buf = vec![];
+    match opts.format {
+        OutputFormat::Text => write!(buf, "{}", usages)?,
+        format => output::write_value(&usages, format, opts.pretty, &mut buf)?,
+    }
+    write_output(opts, &buf)?;
+
+    Ok(0)
+}
+
+/// Runs the common-imports subcommand.
+fn common_imports(opts: &Opts, mut args: CommonImportsArgs) -> crate::Result<i32> {
+    let path = single_project(opts, "common-imports", &mut args.dump)?;
+    let common = dump_options(opts, &args.dump, &path)
+        .run()?
+        .common_imports(args.min_files);
+
+    let mut buf = vec![];
+    match opts.format {
+        OutputFormat::Text => write!(buf, "{}", common)?,
+        format => output::write_value(&common, format, opts.pretty, &mut buf)?,
+    }
+    write_output(opts, &buf)?;
+
+    Ok(0)
+}
+
+/// Runs the coverage subcommand.
+fn coverage(opts: &Opts, args: CoverageArgs) -> crate::Result<i32> {
+    let path = match args.path {
+        Some(path) => path,
+        None => opts.project_path()?,
+    };
+    let coverage = crate::find_api_coverage(&path, &args.package)?;
+    if !opts.quiet && !coverage.diagnostics().is_empty() {
+        eprintln!(
+            "warning: failed to list the exports of {} files of {}",
+            coverage.diagnostics().len(),
+            coverage.package()
+        );
+    }
+
+    let mut buf = vec![];
+    match opts.format {
+        OutputFormat::Text => write!(buf, "{}", coverage)?,
+        format => output::write_value(&coverage, format, opts.pretty, &mut buf)?,
+    }
+    write_output(opts, &buf)?;
+
+    Ok(0)
+}
+
+/// Runs the globs subcommand.
+fn globs(opts: &Opts, args: GlobsArgs) -> crate::Result<i32> {
+    let path = match args.path {
+        Some(path) => path,
+        None => opts.project_path()?,
+    };
+    let globs = crate::find_glob_imports(&path, &args.allow)?;
+
+    let mut buf = vec![];
+    match opts.format {
+        OutputFormat::Text => write!(buf, "{}", globs)?,
+        format => output::write_value(&globs, format, opts.pretty, &mut buf)?,
+    }
+    write_output(opts, &buf)?;
+
+    Ok(if args.deny && globs.denied().next().is_some() {
+        cli::FINDINGS
+    } else {
+        0
+    })
+}
+
+/// Runs the consolidate subcommand.
+fn consolidate(opts: &Opts, args: ConsolidateArgs) -> crate::Result<i32> {
+    let path = match args.path {
+        Some(path) => path,
+        None => opts.project_path()?,
+    };
+    let consolidations = crate::suggest_consolidations(&path)?;
+
+    let mut buf = vec![];
+    match opts.format {
+        OutputFormat::Text => write!(buf, "{}", consolidations)?,
+        format => output::write_value(&consolidations, format, opts.pretty, &mut buf)?,
+    }
+    write_output(opts, &buf)?;
+
+    Ok(0)
+}
+
+/// Runs the lints subcommand.
+fn lints(opts: &Opts, mut args: LintsArgs) -> crate::Result<i32> {
+    let path = single_project(opts, "lints", &mut args.dump)?;
+    let map = dump_options(opts, &args.dump, &path)
+        .locations(true)
+        .run()?;
+    let lints = map.lints(args.shadowing);
+
+    let mut buf = vec![];
+    match opts.format {
+        OutputFormat::Text => write!(buf, "{}", lints)?,
+        format => output::write_value(&lints, format, opts.pretty, &mut buf)?,
+    }
+    write_output(opts, &buf)?;
+
+    Ok(if args.deny && !lints.is_empty() {
+        cli::FINDINGS
+    } else {
+        0
+    })
+}
+
+/// Runs the deprecated subcommand.
+fn deprecated(opts: &Opts, mut args: DeprecatedArgs) -> crate::Result<i32> {
+    let path = single_project(opts, "deprecated", &mut args.dump)?;
+    let map = dump_options(opts, &args.dump, &path)
+        .locations(true)
+        .run()?;
+    let deprecated = map.deprecated_imports();
+
+    let mut buf = vec![];
+    match opts.format {
+        OutputFormat::Text => write!(buf, "{}", deprecated)?,
+        format => output::write_value(&deprecated, format, opts.pretty, &mut buf)?,
+    }
+    write_output(opts, &buf)?;
+
+    Ok(if args.deny && !deprecated.is_empty() {
+        cli::FINDINGS
+    } else {
+        0
+    })
+}
+
+/// Runs the file subcommand.
+fn file(opts: &Opts, args: FileArgs) -> crate::Result<i32> {
+    let text = if args.file == Path::new("-") {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        text
+    } else {
+        fs::read_to_string(&args.file)?
+    };
+    let used_item_map = crate::list_used_items_in_source(&text);
+
+    let mut buf = vec![];
+    output::write_value(&used_item_map, opts.format, opts.pretty, &mut buf)?;
+    write_output(opts, &buf)?;
+
+    Ok(0)
+}
+
+/// Runs the remote subcommand.
+fn remote(opts: &Opts, args: RemoteArgs) -> crate::Result<i32> {
+    // Removed once dumped.
+    let fetched = match (&args.spec, &args.git) {
+        (_, Some(url)) => Some(crate::clone_git(
+            url,
+            args.rev.as_ref().map(String::as_str),
+        )?),
+        (Some(spec), None) => Some(crate::fetch_crate(spec, args.offline)?),
+        (None, None) => None,
+    };
+    let mut dir = match (&fetched, args.path) {
+        (Some(fetched), _) => fetched.path().to_owned(),
+        (None, Some(path)) => path,
+        (None, None) => opts.project_path()?,
+    };
+    if let Some(fetched) = &fetched {
+        if fetched.has_build_script() && !opts.quiet {
+            eprintln!(
+                "warning: `{}` has a build script, the code it generates is not analyzed",
+                fetched.name()
+            );
+        }
+    }
+    if let Some(subdir) = &args.subdir {
+        dir = dir.join(subdir);
+        if !dir.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("there is no directory `{}`", subdir.display()),
+            )
+            .into());
+        }
+    }
+    let map = DumpOptions::new(&dir).offline(args.offline).run()?;
+
+    let mut buf = vec![];
+    output::write_crate_map(&map.without_root(), opts.format, opts.pretty, &mut buf)?;
+    write_output(opts, &buf)?;
+
+    Ok(0)
+}
+
+/// Runs the merge subcommand.
+fn merge(opts: &Opts, args: MergeArgs) -> crate::Result<i32> {
+    let mut merged = CrateMap::default();
+    for path in &args.dumps {
+        let map = output::read_crate_map(path)?;
+        if args.prefer_last {
+            merged.merge_preferring_last(map);
+        } else {
+            merged.merge(map)?;
+        }
+    }
+
+    let mut buf = vec![];
+    if args.no_envelope {
+        output::write_value(&merged, opts.format, opts.pretty, &mut buf)?;
+    } else {
+        let root = merged.root().map(str::to_owned);
+        let mut envelope = Envelope::new(root, merged.without_root());
+        envelope.sysroot = merged.sysroot().map(str::to_owned);
+        output::write_value(&envelope, opts.format, opts.pretty, &mut buf)?;
+    }
+    write_output(opts, &buf)?;
+
+    Ok(0)
+}
+
+/// Runs the schema subcommand.
+fn schema(opts: &Opts) -> crate::Result<i32> {
+    let mut buf = vec![];
+    output::write_value(&crate::dump_schema(), opts.format, opts.pretty, &mut buf)?;
+    write_output(opts, &buf)?;
+
+    Ok(0)
+}
+
+/// Runs the diff subcommand, exiting with 1 when the dumps differ.
+fn diff(opts: &Opts, args: DiffArgs) -> crate::Result<i32> {
+    let old = output::read_crate_map(&args.old)?;
+    let new = output::read_crate_map(&args.new)?;
+    let diff = CrateMapDiff::new(&old, &new);
+
+    let mut buf = vec![];
+    match opts.format {
+        OutputFormat::Text => write!(buf, "{}", diff)?,
+        format => output::write_value(&diff, format, opts.pretty, &mut buf)?,
+    }
+    write_output(opts, &buf)?;
+
+    Ok(if diff.is_empty() { 0 } else { cli::FINDINGS })
+}
+
+/// Runs the who-uses subcommand, exiting with 1 when no file imports a matching item.
+fn who_uses(opts: &Opts, mut args: WhoUsesArgs) -> crate::Result<i32> {
+    let query = if args.regex {
+        ItemQuery::regex(&args.query)?
+    } else {
+        ItemQuery::exact(args.query)
+    };
+    let path = single_project(opts, "who-uses", &mut args.dump)?;
+    let map = dump_options(opts, &args.dump, &path).run()?;
+    let importers = map.importers(&query);
+
+    let mut buf = vec![];
+    output::write_value(&importers, opts.format, opts.pretty, &mut buf)?;
+    write_output(opts, &buf)?;
+
+    Ok(if importers.is_empty() {
+        cli::FINDINGS
+    } else {
+        0
+    })
+}
+
+/// Writes `log` as JSON, which SARIF is.
+fn write_sarif(log: &SarifLog, opts: &Opts, buf: &mut Vec<u8>) -> io::Result<()> {
+    output::write_value(log, OutputFormat::Json, opts.pretty, buf)
+}
+
+fn write_output(opts: &Opts, output: &[u8]) -> io::Result<()> {
+    match &opts.output {
+        Some(path) => {
+            output::write_atomically(path, output)?;
+            if !opts.quiet {
+                eprintln!("wrote `{}`", path.display());
+            }
+        }
+        None => io::stdout().write_all(output)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, io,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    output,
+    unused::{UnusedImport, UnusedImports},
+    used_item::UsedItemKind,
+};
+
+/// The unused imports recorded by `usedump unused --write-baseline`, which later runs with
+/// `--baseline` do not fail on.
+///
+/// Findings are keyed by file, name and kind rather than by location, so that editing a file
+/// does not bring back the findings it already had.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Baseline {
+    /// The findings of every file, by key.
+    unused_imports: BTreeMap<String, BTreeSet<BaselineEntry>>,
+}
+
+/// A finding recorded in a `Baseline`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    name: String,
+    kind: UsedItemKind,
+}
+
+impl Baseline {
+    /// The baseline recording every finding of `unused`.
+    pub fn from_unused_imports(unused: &UnusedImports) -> Self {
+        let unused_imports = unused
+            .files()
+            .iter()
+            .map(|(file, imports)| {
+                let entries = imports.iter().map(BaselineEntry::of).collect();
+                (file.clone(), entries)
+            })
+            .collect();
+        Baseline { unused_imports }
+    }
+
+    /// Reads a baseline written by `write`.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        output::read_value(path)
+    }
+
+    /// Writes the baseline as pretty-printed JSON, which keeps it diffable under version
+    /// control.
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut contents = serde_json::to_vec_pretty(self)?;
+        contents.push(b'\n');
+        output::write_atomically(path, &contents)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.unused_imports.is_empty()
+    }
+
+    /// The recorded findings, by key.
+    pub fn unused_imports(&self) -> &BTreeMap<String, BTreeSet<BaselineEntry>> {
+        &self.unused_imports
+    }
+
+    /// Removes the findings of `unused` which the baseline records, and returns the recorded
+    /// findings which no longer occur, so that they can be dropped from the baseline.
+    pub fn filter(&self, unused: &mut UnusedImports) -> Baseline {
+        let mut stale = self.clone();
+        unused.retain(|file, import| {
+            let entry = BaselineEntry::of(import);
+            match self.unused_imports.get(file) {
+                Some(entries) if entries.contains(&entry) => {
+                    if let Some(entries) = stale.unused_imports.get_mut(file) {
+                        entries.remove(&entry);
+                    }
+                    false
+                }
+                _ => true,
+            }
+        });
+        stale
+            .unused_imports
+            .retain(|_, entries| !entries.is_empty());
+        stale
+    }
+}
+
+impl fmt::Display for Baseline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (file, entries) in &self.unused_imports {
+            for entry in entries {
+                writeln!(f, "{}: {} {}", file, entry.kind.name(), entry.name)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BaselineEntry {
+    fn of(import: &UnusedImport) -> Self {
+        BaselineEntry {
+            name: import.name().to_owned(),
+            kind: import.kind(),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> UsedItemKind {
+        self.kind
+    }
+}
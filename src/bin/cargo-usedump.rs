@@ -0,0 +1,41 @@
+//! `cargo usedump`, which cargo runs as `cargo-usedump usedump ARGS...`. It takes the same
+//! command line as `usedump`, but analyzes the package `cargo locate-project` finds when no
+//! project is given.
+
+use std::{
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use serde::Deserialize;
+use usedump::app;
+
+fn main() {
+    let mut args: Vec<OsString> = env::args_os().collect();
+    if args.get(1).map_or(false, |arg| arg == "usedump") {
+        args.remove(1);
+    }
+    app::run(args, package_dir())
+}
+
+/// The directory of the package being built when run by cargo, otherwise of the package the
+/// current directory belongs to. `CARGO` is the cargo which ran the subcommand, if any.
+fn package_dir() -> Option<PathBuf> {
+    #[derive(Deserialize)]
+    struct ProjectLocation {
+        root: PathBuf,
+    }
+
+    if let Some(dir) = env::var_os("CARGO_MANIFEST_DIR") {
+        return Some(dir.into());
+    }
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let output = Command::new(cargo).arg("locate-project").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let location: ProjectLocation = serde_json::from_slice(&output.stdout).ok()?;
+    location.root.parent().map(Path::to_path_buf)
+}
@@ -0,0 +1,126 @@
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    fs,
+    hash::Hasher,
+    io,
+    path::{Path, PathBuf},
+};
+
+use cargo_metadata::Metadata;
+use serde::{Deserialize, Serialize};
+
+use crate::{output, used_item::UsedItemMap};
+
+/// The used items of every file resolved by previous runs, keyed by the path of the file
+/// relative to the workspace root.
+///
+/// An entry is only reused while the file has the same contents, and so do the member files
+/// its crate can see: those of its own package and of the members it depends on. As a
+/// file's imports may resolve differently when its dependencies change, the whole cache is
+/// dropped when `Cargo.lock` or the version of usedump changes, when derives are listed or
+/// not unlike before, and when the workspace is loaded with other features or another
+/// sysroot.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Cache {
+    #[serde(skip)]
+    path: PathBuf,
+    fingerprint: String,
+    files: BTreeMap<String, CachedFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedFile {
+    hash: String,
+    /// The `CacheEntry::dependencies` the items were resolved with.
+    #[serde(default)]
+    dependencies: String,
+    items: UsedItemMap,
+}
+
+/// What the used items of a file are cached under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CacheEntry {
+    /// The path of the file relative to the workspace root.
+    pub(crate) path: String,
+    /// The `content_hash` of the file.
+    pub(crate) hash: String,
+    /// The hash of the member files the crate of the file can see, see
+    /// `Workspace::dependency_hashes`.
+    pub(crate) dependencies: String,
+}
+
+impl Cache {
+    const FILE_NAME: &'static str = "cache.json";
+
+    /// The directory the cache of `metadata`'s workspace is kept in by default.
+    pub(crate) fn default_dir(metadata: &Metadata) -> PathBuf {
+        metadata.target_directory.join(".usedump-cache")
+    }
+
+    /// Loads the cache kept in `dir`, starting over when it is missing, unreadable or stale,
+    /// including when it was written with derives listed and `include_derives` is unset,
+    /// or the other way around, and when it was written under another `key`, which
+    /// describes how the workspace was loaded.
+    pub(crate) fn load(dir: &Path, metadata: &Metadata, include_derives: bool, key: &str) -> Self {
+        let path = dir.join(Cache::FILE_NAME);
+        let fingerprint = fingerprint(metadata, include_derives, key);
+        let cache: Option<Cache> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok());
+        match cache {
+            Some(cache) if cache.fingerprint == fingerprint => Cache { path, ..cache },
+            _ => Cache {
+                path,
+                fingerprint,
+                files: BTreeMap::new(),
+            },
+        }
+    }
+
+    /// The used items of the file of `entry`, if it is cached with the same contents and
+    /// the same dependencies.
+    pub(crate) fn get(&self, entry: &CacheEntry) -> Option<&UsedItemMap> {
+        self.files
+            .get(&entry.path)
+            .filter(|file| file.hash == entry.hash && file.dependencies == entry.dependencies)
+            .map(|file| &file.items)
+    }
+
+    pub(crate) fn insert(&mut self, entry: CacheEntry, items: UsedItemMap) {
+        let file = CachedFile {
+            hash: entry.hash,
+            dependencies: entry.dependencies,
+            items,
+        };
+        self.files.insert(entry.path, file);
+    }
+
+    pub(crate) fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents = serde_json::to_vec(self)?;
+        output::write_atomically(&self.path, &contents)
+    }
+}
+
+/// A hash of the contents of a file, only meant to be compared by the same build of
+/// usedump.
+pub(crate) fn content_hash(text: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(text.as_bytes());
+    format!("{:016x}", hasher.finish())
+}
+
+/// Identifies what the cached entries depend on besides the member files.
+fn fingerprint(metadata: &Metadata, include_derives: bool, key: &str) -> String {
+    let lockfile =
+        fs::read_to_string(metadata.workspace_root.join("Cargo.lock")).unwrap_or_default();
+    format!(
+        "{}-{}-{}{}",
+        env!("CARGO_PKG_VERSION"),
+        content_hash(&lockfile),
+        content_hash(key),
+        if include_derives { "-derives" } else { "" }
+    )
+}
@@ -0,0 +1,150 @@
+use std::collections::HashSet;
+
+/// Evaluates a normalized cfg predicate such as `all(feature = "a", not(test))` against
+/// the enabled features.
+///
+/// Only `feature` options are known, so the result is `None` when it depends on anything
+/// else, e.g. `unix` or `test`.
+pub(crate) fn evaluate(predicate: &str, features: &HashSet<String>) -> Option<bool> {
+    evaluate_with(predicate, Some(features), None)
+}
+
+/// Whether the normalized cfg predicate only holds when compiling tests, e.g. `test` or
+/// `all(unix, test)`, whatever else is enabled. `any(test, feature = "a")` does not.
+pub(crate) fn requires_test(predicate: &str) -> bool {
+    evaluate_with(predicate, None, Some(false)) == Some(false)
+}
+
+/// Evaluates `predicate` with the enabled `features` and the value of `test`, each unknown
+/// when `None`.
+fn evaluate_with(
+    predicate: &str,
+    features: Option<&HashSet<String>>,
+    test: Option<bool>,
+) -> Option<bool> {
+    let tokens = tokenize(predicate);
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+        features,
+        test,
+    };
+    let result = parser.predicate()?;
+    if parser.pos == tokens.len() {
+        result
+    } else {
+        None
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Eq,
+    Comma,
+    LParen,
+    RParen,
+}
+
+fn tokenize(s: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '=' => tokens.push(Token::Eq),
+            ',' => tokens.push(Token::Comma),
+            '(' => tokens.push(Token::LParen),
+            ')' => tokens.push(Token::RParen),
+            '"' => {
+                let mut value = String::new();
+                while let Some(c) = chars.next() {
+                    if c == '"' {
+                        break;
+                    }
+                    value.push(c);
+                }
+                tokens.push(Token::Str(value));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut ident = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_alphanumeric() || c == '_' {
+                        ident.push(c);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Ident(ident));
+            }
+        }
+    }
+    tokens
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    features: Option<&'a HashSet<String>>,
+    test: Option<bool>,
+}
+
+impl Parser<'_> {
+    /// Parses one predicate. The outer `Option` is `None` on syntax errors, the inner one
+    /// when the value is unknown.
+    fn predicate(&mut self) -> Option<Option<bool>> {
+        let name = match self.tokens.get(self.pos)? {
+            Token::Ident(name) => name.clone(),
+            _ => return None,
+        };
+        self.pos += 1;
+        match self.tokens.get(self.pos) {
+            Some(Token::Eq) => {
+                self.pos += 1;
+                let value = match self.tokens.get(self.pos)? {
+                    Token::Str(value) => value,
+                    _ => return None,
+                };
+                self.pos += 1;
+                Some(if name == "feature" {
+                    self.features.map(|features| features.contains(value))
+                } else {
+                    None
+                })
+            }
+            Some(Token::LParen) => {
+                self.pos += 1;
+                let mut args = vec![];
+                while self.tokens.get(self.pos) != Some(&Token::RParen) {
+                    args.push(self.predicate()?);
+                    if self.tokens.get(self.pos) == Some(&Token::Comma) {
+                        self.pos += 1;
+                    }
+                }
+                self.pos += 1;
+                match name.as_str() {
+                    "all" => Some(combine(&args, false)),
+                    "any" => Some(combine(&args, true)),
+                    "not" if args.len() == 1 => Some(args[0].map(|value| !value)),
+                    _ => None,
+                }
+            }
+            _ if name == "test" => Some(self.test),
+            _ => Some(None),
+        }
+    }
+}
+
+/// Combines the arguments of `all` (`short_circuit` false) or `any` (true): a single
+/// argument equal to `short_circuit` decides, otherwise any unknown one makes it unknown.
+fn combine(args: &[Option<bool>], short_circuit: bool) -> Option<bool> {
+    if args.contains(&Some(short_circuit)) {
+        Some(short_circuit)
+    } else if args.contains(&None) {
+        None
+    } else {
+        Some(!short_circuit)
+    }
+}
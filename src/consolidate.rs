@@ -0,0 +1,362 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::Path,
+};
+
+use ra_syntax::{
+    ast::{AttrsOwner, UseItem, UseTree},
+    AstNode, SourceFile, SyntaxKind,
+};
+use serde::Serialize;
+
+use crate::{
+    error::Result,
+    options::{AnalysisConfig, LoadConfig},
+    used_item::{analyze_files, load_workspace, Diagnostic},
+};
+
+/// The edits merging the use items of the workspace files which share a path prefix, see
+/// `suggest_consolidations`.
+#[derive(Debug, Default, Serialize)]
+pub struct Consolidations {
+    patches: Vec<Patch>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// Replaces the text of `file` within `range` with `replacement`. Removed use items have an
+/// empty replacement, their range covering their whole lines.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Patch {
+    /// The key of the file, e.g. `foo/src/bar.rs`.
+    file: String,
+    /// Byte offsets of the start and the end.
+    range: [u32; 2],
+    replacement: String,
+    /// The 1-based line of the start.
+    #[serde(skip)]
+    line: u32,
+}
+
+impl Consolidations {
+    pub fn is_empty(&self) -> bool {
+        self.patches.is_empty()
+    }
+
+    /// The patches of every file, ordered by file then by range. The ranges of a file never
+    /// overlap.
+    pub fn patches(&self) -> &[Patch] {
+        &self.patches
+    }
+
+    /// The files of the workspace which could not be read.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+}
+
+impl Patch {
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    pub fn range(&self) -> [u32; 2] {
+        self.range
+    }
+
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+}
+
+impl fmt::Display for Consolidations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for patch in &self.patches {
+            if patch.replacement.is_empty() {
+                writeln!(f, "{}:{}: remove, merged above", patch.file, patch.line)?;
+            } else {
+                writeln!(
+                    f,
+                    "{}:{}: replace with `{}`",
+                    patch.file, patch.line, patch.replacement
+                )?;
+            }
+        }
+        for diagnostic in &self.diagnostics {
+            writeln!(
+                f,
+                "failed to read {}: {}",
+                diagnostic.file(),
+                diagnostic.error()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Suggests how to merge the use items of every file of the workspace containing `dir`.
+///
+/// The use items of a module which start with the same path segment, e.g. `use std::fmt;`
+/// and `use std::fmt::Debug;`, are merged into the first of them, here into
+/// `use std::fmt::{self, Debug};`, and the others are removed. Aliases are kept and imports
+/// repeated verbatim are dropped. Use items are only merged when their attributes, e.g.
+/// `#[cfg(test)]`, and their visibility are the same, and never when they contain comments,
+/// which could not be placed.
+pub fn suggest_consolidations<Q: AsRef<Path>>(dir: Q) -> Result<Consolidations> {
+    let mut loaded = load_workspace(dir.as_ref(), &LoadConfig::default())?;
+    let analyzed = analyze_files(
+        &mut loaded.host,
+        &loaded.source_map,
+        Some(&loaded.metadata),
+        &AnalysisConfig::default(),
+        |analysis, _, file_id| Ok(consolidate_text(&analysis.file_text(file_id)?)),
+    )?;
+
+    let mut patches = vec![];
+    for (file, file_patches) in analyzed.values {
+        for mut patch in file_patches {
+            patch.file = file.clone();
+            patches.push(patch);
+        }
+    }
+    Ok(Consolidations {
+        patches,
+        diagnostics: analyzed.diagnostics,
+    })
+}
+
+/// The patches merging the use items of the source `text`, see `suggest_consolidations`,
+/// without their file.
+pub(crate) fn consolidate_text(text: &str) -> Vec<Patch> {
+    let source_file = SourceFile::parse(text).tree();
+
+    // The use items which can be merged together: those of the same module, with the same
+    // attributes and visibility, starting with the same segment.
+    let mut groups: BTreeMap<(u32, String, String, String), Vec<(UseItem, Vec<Leaf>)>> =
+        BTreeMap::new();
+    for use_item in source_file.syntax().descendants().filter_map(UseItem::cast) {
+        let has_comments = use_item
+            .syntax()
+            .descendants_with_tokens()
+            .any(|element| element.kind() == SyntaxKind::COMMENT);
+        if has_comments {
+            continue;
+        }
+        let mut leaves = vec![];
+        let flattened = use_item
+            .use_tree()
+            .map_or(false, |use_tree| flatten(&use_tree, &[], &mut leaves));
+        let first = match leaves.first() {
+            Some(leaf) if flattened => leaf.segments[0].clone(),
+            _ => continue,
+        };
+        if leaves.iter().any(|leaf| leaf.segments[0] != first) {
+            continue;
+        }
+        let scope = match use_item.syntax().parent() {
+            Some(parent) => u32::from(parent.text_range().start()),
+            None => continue,
+        };
+        let attrs: Vec<String> = use_item
+            .attrs()
+            .map(|attr| without_whitespace(&attr.syntax().text().to_string()))
+            .collect();
+        let visibility = use_item
+            .syntax()
+            .children()
+            .find(|child| child.kind() == SyntaxKind::VISIBILITY)
+            .map(|visibility| without_whitespace(&visibility.text().to_string()))
+            .unwrap_or_default();
+        groups
+            .entry((scope, attrs.join(" "), visibility, first))
+            .or_default()
+            .push((use_item, leaves));
+    }
+
+    let mut patches = vec![];
+    for ((_, _, _, first), use_items) in groups {
+        if use_items.len() < 2 {
+            continue;
+        }
+        let mut root = Node::default();
+        for leaf in use_items.iter().flat_map(|(_, leaves)| leaves) {
+            root.insert(leaf);
+        }
+        let merged = match root.children.get(&first) {
+            Some(node) => join(node.render(&first)),
+            None => continue,
+        };
+
+        let (head, _) = &use_items[0];
+        let start = head.syntax().text_range().start();
+        // The attributes and the visibility of the first use item are kept as written.
+        let prefix_end = head
+            .syntax()
+            .children_with_tokens()
+            .find(|element| element.kind() == SyntaxKind::USE_KW)
+            .map_or(start, |element| element.text_range().start());
+        let prefix = &text[start.to_usize()..prefix_end.to_usize()];
+        patches.push(patch(
+            text,
+            [
+                u32::from(start),
+                u32::from(head.syntax().text_range().end()),
+            ],
+            format!("{}use {};", prefix, merged),
+        ));
+        for (use_item, _) in &use_items[1..] {
+            let range = use_item.syntax().text_range();
+            let range = whole_lines(text, range.start().to_usize(), range.end().to_usize());
+            patches.push(patch(text, range, String::new()));
+        }
+    }
+    patches.sort_by_key(|patch| patch.range);
+    patches
+}
+
+/// An import of a use tree: the path of the imported item, a module for globs.
+struct Leaf {
+    segments: Vec<String>,
+    alias: Option<String>,
+    glob: bool,
+}
+
+/// Collects the imports of `use_tree` under `prefix` into `leaves`, returning `false` for
+/// the trees which are not merged: empty ones and those starting with `::`.
+fn flatten(use_tree: &UseTree, prefix: &[String], leaves: &mut Vec<Leaf>) -> bool {
+    let mut segments = prefix.to_vec();
+    if let Some(path) = use_tree.path() {
+        let text = without_whitespace(&path.syntax().text().to_string());
+        if text.starts_with("::") {
+            return false;
+        }
+        segments.extend(text.split("::").map(str::to_owned));
+    }
+
+    if use_tree.has_star() {
+        if segments.is_empty() {
+            return false;
+        }
+        leaves.push(Leaf {
+            segments,
+            alias: None,
+            glob: true,
+        });
+        return true;
+    }
+    if let Some(use_tree_list) = use_tree.use_tree_list() {
+        return use_tree_list
+            .use_trees()
+            .all(|use_tree| flatten(&use_tree, &segments, leaves));
+    }
+
+    // `foo::{self}` imports `foo`.
+    if segments.last().map(String::as_str) == Some("self") {
+        segments.pop();
+    }
+    if segments.is_empty() {
+        return false;
+    }
+    let alias = use_tree.alias().map(|alias| {
+        let text = alias.syntax().text().to_string();
+        text.trim_start_matches("as").trim().to_owned()
+    });
+    leaves.push(Leaf {
+        segments,
+        alias,
+        glob: false,
+    });
+    true
+}
+
+/// The imports under a path, merged.
+#[derive(Default)]
+struct Node {
+    /// How the path itself is imported, `None` standing for no alias.
+    imports: BTreeSet<Option<String>>,
+    glob: bool,
+    children: BTreeMap<String, Node>,
+}
+
+impl Node {
+    fn insert(&mut self, leaf: &Leaf) {
+        let mut node = self;
+        for segment in &leaf.segments {
+            node = node.children.entry(segment.clone()).or_default();
+        }
+        if leaf.glob {
+            node.glob = true;
+        } else {
+            node.imports.insert(leaf.alias.clone());
+        }
+    }
+
+    /// The use trees importing what the node holds under the segment `name`, e.g.
+    /// `fmt::{self, Debug}`, several when an item which is not a module is imported under
+    /// several names, e.g. `Circle` and `Circle as Round`, as only modules and enums can be
+    /// imported with `self`.
+    fn render(&self, name: &str) -> Vec<String> {
+        let import = |alias: &Option<String>| match alias {
+            Some(alias) => format!("{} as {}", name, alias),
+            None => name.to_owned(),
+        };
+        if self.children.is_empty() && !self.glob {
+            return self.imports.iter().map(import).collect();
+        }
+
+        let mut parts: Vec<String> = self
+            .imports
+            .iter()
+            .map(|alias| match alias {
+                Some(alias) => format!("self as {}", alias),
+                None => "self".to_owned(),
+            })
+            .collect();
+        parts.extend(
+            self.children
+                .iter()
+                .flat_map(|(segment, child)| child.render(segment)),
+        );
+        if self.glob {
+            parts.push("*".to_owned());
+        }
+        vec![format!("{}::{}", name, join(parts))]
+    }
+}
+
+/// `parts` as a single use tree, braced when there are several.
+fn join(parts: Vec<String>) -> String {
+    if parts.len() == 1 {
+        parts.into_iter().next().unwrap()
+    } else {
+        format!("{{{}}}", parts.join(", "))
+    }
+}
+
+fn patch(text: &str, range: [u32; 2], replacement: String) -> Patch {
+    let line = text[..range[0] as usize].matches('\n').count() as u32 + 1;
+    Patch {
+        file: String::new(),
+        range,
+        replacement,
+        line,
+    }
+}
+
+/// Extends `start..end` to the whole lines it spans when nothing else is on them, so that
+/// removing it leaves no blank line.
+fn whole_lines(text: &str, start: usize, end: usize) -> [u32; 2] {
+    let line_start = text[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = text[end..].find('\n').map_or(text.len(), |i| end + i + 1);
+    let alone = text[line_start..start].trim().is_empty() && text[end..line_end].trim().is_empty();
+    if alone {
+        [line_start as u32, line_end as u32]
+    } else {
+        [start as u32, end as u32]
+    }
+}
+
+fn without_whitespace(text: &str) -> String {
+    text.split_whitespace().collect()
+}
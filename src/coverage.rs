@@ -0,0 +1,178 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt,
+    path::Path,
+};
+
+use serde::Serialize;
+
+use crate::{
+    error::Result,
+    options::{AnalysisConfig, LoadConfig},
+    used_item::{
+        analyze_files, for_each_dependency_export, load_workspace, used_items_in_file, Diagnostic,
+    },
+};
+
+/// How much of the public API of a dependency the workspace imports, see
+/// `find_api_coverage`.
+#[derive(Debug, Default, Serialize)]
+pub struct ApiCoverage {
+    package: String,
+    /// The number of public items imported, over all modules.
+    imported: usize,
+    /// The number of public items, over all modules.
+    total: usize,
+    modules: BTreeMap<String, ModuleCoverage>,
+    /// The paths of the imported public items, as the workspace names them.
+    imported_items: BTreeSet<String>,
+    /// The files of the dependency whose exports could not be listed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// How many of the public items of a module are imported.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ModuleCoverage {
+    imported: usize,
+    total: usize,
+}
+
+impl ApiCoverage {
+    pub fn package(&self) -> &str {
+        &self.package
+    }
+
+    pub fn imported(&self) -> usize {
+        self.imported
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// The coverage of every module of the dependency exporting something, by path, e.g.
+    /// `serde::de`.
+    pub fn modules(&self) -> &BTreeMap<String, ModuleCoverage> {
+        &self.modules
+    }
+
+    pub fn imported_items(&self) -> &BTreeSet<String> {
+        &self.imported_items
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+}
+
+impl ModuleCoverage {
+    pub fn imported(&self) -> usize {
+        self.imported
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+}
+
+impl fmt::Display for ApiCoverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{}: {} of {} public items imported with `use`",
+            self.package, self.imported, self.total
+        )?;
+        for (module, coverage) in &self.modules {
+            writeln!(f, "  {}: {}/{}", module, coverage.imported, coverage.total)?;
+        }
+        if !self.imported_items.is_empty() {
+            writeln!(f, "imported:")?;
+            for item in &self.imported_items {
+                writeln!(f, "  {}", item)?;
+            }
+        }
+        for diagnostic in &self.diagnostics {
+            writeln!(
+                f,
+                "failed to list the exports of {}: {}",
+                diagnostic.file(),
+                diagnostic.error()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists the public items of the dependency `package` of the workspace containing `dir`,
+/// as `DumpOptions::run_exports` lists those of the members, and how many of them the
+/// workspace imports, by module.
+///
+/// An item counts as imported when a use item of a member names it by one of the paths it
+/// is exported under, e.g. `serde::Deserialize` for the re-export of
+/// `serde::de::Deserialize`, each path counting as an item of its own module. Items which
+/// the workspace only refers to with a qualified path such as `serde::de::Error::custom`
+/// are not counted. The files of the dependency are walked one at a time, only keeping
+/// the paths of its public items.
+pub fn find_api_coverage<Q: AsRef<Path>>(dir: Q, package: &str) -> Result<ApiCoverage> {
+    let mut loaded = load_workspace(dir.as_ref(), &LoadConfig::default())?;
+    let imports = analyze_files(
+        &mut loaded.host,
+        &loaded.source_map,
+        Some(&loaded.metadata),
+        // The derive macros of the dependency are among its public items.
+        &AnalysisConfig {
+            include_derives: true,
+            ..AnalysisConfig::default()
+        },
+        used_items_in_file,
+    )?;
+    // The paths the workspace names items by, re-exports included.
+    let imported: HashSet<&str> = imports
+        .values
+        .values()
+        .flat_map(|used_item_map| used_item_map.iter())
+        .map(|item| item.surface_path().unwrap_or_else(|| item.path()))
+        .collect();
+
+    let mut coverage = ApiCoverage {
+        package: package.to_owned(),
+        ..ApiCoverage::default()
+    };
+    let mut seen: HashSet<String> = HashSet::new();
+    for_each_dependency_export(
+        &loaded.host,
+        &loaded.source_map,
+        Some(&loaded.metadata),
+        package,
+        |file, exports| {
+            let exports = match exports {
+                Ok(exports) => exports,
+                Err(error) => {
+                    coverage.diagnostics.push(Diagnostic::new(file, error));
+                    return;
+                }
+            };
+            for item in exports.iter() {
+                let path = item.reexported_as().unwrap_or_else(|| item.path());
+                if path.starts_with("crate::") || !seen.insert(path.to_owned()) {
+                    continue;
+                }
+                let module = match path.rfind("::") {
+                    Some(i) => &path[..i],
+                    None => continue,
+                };
+                let module_coverage = coverage.modules.entry(module.to_owned()).or_default();
+                module_coverage.total += 1;
+                coverage.total += 1;
+                if imported.contains(path) {
+                    module_coverage.imported += 1;
+                    coverage.imported += 1;
+                    coverage.imported_items.insert(path.to_owned());
+                }
+            }
+        },
+    )?;
+    coverage.diagnostics.sort_by(|a, b| a.file().cmp(b.file()));
+    Ok(coverage)
+}
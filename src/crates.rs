@@ -0,0 +1,165 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use serde::{ser::SerializeMap, Serialize, Serializer};
+
+use crate::{origin::OriginKind, used_item::CrateMap};
+
+/// How much of a crate the workspace uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CrateUsage {
+    files: usize,
+    items: usize,
+}
+
+impl CrateUsage {
+    /// The number of files importing at least one item of the crate.
+    pub fn files(&self) -> usize {
+        self.files
+    }
+
+    /// The number of distinct items of the crate imported in the workspace.
+    pub fn items(&self) -> usize {
+        self.items
+    }
+}
+
+/// The usage of every crate items are imported from, see `CrateMap::crate_usages`. The
+/// external crates, the standard library and the workspace members are kept apart, each
+/// sorted by the number of files, most used first.
+#[derive(Debug, Clone, Default)]
+pub struct CrateUsages {
+    external: Vec<(String, CrateUsage)>,
+    std: Vec<(String, CrateUsage)>,
+    local: Vec<(String, CrateUsage)>,
+}
+
+impl CrateUsages {
+    pub(crate) fn new(crate_map: &CrateMap) -> Self {
+        let mut usages: BTreeMap<(OriginKind, &str), (BTreeSet<&str>, BTreeSet<&str>)> =
+            BTreeMap::new();
+        for (file, _, item) in crate_map.iter() {
+            let origin = match item.origin() {
+                Some(origin) => origin,
+                None => continue,
+            };
+            let (files, items) = usages
+                .entry((origin.kind(), origin.crate_name()))
+                .or_default();
+            files.insert(file);
+            items.insert(item.path());
+        }
+
+        let mut crate_usages = CrateUsages::default();
+        for ((kind, name), (files, items)) in usages {
+            let usage = CrateUsage {
+                files: files.len(),
+                items: items.len(),
+            };
+            let section = match kind {
+                OriginKind::External => &mut crate_usages.external,
+                OriginKind::Std => &mut crate_usages.std,
+                OriginKind::Local => &mut crate_usages.local,
+            };
+            section.push((name.to_owned(), usage));
+        }
+        for section in crate_usages.sections_mut() {
+            section.sort_by(|a, b| b.1.files.cmp(&a.1.files).then_with(|| a.0.cmp(&b.0)));
+        }
+        crate_usages
+    }
+
+    /// The external crates, by name.
+    pub fn external(&self) -> &[(String, CrateUsage)] {
+        &self.external
+    }
+
+    /// The crates of the standard library, e.g. `std` and `core`.
+    pub fn std(&self) -> &[(String, CrateUsage)] {
+        &self.std
+    }
+
+    /// The workspace members, including a crate importing its own items.
+    pub fn local(&self) -> &[(String, CrateUsage)] {
+        &self.local
+    }
+
+    /// The usage of the crate `name`, whatever its origin.
+    pub fn get(&self, name: &str) -> Option<CrateUsage> {
+        self.sections()
+            .flat_map(|(_, section)| section)
+            .find(|(crate_name, _)| crate_name == name)
+            .map(|(_, usage)| *usage)
+    }
+
+    fn sections(&self) -> impl Iterator<Item = (&'static str, &[(String, CrateUsage)])> {
+        vec![
+            ("external", &self.external[..]),
+            ("std", &self.std[..]),
+            ("local", &self.local[..]),
+        ]
+        .into_iter()
+    }
+
+    fn sections_mut(&mut self) -> impl Iterator<Item = &mut Vec<(String, CrateUsage)>> {
+        vec![&mut self.external, &mut self.std, &mut self.local].into_iter()
+    }
+}
+
+/// Serialized as an object per origin, each mapping the crates to their usage in order.
+impl Serialize for CrateUsages {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(3))?;
+        for (name, section) in self.sections() {
+            map.serialize_entry(name, &Section(section))?;
+        }
+        map.end()
+    }
+}
+
+struct Section<'a>(&'a [(String, CrateUsage)]);
+
+impl Serialize for Section<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (name, usage) in self.0 {
+            map.serialize_entry(name, usage)?;
+        }
+        map.end()
+    }
+}
+
+impl fmt::Display for CrateUsages {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .sections()
+            .flat_map(|(_, section)| section)
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or(0);
+        let mut first = true;
+        for (title, section) in self.sections() {
+            if section.is_empty() {
+                continue;
+            }
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+            writeln!(f, "{}:", title)?;
+            for (name, usage) in section {
+                writeln!(
+                    f,
+                    "  {:width$}  {} files, {} items",
+                    name,
+                    usage.files,
+                    usage.items,
+                    width = width
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    path::Path,
+};
+
+use serde::Serialize;
+
+use crate::{
+    error::Result,
+    file_paths::FilePaths,
+    options::{AnalysisConfig, LoadConfig},
+    used_item::{
+        analyze_files, exports_in_file, load_workspace, qualified_references, used_items_in_file,
+        Location, UsedItem, UsedItemKind,
+    },
+};
+
+/// The exports of the workspace members which no other member uses, keyed by member.
+#[derive(Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct DeadExports {
+    members: BTreeMap<String, Vec<DeadExport>>,
+    #[serde(skip)]
+    paths: FilePaths,
+}
+
+/// An item a member exports which no other member uses.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeadExport {
+    kind: UsedItemKind,
+    /// The path of the definition, e.g. `foo::bar::Baz`.
+    path: String,
+    /// The path a `pub use` re-exports the item under, for re-exports.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reexported_as: Option<String>,
+    /// The key of the file exporting the item, e.g. `foo/src/bar.rs`.
+    file: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+}
+
+impl DeadExports {
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    pub fn members(&self) -> &BTreeMap<String, Vec<DeadExport>> {
+        &self.members
+    }
+
+    pub fn paths(&self) -> &FilePaths {
+        &self.paths
+    }
+}
+
+impl DeadExport {
+    pub fn kind(&self) -> UsedItemKind {
+        self.kind
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn reexported_as(&self) -> Option<&str> {
+        self.reexported_as.as_ref().map(String::as_str)
+    }
+
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    /// Where the item is named in `file`.
+    pub fn location(&self) -> Option<Location> {
+        self.location
+    }
+}
+
+impl fmt::Display for DeadExports {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (member, exports) in &self.members {
+            for export in exports {
+                write!(f, "{}: {}", member, export.file)?;
+                if let Some(location) = export.location {
+                    write!(f, ":{}:{}", location.line, location.col)?;
+                }
+                write!(f, ": {} `{}`", export.kind.name(), export.path)?;
+                if let Some(reexported_as) = &export.reexported_as {
+                    write!(f, ", re-exported as `{}`,", reexported_as)?;
+                }
+                writeln!(f, " is not used by any other member")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds the items exported by every workspace member, as listed by
+/// `DumpOptions::run_exports`, which no other member uses, keyed by member.
+///
+/// An item counts as used when another member imports it or something under it, e.g. an
+/// enum through one of its variants, whether directly or through a `pub use` of any
+/// member, or when another member refers to it with a qualified path such as
+/// `foo::Bar::new()`. Exported macros also count as used by the members importing their
+/// crate with `#[macro_use]`. The crates which no crate depends on, such as binaries, are
+/// not reported.
+pub fn find_dead_exports<Q: AsRef<Path>>(dir: Q) -> Result<DeadExports> {
+    let mut loaded = load_workspace(dir.as_ref(), &LoadConfig::default())?;
+    // The derive macros a crate exports are used through `#[derive(...)]`.
+    let config = AnalysisConfig {
+        include_derives: true,
+        ..AnalysisConfig::default()
+    };
+    let analyzed = analyze_files(
+        &mut loaded.host,
+        &loaded.source_map,
+        Some(&loaded.metadata),
+        &config,
+        |analysis, workspace, file_id| {
+            let imports = used_items_in_file(analysis, workspace, file_id)?;
+            let exports = exports_in_file(analysis, workspace, file_id)?;
+            Ok((imports, exports))
+        },
+    )?;
+
+    // Definition path -> members using it.
+    let mut users: HashMap<String, HashSet<&str>> = HashMap::new();
+    for (file, (imports, _)) in &analyzed.values {
+        let package = match analyzed.packages.get(file) {
+            Some(package) => package.as_str(),
+            None => continue,
+        };
+        for item in imports.iter() {
+            for path in path_prefixes(item.path()) {
+                users.entry(path.to_owned()).or_default().insert(package);
+            }
+            if item.macro_use() {
+                let macros = format!("{}::{}", item.path(), MACROS);
+                users.entry(macros).or_default().insert(package);
+            }
+        }
+    }
+
+    let mut candidates: Vec<(&str, &str, &UsedItem)> = vec![];
+    for (file, (_, exports)) in &analyzed.values {
+        let package = match analyzed.packages.get(file) {
+            Some(package) => package.as_str(),
+            None => continue,
+        };
+        for item in exports.iter() {
+            if item.path().starts_with("crate::") || is_used_by_others(&users, package, item) {
+                continue;
+            }
+            candidates.push((file.as_str(), package, item));
+        }
+    }
+
+    // Only the names of the candidates are resolved, as there are far fewer of them than
+    // qualified paths.
+    let names: HashSet<String> = candidates
+        .iter()
+        .map(|(_, _, item)| item.local_name().to_owned())
+        .collect();
+    if !names.is_empty() {
+        let referenced = analyze_files(
+            &mut loaded.host,
+            &loaded.source_map,
+            Some(&loaded.metadata),
+            &config,
+            |analysis, workspace, file_id| {
+                qualified_references(analysis, workspace, file_id, &names)
+            },
+        )?;
+        for (file, paths) in &referenced.values {
+            if let Some(package) = analyzed.packages.get(file) {
+                for path in paths {
+                    users.entry(path.clone()).or_default().insert(package);
+                }
+            }
+        }
+    }
+
+    let mut dead = DeadExports::default();
+    for (file, package, item) in candidates {
+        if is_used_by_others(&users, package, item) {
+            continue;
+        }
+        dead.members
+            .entry(package.to_owned())
+            .or_default()
+            .push(DeadExport {
+                kind: item.kind(),
+                path: item.path().to_owned(),
+                reexported_as: item.reexported_as().map(str::to_owned),
+                file: file.to_owned(),
+                location: item.location(),
+            });
+        if let Some(path) = analyzed.definitions.get(file) {
+            dead.paths.insert(file.to_owned(), path.clone());
+        }
+    }
+    Ok(dead)
+}
+
+/// The pseudo item under a crate path which `#[macro_use] extern crate` uses.
+const MACROS: &str = "<macros>";
+
+fn is_used_by_others(
+    users: &HashMap<String, HashSet<&str>>,
+    package: &str,
+    item: &UsedItem,
+) -> bool {
+    let used_by_others = |path: &str| {
+        users
+            .get(path)
+            .map_or(false, |users| users.iter().any(|user| *user != package))
+    };
+    if used_by_others(item.path()) {
+        return true;
+    }
+    if item.kind() != UsedItemKind::Macro {
+        return false;
+    }
+    let crate_path = item.path().split("::").next().unwrap_or_default();
+    used_by_others(&format!("{}::{}", crate_path, MACROS))
+}
+
+/// `a::b::c` and the paths it is under, `a::b` and `a`.
+fn path_prefixes(path: &str) -> impl Iterator<Item = &str> {
+    let ends = path
+        .match_indices("::")
+        .map(|(i, _)| i)
+        .chain(Some(path.len()));
+    ends.map(move |end| &path[..end])
+}
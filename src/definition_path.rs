@@ -0,0 +1,611 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use ra_db::{CrateGraph, CrateId, FileId};
+use ra_ide::{Analysis, FilePosition, NavigationTarget};
+use ra_syntax::{
+    algo::find_covering_element,
+    ast::{self, AttrsOwner, NameOwner},
+    AstNode, NodeOrToken, SyntaxKind, SyntaxNode, TextRange,
+};
+
+use crate::used_item::{cfgs_of, MacroKind};
+
+/// Computes fully qualified paths such as `serde::ser::Serialize` for navigation targets.
+///
+/// Module trees are built lazily, one crate at a time, by following every `mod foo;`
+/// declaration from the crate root with `goto_definition`. Modules are named after their
+/// declaration, so `#[path = "platform/linux.rs"] mod imp;` is `imp` wherever its file is.
+#[derive(Default)]
+pub struct DefinitionPaths {
+    crate_names: HashMap<CrateId, String>,
+    module_trees: Mutex<HashMap<FileId, ModuleTree>>,
+    /// The absolute path of every file given by `with_files`, and the other way around,
+    /// to find the files of `#[path]` attributes `goto_definition` does not follow.
+    paths: HashMap<FileId, PathBuf>,
+    files: HashMap<PathBuf, FileId>,
+    /// The number of `goto_definition` calls made to build the module trees.
+    goto_definition_calls: AtomicUsize,
+}
+
+type ModuleTree = HashMap<FileId, ModulePath>;
+
+/// Where a file is in the module tree of its crate.
+#[derive(Clone)]
+struct ModulePath {
+    /// The modules leading to the file from the crate root, e.g. `["ser", "impls"]`.
+    segments: Vec<String>,
+    /// Whether every `mod` declaration leading to the file is `pub`.
+    public: bool,
+    /// The predicates of the `#[cfg(...)]` attributes of the `mod` declarations leading to
+    /// the file and of the items enclosing them, outermost first.
+    cfgs: Vec<String>,
+}
+
+impl DefinitionPaths {
+    pub fn new(crate_graph: &CrateGraph) -> Self {
+        let mut crate_names = HashMap::new();
+        for crate_id in crate_graph.iter() {
+            for dependency in crate_graph.dependencies(crate_id) {
+                crate_names
+                    .entry(dependency.crate_id())
+                    .or_insert_with(|| dependency.name.to_string());
+            }
+        }
+
+        DefinitionPaths {
+            crate_names,
+            module_trees: Default::default(),
+            paths: HashMap::new(),
+            files: HashMap::new(),
+            goto_definition_calls: AtomicUsize::new(0),
+        }
+    }
+
+    /// Knows the files by their absolute path, so that the modules declared with a
+    /// `#[path]` attribute are found even when `goto_definition` misses them.
+    pub fn with_files<I: IntoIterator<Item = (PathBuf, FileId)>>(mut self, files: I) -> Self {
+        for (path, file_id) in files {
+            let path = normalize_lexically(&path);
+            self.paths.insert(file_id, path.clone());
+            self.files.insert(path, file_id);
+        }
+        self
+    }
+
+    pub fn path_of(&self, analysis: &Analysis, navigation_target: &NavigationTarget) -> String {
+        let file_id = navigation_target.file_id();
+        let mut segments = self.file_segments(analysis, file_id);
+
+        if navigation_target.kind() == SyntaxKind::SOURCE_FILE {
+            return segments.join("::");
+        }
+
+        if let Ok(source_file) = analysis.parse(file_id) {
+            let node = covering_node(source_file.syntax(), navigation_target.full_range());
+            segments.extend(inline_modules(&node));
+        }
+        if let Some(enum_name) = parent_enum_name(analysis, navigation_target) {
+            segments.push(enum_name);
+        }
+        segments.push(raw_identifier(navigation_target.name()));
+
+        segments.join("::")
+    }
+
+    /// The fully qualified path of the item named `name` which `node` defines in `file_id`,
+    /// as `path_of` would compute it for the same item.
+    pub fn path_of_node(
+        &self,
+        analysis: &Analysis,
+        file_id: FileId,
+        node: &SyntaxNode,
+        name: &str,
+    ) -> String {
+        let mut segments = self.file_segments(analysis, file_id);
+        segments.extend(inline_modules(node));
+        segments.push(raw_identifier(name));
+        segments.join("::")
+    }
+
+    /// `path`, as written in the use tree `node` of `file_id`, from the root of its crate,
+    /// e.g. `foo::bar::Baz` for `super::Baz` in `foo/src/bar/qux.rs`. Paths which do not
+    /// start with `crate`, `self` or `super` are taken to start with a crate name.
+    pub fn absolute_path(
+        &self,
+        analysis: &Analysis,
+        file_id: FileId,
+        node: &SyntaxNode,
+        path: &str,
+    ) -> String {
+        let path: String = path.split_whitespace().collect();
+        let path = path.trim_start_matches("::");
+        let mut written = path.split("::").peekable();
+        let mut segments = self.file_segments(analysis, file_id);
+        match written.peek() {
+            Some(&"crate") => {
+                segments.truncate(1);
+                written.next();
+            }
+            Some(&"self") | Some(&"super") => {
+                segments.extend(inline_modules(node));
+                while let Some(&segment) = written.peek() {
+                    match segment {
+                        "self" => {}
+                        "super" => {
+                            segments.pop();
+                        }
+                        _ => break,
+                    }
+                    written.next();
+                }
+            }
+            _ => return path.to_owned(),
+        }
+        segments.extend(written.map(str::to_owned));
+        segments.join("::")
+    }
+
+    /// The path of the root of the crate `file_id` belongs to, e.g. `serde`.
+    pub fn crate_path(&self, analysis: &Analysis, file_id: FileId) -> String {
+        self.file_segments(analysis, file_id)
+            .into_iter()
+            .next()
+            .unwrap_or_default()
+    }
+
+    /// Whether the items of a file can be reached from other crates, that is whether
+    /// every `mod` declaration leading to it from its crate root is `pub`. Files which no
+    /// crate root reaches are not.
+    pub fn is_public_file(&self, analysis: &Analysis, file_id: FileId) -> bool {
+        let crate_id = match analysis.crate_for(file_id) {
+            Ok(crates) => crates.into_iter().next(),
+            Err(_) => None,
+        };
+        crate_id
+            .and_then(|crate_id| self.module_in_crate(analysis, crate_id, file_id))
+            .map_or(false, |module| module.public)
+    }
+
+    /// The cfgs a file is compiled under, those of the `mod` declarations leading to it
+    /// from the root of its first crate, e.g. `["test"]` for the file of
+    /// `#[cfg(test)] mod tests;`. Files which no crate root reaches have none.
+    pub fn file_cfgs(&self, analysis: &Analysis, file_id: FileId) -> Vec<String> {
+        let crate_id = match analysis.crate_for(file_id) {
+            Ok(crates) => crates.into_iter().next(),
+            Err(_) => None,
+        };
+        crate_id
+            .and_then(|crate_id| self.module_in_crate(analysis, crate_id, file_id))
+            .map_or_else(Vec::new, |module| module.cfgs)
+    }
+
+    /// The crate name and the modules leading to `file_id`, e.g. `["serde", "ser"]`.
+    fn file_segments(&self, analysis: &Analysis, file_id: FileId) -> Vec<String> {
+        let mut segments = vec![];
+        let crate_id = analysis
+            .crate_for(file_id)
+            .ok()
+            .and_then(|crates| crates.into_iter().next());
+        if let Some(crate_id) = crate_id {
+            segments.push(self.crate_name(crate_id).to_owned());
+            if let Some(modules) = self.module_path_in_crate(analysis, crate_id, file_id) {
+                segments.extend(modules);
+            }
+        }
+        segments
+    }
+
+    /// The module path of a file, e.g. `serde::ser`, in the first crate it belongs to.
+    /// Crates no other crate depends on, such as binaries, are named `crate_name`.
+    ///
+    /// Returns `None` for files which no crate root reaches.
+    pub fn module_path_of_file(
+        &self,
+        analysis: &Analysis,
+        file_id: FileId,
+        crate_name: &str,
+    ) -> Option<String> {
+        let crate_id = analysis.crate_for(file_id).ok()?.into_iter().next()?;
+        let mut segments = vec![self
+            .crate_names
+            .get(&crate_id)
+            .map_or(crate_name, String::as_str)
+            .to_owned()];
+        segments.extend(self.module_path_in_crate(analysis, crate_id, file_id)?);
+        Some(segments.join("::"))
+    }
+
+    pub fn goto_definition_calls(&self) -> usize {
+        self.goto_definition_calls.load(Ordering::Relaxed)
+    }
+
+    fn crate_name(&self, crate_id: CrateId) -> &str {
+        self.crate_names
+            .get(&crate_id)
+            .map(String::as_str)
+            .unwrap_or("crate")
+    }
+
+    fn module_path_in_crate(
+        &self,
+        analysis: &Analysis,
+        crate_id: CrateId,
+        file_id: FileId,
+    ) -> Option<Vec<String>> {
+        self.module_in_crate(analysis, crate_id, file_id)
+            .map(|module| module.segments)
+    }
+
+    fn module_in_crate(
+        &self,
+        analysis: &Analysis,
+        crate_id: CrateId,
+        file_id: FileId,
+    ) -> Option<ModulePath> {
+        let crate_root = analysis.crate_root(crate_id).ok()?;
+        let mut module_trees = self.module_trees.lock().unwrap();
+        let module_tree = module_trees.entry(crate_root).or_insert_with(|| {
+            let mut module_tree = ModuleTree::new();
+            let root = ModulePath {
+                segments: vec![],
+                public: true,
+                cfgs: vec![],
+            };
+            self.walk_module_tree(analysis, crate_root, root, &mut module_tree);
+            module_tree
+        });
+        module_tree.get(&file_id).cloned()
+    }
+
+    fn walk_module_tree(
+        &self,
+        analysis: &Analysis,
+        file_id: FileId,
+        module_path: ModulePath,
+        module_tree: &mut ModuleTree,
+    ) {
+        let source_file = match analysis.parse(file_id) {
+            Ok(s) => s,
+            Err(_) => return,
+        };
+        module_tree.insert(file_id, module_path.clone());
+
+        for module in source_file
+            .syntax()
+            .descendants()
+            .filter_map(ast::Module::cast)
+        {
+            if module.item_list().is_some() {
+                continue;
+            }
+            let name = match module.name() {
+                Some(name) => name,
+                None => continue,
+            };
+            let file_position = FilePosition {
+                file_id,
+                offset: name.syntax().text_range().start(),
+            };
+            self.goto_definition_calls.fetch_add(1, Ordering::Relaxed);
+            let child = match analysis.goto_definition(file_position) {
+                Ok(Some(range_info)) => range_info
+                    .info
+                    .into_iter()
+                    .find(|target| target.kind() == SyntaxKind::SOURCE_FILE)
+                    .map(|target| target.file_id()),
+                _ => None,
+            };
+            let child = child.or_else(|| self.path_attribute_file(file_id, &module));
+            if let Some(child) = child {
+                // A file declared as several modules, e.g. through `#[path]`, is only
+                // walked, and named, once.
+                if module_tree.contains_key(&child) {
+                    continue;
+                }
+                let mut segments = module_path.segments.clone();
+                segments.extend(inline_modules(module.syntax()));
+                segments.push(name.text().to_string());
+                let public = module_path.public
+                    && module
+                        .syntax()
+                        .ancestors()
+                        .filter(|node| node.kind() == SyntaxKind::MODULE)
+                        .all(|node| is_pub(&node));
+                let mut cfgs = module_path.cfgs.clone();
+                cfgs.extend(cfgs_of(module.syntax()));
+                let child_path = ModulePath {
+                    segments,
+                    public,
+                    cfgs,
+                };
+                self.walk_module_tree(analysis, child, child_path, module_tree);
+            }
+        }
+    }
+
+    /// The file which the `#[path = "..."]` attribute of `module`, declared in `file_id`,
+    /// points to.
+    ///
+    /// The path is relative to the directory of the declaring file, and for modules nested
+    /// in inline modules to the directory those would have, e.g. `src/foo/a/` for `mod a`
+    /// in `src/foo.rs`.
+    fn path_attribute_file(&self, file_id: FileId, module: &ast::Module) -> Option<FileId> {
+        let path = path_attribute(module)?;
+        let file = self.paths.get(&file_id)?;
+        let mut dir = file.parent()?.to_owned();
+        let inline = inline_modules(module.syntax());
+        if !inline.is_empty() {
+            let is_mod_rs = file.file_name().map_or(false, |name| {
+                name == "mod.rs" || name == "lib.rs" || name == "main.rs"
+            });
+            if !is_mod_rs {
+                dir.push(file.file_stem()?);
+            }
+            dir.extend(inline);
+        }
+        self.files
+            .get(&normalize_lexically(&dir.join(path)))
+            .copied()
+    }
+}
+
+/// The value of the `#[path = "..."]` attribute of `module`, if any.
+fn path_attribute(module: &ast::Module) -> Option<String> {
+    module.attrs().find_map(|attr| {
+        let text = attr.syntax().text().to_string();
+        let inner = text.trim_start_matches("#[").trim_end_matches(']');
+        let mut parts = inner.splitn(2, '=');
+        if parts.next()?.trim() != "path" {
+            return None;
+        }
+        let value = parts.next()?.trim();
+        if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+            Some(value[1..value.len() - 1].to_owned())
+        } else {
+            None
+        }
+    })
+}
+
+/// `path` without its `.` and `..` components, which `#[path]` attributes often have.
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            component => result.push(component),
+        }
+    }
+    result
+}
+
+/// The name of the enum an enum variant target belongs to.
+pub fn parent_enum_name(
+    analysis: &Analysis,
+    navigation_target: &NavigationTarget,
+) -> Option<String> {
+    if navigation_target.kind() != SyntaxKind::ENUM_VARIANT {
+        return None;
+    }
+    let source_file = analysis.parse(navigation_target.file_id()).ok()?;
+    let node = covering_node(source_file.syntax(), navigation_target.full_range());
+    let enum_def = node.ancestors().find_map(ast::EnumDef::cast)?;
+    Some(enum_def.name()?.text().to_string())
+}
+
+/// How the proc macro a function target defines is invoked, and its name, e.g.
+/// `(Derive, "Serialize")` for `#[proc_macro_derive(Serialize)] fn derive_serialize`.
+pub fn proc_macro_of(
+    analysis: &Analysis,
+    navigation_target: &NavigationTarget,
+) -> Option<(MacroKind, String)> {
+    if navigation_target.kind() != SyntaxKind::FN_DEF {
+        return None;
+    }
+    let source_file = analysis.parse(navigation_target.file_id()).ok()?;
+    let node = covering_node(source_file.syntax(), navigation_target.full_range());
+    let fn_def = node.ancestors().find_map(ast::FnDef::cast)?;
+    let fn_name = fn_def.name()?.text().to_string();
+    fn_def.attrs().find_map(|attr| {
+        if let Some((name, token_tree)) = attr.as_simple_call() {
+            if name != "proc_macro_derive" {
+                return None;
+            }
+            // The derive is named first, before e.g. `attributes(serde)`.
+            let derive = token_tree
+                .syntax()
+                .children_with_tokens()
+                .filter_map(|element| element.into_token())
+                .find(|token| token.kind() == SyntaxKind::IDENT)?;
+            return Some((MacroKind::Derive, derive.text().to_string()));
+        }
+        match attr.simple_name()?.as_str() {
+            "proc_macro" => Some((MacroKind::Bang, fn_name.clone())),
+            "proc_macro_attribute" => Some((MacroKind::Attribute, fn_name.clone())),
+            _ => None,
+        }
+    })
+}
+
+/// The `since` and `note` of the `#[deprecated]` attribute of the item a target defines,
+/// or of the innermost item of its file enclosing it, as rustc deprecates the contents of
+/// deprecated modules and enums too. `None` when there is no such attribute, and
+/// `(None, None)` for a bare `#[deprecated]`, while `#[deprecated = "..."]` is a note.
+pub fn deprecation_of(
+    analysis: &Analysis,
+    navigation_target: &NavigationTarget,
+) -> Option<(Option<String>, Option<String>)> {
+    let source_file = analysis.parse(navigation_target.file_id()).ok()?;
+    let node = covering_node(source_file.syntax(), navigation_target.full_range());
+    enclosing_attrs(&node).find_map(|attr| {
+        let input = attribute_input(&attr, "deprecated")?;
+        if input.is_empty() {
+            Some((None, None))
+        } else if input.starts_with('=') {
+            Some((None, string_literal(input[1..].trim())))
+        } else {
+            Some((
+                attribute_argument(&input, "since"),
+                attribute_argument(&input, "note"),
+            ))
+        }
+    })
+}
+
+/// The feature of the `#[unstable(feature = "...")]` attribute of the item a target
+/// defines, e.g. `try_trait` for `std::ops::Try`. The innermost `#[stable]` or
+/// `#[unstable]` of the item and of those of its file enclosing it decides, as items
+/// without either inherit the stability of their module. Only the standard library is
+/// annotated so.
+pub fn unstable_feature_of(
+    analysis: &Analysis,
+    navigation_target: &NavigationTarget,
+) -> Option<String> {
+    let source_file = analysis.parse(navigation_target.file_id()).ok()?;
+    let node = covering_node(source_file.syntax(), navigation_target.full_range());
+    enclosing_attrs(&node)
+        .find_map(|attr| {
+            if attribute_input(&attr, "stable").is_some() {
+                return Some(None);
+            }
+            let input = attribute_input(&attr, "unstable")?;
+            Some(attribute_argument(&input, "feature"))
+        })
+        .and_then(|feature| feature)
+}
+
+/// The attributes of `node` and of the nodes enclosing it, innermost first. Inner
+/// attributes, e.g. `#![deprecated]` atop a module file, count too.
+fn enclosing_attrs(node: &SyntaxNode) -> impl Iterator<Item = ast::Attr> {
+    node.ancestors()
+        .flat_map(|ancestor| ancestor.children().filter_map(ast::Attr::cast))
+}
+
+/// What follows the name of `attr` when it is `name`, e.g. `(since = "1.2.0")` for
+/// `#[deprecated(since = "1.2.0")]`, empty for a bare `#[deprecated]`.
+fn attribute_input(attr: &ast::Attr, name: &str) -> Option<String> {
+    let text = attr.syntax().text().to_string();
+    let inner = text
+        .trim_start_matches('#')
+        .trim_start_matches('!')
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .trim();
+    if !inner.starts_with(name) {
+        return None;
+    }
+    let input = inner[name.len()..].trim();
+    // Not another attribute, e.g. `#[deprecated_safe]`.
+    if input.is_empty() || input.starts_with('=') || input.starts_with('(') {
+        Some(input.to_owned())
+    } else {
+        None
+    }
+}
+
+/// The string given to `key` in the arguments of an attribute, e.g. `1.2.0` for `since`
+/// in `(since = "1.2.0", note = "...")`.
+fn attribute_argument(arguments: &str, key: &str) -> Option<String> {
+    let mut rest = arguments;
+    while let Some(i) = rest.find(key) {
+        let before = rest[..i].chars().next_back();
+        let after = rest[i + key.len()..].trim_start();
+        rest = &rest[i + key.len()..];
+        let delimited = before.map_or(true, |c| c == '(' || c == ',' || c.is_whitespace());
+        if delimited && after.starts_with('=') {
+            return string_literal(after[1..].trim_start());
+        }
+    }
+    None
+}
+
+/// The contents of the string literal at the start of `text`, without its quotes and with
+/// its escaped quotes, backslashes and newlines unescaped.
+pub(crate) fn string_literal(text: &str) -> Option<String> {
+    if !text.starts_with('"') {
+        return None;
+    }
+    let mut value = String::new();
+    let mut chars = text[1..].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                c => value.push(c),
+            },
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+/// The keywords which can only be used as names as raw identifiers, e.g. `r#type`.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+
+/// Whether `name` can only be spelled as a raw identifier.
+pub(crate) fn is_keyword(name: &str) -> bool {
+    KEYWORDS.contains(&name)
+}
+
+/// `name` as a path segment, with the `r#` of raw identifiers however it is spelled.
+fn raw_identifier(name: &str) -> String {
+    if is_keyword(name) {
+        format!("r#{}", name)
+    } else {
+        name.to_owned()
+    }
+}
+
+/// Names of the inline modules enclosing `node`, outermost first, excluding `node` itself.
+fn inline_modules(node: &SyntaxNode) -> Vec<String> {
+    let mut names: Vec<String> = node
+        .ancestors()
+        .skip(1)
+        .filter_map(ast::Module::cast)
+        .filter_map(|module| module.name())
+        .map(|name| name.text().to_string())
+        .collect();
+    names.reverse();
+    names
+}
+
+/// Whether the item `node` is declared with a plain `pub`, rather than e.g. `pub(crate)`.
+pub fn is_pub(node: &SyntaxNode) -> bool {
+    node.children()
+        .find(|child| child.kind() == SyntaxKind::VISIBILITY)
+        .map_or(false, |visibility| visibility.text() == "pub")
+}
+
+/// The visibility of `node` as written, e.g. `pub(crate)`, `None` when it is private.
+pub fn visibility_of(node: &SyntaxNode) -> Option<String> {
+    let visibility = node
+        .children()
+        .find(|child| child.kind() == SyntaxKind::VISIBILITY)?;
+    let text: String = visibility.text().to_string().split_whitespace().collect();
+    // `pub(in path)` is the only visibility with a space.
+    Some(text.replacen("(in", "(in ", 1))
+}
+
+pub fn covering_node(root: &SyntaxNode, range: TextRange) -> SyntaxNode {
+    match find_covering_element(root, range) {
+        NodeOrToken::Node(node) => node,
+        NodeOrToken::Token(token) => token.parent(),
+    }
+}
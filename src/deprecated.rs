@@ -0,0 +1,116 @@
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::used_item::{CrateMap, Location, UsedItemKind};
+
+/// An import of a deprecated item, see `CrateMap::deprecated_imports`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeprecatedImport {
+    file: String,
+    path: String,
+    kind: UsedItemKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    since: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<String>,
+}
+
+/// The imports of deprecated items of every file.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct DeprecatedImports {
+    imports: Vec<DeprecatedImport>,
+}
+
+impl DeprecatedImports {
+    /// Lists the imports of `crate_map` whose items are deprecated, ordered by file then
+    /// by location, an item imported twice being listed twice.
+    pub(crate) fn new(crate_map: &CrateMap) -> Self {
+        let mut imports = vec![];
+        for (file, used_item_map) in crate_map.files() {
+            let mut file_imports: Vec<DeprecatedImport> = used_item_map
+                .iter()
+                .filter(|item| item.deprecated())
+                .map(|item| DeprecatedImport {
+                    file: file.to_owned(),
+                    path: item.path().to_owned(),
+                    kind: item.kind(),
+                    location: item.location(),
+                    since: item.deprecated_since().map(str::to_owned),
+                    note: item.deprecated_note().map(str::to_owned),
+                })
+                .collect();
+            file_imports.sort_by_key(|import| import.location);
+            imports.extend(file_imports);
+        }
+        DeprecatedImports { imports }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.imports.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.imports.len()
+    }
+
+    pub fn imports(&self) -> &[DeprecatedImport] {
+        &self.imports
+    }
+}
+
+impl DeprecatedImport {
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn kind(&self) -> UsedItemKind {
+        self.kind
+    }
+
+    pub fn location(&self) -> Option<Location> {
+        self.location
+    }
+
+    /// The version the item is deprecated since.
+    pub fn since(&self) -> Option<&str> {
+        self.since.as_ref().map(String::as_str)
+    }
+
+    /// Why the item is deprecated, or what to use instead.
+    pub fn note(&self) -> Option<&str> {
+        self.note.as_ref().map(String::as_str)
+    }
+}
+
+impl fmt::Display for DeprecatedImports {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for import in &self.imports {
+            write!(f, "{}", import.file)?;
+            if let Some(location) = import.location {
+                write!(f, ":{}:{}", location.line, location.col)?;
+            }
+            write!(
+                f,
+                ": {} `{}` is deprecated",
+                import.kind.name(),
+                import.path
+            )?;
+            if let Some(since) = &import.since {
+                write!(f, " since {}", since)?;
+            }
+            if let Some(note) = &import.note {
+                write!(f, ": {}", note)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,324 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt,
+    path::Path,
+};
+
+use cargo_metadata::{DependencyKind, Metadata, Package};
+use serde::Serialize;
+
+use crate::{
+    error::Result,
+    file_paths::FilePaths,
+    options::{AnalysisConfig, LoadConfig},
+    origin::Origin,
+    output::normalize_path,
+    target::TargetKind,
+    unused::References,
+    used_item::{analyze_files, load_workspace, used_items_in_file, UsedItem},
+};
+
+/// The crates a file refers to.
+struct CrateUsage {
+    /// Package names of the crates the file imports items from, including `extern crate`s
+    /// and derives.
+    origins: HashSet<String>,
+    /// Every identifier of the file, which catches crates only used through qualified
+    /// paths such as `serde_json::to_string(..)`.
+    identifiers: HashSet<String>,
+}
+
+/// Finds the dependencies of every workspace member which none of its files uses, keyed by
+/// member. Members using all of their dependencies are omitted.
+///
+/// A dependency counts as used when an item of it is imported, or when its name appears as
+/// an identifier anywhere in the member. Build-dependencies, which are used by build
+/// scripts rather than by the member itself, are never reported. Neither are the
+/// dependencies named in `ignore`, which is meant for crates used in ways no import shows,
+/// such as proc-macro crates whose derives are re-exported by another crate (e.g.
+/// `serde_derive` behind `serde`'s `derive` feature).
+pub fn find_unused_dependencies<Q: AsRef<Path>>(
+    dir: Q,
+    ignore: &[String],
+) -> Result<BTreeMap<String, BTreeSet<String>>> {
+    let mut loaded = load_workspace(dir.as_ref(), &LoadConfig::default())?;
+    let analyzed = analyze_files(
+        &mut loaded.host,
+        &loaded.source_map,
+        Some(&loaded.metadata),
+        // A crate may only be used through its derives, e.g. `serde_derive`.
+        &AnalysisConfig {
+            include_derives: true,
+            ..AnalysisConfig::default()
+        },
+        |analysis, workspace, file_id| {
+            let used_item_map = used_items_in_file(analysis, workspace, file_id)?;
+            let references = References::collect(analysis, file_id, false)?;
+            Ok(CrateUsage {
+                origins: used_item_map
+                    .iter()
+                    .filter_map(|item| item.origin())
+                    .map(|origin| normalize_crate_name(origin.crate_name()))
+                    .collect(),
+                identifiers: references.names().map(str::to_owned).collect(),
+            })
+        },
+    )?;
+
+    let mut by_package: BTreeMap<&str, Vec<&CrateUsage>> = BTreeMap::new();
+    for (file, usage) in &analyzed.values {
+        if let Some(package) = analyzed.packages.get(file) {
+            by_package.entry(package).or_default().push(usage);
+        }
+    }
+
+    let mut result = BTreeMap::new();
+    for package in workspace_members(&loaded.metadata) {
+        let usages = by_package
+            .get(package.name.as_str())
+            .map_or(&[][..], Vec::as_slice);
+        let unused: BTreeSet<String> = package
+            .dependencies
+            .iter()
+            .filter(|dependency| dependency.kind != DependencyKind::Build)
+            .filter(|dependency| !ignore.contains(&dependency.name))
+            .filter(|dependency| {
+                let package_name = normalize_crate_name(&dependency.name);
+                let local_name = dependency
+                    .rename
+                    .as_ref()
+                    .map_or_else(|| package_name.clone(), |name| normalize_crate_name(name));
+                !usages.iter().any(|usage| {
+                    usage.origins.contains(&package_name) || usage.identifiers.contains(&local_name)
+                })
+            })
+            .map(|dependency| dependency.name.clone())
+            .collect();
+        if !unused.is_empty() {
+            result.insert(package.name.clone(), unused);
+        }
+    }
+    Ok(result)
+}
+
+/// Dependencies of the workspace members declared in the wrong section of their manifest.
+#[derive(Debug, Default, Serialize)]
+pub struct DepsAudit {
+    /// Dev-dependencies imported outside of test code, keyed by member, then by dependency,
+    /// with the files importing them.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    dev_dependencies_in_non_test_code: BTreeMap<String, BTreeMap<String, BTreeSet<String>>>,
+    /// Normal dependencies only imported by test code, which could be dev-dependencies,
+    /// keyed by member.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    test_only_dependencies: BTreeMap<String, BTreeSet<String>>,
+    /// Crates imported by build scripts which are not build-dependencies, keyed by member,
+    /// then by crate, with the files importing them.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    non_build_dependencies_in_build_scripts: BTreeMap<String, BTreeMap<String, BTreeSet<String>>>,
+    #[serde(skip)]
+    paths: FilePaths,
+    /// The path of the manifest of every member relative to the workspace root.
+    #[serde(skip)]
+    manifests: BTreeMap<String, String>,
+}
+
+impl DepsAudit {
+    pub fn is_empty(&self) -> bool {
+        self.dev_dependencies_in_non_test_code.is_empty()
+            && self.test_only_dependencies.is_empty()
+            && self.non_build_dependencies_in_build_scripts.is_empty()
+    }
+
+    pub fn dev_dependencies_in_non_test_code(
+        &self,
+    ) -> &BTreeMap<String, BTreeMap<String, BTreeSet<String>>> {
+        &self.dev_dependencies_in_non_test_code
+    }
+
+    pub fn test_only_dependencies(&self) -> &BTreeMap<String, BTreeSet<String>> {
+        &self.test_only_dependencies
+    }
+
+    pub fn non_build_dependencies_in_build_scripts(
+        &self,
+    ) -> &BTreeMap<String, BTreeMap<String, BTreeSet<String>>> {
+        &self.non_build_dependencies_in_build_scripts
+    }
+
+    pub fn paths(&self) -> &FilePaths {
+        &self.paths
+    }
+
+    /// The path of the manifest of `member` relative to the workspace root.
+    pub fn manifest_of(&self, member: &str) -> Option<&str> {
+        self.manifests.get(member).map(String::as_str)
+    }
+}
+
+impl fmt::Display for DepsAudit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (member, dependencies) in &self.dev_dependencies_in_non_test_code {
+            for (dependency, files) in dependencies {
+                let files: Vec<&str> = files.iter().map(String::as_str).collect();
+                writeln!(
+                    f,
+                    "{}: dev-dependency `{}` is imported outside of tests in {}",
+                    member,
+                    dependency,
+                    files.join(", ")
+                )?;
+            }
+        }
+        for (member, dependencies) in &self.test_only_dependencies {
+            for dependency in dependencies {
+                writeln!(
+                    f,
+                    "{}: dependency `{}` is only imported by tests, consider making it a \
+                     dev-dependency",
+                    member, dependency
+                )?;
+            }
+        }
+        for (member, crates) in &self.non_build_dependencies_in_build_scripts {
+            for (crate_name, files) in crates {
+                let files: Vec<&str> = files.iter().map(String::as_str).collect();
+                writeln!(
+                    f,
+                    "{}: `{}` is imported by the build script in {}, but is not a \
+                     build-dependency",
+                    member,
+                    crate_name,
+                    files.join(", ")
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks that the dev-dependencies of every workspace member are only imported by test
+/// code, and that its normal dependencies are imported by something else too.
+///
+/// Test code is anything under `tests/`, `benches/` or `examples/`, and imports under a
+/// `cfg(test)`, whether on the use item itself or on an enclosing module. Dependencies
+/// listed in both sections are never reported.
+///
+/// Build scripts are crates of their own, which only see the build-dependencies, so their
+/// imports are checked against those alone and count for neither of the other sections.
+pub fn audit_dependencies<Q: AsRef<Path>>(dir: Q) -> Result<DepsAudit> {
+    let mut loaded = load_workspace(dir.as_ref(), &LoadConfig::default())?;
+    let analyzed = analyze_files(
+        &mut loaded.host,
+        &loaded.source_map,
+        Some(&loaded.metadata),
+        &AnalysisConfig {
+            include_derives: true,
+            ..AnalysisConfig::default()
+        },
+        used_items_in_file,
+    )?;
+
+    let mut audit = DepsAudit {
+        paths: FilePaths::new(analyzed.definitions.clone()),
+        ..DepsAudit::default()
+    };
+    for package in workspace_members(&loaded.metadata) {
+        if let Ok(manifest) = package
+            .manifest_path
+            .strip_prefix(&loaded.metadata.workspace_root)
+        {
+            audit
+                .manifests
+                .insert(package.name.clone(), normalize_path(manifest));
+        }
+        let names_of = |kind: DependencyKind| -> BTreeSet<String> {
+            package
+                .dependencies
+                .iter()
+                .filter(|dependency| dependency.kind == kind)
+                .map(|dependency| normalize_crate_name(&dependency.name))
+                .collect()
+        };
+        let normal = names_of(DependencyKind::Normal);
+        let dev = names_of(DependencyKind::Development);
+        let build = names_of(DependencyKind::Build);
+
+        // Crate name -> (files importing it outside of tests, whether tests import it).
+        let mut imports: BTreeMap<String, (BTreeSet<String>, bool)> = BTreeMap::new();
+        for (file, used_item_map) in &analyzed.values {
+            if analyzed.packages.get(file) != Some(&package.name) {
+                continue;
+            }
+            let in_build_script = analyzed.targets.get(file) == Some(&TargetKind::BuildScript);
+            for item in used_item_map.iter() {
+                let crate_name = match item.origin() {
+                    Some(Origin::External(name)) => normalize_crate_name(name),
+                    _ => continue,
+                };
+                if in_build_script {
+                    if !build.contains(&crate_name) {
+                        audit
+                            .non_build_dependencies_in_build_scripts
+                            .entry(package.name.clone())
+                            .or_default()
+                            .entry(crate_name)
+                            .or_default()
+                            .insert(file.clone());
+                    }
+                    continue;
+                }
+                let entry = imports.entry(crate_name).or_default();
+                // Keys are prefixed by the package name.
+                let relative = &file[package.name.len() + 1..];
+                if is_test_code(relative, item) {
+                    entry.1 = true;
+                } else {
+                    entry.0.insert(file.clone());
+                }
+            }
+        }
+
+        for (crate_name, (non_test_files, in_tests)) in imports {
+            if dev.contains(&crate_name) && !normal.contains(&crate_name) {
+                if !non_test_files.is_empty() {
+                    audit
+                        .dev_dependencies_in_non_test_code
+                        .entry(package.name.clone())
+                        .or_default()
+                        .insert(crate_name, non_test_files);
+                }
+            } else if normal.contains(&crate_name)
+                && !dev.contains(&crate_name)
+                && in_tests
+                && non_test_files.is_empty()
+            {
+                audit
+                    .test_only_dependencies
+                    .entry(package.name.clone())
+                    .or_default()
+                    .insert(crate_name);
+            }
+        }
+    }
+    Ok(audit)
+}
+
+fn is_test_code(file: &str, item: &UsedItem) -> bool {
+    let in_test_dir = ["tests/", "benches/", "examples/"]
+        .iter()
+        .any(|dir| file.starts_with(dir));
+    in_test_dir || item.cfgs().iter().any(|cfg| cfg == "test")
+}
+
+pub(crate) fn workspace_members(metadata: &Metadata) -> impl Iterator<Item = &Package> {
+    metadata
+        .packages
+        .iter()
+        .filter(move |package| metadata.workspace_members.contains(&package.id))
+}
+
+/// Package names may contain dashes, which become underscores in crate names.
+pub(crate) fn normalize_crate_name(name: &str) -> String {
+    name.replace('-', "_")
+}
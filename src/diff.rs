@@ -0,0 +1,146 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::used_item::{CrateMap, UsedItem, UsedItemMap};
+
+/// The imports added and removed between two dumps of the same workspace.
+///
+/// Items are compared by everything but their reference count, so that only changes of
+/// the imports themselves show up.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrateMapDiff {
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    added_files: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    removed_files: BTreeSet<String>,
+    /// Files present in both dumps whose imports differ.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    files: BTreeMap<String, FileDiff>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileDiff {
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    added: BTreeSet<String>,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    removed: BTreeSet<String>,
+}
+
+impl CrateMapDiff {
+    pub fn new(old: &CrateMap, new: &CrateMap) -> Self {
+        let mut diff = CrateMapDiff::default();
+        for (file, old_map) in old.files() {
+            match new.get(file) {
+                Some(new_map) => {
+                    let file_diff = FileDiff::new(old_map, new_map);
+                    if !file_diff.is_empty() {
+                        diff.files.insert(file.to_owned(), file_diff);
+                    }
+                }
+                None => {
+                    diff.removed_files.insert(file.to_owned());
+                }
+            }
+        }
+        for (file, _) in new.files() {
+            if old.get(file).is_none() {
+                diff.added_files.insert(file.to_owned());
+            }
+        }
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added_files.is_empty() && self.removed_files.is_empty() && self.files.is_empty()
+    }
+
+    pub fn added_files(&self) -> &BTreeSet<String> {
+        &self.added_files
+    }
+
+    pub fn removed_files(&self) -> &BTreeSet<String> {
+        &self.removed_files
+    }
+
+    /// The files present in both dumps whose imports differ, ordered by file.
+    pub fn files(&self) -> impl Iterator<Item = (&str, &FileDiff)> {
+        self.files.iter().map(|(file, diff)| (file.as_str(), diff))
+    }
+}
+
+impl FileDiff {
+    fn new(old: &UsedItemMap, new: &UsedItemMap) -> Self {
+        let old = describe_items(old);
+        let new = describe_items(new);
+        FileDiff {
+            added: new.difference(&old).cloned().collect(),
+            removed: old.difference(&new).cloned().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// The imports of the new dump which the old one lacks, e.g. `trait serde::Serialize`.
+    pub fn added(&self) -> &BTreeSet<String> {
+        &self.added
+    }
+
+    /// The imports of the old dump which the new one lacks.
+    pub fn removed(&self) -> &BTreeSet<String> {
+        &self.removed
+    }
+}
+
+fn describe_items(map: &UsedItemMap) -> BTreeSet<String> {
+    map.iter()
+        .map(describe_item)
+        .chain(map.globs().iter().map(|glob| format!("glob {}", glob)))
+        .chain(
+            map.unresolved()
+                .iter()
+                .map(|path| format!("unresolved {}", path)),
+        )
+        .collect()
+}
+
+/// Describes an import as `<kind> <path>[ as <alias>][ in <scope>]`.
+fn describe_item(item: &UsedItem) -> String {
+    let mut s = format!("{} {}", item.kind().name(), item.path());
+    if let Some(alias) = item.alias() {
+        s.push_str(" as ");
+        s.push_str(alias);
+    }
+    if let Some(scope) = item.scope() {
+        s.push_str(" in ");
+        s.push_str(scope);
+    }
+    s
+}
+
+/// Lists the changes like a unified diff, one file after another.
+impl fmt::Display for CrateMapDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for file in &self.removed_files {
+            writeln!(f, "removed file {}", file)?;
+        }
+        for file in &self.added_files {
+            writeln!(f, "added file {}", file)?;
+        }
+        for (file, diff) in &self.files {
+            writeln!(f, "{}", file)?;
+            for item in &diff.removed {
+                writeln!(f, "- {}", item)?;
+            }
+            for item in &diff.added {
+                writeln!(f, "+ {}", item)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{origin::OriginKind, used_item::CrateMap};
+
+/// Renders a `CrateMap` as a Graphviz graph of its files, with an edge from every file to
+/// each file of the workspace it imports items from, as `usedump dump --format dot` does.
+///
+/// The items must have been dumped with their definitions, and edges are labelled and
+/// weighted by their number of items.
+///
+/// ```no_run
+/// let map = usedump::DumpOptions::new(".").definitions(true).run()?;
+/// let dot = usedump::FileGraph::new().cluster_by_package(true).render(&map);
+/// # Ok::<(), usedump::UsedumpError>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FileGraph {
+    cluster_by_package: bool,
+    include_external: bool,
+}
+
+impl FileGraph {
+    pub fn new() -> Self {
+        FileGraph::default()
+    }
+
+    /// Draws the files of each workspace member in a cluster of their own.
+    pub fn cluster_by_package(mut self, yes: bool) -> Self {
+        self.cluster_by_package = yes;
+        self
+    }
+
+    /// Also draws the crates outside the workspace, as one box per crate.
+    pub fn include_external(mut self, yes: bool) -> Self {
+        self.include_external = yes;
+        self
+    }
+
+    pub fn render(&self, map: &CrateMap) -> String {
+        let mut weights: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+        let mut crates = BTreeSet::new();
+        for (file, used_item_map) in map.files() {
+            for item in used_item_map.iter() {
+                let defined_in = match item.defined_in() {
+                    Some(defined_in) => defined_in,
+                    None => continue,
+                };
+                let to = match map.key_of_definition(&defined_in.file) {
+                    Some(key) => key,
+                    None => match item.origin() {
+                        Some(origin)
+                            if self.include_external && origin.kind() != OriginKind::Local =>
+                        {
+                            crates.insert(origin.crate_name());
+                            origin.crate_name()
+                        }
+                        // Members which were not analyzed, e.g. with `--package`.
+                        _ => continue,
+                    },
+                };
+                if to != file {
+                    *weights.entry((file, to)).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut dot = String::from("digraph files {\n");
+        if self.cluster_by_package {
+            let mut by_package: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+            for (file, _) in map.files() {
+                let package = map.package_of(file).unwrap_or_default();
+                by_package.entry(package).or_default().push(file);
+            }
+            for (package, files) in by_package {
+                dot.push_str(&format!(
+                    "    subgraph {} {{\n",
+                    quote(&format!("cluster_{}", package))
+                ));
+                dot.push_str(&format!("        label={};\n", quote(package)));
+                for file in files {
+                    dot.push_str(&format!("        {};\n", quote(file)));
+                }
+                dot.push_str("    }\n");
+            }
+        } else {
+            for (file, _) in map.files() {
+                dot.push_str(&format!("    {};\n", quote(file)));
+            }
+        }
+        for krate in crates {
+            dot.push_str(&format!("    {} [shape=box];\n", quote(krate)));
+        }
+        for ((from, to), items) in weights {
+            dot.push_str(&format!(
+                "    {} -> {} [label=\"{}\", weight={}];\n",
+                quote(from),
+                quote(to),
+                items,
+                items
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// A DOT identifier for `name`, which may contain dashes, slashes or colons.
+pub(crate) fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn item(path: &str, kind: &str, origin: Value, file: &str) -> Value {
+        json!({
+            "name": path.rsplit("::").next().unwrap(),
+            "path": path,
+            "kind": kind,
+            "origin": origin,
+            "count": 1,
+            "defined_in": {"crate": origin["crate"], "file": file}
+        })
+    }
+
+    /// Two members, `right` importing two structs of `left/src/shapes.rs`, a function of
+    /// `left/src/lib.rs`, and items of `std` and `serde`.
+    fn map() -> CrateMap {
+        let left = json!({"kind": "local", "crate": "left"});
+        CrateMap::from_json(json!({
+            "left/src/lib.rs": {
+                "structs": [
+                    item("left::shapes::Square", "struct", left.clone(), "left/src/shapes.rs")
+                ]
+            },
+            "left/src/shapes.rs": {
+                "fns": [item("left::unit_square", "fn", left.clone(), "left/src/lib.rs")]
+            },
+            "right/src/lib.rs": {
+                "structs": [
+                    item("left::shapes::Circle", "struct", left.clone(), "left/src/shapes.rs"),
+                    item("left::shapes::Square", "struct", left.clone(), "left/src/shapes.rs"),
+                    item(
+                        "std::collections::HashMap",
+                        "struct",
+                        json!({"kind": "std", "crate": "std"}),
+                        "std/src/collections/hash/map.rs"
+                    )
+                ],
+                "traits": [item(
+                    "serde::Serialize",
+                    "trait",
+                    json!({"kind": "external", "crate": "serde"}),
+                    "serde-1.0.104/src/ser/mod.rs"
+                )],
+                "fns": [item("left::unit_square", "fn", left, "left/src/lib.rs")]
+            }
+        }))
+    }
+
+    #[test]
+    fn edges_are_weighted_by_their_items() {
+        assert_eq!(
+            FileGraph::new().render(&map()),
+            r#"digraph files {
+    "left/src/lib.rs";
+    "left/src/shapes.rs";
+    "right/src/lib.rs";
+    "left/src/lib.rs" -> "left/src/shapes.rs" [label="1", weight=1];
+    "left/src/shapes.rs" -> "left/src/lib.rs" [label="1", weight=1];
+    "right/src/lib.rs" -> "left/src/lib.rs" [label="1", weight=1];
+    "right/src/lib.rs" -> "left/src/shapes.rs" [label="2", weight=2];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn files_are_clustered_by_package() {
+        let dot = FileGraph::new().cluster_by_package(true).render(&map());
+        assert!(dot.starts_with(
+            r#"digraph files {
+    subgraph "cluster_left" {
+        label="left";
+        "left/src/lib.rs";
+        "left/src/shapes.rs";
+    }
+    subgraph "cluster_right" {
+        label="right";
+        "right/src/lib.rs";
+    }
+    "left/src/lib.rs" -> "left/src/shapes.rs""#
+        ));
+    }
+
+    #[test]
+    fn external_crates_are_boxes() {
+        let dot = FileGraph::new().include_external(true).render(&map());
+        assert!(dot.contains("    \"serde\" [shape=box];\n    \"std\" [shape=box];\n"));
+        assert!(dot.contains("    \"right/src/lib.rs\" -> \"serde\" [label=\"1\", weight=1];\n"));
+        assert!(dot.contains("    \"right/src/lib.rs\" -> \"std\" [label=\"1\", weight=1];\n"));
+        assert!(!FileGraph::new().render(&map()).contains("\"std\""));
+    }
+
+    #[test]
+    fn names_are_quoted() {
+        assert_eq!(quote(r#"a "b"\c"#), r#""a \"b\"\\c""#);
+    }
+}
@@ -0,0 +1,247 @@
+use std::{error::Error, fmt, io, path::PathBuf};
+
+#[derive(Debug)]
+pub enum UsedumpError {
+    /// No `Cargo.toml` was found in the given directory or any of its parents.
+    ManifestNotFound {
+        path: PathBuf,
+    },
+    /// The `Cargo.toml` found describes neither a package nor a workspace.
+    InvalidManifest {
+        path: PathBuf,
+        reason: String,
+    },
+    /// rust-analyzer failed to load the cargo workspace.
+    WorkspaceLoad {
+        path: PathBuf,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// `cargo metadata` failed.
+    Metadata(cargo_metadata::Error),
+    /// rust-analyzer failed to analyze a file.
+    Analysis {
+        file: String,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// The analysis of a workspace member panicked outside of the calls made for a single
+    /// file, losing the results of all of its files.
+    AnalysisPanicked {
+        package: String,
+        reason: String,
+    },
+    /// A package selected with `-p` is not a member of the workspace.
+    UnknownPackage {
+        name: String,
+        available: Vec<String>,
+    },
+    /// The package given to `find_api_coverage` is not a dependency of the workspace.
+    UnknownDependency {
+        name: String,
+        available: Vec<String>,
+    },
+    /// An include or exclude pattern is not a valid glob.
+    InvalidGlob {
+        pattern: String,
+        source: globset::Error,
+    },
+    /// A query given with `--regex` is not a valid regular expression.
+    InvalidRegex {
+        pattern: String,
+        source: regex::Error,
+    },
+    /// Dumps being merged have different used items for the same file.
+    MergeConflict(MergeConflict),
+    /// A crate given to `fetch_crate` could not be fetched, e.g. as the version does not
+    /// exist or the network is unreachable.
+    Fetch {
+        spec: String,
+        reason: String,
+    },
+    /// Loading the workspace with `--offline` needs a dependency which is not available
+    /// locally.
+    Offline {
+        dependency: Option<String>,
+        reason: String,
+    },
+    /// Loading the workspace with `--locked` needs `Cargo.lock` to change, e.g. for a
+    /// dependency added to a manifest since.
+    LockfileOutOfDate {
+        path: PathBuf,
+        dependency: Option<String>,
+    },
+    /// The workspace has no `Cargo.lock`, which `--locked` requires.
+    MissingLockfile {
+        path: PathBuf,
+    },
+    /// rustc knows no target of the triple given to `DumpOptions::target_triples`.
+    UnknownTarget {
+        triple: String,
+        reason: String,
+    },
+    Io(io::Error),
+}
+
+/// A file which two merged `CrateMap`s have different used items for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MergeConflict {
+    key: String,
+}
+
+impl MergeConflict {
+    pub(crate) fn new(key: String) -> Self {
+        MergeConflict { key }
+    }
+
+    /// The key of the file, as in the maps.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+}
+
+impl fmt::Display for MergeConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` has different used items in the merged dumps",
+            self.key
+        )
+    }
+}
+
+impl Error for MergeConflict {}
+
+impl fmt::Display for UsedumpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UsedumpError::ManifestNotFound { path } => write!(
+                f,
+                "could not find `Cargo.toml` in `{}` or any parent directory",
+                path.display()
+            ),
+            UsedumpError::InvalidManifest { path, reason } => write!(
+                f,
+                "`{}` is not a valid manifest: {}",
+                path.display(),
+                reason
+            ),
+            UsedumpError::WorkspaceLoad { path, source } => write!(
+                f,
+                "failed to load the cargo workspace at `{}`: {}",
+                path.display(),
+                source
+            ),
+            UsedumpError::Metadata(e) => write!(f, "failed to run `cargo metadata`: {}", e),
+            UsedumpError::Analysis { file, source } => {
+                write!(f, "failed to analyze `{}`: {}", file, source)
+            }
+            UsedumpError::AnalysisPanicked { package, reason } => {
+                write!(f, "the analysis of `{}` {}", package, reason)
+            }
+            UsedumpError::UnknownPackage { name, available } => write!(
+                f,
+                "package `{}` is not a member of the workspace, available members: {}",
+                name,
+                available.join(", ")
+            ),
+            UsedumpError::UnknownDependency { name, available } => write!(
+                f,
+                "package `{}` is not a dependency of the workspace, available dependencies: {}",
+                name,
+                available.join(", ")
+            ),
+            UsedumpError::InvalidGlob { pattern, source } => {
+                write!(f, "invalid glob `{}`: {}", pattern, source)
+            }
+            UsedumpError::InvalidRegex { pattern, source } => {
+                write!(f, "invalid regex `{}`: {}", pattern, source)
+            }
+            UsedumpError::MergeConflict(e) => write!(f, "{}", e),
+            UsedumpError::Fetch { spec, reason } => {
+                write!(f, "failed to fetch `{}`: {}", spec, reason)
+            }
+            UsedumpError::Offline {
+                dependency: Some(dependency),
+                reason,
+            } => write!(
+                f,
+                "`{}` is not available offline, so the workspace cannot be loaded: {}",
+                dependency, reason
+            ),
+            UsedumpError::Offline {
+                dependency: None,
+                reason,
+            } => write!(f, "the workspace cannot be loaded offline: {}", reason),
+            UsedumpError::LockfileOutOfDate {
+                path,
+                dependency: Some(dependency),
+            } => write!(
+                f,
+                "`{}` needs to be updated for `{}`, which `--locked` forbids",
+                path.display(),
+                dependency
+            ),
+            UsedumpError::LockfileOutOfDate {
+                path,
+                dependency: None,
+            } => write!(
+                f,
+                "`{}` needs to be updated, which `--locked` forbids",
+                path.display()
+            ),
+            UsedumpError::MissingLockfile { path } => write!(
+                f,
+                "the workspace of `{}` has no `Cargo.lock`, which `--locked` requires",
+                path.display()
+            ),
+            UsedumpError::UnknownTarget { triple, reason } => {
+                write!(f, "unknown target `{}`: {}", triple, reason)
+            }
+            UsedumpError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for UsedumpError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            UsedumpError::ManifestNotFound { .. }
+            | UsedumpError::InvalidManifest { .. }
+            | UsedumpError::AnalysisPanicked { .. }
+            | UsedumpError::UnknownPackage { .. }
+            | UsedumpError::UnknownDependency { .. }
+            | UsedumpError::Fetch { .. }
+            | UsedumpError::Offline { .. }
+            | UsedumpError::LockfileOutOfDate { .. }
+            | UsedumpError::MissingLockfile { .. }
+            | UsedumpError::UnknownTarget { .. } => None,
+            UsedumpError::WorkspaceLoad { source, .. } | UsedumpError::Analysis { source, .. } => {
+                Some(&**source)
+            }
+            UsedumpError::Metadata(e) => Some(e),
+            UsedumpError::InvalidGlob { source, .. } => Some(source),
+            UsedumpError::InvalidRegex { source, .. } => Some(source),
+            UsedumpError::MergeConflict(e) => Some(e),
+            UsedumpError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for UsedumpError {
+    fn from(e: io::Error) -> Self {
+        UsedumpError::Io(e)
+    }
+}
+
+impl From<MergeConflict> for UsedumpError {
+    fn from(e: MergeConflict) -> Self {
+        UsedumpError::MergeConflict(e)
+    }
+}
+
+impl From<cargo_metadata::Error> for UsedumpError {
+    fn from(e: cargo_metadata::Error) -> Self {
+        UsedumpError::Metadata(e)
+    }
+}
+
+pub type Result<T, E = UsedumpError> = std::result::Result<T, E>;
@@ -0,0 +1,73 @@
+use globset::{Glob, GlobBuilder, GlobSet, GlobSetBuilder};
+
+use crate::error::{Result, UsedumpError};
+
+/// Selects workspace files by their relative path with gitignore-style globs.
+///
+/// A file is selected when it matches one of the include globs, or when there are none,
+/// and none of the exclude globs. Excluded files are never analyzed.
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl FileFilter {
+    pub fn new<S: AsRef<str>>(include: &[S], exclude: &[S]) -> Result<Self> {
+        Ok(FileFilter {
+            include: build_glob_set(include)?,
+            exclude: build_glob_set(exclude)?,
+        })
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        let included = self.include.as_ref().map_or(true, |set| set.is_match(path));
+        let excluded = self
+            .exclude
+            .as_ref()
+            .map_or(false, |set| set.is_match(path));
+        included && !excluded
+    }
+}
+
+fn build_glob_set<S: AsRef<str>>(patterns: &[S]) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.as_ref();
+        let glob = gitignore_glob(pattern).map_err(|source| UsedumpError::InvalidGlob {
+            pattern: pattern.to_owned(),
+            source,
+        })?;
+        builder.add(glob);
+    }
+    builder
+        .build()
+        .map(Some)
+        .map_err(|source| UsedumpError::InvalidGlob {
+            pattern: patterns
+                .iter()
+                .map(|p| p.as_ref())
+                .collect::<Vec<_>>()
+                .join(", "),
+            source,
+        })
+}
+
+/// Translates a gitignore pattern into a glob over relative paths: patterns without a
+/// slash match at any depth, a leading slash anchors the pattern at the root and a
+/// trailing slash matches everything below a directory.
+fn gitignore_glob(pattern: &str) -> Result<Glob, globset::Error> {
+    let mut glob = pattern.to_owned();
+    if glob.ends_with('/') {
+        glob.push_str("**");
+    }
+    if glob.starts_with('/') {
+        glob.remove(0);
+    } else if !pattern.trim_end_matches('/').contains('/') {
+        glob.insert_str(0, "**/");
+    }
+    GlobBuilder::new(&glob).literal_separator(true).build()
+}
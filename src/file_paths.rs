@@ -0,0 +1,28 @@
+use std::collections::BTreeMap;
+
+/// The path of every file of a report relative to the workspace root, by key, as keys
+/// are flattened, absolute or module paths depending on the options of the dump.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilePaths {
+    paths: BTreeMap<String, String>,
+}
+
+impl FilePaths {
+    pub(crate) fn new(paths: BTreeMap<String, String>) -> Self {
+        FilePaths { paths }
+    }
+
+    /// The path of the file keyed `file`, `file` itself when its path is unknown.
+    pub fn path_of<'a>(&'a self, file: &'a str) -> &'a str {
+        self.paths.get(file).map_or(file, String::as_str)
+    }
+
+    pub(crate) fn insert(&mut self, file: String, path: String) {
+        self.paths.insert(file, path);
+    }
+
+    /// Keeps only the paths of the files `f` returns `true` for.
+    pub(crate) fn retain<F: FnMut(&str) -> bool>(&mut self, mut f: F) {
+        self.paths.retain(|file, _| f(file));
+    }
+}
@@ -0,0 +1,284 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Component, Path, PathBuf},
+    time::SystemTime,
+};
+
+use cargo_metadata::Metadata;
+use ra_syntax::{ast, AstNode, SourceFile, SyntaxKind};
+
+use crate::{
+    definition_path::string_literal,
+    options::{AnalysisConfig, GeneratedMode},
+    origin::member_dirs,
+    output::normalize_path,
+    syntax::SyntaxOrigins,
+    target::TargetKind,
+    used_item::{used_items_by_syntax, AnalyzedFiles, Diagnostic, UsedItemMap},
+};
+
+/// A file which a file of a member `include!`s from outside the directories of the members,
+/// e.g. the code a build script writes to `OUT_DIR`.
+#[derive(Debug, Clone)]
+pub(crate) struct Included {
+    path: PathBuf,
+    /// The key of the generated file, see `GeneratedFile::key`.
+    key: String,
+    package: String,
+    /// The key of the file including it.
+    pub(crate) includer: String,
+    target: TargetKind,
+}
+
+/// The used items of an `Included` file. rust-analyzer loads neither the files of `OUT_DIR`
+/// nor the others outside of the package roots, so they are listed from their syntax only,
+/// like with `DumpOptions::no_resolve`.
+pub(crate) struct GeneratedFile {
+    /// The key of the file, `<package>/OUT_DIR/<path>` for a file of `OUT_DIR`, and its path
+    /// relative to the workspace root, or absolute outside of it, for the others. With
+    /// `config.flat_paths` the package name is left out, and with `config.absolute_paths`
+    /// it is always the absolute path.
+    pub(crate) key: String,
+    pub(crate) package: String,
+    pub(crate) includer: String,
+    pub(crate) target: TargetKind,
+    /// The used items, or why the file could not be read.
+    pub(crate) result: Result<UsedItemMap, String>,
+}
+
+/// Lists the generated files which `text`, the file of the member `package` at `path` keyed
+/// `key`, includes with `include!("...")` or `include!(concat!(env!("OUT_DIR"), "..."))`.
+/// Paths given by other environment variables or macros are not followed.
+///
+/// A file is generated when it lies outside the directories of the members, or in the
+/// target directory, which is inside the root package of a workspace. The files of
+/// `OUT_DIR` are looked for in the latest build of `package`.
+pub(crate) fn included_files(
+    metadata: &Metadata,
+    text: &str,
+    path: &Path,
+    key: &str,
+    package: &str,
+    target: TargetKind,
+    config: &AnalysisConfig,
+) -> Vec<Included> {
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    let member_dirs = member_dirs(Some(metadata));
+    let parse = SourceFile::parse(text);
+    let mut result = vec![];
+    for macro_call in parse
+        .tree()
+        .syntax()
+        .descendants()
+        .filter_map(ast::MacroCall::cast)
+    {
+        let is_include = macro_call.path().map_or(false, |path| {
+            let path = path.syntax().text().to_string();
+            path == "include" || path == "std::include" || path == "core::include"
+        });
+        let token_tree = match macro_call.token_tree() {
+            Some(token_tree) if is_include => token_tree,
+            _ => continue,
+        };
+        let (path, rest) = match include_argument(&token_tree) {
+            Some(IncludeArgument::OutDir(rest)) => match out_dir_file(metadata, package, &rest) {
+                Some(path) => (path, Some(rest)),
+                None => continue,
+            },
+            Some(IncludeArgument::Path(relative)) => (lexical(&dir.join(relative)), None),
+            None => continue,
+        };
+        let inside_member = member_dirs.iter().any(|dir| path.starts_with(dir));
+        if inside_member && !path.starts_with(&metadata.target_directory) {
+            continue;
+        }
+        let generated_key = if config.absolute_paths {
+            normalize_path(&path)
+        } else if let Some(rest) = rest {
+            let rest = format!("OUT_DIR/{}", rest.trim_start_matches('/'));
+            if config.flat_paths {
+                rest
+            } else {
+                format!("{}/{}", package, rest)
+            }
+        } else {
+            normalize_path(path.strip_prefix(&metadata.workspace_root).unwrap_or(&path))
+        };
+        result.push(Included {
+            path,
+            key: generated_key,
+            package: package.to_owned(),
+            includer: key.to_owned(),
+            target,
+        });
+    }
+    result
+}
+
+/// Lists the used items of the `included` files, every file being listed once, as included
+/// by the first file including it.
+pub(crate) fn generated_files(metadata: &Metadata, included: &[Included]) -> Vec<GeneratedFile> {
+    let origins = SyntaxOrigins::new(metadata);
+    let mut seen = HashSet::new();
+    let mut result = vec![];
+    for included in included {
+        if !seen.insert(&included.key) {
+            continue;
+        }
+        let origin_of = |first: &str| origins.origin_of(&included.package, first);
+        let items = fs::read_to_string(&included.path)
+            .map(|text| used_items_by_syntax(&text, &origin_of))
+            .map_err(|e| e.to_string());
+        result.push(GeneratedFile {
+            key: included.key.clone(),
+            package: included.package.clone(),
+            includer: included.includer.clone(),
+            target: included.target,
+            result: items,
+        });
+    }
+    result
+}
+
+/// Adds the generated files which the files of `analyzed` include to `values`, per
+/// `config.generated`, giving them the module of the file including them with
+/// `Keys::Module`.
+pub(crate) fn add_generated_files<T>(
+    values: &mut BTreeMap<String, UsedItemMap>,
+    analyzed: &mut AnalyzedFiles<T>,
+    metadata: Option<&Metadata>,
+    config: &AnalysisConfig,
+) {
+    let metadata = match metadata {
+        Some(metadata) if config.generated != GeneratedMode::Skip => metadata,
+        _ => return,
+    };
+    for file in generated_files(metadata, &analyzed.included) {
+        if let Some(module) = analyzed.modules.get(&file.includer).cloned() {
+            analyzed.modules.insert(file.key.clone(), module);
+        }
+        analyzed.packages.insert(file.key.clone(), file.package);
+        analyzed.targets.insert(file.key.clone(), file.target);
+        if config.generated == GeneratedMode::Separate {
+            analyzed.generated.insert(file.key.clone());
+        }
+        match file.result {
+            Ok(used_item_map) => {
+                values.insert(file.key, used_item_map);
+            }
+            Err(error) => analyzed.diagnostics.push(Diagnostic::new(file.key, error)),
+        }
+    }
+    analyzed.diagnostics.sort_by(|a, b| a.file().cmp(b.file()));
+}
+
+/// What `include!` is given.
+enum IncludeArgument {
+    /// A path relative to the including file.
+    Path(String),
+    /// A path appended to `OUT_DIR`, e.g. `/generated.rs`.
+    OutDir(String),
+}
+
+/// The argument of `include!(...)`, made of string literals and `env!("OUT_DIR")`, which
+/// can only come first, joined by `concat!`.
+fn include_argument(token_tree: &ast::TokenTree) -> Option<IncludeArgument> {
+    let mut out_dir = false;
+    let mut in_env = false;
+    let mut path = String::new();
+    for token in token_tree
+        .syntax()
+        .descendants_with_tokens()
+        .filter_map(|element| element.into_token())
+    {
+        match token.kind() {
+            SyntaxKind::IDENT if token.text() == "env" => in_env = true,
+            SyntaxKind::IDENT if token.text() == "concat" => {}
+            SyntaxKind::IDENT => return None,
+            SyntaxKind::STRING => {
+                let literal = string_literal(token.text())?;
+                if in_env {
+                    if literal != "OUT_DIR" || !path.is_empty() || out_dir {
+                        return None;
+                    }
+                    out_dir = true;
+                    in_env = false;
+                } else {
+                    path.push_str(&literal);
+                }
+            }
+            _ => {}
+        }
+    }
+    if out_dir {
+        Some(IncludeArgument::OutDir(path))
+    } else if !path.is_empty() {
+        Some(IncludeArgument::Path(path))
+    } else {
+        None
+    }
+}
+
+/// `rest` in the `OUT_DIR` of the latest build of `package` which has it. Build scripts
+/// run in `<target>/[<triple>/]<profile>/build/<package>-<hash>/out`.
+fn out_dir_file(metadata: &Metadata, package: &str, rest: &str) -> Option<PathBuf> {
+    let target_dir = &metadata.target_directory;
+    let mut profile_dirs = vec![];
+    for dir in read_dirs(target_dir) {
+        if dir.join("build").is_dir() {
+            profile_dirs.push(dir);
+        } else {
+            profile_dirs.extend(read_dirs(&dir));
+        }
+    }
+    let prefix = format!("{}-", package);
+    let mut candidates: Vec<(SystemTime, PathBuf)> = profile_dirs
+        .iter()
+        .flat_map(|dir| read_dirs(&dir.join("build")))
+        .filter(|dir| {
+            let name = dir
+                .file_name()
+                .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+            name.starts_with(&prefix)
+                && name.len() == prefix.len() + 16
+                && name[prefix.len()..].chars().all(|c| c.is_ascii_hexdigit())
+        })
+        .map(|dir| dir.join("out").join(rest.trim_start_matches('/')))
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((modified, path))
+        })
+        .collect();
+    candidates.sort();
+    candidates.pop().map(|(_, path)| path)
+}
+
+/// The directories in `dir`, none if it cannot be read.
+fn read_dirs(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// `path` without its `.` and `..` components, which `include!` paths are relative with,
+/// without following symbolic links.
+fn lexical(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                result.pop();
+            }
+            component => result.push(component),
+        }
+    }
+    result
+}
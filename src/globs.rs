@@ -0,0 +1,188 @@
+use std::{collections::BTreeSet, fmt, path::Path};
+
+use serde::Serialize;
+
+use crate::{
+    error::Result,
+    options::{AnalysisConfig, LoadConfig},
+    used_item::{analyze_files, glob_imports_in_file, load_workspace, Diagnostic, Location},
+};
+
+/// The glob imports of the workspace, see `find_glob_imports`.
+#[derive(Debug, Default, Serialize)]
+pub struct GlobImports {
+    imports: Vec<GlobImport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// A `use ...::*` of a workspace file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct GlobImport {
+    /// The key of the file, e.g. `foo/src/bar.rs`.
+    file: String,
+    /// The path of the use tree as written, e.g. `std::io::prelude::*`.
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+    /// The path of the module or the enum the glob imports from, when it resolves.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    module: Option<String>,
+    /// The names of the imported items which the file refers to, when the glob could be
+    /// expanded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    referenced: Option<BTreeSet<String>>,
+    /// Whether the allowlist given to `find_glob_imports` lets the glob be.
+    #[serde(skip_serializing_if = "is_false")]
+    allowed: bool,
+}
+
+impl GlobImports {
+    pub fn imports(&self) -> &[GlobImport] {
+        &self.imports
+    }
+
+    /// The glob imports which the allowlist does not cover.
+    pub fn denied(&self) -> impl Iterator<Item = &GlobImport> {
+        self.imports.iter().filter(|import| !import.allowed)
+    }
+
+    /// The files of the workspace whose glob imports could not be listed.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+}
+
+impl GlobImport {
+    pub(crate) fn new(
+        path: String,
+        location: Option<Location>,
+        module: Option<String>,
+        referenced: Option<BTreeSet<String>>,
+    ) -> Self {
+        GlobImport {
+            file: String::new(),
+            path,
+            location,
+            module,
+            referenced,
+            allowed: false,
+        }
+    }
+
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Where the use tree is in `file`.
+    pub fn location(&self) -> Option<Location> {
+        self.location
+    }
+
+    pub fn module(&self) -> Option<&str> {
+        self.module.as_ref().map(String::as_str)
+    }
+
+    pub fn referenced(&self) -> Option<&BTreeSet<String>> {
+        self.referenced.as_ref()
+    }
+
+    pub fn is_allowed(&self) -> bool {
+        self.allowed
+    }
+
+    /// The use tree importing the referenced names explicitly, e.g. `std::io::{Read, Write}`,
+    /// or `None` when the glob could not be expanded. An empty list means the glob can be
+    /// removed.
+    pub fn replacement(&self) -> Option<String> {
+        let referenced = self.referenced.as_ref()?;
+        let prefix = self.path.trim_end_matches('*').trim_end_matches("::");
+        let names: Vec<&str> = referenced.iter().map(String::as_str).collect();
+        Some(match names.as_slice() {
+            [] => String::new(),
+            [name] => format!("{}::{}", prefix, name),
+            names => format!("{}::{{{}}}", prefix, names.join(", ")),
+        })
+    }
+
+    /// Whether one of the `allow` entries names the glob, by its path as written or by the
+    /// module it resolves to, with or without the trailing `::*`.
+    fn is_allowed_by(&self, allow: &[String]) -> bool {
+        let written = self.path.trim_end_matches("::*");
+        allow.iter().any(|entry| {
+            let entry = entry.trim_end_matches("::*");
+            entry == written || self.module.as_ref().map_or(false, |module| module == entry)
+        })
+    }
+}
+
+impl fmt::Display for GlobImports {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for import in &self.imports {
+            write!(f, "{}", import.file)?;
+            if let Some(location) = import.location {
+                write!(f, ":{}:{}", location.line, location.col)?;
+            }
+            write!(f, ": `{}`", import.path)?;
+            if let Some(module) = &import.module {
+                write!(f, " from `{}`", module)?;
+            }
+            match import.replacement() {
+                Some(replacement) if replacement.is_empty() => write!(f, ", unused")?,
+                Some(replacement) => write!(f, ", replace with `{}`", replacement)?,
+                None => write!(f, ", could not be expanded")?,
+            }
+            if import.allowed {
+                write!(f, " (allowed)")?;
+            }
+            writeln!(f)?;
+        }
+        for diagnostic in &self.diagnostics {
+            writeln!(
+                f,
+                "failed to list the glob imports of {}: {}",
+                diagnostic.file(),
+                diagnostic.error()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Lists the glob imports of every file of the workspace containing `dir`, ordered by file
+/// then by position, with the names of their items each file refers to, so that they can
+/// be replaced with explicit imports.
+///
+/// The globs named in `allow`, by their path as written, e.g. `super::*`, or by the module
+/// they resolve to, e.g. `std::io::prelude`, are marked as allowed.
+pub fn find_glob_imports<Q: AsRef<Path>>(dir: Q, allow: &[String]) -> Result<GlobImports> {
+    let mut loaded = load_workspace(dir.as_ref(), &LoadConfig::default())?;
+    let analyzed = analyze_files(
+        &mut loaded.host,
+        &loaded.source_map,
+        Some(&loaded.metadata),
+        &AnalysisConfig::default(),
+        glob_imports_in_file,
+    )?;
+
+    let mut imports = vec![];
+    for (file, file_imports) in analyzed.values {
+        for mut import in file_imports {
+            import.file = file.clone();
+            import.allowed = import.is_allowed_by(allow);
+            imports.push(import);
+        }
+    }
+    Ok(GlobImports {
+        imports,
+        diagnostics: analyzed.diagnostics,
+    })
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
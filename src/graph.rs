@@ -0,0 +1,344 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
+};
+
+use serde::Serialize;
+
+use crate::{dot::quote, origin::OriginKind, used_item::CrateMap};
+
+/// Which crates each workspace member imports items from, with the number of items.
+#[derive(Debug, Default, Serialize)]
+pub struct MemberGraph {
+    edges: Vec<Edge>,
+    /// The groups of members which use each other, which cargo only allows through
+    /// dev-dependencies, each ordered by name.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cycles: Vec<Vec<String>>,
+    #[serde(skip)]
+    members: BTreeSet<String>,
+}
+
+/// The items a member imports from a crate.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Edge {
+    from: String,
+    to: String,
+    /// How many used items the files of `from` import from `to`, counting an item once
+    /// per file importing it.
+    items: usize,
+}
+
+impl Edge {
+    pub fn from(&self) -> &str {
+        &self.from
+    }
+
+    pub fn to(&self) -> &str {
+        &self.to
+    }
+
+    pub fn items(&self) -> usize {
+        self.items
+    }
+}
+
+impl MemberGraph {
+    /// Builds the graph of the imports of `map` whose origin is known, only keeping those of
+    /// other members when `workspace_only` is set. Imports within a member are left out.
+    pub fn new(map: &CrateMap, workspace_only: bool) -> Self {
+        let mut members = BTreeSet::new();
+        let mut weights: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+        for (file, used_item_map) in map.files() {
+            let from = match map.package_of(file) {
+                Some(package) => package,
+                None => continue,
+            };
+            members.insert(from.to_owned());
+            for item in used_item_map.iter() {
+                let origin = match item.origin() {
+                    Some(origin) => origin,
+                    None => continue,
+                };
+                if origin.crate_name() == from
+                    || (workspace_only && origin.kind() != OriginKind::Local)
+                {
+                    continue;
+                }
+                *weights.entry((from, origin.crate_name())).or_insert(0) += 1;
+            }
+        }
+
+        let edges: Vec<Edge> = weights
+            .into_iter()
+            .map(|((from, to), items)| Edge {
+                from: from.to_owned(),
+                to: to.to_owned(),
+                items,
+            })
+            .collect();
+        let cycles = find_cycles(&edges);
+        MemberGraph {
+            edges,
+            cycles,
+            members,
+        }
+    }
+
+    /// The edges, ordered by `from` then `to`.
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    pub fn cycles(&self) -> &[Vec<String>] {
+        &self.cycles
+    }
+
+    /// Renders the graph for Graphviz. Edges are labelled and weighted by their number of
+    /// items, those within a cycle are red, and crates which are not members are boxes.
+    pub fn to_dot(&self) -> String {
+        let in_cycle: HashMap<&str, usize> = self
+            .cycles
+            .iter()
+            .enumerate()
+            .flat_map(|(i, cycle)| cycle.iter().map(move |member| (member.as_str(), i)))
+            .collect();
+
+        let mut dot = String::from("digraph members {\n");
+        for member in &self.members {
+            dot.push_str(&format!("    {};\n", quote(member)));
+        }
+        let others: BTreeSet<&str> = self
+            .edges
+            .iter()
+            .map(|edge| edge.to.as_str())
+            .filter(|to| !self.members.contains(*to))
+            .collect();
+        for other in others {
+            dot.push_str(&format!("    {} [shape=box];\n", quote(other)));
+        }
+        for edge in &self.edges {
+            let cyclic = match (in_cycle.get(edge.from()), in_cycle.get(edge.to())) {
+                (Some(from), Some(to)) => from == to,
+                _ => false,
+            };
+            dot.push_str(&format!(
+                "    {} -> {} [label=\"{}\", weight={}{}];\n",
+                quote(&edge.from),
+                quote(&edge.to),
+                edge.items,
+                edge.items,
+                if cyclic { ", color=red" } else { "" }
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl fmt::Display for MemberGraph {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for edge in &self.edges {
+            writeln!(f, "{} -> {}: {} items", edge.from, edge.to, edge.items)?;
+        }
+        for cycle in &self.cycles {
+            writeln!(f, "cycle between {}", cycle.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The strongly connected components of more than one node, found with Tarjan's algorithm.
+fn find_cycles(edges: &[Edge]) -> Vec<Vec<String>> {
+    let mut successors: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for edge in edges {
+        successors.entry(&edge.from).or_default().push(&edge.to);
+        successors.entry(&edge.to).or_default();
+    }
+
+    let mut tarjan = Tarjan {
+        successors: &successors,
+        next_index: 0,
+        indices: HashMap::new(),
+        low_links: HashMap::new(),
+        stack: vec![],
+        on_stack: HashSet::new(),
+        cycles: vec![],
+    };
+    for node in successors.keys() {
+        if !tarjan.indices.contains_key(node) {
+            tarjan.visit(node);
+        }
+    }
+    let mut cycles = tarjan.cycles;
+    cycles.sort();
+    cycles
+}
+
+struct Tarjan<'a> {
+    successors: &'a BTreeMap<&'a str, Vec<&'a str>>,
+    next_index: usize,
+    indices: HashMap<&'a str, usize>,
+    low_links: HashMap<&'a str, usize>,
+    stack: Vec<&'a str>,
+    on_stack: HashSet<&'a str>,
+    cycles: Vec<Vec<String>>,
+}
+
+impl<'a> Tarjan<'a> {
+    fn visit(&mut self, node: &'a str) {
+        self.indices.insert(node, self.next_index);
+        self.low_links.insert(node, self.next_index);
+        self.next_index += 1;
+        self.stack.push(node);
+        self.on_stack.insert(node);
+
+        for &successor in &self.successors[node] {
+            if !self.indices.contains_key(successor) {
+                self.visit(successor);
+                let low_link = self.low_links[node].min(self.low_links[successor]);
+                self.low_links.insert(node, low_link);
+            } else if self.on_stack.contains(successor) {
+                let low_link = self.low_links[node].min(self.indices[successor]);
+                self.low_links.insert(node, low_link);
+            }
+        }
+
+        if self.low_links[node] == self.indices[node] {
+            let mut component = vec![];
+            while let Some(member) = self.stack.pop() {
+                self.on_stack.remove(member);
+                component.push(member.to_owned());
+                if member == node {
+                    break;
+                }
+            }
+            if component.len() > 1 {
+                component.sort();
+                self.cycles.push(component);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn item(path: &str, origin: &str, krate: &str) -> Value {
+        json!({
+            "name": path.rsplit("::").next().unwrap(),
+            "path": path,
+            "kind": "struct",
+            "origin": {"kind": origin, "crate": krate},
+            "count": 1
+        })
+    }
+
+    /// `a` and `b` importing from each other, `c` from `a`, `serde` and `std`.
+    fn map() -> CrateMap {
+        CrateMap::from_json(json!({
+            "a/src/lib.rs": {
+                "structs": [
+                    item("a::Inner", "local", "a"),
+                    item("b::Left", "local", "b"),
+                    item("b::Right", "local", "b")
+                ]
+            },
+            "a/src/shapes.rs": {"structs": [item("b::Left", "local", "b")]},
+            "b/src/lib.rs": {"structs": [item("a::Inner", "local", "a")]},
+            "c/src/lib.rs": {
+                "structs": [
+                    item("a::Inner", "local", "a"),
+                    item("serde::Serializer", "external", "serde"),
+                    item("std::string::String", "std", "std"),
+                    {"name": "Unknown", "path": "Unknown", "kind": "struct", "count": 1}
+                ]
+            }
+        }))
+    }
+
+    fn edges(graph: &MemberGraph) -> Vec<(&str, &str, usize)> {
+        graph
+            .edges()
+            .iter()
+            .map(|edge| (edge.from(), edge.to(), edge.items()))
+            .collect()
+    }
+
+    #[test]
+    fn edges_count_the_items_of_every_file() {
+        let graph = MemberGraph::new(&map(), false);
+        assert_eq!(
+            edges(&graph),
+            vec![
+                ("a", "b", 3),
+                ("b", "a", 1),
+                ("c", "a", 1),
+                ("c", "serde", 1),
+                ("c", "std", 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn workspace_only_drops_the_external_crates() {
+        let graph = MemberGraph::new(&map(), true);
+        assert_eq!(
+            edges(&graph),
+            vec![("a", "b", 3), ("b", "a", 1), ("c", "a", 1)]
+        );
+    }
+
+    #[test]
+    fn members_using_each_other_are_a_cycle() {
+        let graph = MemberGraph::new(&map(), false);
+        assert_eq!(graph.cycles(), &[vec!["a".to_owned(), "b".to_owned()]]);
+        assert_eq!(
+            graph.to_string(),
+            "a -> b: 3 items\nb -> a: 1 items\nc -> a: 1 items\nc -> serde: 1 items\n\
+             c -> std: 1 items\ncycle between a, b\n"
+        );
+        assert_eq!(MemberGraph::new(&map(), true).cycles().len(), 1);
+    }
+
+    #[test]
+    fn cycles_are_red_and_external_crates_boxes() {
+        assert_eq!(
+            MemberGraph::new(&map(), false).to_dot(),
+            r#"digraph members {
+    "a";
+    "b";
+    "c";
+    "serde" [shape=box];
+    "std" [shape=box];
+    "a" -> "b" [label="3", weight=3, color=red];
+    "b" -> "a" [label="1", weight=1, color=red];
+    "c" -> "a" [label="1", weight=1];
+    "c" -> "serde" [label="1", weight=1];
+    "c" -> "std" [label="1", weight=1];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn a_chain_has_no_cycle() {
+        let edge = |from: &str, to: &str| Edge {
+            from: from.to_owned(),
+            to: to.to_owned(),
+            items: 1,
+        };
+        let chain = [edge("a", "b"), edge("b", "c"), edge("a", "c")];
+        assert!(find_cycles(&chain).is_empty());
+        let ring = [
+            edge("a", "b"),
+            edge("b", "c"),
+            edge("c", "a"),
+            edge("d", "a"),
+        ];
+        assert_eq!(find_cycles(&ring), vec![vec!["a", "b", "c"]]);
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::used_item::{CrateMap, UsedItemKind};
+
+/// The page `render_html` fills in, which loads nothing from the network.
+const TEMPLATE: &str = include_str!("report.html");
+const DATA_PLACEHOLDER: &str = "/*DATA*/";
+
+/// What the page is generated from. Every distinct item is listed once and files refer to
+/// them by index, which keeps the page small and lets it filter over flat arrays.
+#[derive(Serialize)]
+struct ReportData<'a> {
+    /// The names of the kinds, indexed by `UsedItemKind::ALL`.
+    kinds: Vec<&'static str>,
+    /// The crates items come from, the first one standing for an unknown origin.
+    crates: Vec<&'a str>,
+    /// The name, path, kind index and crate index of every distinct item.
+    items: Vec<(&'a str, &'a str, usize, usize)>,
+    /// The items of every file, by package then by file.
+    packages: BTreeMap<&'a str, BTreeMap<&'a str, Vec<usize>>>,
+    /// The items of every crate, indexed like `crates`.
+    by_origin: Vec<Vec<usize>>,
+}
+
+/// Renders `map` as a self-contained HTML page, as `--format html` emits, with a section per
+/// package and a search box filtering the items by name, path or crate.
+pub fn render_html(map: &CrateMap) -> String {
+    let mut data = ReportData {
+        kinds: UsedItemKind::ALL.iter().map(|kind| kind.name()).collect(),
+        crates: vec![""],
+        items: vec![],
+        packages: BTreeMap::new(),
+        by_origin: vec![vec![]],
+    };
+    let mut crate_indices: HashMap<&str, usize> = HashMap::new();
+    let mut item_indices: HashMap<(&str, &str, usize, usize), usize> = HashMap::new();
+
+    for (file, kind, item) in map.iter() {
+        let crate_index = match item.origin() {
+            Some(origin) => {
+                let crates = &mut data.crates;
+                let by_origin = &mut data.by_origin;
+                *crate_indices.entry(origin.crate_name()).or_insert_with(|| {
+                    crates.push(origin.crate_name());
+                    by_origin.push(vec![]);
+                    crates.len() - 1
+                })
+            }
+            None => 0,
+        };
+        let kind_index = UsedItemKind::ALL
+            .iter()
+            .position(|&k| k == kind)
+            .unwrap_or_default();
+        let key = (item.name(), item.path(), kind_index, crate_index);
+        let items = &mut data.items;
+        let by_origin = &mut data.by_origin;
+        let item_index = *item_indices.entry(key).or_insert_with(|| {
+            items.push(key);
+            by_origin[crate_index].push(items.len() - 1);
+            items.len() - 1
+        });
+
+        let package = map.package_of(file).unwrap_or_default();
+        data.packages
+            .entry(package)
+            .or_default()
+            .entry(file)
+            .or_default()
+            .push(item_index);
+    }
+
+    // `</script>` in an item name must not end the data block.
+    let json = serde_json::to_string(&data)
+        .expect("failed to serialize the report")
+        .replace("</", "<\\/");
+    TEMPLATE.replace(DATA_PLACEHOLDER, &json)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn names_do_not_end_the_data_block() {
+        let map: CrateMap = serde_json::from_value(json!({
+            "app/src/lib.rs": {
+                "structs": [{
+                    "name": "</script><b>&",
+                    "path": "app::</script><b>&",
+                    "kind": "struct",
+                    "count": 1
+                }]
+            }
+        }))
+        .unwrap();
+        let page = render_html(&map);
+        assert_eq!(page.matches("</script>").count(), 2, "{}", page);
+        assert!(page.contains(r#""<\/script><b>&""#), "{}", page);
+        assert!(page.contains(r#""app::<\/script><b>&""#), "{}", page);
+    }
+}
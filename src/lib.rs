@@ -0,0 +1,88 @@
+//! Lists the items each file of a cargo workspace imports with `use` declarations.
+
+mod analysis;
+#[doc(hidden)]
+pub mod app;
+mod baseline;
+mod cache;
+mod cfg;
+mod consolidate;
+mod coverage;
+mod crates;
+mod dead_exports;
+mod definition_path;
+mod deprecated;
+mod deps;
+mod diff;
+mod dot;
+mod error;
+mod file_filter;
+mod file_paths;
+mod generated;
+mod globs;
+mod graph;
+mod html;
+mod lints;
+mod markdown;
+mod options;
+mod origin;
+pub mod output;
+mod platform;
+mod progress;
+mod query;
+mod remote;
+mod sarif;
+mod schema;
+mod serve;
+mod sqlite;
+mod stats;
+mod summary;
+mod syntax;
+mod sysroot;
+mod target;
+mod unused;
+mod used_item;
+mod watch;
+
+pub use crate::{
+    analysis::{CommonImports, Prelude},
+    baseline::{Baseline, BaselineEntry},
+    consolidate::{suggest_consolidations, Consolidations, Patch},
+    coverage::{find_api_coverage, ApiCoverage, ModuleCoverage},
+    crates::{CrateUsage, CrateUsages},
+    dead_exports::{find_dead_exports, DeadExport, DeadExports},
+    deprecated::{DeprecatedImport, DeprecatedImports},
+    deps::{audit_dependencies, find_unused_dependencies, DepsAudit},
+    diff::{CrateMapDiff, FileDiff},
+    dot::FileGraph,
+    error::{MergeConflict, Result, UsedumpError},
+    file_filter::FileFilter,
+    file_paths::FilePaths,
+    globs::{find_glob_imports, GlobImport, GlobImports},
+    graph::{Edge, MemberGraph},
+    html::render_html,
+    lints::{Lint, LintImport, LintKind, Lints},
+    markdown::{render_markdown, MarkdownOptions},
+    options::{dump_streaming, DumpOptions, GeneratedMode, Keys, ResolveReexports},
+    origin::{DefinedIn, Origin, OriginFilter, OriginKind},
+    progress::Progress,
+    query::{Importers, ItemQuery},
+    remote::{clone_git, fetch_crate, FetchedCrate},
+    sarif::SarifLog,
+    schema::{dump_schema, DUMP_SCHEMA},
+    serve::Server,
+    sqlite::{write_sqlite, SQLITE_SCHEMA_VERSION},
+    stats::DumpStats,
+    summary::Summary,
+    target::TargetKind,
+    unused::{
+        find_unused_imports, find_unused_imports_in_cargo, find_unused_imports_with_locations,
+        UnusedImport, UnusedImports,
+    },
+    used_item::{
+        find_lockfile, find_manifest, list_used_items_in_cargo, list_used_items_in_file,
+        list_used_items_in_source, list_used_items_with_host, normalize_cfg, CrateMap, Diagnostic,
+        FileEntry, LegacyNames, Location, MacroKind, SourceMap, StreamedDump, UsedItem,
+        UsedItemKind, UsedItemMap, UNKNOWN_PACKAGE_KEY, UNREACHABLE_KEY,
+    },
+};
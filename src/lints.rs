@@ -0,0 +1,338 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use serde::Serialize;
+
+use crate::used_item::{CrateMap, Location, UsedItem, UsedItemKind};
+
+/// The kind of problem a `Lint` reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LintKind {
+    /// The same item imported more than once under the same name in the same scope.
+    DuplicateImport,
+    /// Different items imported under the same name in the same scope and namespace.
+    NameConflict,
+    /// An import in a function under the name of an import of the scope enclosing it, only
+    /// reported when asked for.
+    Shadowing,
+    /// An import of an item of the standard library gated behind a nightly feature.
+    UnstableFeature,
+}
+
+impl LintKind {
+    pub const ALL: &'static [LintKind] = &[
+        LintKind::DuplicateImport,
+        LintKind::NameConflict,
+        LintKind::Shadowing,
+        LintKind::UnstableFeature,
+    ];
+
+    pub const NAMES: &'static [&'static str] = &[
+        "duplicate-import",
+        "name-conflict",
+        "shadowing",
+        "unstable-feature",
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LintKind::DuplicateImport => "duplicate-import",
+            LintKind::NameConflict => "name-conflict",
+            LintKind::Shadowing => "shadowing",
+            LintKind::UnstableFeature => "unstable-feature",
+        }
+    }
+}
+
+impl FromStr for LintKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LintKind::ALL
+            .iter()
+            .find(|kind| kind.name() == s)
+            .copied()
+            .ok_or_else(|| {
+                format!(
+                    "unknown lint `{}`, expected one of: {}",
+                    s,
+                    LintKind::NAMES.join(", ")
+                )
+            })
+    }
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The imports of a file which bind the same name, or an import of an unstable item, see
+/// `CrateMap::lints`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Lint {
+    kind: LintKind,
+    file: String,
+    /// The name the imports bind.
+    name: String,
+    /// The imports, ordered by location, the shadowed one first for `Shadowing`.
+    imports: Vec<LintImport>,
+}
+
+/// One of the imports of a `Lint`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintImport {
+    path: String,
+    kind: UsedItemKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+    /// The nightly feature gating the item, for `UnstableFeature`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    feature: Option<String>,
+}
+
+/// The duplicate and conflicting imports of every file.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(transparent)]
+pub struct Lints {
+    lints: Vec<Lint>,
+}
+
+impl Lints {
+    /// Finds the lints of every file of `crate_map`, ordered by file. Imports through globs
+    /// and as `_`, `extern crate` items and derives are left out, and imports guarded by
+    /// different `#[cfg(...)]` predicates never clash. Shadowing is only reported with
+    /// `shadowing`, as importing a name again in a function is legitimate. Every import of
+    /// an unstable item of the standard library is reported on its own.
+    pub(crate) fn new(crate_map: &CrateMap, shadowing: bool) -> Self {
+        let mut lints = vec![];
+        for (file, used_item_map) in crate_map.files() {
+            let mut unstable: Vec<&UsedItem> = used_item_map
+                .iter()
+                .filter(|item| item.unstable_feature().is_some())
+                .collect();
+            unstable.sort_by_key(|item| item.location());
+            for item in unstable {
+                let name = item.local_name();
+                lints.push(Lint::new(LintKind::UnstableFeature, file, name, &[item]));
+            }
+
+            // Imports by scope, cfgs and local name.
+            let mut bindings: BTreeMap<(Option<&str>, &[String], &str), Vec<&UsedItem>> =
+                BTreeMap::new();
+            for item in used_item_map.iter() {
+                let binds = !item.via_glob()
+                    && !item.anonymous()
+                    && !item.extern_crate()
+                    && item.kind() != UsedItemKind::Derive;
+                if binds {
+                    bindings
+                        .entry((item.scope(), item.cfgs(), item.local_name()))
+                        .or_default()
+                        .push(item);
+                }
+            }
+
+            for ((_, _, name), items) in &bindings {
+                let mut by_path: BTreeMap<(&str, UsedItemKind), Vec<&UsedItem>> = BTreeMap::new();
+                for &item in items {
+                    by_path
+                        .entry((item.path(), item.kind()))
+                        .or_default()
+                        .push(item);
+                }
+                for duplicates in by_path.values().filter(|items| items.len() > 1) {
+                    lints.push(Lint::new(LintKind::DuplicateImport, file, name, duplicates));
+                }
+
+                let distinct: Vec<&UsedItem> = by_path
+                    .values()
+                    .map(|items| items[0])
+                    .filter(|item| !item.unresolved())
+                    .collect();
+                for (i, a) in distinct.iter().enumerate() {
+                    for b in &distinct[i + 1..] {
+                        if a.path() != b.path() && share_namespace(a.kind(), b.kind()) {
+                            lints.push(Lint::new(LintKind::NameConflict, file, name, &[*a, *b]));
+                        }
+                    }
+                }
+            }
+
+            if !shadowing {
+                continue;
+            }
+            for ((scope, cfgs, name), items) in &bindings {
+                let scope = match scope {
+                    Some(scope) => *scope,
+                    None => continue,
+                };
+                let outer = bindings
+                    .iter()
+                    .filter(|((outer_scope, outer_cfgs, outer_name), _)| {
+                        outer_name == name && outer_cfgs == cfgs && encloses(*outer_scope, scope)
+                    })
+                    .flat_map(|(_, outer)| outer);
+                for &shadowed in outer {
+                    for &item in items.iter().filter(|item| {
+                        item.path() != shadowed.path()
+                            && share_namespace(item.kind(), shadowed.kind())
+                    }) {
+                        lints.push(Lint::new(
+                            LintKind::Shadowing,
+                            file,
+                            name,
+                            &[shadowed, item],
+                        ));
+                    }
+                }
+            }
+        }
+        Lints { lints }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lints.is_empty()
+    }
+
+    pub fn lints(&self) -> &[Lint] {
+        &self.lints
+    }
+}
+
+impl Lint {
+    fn new(kind: LintKind, file: &str, name: &str, items: &[&UsedItem]) -> Self {
+        let mut imports: Vec<LintImport> = items
+            .iter()
+            .map(|item| LintImport {
+                path: item.path().to_owned(),
+                kind: item.kind(),
+                scope: item.scope().map(str::to_owned),
+                location: item.location(),
+                feature: item.unstable_feature().map(str::to_owned),
+            })
+            .collect();
+        if kind != LintKind::Shadowing {
+            imports.sort_by_key(|import| import.location);
+        }
+        Lint {
+            kind,
+            file: file.to_owned(),
+            name: name.to_owned(),
+            imports,
+        }
+    }
+
+    pub fn kind(&self) -> LintKind {
+        self.kind
+    }
+
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn imports(&self) -> &[LintImport] {
+        &self.imports
+    }
+}
+
+impl LintImport {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn kind(&self) -> UsedItemKind {
+        self.kind
+    }
+
+    pub fn scope(&self) -> Option<&str> {
+        self.scope.as_ref().map(String::as_str)
+    }
+
+    pub fn location(&self) -> Option<Location> {
+        self.location
+    }
+
+    /// The nightly feature gating the item, only told for `LintKind::UnstableFeature`.
+    pub fn feature(&self) -> Option<&str> {
+        self.feature.as_ref().map(String::as_str)
+    }
+}
+
+impl fmt::Display for Lints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for lint in &self.lints {
+            let message = match lint.kind {
+                LintKind::DuplicateImport => "is imported more than once".to_owned(),
+                LintKind::NameConflict => "is bound to different items".to_owned(),
+                LintKind::Shadowing => "shadows an import of an enclosing scope".to_owned(),
+                LintKind::UnstableFeature => {
+                    let feature = lint.imports.iter().find_map(|import| import.feature());
+                    format!(
+                        "is gated behind the unstable feature `{}`",
+                        feature.unwrap_or_default()
+                    )
+                }
+            };
+            writeln!(
+                f,
+                "{}: `{}` {} [{}]",
+                lint.file, lint.name, message, lint.kind
+            )?;
+            for import in &lint.imports {
+                write!(f, "  {} `{}`", import.kind.name(), import.path)?;
+                if let Some(location) = import.location {
+                    write!(f, " at {}:{}", location.line, location.col)?;
+                }
+                if let Some(scope) = &import.scope {
+                    write!(f, " in `{}`", scope)?;
+                }
+                writeln!(f)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether the imports of the scope `outer`, `None` standing for the top level of the
+/// file, are visible in `inner`, i.e. whether only functions lead from one to the other.
+/// Inline modules do not see the imports of their parent.
+fn encloses(outer: Option<&str>, inner: &str) -> bool {
+    let rest = match outer {
+        None => inner,
+        Some(outer) if inner.starts_with(&format!("{}::", outer)) => &inner[outer.len() + 2..],
+        Some(_) => return false,
+    };
+    rest.split("::").all(|segment| segment.ends_with("()"))
+}
+
+/// Whether items of the two kinds may be bound to the same name in the same namespace.
+/// Structs are taken to be in both the type and the value namespaces, as tuple structs
+/// and unit structs are.
+fn share_namespace(a: UsedItemKind, b: UsedItemKind) -> bool {
+    let (a_types, a_values, a_macros) = namespaces(a);
+    let (b_types, b_values, b_macros) = namespaces(b);
+    (a_types && b_types) || (a_values && b_values) || (a_macros && b_macros)
+}
+
+/// Whether items of `kind` live in the type, the value and the macro namespaces.
+fn namespaces(kind: UsedItemKind) -> (bool, bool, bool) {
+    match kind {
+        UsedItemKind::Module
+        | UsedItemKind::Trait
+        | UsedItemKind::Enum
+        | UsedItemKind::TypeAlias
+        | UsedItemKind::Union => (true, false, false),
+        UsedItemKind::Struct | UsedItemKind::EnumVariant => (true, true, false),
+        UsedItemKind::Fn | UsedItemKind::Const | UsedItemKind::Static => (false, true, false),
+        UsedItemKind::Macro | UsedItemKind::Derive => (false, false, true),
+        UsedItemKind::Other => (false, false, false),
+    }
+}
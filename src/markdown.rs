@@ -0,0 +1,124 @@
+use std::fmt::Write;
+
+use crate::used_item::{CrateMap, UsedItem, UsedItemKind};
+
+/// How `render_markdown` lays out a report.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MarkdownOptions {
+    collapsible: bool,
+}
+
+impl MarkdownOptions {
+    /// The number of items above which `collapsible` folds a list.
+    pub const COLLAPSE_AFTER: usize = 10;
+
+    pub fn new() -> Self {
+        MarkdownOptions::default()
+    }
+
+    /// Folds the lists of more than `COLLAPSE_AFTER` items into `<details>` blocks.
+    pub fn collapsible(mut self, yes: bool) -> Self {
+        self.collapsible = yes;
+        self
+    }
+}
+
+/// Renders `map` as a Markdown report, as `--format markdown` emits: a table summarizing the
+/// map, then a section per key, i.e. per file or per package, listing its items by kind.
+pub fn render_markdown(map: &CrateMap, options: &MarkdownOptions) -> String {
+    let summary = map.summarize();
+    let mut md = String::from("# Used items\n\n| | |\n|---|---:|\n");
+    // Writing to a `String` cannot fail.
+    writeln!(md, "| Files | {} |", summary.files()).unwrap();
+    writeln!(
+        md,
+        "| External crates | {} |",
+        summary.external_crates().len()
+    )
+    .unwrap();
+    for &kind in UsedItemKind::ALL {
+        let count = summary.unique_items(kind);
+        if count > 0 {
+            writeln!(md, "| {} | {} |", title(kind), count).unwrap();
+        }
+    }
+    if !summary.external_crates().is_empty() {
+        let crates: Vec<String> = summary
+            .external_crates()
+            .iter()
+            .map(|name| format!("`{}`", name))
+            .collect();
+        writeln!(md, "\nExternal crates: {}.", crates.join(", ")).unwrap();
+    }
+
+    for (key, used_item_map) in map.files() {
+        if used_item_map.is_empty() {
+            continue;
+        }
+        writeln!(md, "\n## `{}`", key).unwrap();
+        for &kind in UsedItemKind::ALL {
+            let items: Vec<String> = used_item_map.get(kind).iter().map(bullet).collect();
+            write_list(&mut md, options, title(kind), &items);
+        }
+        let globs: Vec<String> = used_item_map
+            .globs()
+            .iter()
+            .map(|glob| format!("- `{}::*`", glob))
+            .collect();
+        write_list(&mut md, options, "Unresolved globs", &globs);
+        let unresolved: Vec<String> = used_item_map
+            .unresolved()
+            .iter()
+            .map(|path| format!("- `{}`", path))
+            .collect();
+        write_list(&mut md, options, "Unresolved", &unresolved);
+    }
+    md
+}
+
+fn write_list(md: &mut String, options: &MarkdownOptions, title: &str, items: &[String]) {
+    if items.is_empty() {
+        return;
+    }
+    if options.collapsible && items.len() > MarkdownOptions::COLLAPSE_AFTER {
+        writeln!(
+            md,
+            "\n<details>\n<summary>{} ({})</summary>\n\n{}\n\n</details>",
+            title,
+            items.len(),
+            items.join("\n")
+        )
+        .unwrap();
+    } else {
+        writeln!(md, "\n### {}\n\n{}", title, items.join("\n")).unwrap();
+    }
+}
+
+fn bullet(item: &UsedItem) -> String {
+    let mut bullet = format!("- `{}`", item.path());
+    if let Some(alias) = item.alias() {
+        write!(bullet, " as `{}`", alias).unwrap();
+    }
+    if let Some(origin) = item.origin() {
+        write!(bullet, " ({})", origin.crate_name()).unwrap();
+    }
+    bullet
+}
+
+fn title(kind: UsedItemKind) -> &'static str {
+    match kind {
+        UsedItemKind::Module => "Modules",
+        UsedItemKind::Trait => "Traits",
+        UsedItemKind::Struct => "Structs",
+        UsedItemKind::Enum => "Enums",
+        UsedItemKind::Fn => "Functions",
+        UsedItemKind::Const => "Constants",
+        UsedItemKind::Static => "Statics",
+        UsedItemKind::TypeAlias => "Type aliases",
+        UsedItemKind::Union => "Unions",
+        UsedItemKind::EnumVariant => "Enum variants",
+        UsedItemKind::Macro => "Macros",
+        UsedItemKind::Derive => "Derives",
+        UsedItemKind::Other => "Others",
+    }
+}
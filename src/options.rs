@@ -0,0 +1,939 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use cargo_metadata::Metadata;
+use ra_db::FileId;
+
+use crate::{
+    cfg,
+    error::Result,
+    file_filter::FileFilter,
+    generated::generated_files,
+    origin::OriginFilter,
+    progress::{Progress, ProgressHandler},
+    stats::DumpStats,
+    syntax::resolve_by_syntax,
+    sysroot::SysrootConfig,
+    used_item::{
+        for_each_file, load_cache, load_metadata, load_workspace, normalize_cfg, resolve_exports,
+        resolve_workspace_with_stats, used_items_in_file_cached, CrateMap, Diagnostic, FileEntry,
+        LoadedWorkspace, StreamedDump,
+    },
+    watch, TargetKind, UsedItemKind, UsedItemMap,
+};
+
+/// Configures a dump of a cargo workspace, run with `run`.
+///
+/// ```no_run
+/// let crate_map = usedump::DumpOptions::new(".")
+///     .packages(&["foo"])
+///     .exclude(&["**/tests/**"])
+///     .run()?;
+/// # Ok::<(), usedump::UsedumpError>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct DumpOptions {
+    path: PathBuf,
+    packages: Vec<String>,
+    targets: BTreeSet<TargetKind>,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    kinds: Vec<UsedItemKind>,
+    cfg_filter: Option<String>,
+    origin_filters: Vec<OriginFilter>,
+    features: CargoFeatures,
+    include_derives: bool,
+    only_reexports: bool,
+    no_reexports: bool,
+    exclude_tests: bool,
+    only_tests: bool,
+    include_empty: bool,
+    locations: bool,
+    definitions: bool,
+    flat_paths: bool,
+    absolute_paths: bool,
+    keys: Keys,
+    generated: GeneratedMode,
+    resolve_reexports: ResolveReexports,
+    jobs: Option<usize>,
+    progress: Option<ProgressHandler>,
+    cache: CacheConfig,
+    cancel: Option<Arc<AtomicBool>>,
+    file_timeout: Option<Duration>,
+    no_resolve: bool,
+    sysroot: SysrootConfig,
+    offline: bool,
+    locked: bool,
+    target_triples: Vec<String>,
+}
+
+impl DumpOptions {
+    /// Dumps the workspace containing `path`, with the libraries and binaries of every
+    /// member analyzed.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        DumpOptions {
+            path: path.as_ref().to_owned(),
+            packages: vec![],
+            targets: TargetKind::DEFAULT.iter().copied().collect(),
+            include: vec![],
+            exclude: vec![],
+            kinds: vec![],
+            cfg_filter: None,
+            origin_filters: vec![],
+            features: CargoFeatures::default(),
+            include_derives: false,
+            only_reexports: false,
+            no_reexports: false,
+            exclude_tests: false,
+            only_tests: false,
+            include_empty: false,
+            locations: false,
+            definitions: false,
+            flat_paths: false,
+            absolute_paths: false,
+            keys: Keys::File,
+            generated: GeneratedMode::Include,
+            resolve_reexports: ResolveReexports::Definition,
+            jobs: None,
+            progress: None,
+            cache: CacheConfig::Disabled,
+            cancel: None,
+            file_timeout: None,
+            no_resolve: false,
+            sysroot: SysrootConfig::Discover,
+            offline: false,
+            locked: false,
+            target_triples: vec![],
+        }
+    }
+
+    /// Only analyzes the workspace members with the given package names.
+    pub fn packages<S: AsRef<str>>(mut self, packages: &[S]) -> Self {
+        self.packages = packages.iter().map(|p| p.as_ref().to_owned()).collect();
+        self
+    }
+
+    /// Only analyzes the files of the given kinds of targets, the libraries and binaries by
+    /// default. A file is attributed to the target whose root is closest to it, e.g.
+    /// `tests/common/mod.rs` to the integration tests.
+    pub fn targets(mut self, targets: &[TargetKind]) -> Self {
+        self.targets = targets.iter().copied().collect();
+        self
+    }
+
+    /// Only analyzes the files matching one of the gitignore-style globs.
+    pub fn include<S: AsRef<str>>(mut self, globs: &[S]) -> Self {
+        self.include = globs.iter().map(|g| g.as_ref().to_owned()).collect();
+        self
+    }
+
+    /// Skips the files matching one of the gitignore-style globs, even if included.
+    pub fn exclude<S: AsRef<str>>(mut self, globs: &[S]) -> Self {
+        self.exclude = globs.iter().map(|g| g.as_ref().to_owned()).collect();
+        self
+    }
+
+    /// Keeps only the used items of the given kinds. Every kind is kept when empty.
+    pub fn kinds(mut self, kinds: &[UsedItemKind]) -> Self {
+        self.kinds = kinds.to_vec();
+        self
+    }
+
+    /// Keeps only the items imported under `#[cfg(predicate)]`, or only the items which
+    /// are not when the predicate is prefixed with `!`.
+    pub fn cfg_filter<S: Into<String>>(mut self, predicate: Option<S>) -> Self {
+        self.cfg_filter = predicate.map(Into::into);
+        self
+    }
+
+    /// Keeps only the used items matching every filter by origin.
+    pub fn origin_filters(mut self, filters: &[OriginFilter]) -> Self {
+        self.origin_filters = filters.to_vec();
+        self
+    }
+
+    /// Activates the given features of the workspace members.
+    ///
+    /// The crates are analyzed with the features cargo enables then, so that the items of
+    /// e.g. optional dependencies resolve as they build. Every file of a member is analyzed
+    /// regardless, so the files under a `mod` declaration gated on a disabled feature, e.g.
+    /// `#[cfg(feature = "serde")] mod ser;`, are dropped afterwards, as are the imports under
+    /// such a `#[cfg(...)]`, including cfgs combining features with e.g. `all` or `not`.
+    pub fn features<S: AsRef<str>>(mut self, features: &[S]) -> Self {
+        self.features.features = features.iter().map(|f| f.as_ref().to_owned()).collect();
+        self
+    }
+
+    /// Activates every feature of the workspace members, see `features`.
+    pub fn all_features(mut self, yes: bool) -> Self {
+        self.features.all_features = yes;
+        self
+    }
+
+    /// Does not activate the default features of the workspace members, see `features`.
+    pub fn no_default_features(mut self, yes: bool) -> Self {
+        self.features.no_default_features = yes;
+        self
+    }
+
+    /// Also lists the derives named in `#[derive(...)]` attributes. They are only resolved
+    /// then, as each costs a `goto_definition` call.
+    pub fn include_derives(mut self, yes: bool) -> Self {
+        self.include_derives = yes;
+        self
+    }
+
+    /// Keeps only the items of `pub use` items, which re-export them, but not those of
+    /// restricted ones such as `pub(crate) use`.
+    pub fn only_reexports(mut self, yes: bool) -> Self {
+        self.only_reexports = yes;
+        self
+    }
+
+    /// Drops the items of `pub use` items, keeping the private and restricted imports.
+    pub fn no_reexports(mut self, yes: bool) -> Self {
+        self.no_reexports = yes;
+        self
+    }
+
+    /// Drops the test code: the items imported under a cfg which only holds for tests,
+    /// e.g. `#[cfg(test)]` or `#[cfg(all(unix, test))]`, on the use item, on an item
+    /// enclosing it such as an inline `mod tests`, or on a `mod` declaration leading to its
+    /// file, along with the files of the integration tests. Without resolution, only the
+    /// attributes in the file itself are seen.
+    pub fn exclude_tests(mut self, yes: bool) -> Self {
+        self.exclude_tests = yes;
+        self
+    }
+
+    /// Keeps only the test code `exclude_tests` drops, analyzing the integration tests
+    /// too.
+    pub fn only_tests(mut self, yes: bool) -> Self {
+        self.only_tests = yes;
+        self
+    }
+
+    /// Keeps the files which have nothing to list, whether they import nothing or nothing is
+    /// left after filtering, e.g. to use the keys as an inventory of files.
+    pub fn include_empty(mut self, yes: bool) -> Self {
+        self.include_empty = yes;
+        self
+    }
+
+    /// Records the line, column and byte range of each import.
+    pub fn locations(mut self, yes: bool) -> Self {
+        self.locations = yes;
+        self
+    }
+
+    /// Records the crate, version and file each imported item is defined in.
+    pub fn definitions(mut self, yes: bool) -> Self {
+        self.definitions = yes;
+        self
+    }
+
+    /// Keys files by their path relative to their package only, as older versions did,
+    /// instead of prefixing it with the package name.
+    ///
+    /// Members with files at the same relative path, e.g. two `src/lib.rs`, then overwrite
+    /// each other.
+    pub fn flat_paths(mut self, yes: bool) -> Self {
+        self.flat_paths = yes;
+        self
+    }
+
+    /// Keys files by their absolute path, taking precedence over `flat_paths`.
+    pub fn absolute_paths(mut self, yes: bool) -> Self {
+        self.absolute_paths = yes;
+        self
+    }
+
+    /// Keys files by their path or by their module path. Files sharing a module path, such
+    /// as the unreachable ones, are merged into one entry.
+    pub fn keys(mut self, keys: Keys) -> Self {
+        self.keys = keys;
+        self
+    }
+
+    /// Skips the generated files, dumps them like the other ones (the default) or apart,
+    /// under the `generated` key.
+    pub fn generated(mut self, generated: GeneratedMode) -> Self {
+        self.generated = generated;
+        self
+    }
+
+    /// Lists the re-exported items under the path of their definition (the default), under
+    /// the path they are imported as, or under both.
+    pub fn resolve_reexports(mut self, resolve_reexports: ResolveReexports) -> Self {
+        self.resolve_reexports = resolve_reexports;
+        self
+    }
+
+    /// Resolves files on `jobs` threads instead of rayon's global thread pool.
+    pub fn jobs(mut self, jobs: Option<usize>) -> Self {
+        self.jobs = jobs;
+        self
+    }
+
+    /// Calls `f` as the dump goes, e.g. to report progress. It is called from the threads
+    /// analyzing files, as each of them is done.
+    pub fn progress<F: Fn(Progress<'_>) + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.progress = Some(ProgressHandler::new(f));
+        self
+    }
+
+    /// Reuses the used items of the files which did not change since the previous cached
+    /// run, keeping the cache in `.usedump-cache` under the target directory.
+    ///
+    /// A file is resolved again when another file of its package, or of a member its
+    /// package depends on, changes. The cache is dropped whenever `Cargo.lock` or the
+    /// version of usedump changes, and when the features or the sysroot differ from the
+    /// previous run. It is not used with `target_triples`.
+    pub fn cache(mut self, yes: bool) -> Self {
+        self.cache = match (yes, self.cache) {
+            (false, _) => CacheConfig::Disabled,
+            (true, CacheConfig::Disabled) => CacheConfig::Default,
+            (true, cache) => cache,
+        };
+        self
+    }
+
+    /// Keeps the cache in `dir` instead of the target directory, enabling it.
+    pub fn cache_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.cache = CacheConfig::In(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Stops the dump once `cancel` is set, e.g. by a Ctrl-C handler, skipping the files
+    /// which are not analyzed yet. The resulting map is then `CrateMap::is_partial`, and
+    /// the result of `run_streaming` `StreamedDump::is_partial`.
+    ///
+    /// The queries in progress are cancelled through the `AnalysisHost`, so the files
+    /// being analyzed are given up on without waiting for them.
+    pub fn cancel_on(mut self, cancel: Arc<AtomicBool>) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Gives up on the files whose resolution takes longer than `timeout`, reporting them
+    /// as diagnostics. Checked between rust-analyzer queries like `cancel_on`.
+    pub fn file_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.file_timeout = timeout;
+        self
+    }
+
+    /// Loads the standard library from the sysroot in `dir`, e.g. as printed by
+    /// `rustc --print sysroot`, or from `dir` itself when it holds the sources of the
+    /// standard library, instead of the sysroot of the toolchain of the workspace.
+    pub fn sysroot<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.sysroot = SysrootConfig::Path(dir.as_ref().to_owned());
+        self
+    }
+
+    /// Loads no standard library, leaving the items of `std`, `core` and `alloc`
+    /// unresolved.
+    pub fn no_sysroot(mut self, yes: bool) -> Self {
+        if yes {
+            self.sysroot = SysrootConfig::Disabled;
+        } else if self.sysroot == SysrootConfig::Disabled {
+            self.sysroot = SysrootConfig::Discover;
+        }
+        self
+    }
+
+    /// Analyzes the crates with the cfgs of the target `triples`, e.g.
+    /// `thumbv7em-none-eabihf`, as `rustc --print cfg --target` prints them, instead of
+    /// those of the host, so that the imports of the modules gated on e.g. `target_os` or
+    /// `target_arch` are listed.
+    ///
+    /// With several triples, the workspace is analyzed for each of them and the imports of
+    /// all are listed, those missing for some annotated with the triples they are there for,
+    /// see `UsedItem::targets`. `watch`, `run_streaming` and `run_exports` only take the
+    /// first triple.
+    pub fn target_triples<S: AsRef<str>>(mut self, triples: &[S]) -> Self {
+        self.target_triples = triples.iter().map(|t| t.as_ref().to_owned()).collect();
+        self
+    }
+
+    /// Loads the workspace without accessing the network, failing with
+    /// `UsedumpError::Offline` when a dependency is not available locally.
+    pub fn offline(mut self, yes: bool) -> Self {
+        self.offline = yes;
+        self
+    }
+
+    /// Loads the workspace without changing its `Cargo.lock`, failing with
+    /// `UsedumpError::LockfileOutOfDate` when it needs to be, and with
+    /// `UsedumpError::MissingLockfile` when there is none.
+    pub fn locked(mut self, yes: bool) -> Self {
+        self.locked = yes;
+        self
+    }
+
+    /// Lists the use trees of the files as written, parsing each of them on its own instead
+    /// of loading the workspace into rust-analyzer, which is much faster.
+    ///
+    /// Only `cargo metadata` is run, to find the members. Every item is then unresolved,
+    /// with its path as written and its kind guessed from its casing, its origin told by the
+    /// first segment of its path, e.g. `super::Foo` is local. Derives are not listed, files
+    /// are keyed by path whatever `keys`, and the cache is not used. Only `run`,
+    /// `run_with_stats` and `run_streaming` honor it.
+    pub fn no_resolve(mut self, yes: bool) -> Self {
+        self.no_resolve = yes;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn run(&self) -> Result<CrateMap> {
+        self.run_with_stats().map(|(map, _)| map)
+    }
+
+    /// Runs the dump like `run`, also returning where the time went.
+    pub fn run_with_stats(&self) -> Result<(CrateMap, DumpStats)> {
+        let started = Instant::now();
+        if self.no_resolve {
+            if let Some(progress) = &self.progress {
+                progress.report(Progress::LoadingWorkspace);
+            }
+            let metadata = load_metadata(&self.path, &self.load_config())?;
+            let load = started.elapsed();
+            let (mut map, mut stats) = resolve_by_syntax(&metadata, &self.analysis_config()?)?;
+            stats.load = load;
+            self.filter(&mut map, &metadata);
+            return Ok((map, stats));
+        }
+        if self.target_triples.len() < 2 {
+            return self.run_for_target(self.target_triples.first());
+        }
+        let mut maps = vec![];
+        let mut stats = DumpStats::default();
+        for triple in &self.target_triples {
+            let (map, target_stats) = self.run_for_target(Some(triple))?;
+            stats.add(target_stats);
+            maps.push((triple.clone(), map));
+        }
+        let map = CrateMap::by_target(maps);
+        stats.unresolved = map.unresolved_count();
+        stats.failed_files = map.diagnostics().len();
+        Ok((map, stats))
+    }
+
+    /// Runs the dump like `run_with_stats` with the cfgs of the target `triple`, of the
+    /// host when `None`.
+    fn run_for_target(&self, triple: Option<&String>) -> Result<(CrateMap, DumpStats)> {
+        let started = Instant::now();
+        let mut loaded = self.load_workspace_for(triple)?;
+        let load = started.elapsed();
+        let config = self.analysis_config_for(&loaded)?;
+        let (mut map, mut stats) = resolve_workspace_with_stats(
+            &mut loaded.host,
+            &loaded.source_map,
+            Some(&loaded.metadata),
+            &config,
+        )?;
+        stats.load = load;
+        self.filter(&mut map, &loaded.metadata);
+        Ok((map.with_sysroot(loaded.sysroot), stats))
+    }
+
+    /// Lists the items each file exports instead of the ones it imports: its `pub` items
+    /// and `pub use` re-exports, the latter with the path they re-export the item under in
+    /// `UsedItem::reexported_as`. Files are selected, keyed and filtered as with `run`,
+    /// without the cache.
+    ///
+    /// Only the items which other crates can reach are listed, e.g. not those of a private
+    /// module, except for `#[macro_export]` macros, which are exported from the crate root.
+    pub fn run_exports(&self) -> Result<CrateMap> {
+        let mut loaded = self.load_workspace()?;
+        let mut config = self.analysis_config()?;
+        // The cache holds imports.
+        config.cache = CacheConfig::Disabled;
+        let mut map = resolve_exports(
+            &mut loaded.host,
+            &loaded.source_map,
+            Some(&loaded.metadata),
+            &config,
+        )?;
+        self.filter(&mut map, &loaded.metadata);
+        Ok(map.with_sysroot(loaded.sysroot))
+    }
+
+    /// Keeps re-dumping the workspace as its files change, until `stop` is set, e.g. by a
+    /// Ctrl-C handler. `on_change` is first called with the whole dump, then after every
+    /// batch of changes along with the keys of the files whose used items were resolved
+    /// again, or `None` when the whole workspace was.
+    ///
+    /// Changed files are resolved again on their own, except with `Keys::Module`. Creating,
+    /// removing or renaming a file reloads the whole workspace.
+    pub fn watch<F>(&self, stop: &AtomicBool, on_change: F) -> Result<()>
+    where
+        F: FnMut(&CrateMap, Option<&BTreeSet<String>>) -> Result<()>,
+    {
+        watch::watch(self, stop, on_change)
+    }
+
+    /// Applies the filters to the used items `resolve_workspace` listed.
+    pub(crate) fn filter(&self, map: &mut CrateMap, metadata: &Metadata) {
+        let filters = ItemFilters::new(self, metadata);
+        map.update_files(|package, used_item_map| filters.apply(package, used_item_map));
+        if !self.include_empty {
+            map.remove_empty_files();
+        }
+    }
+
+    pub(crate) fn keys_by_module(&self) -> bool {
+        self.keys == Keys::Module
+    }
+
+    /// Runs the dump like `run`, but passes every file to `f` instead of collecting them
+    /// into a `CrateMap`, so that only the files of one workspace member at a time are held
+    /// in memory. Returns the files whose analysis failed, and whether the dump is
+    /// `StreamedDump::is_partial`.
+    ///
+    /// Members are visited by package name, and the files of each member by key. With
+    /// `Keys::Module`, files sharing a module path are passed separately instead of being
+    /// merged.
+    pub fn run_streaming<F: FnMut(FileEntry)>(&self, mut f: F) -> Result<StreamedDump> {
+        if self.no_resolve {
+            // Without analysis, the used items of the whole workspace are small.
+            let map = self.run()?;
+            for (file, used_item_map) in map.files() {
+                f(FileEntry::new(
+                    file.to_owned(),
+                    used_item_map.clone(),
+                    false,
+                ));
+            }
+            for (file, used_item_map) in map.generated_files() {
+                f(FileEntry::new(file.to_owned(), used_item_map.clone(), true));
+            }
+            return Ok(StreamedDump::new(
+                map.diagnostics().to_vec(),
+                map.is_partial(),
+            ));
+        }
+        let mut loaded = self.load_workspace()?;
+        let config = self.analysis_config_for(&loaded)?;
+        let filters = ItemFilters::new(self, &loaded.metadata);
+        let cache = load_cache(Some(&loaded.metadata), &config);
+        let cached_files = AtomicUsize::new(0);
+        let mut new_entries = vec![];
+        let mut diagnostics = vec![];
+        // The generated files are passed once the members are, the module path of the file
+        // including them being known by then.
+        let mut modules = HashMap::new();
+        let visited = for_each_file(
+            &mut loaded.host,
+            &loaded.source_map,
+            Some(&loaded.metadata),
+            &config,
+            |analysis, workspace, file_id| {
+                let cache = cache.as_ref();
+                used_items_in_file_cached(analysis, workspace, file_id, cache, &cached_files)
+            },
+            |file| match file.result {
+                Ok((mut used_item_map, cache_entry)) => {
+                    used_item_map.set_syntax_errors(file.syntax_errors);
+                    if let (Some(_), Some(cache_entry)) = (&cache, cache_entry) {
+                        new_entries.push((cache_entry, used_item_map.clone()));
+                    }
+                    // The filters tell the files of the integration tests by their target.
+                    used_item_map.set_target(file.target);
+                    filters.apply(Some(&file.package), &mut used_item_map);
+                    if let Some(module) = &file.module {
+                        modules.insert(file.key.clone(), module.clone());
+                    }
+                    if self.include_empty || !used_item_map.is_empty() {
+                        f(FileEntry::new(
+                            file.module.unwrap_or(file.key),
+                            used_item_map,
+                            false,
+                        ));
+                    }
+                }
+                Err(error) => diagnostics.push(Diagnostic::new(file.key, error)),
+            },
+        )?;
+        for file in generated_files(&loaded.metadata, &visited.included) {
+            match file.result {
+                Ok(mut used_item_map) => {
+                    used_item_map.set_target(file.target);
+                    filters.apply(Some(&file.package), &mut used_item_map);
+                    if self.include_empty || !used_item_map.is_empty() {
+                        f(FileEntry::new(
+                            modules.get(&file.includer).cloned().unwrap_or(file.key),
+                            used_item_map,
+                            self.generated == GeneratedMode::Separate,
+                        ));
+                    }
+                }
+                Err(error) => diagnostics.push(Diagnostic::new(file.key, error)),
+            }
+        }
+        if let Some(mut cache) = cache {
+            for (cache_entry, used_item_map) in new_entries {
+                cache.insert(cache_entry, used_item_map);
+            }
+            cache.save()?;
+        }
+        Ok(StreamedDump::new(diagnostics, visited.cancelled))
+    }
+
+    pub(crate) fn load_workspace(&self) -> Result<LoadedWorkspace> {
+        self.load_workspace_for(self.target_triples.first())
+    }
+
+    /// Loads the workspace with the cfgs of the target `triple`, of the host when `None`.
+    fn load_workspace_for(&self, triple: Option<&String>) -> Result<LoadedWorkspace> {
+        if let Some(progress) = &self.progress {
+            progress.report(Progress::LoadingWorkspace);
+        }
+        let mut config = self.load_config();
+        config.target_triple = triple.cloned();
+        load_workspace(&self.path, &config)
+    }
+
+    fn load_config(&self) -> LoadConfig {
+        LoadConfig {
+            features: self.features.clone(),
+            sysroot: self.sysroot.clone(),
+            offline: self.offline,
+            locked: self.locked,
+            target_triple: None,
+        }
+    }
+
+    pub(crate) fn analysis_config(&self) -> Result<AnalysisConfig> {
+        let mut targets = self.targets.clone();
+        if self.only_tests {
+            targets.insert(TargetKind::Test);
+        }
+        Ok(AnalysisConfig {
+            packages: self.packages.clone(),
+            targets: Some(targets),
+            filter: FileFilter::new(&self.include, &self.exclude)?,
+            jobs: self.jobs,
+            flat_paths: self.flat_paths,
+            absolute_paths: self.absolute_paths,
+            keys: self.keys,
+            generated: self.generated,
+            progress: self.progress.clone(),
+            // The cache does not tell the cfgs of targets apart.
+            cache: if self.target_triples.is_empty() {
+                self.cache.clone()
+            } else {
+                CacheConfig::Disabled
+            },
+            // The features decide which optional dependencies and `#[cfg(feature)]` items
+            // there are to resolve to.
+            cache_key: format!("{:?}", self.features),
+            files: None,
+            cancel: self.cancel.clone(),
+            file_timeout: self.file_timeout,
+            include_derives: self.include_derives,
+        })
+    }
+
+    /// The `analysis_config` of a dump of `loaded`, whose cache is also keyed on the
+    /// sysroot the standard library was loaded from, if any.
+    pub(crate) fn analysis_config_for(&self, loaded: &LoadedWorkspace) -> Result<AnalysisConfig> {
+        let mut config = self.analysis_config()?;
+        config.cache_key = format!("{} {:?}", config.cache_key, loaded.sysroot);
+        Ok(config)
+    }
+}
+
+/// Runs `options` with `DumpOptions::run_streaming`.
+pub fn dump_streaming<F: FnMut(FileEntry)>(options: &DumpOptions, f: F) -> Result<StreamedDump> {
+    options.run_streaming(f)
+}
+
+/// The filters of `DumpOptions` which apply to the used items of each file on its own.
+struct ItemFilters<'a> {
+    options: &'a DumpOptions,
+    /// The features enabled for each package, when they are not the default ones.
+    enabled_features: Option<HashMap<String, HashSet<String>>>,
+    /// Whether the cfg filter is negated, and its normalized predicate.
+    cfg_filter: Option<(bool, String)>,
+}
+
+impl<'a> ItemFilters<'a> {
+    fn new(options: &'a DumpOptions, metadata: &Metadata) -> Self {
+        let enabled_features = if options.features != CargoFeatures::default() {
+            Some(enabled_features(metadata))
+        } else {
+            None
+        };
+        let cfg_filter = options.cfg_filter.as_ref().map(|cfg_filter| {
+            let cfg_filter = cfg_filter.trim();
+            if cfg_filter.starts_with('!') {
+                (true, normalize_cfg(&cfg_filter[1..]))
+            } else {
+                (false, normalize_cfg(cfg_filter))
+            }
+        });
+        ItemFilters {
+            options,
+            enabled_features,
+            cfg_filter,
+        }
+    }
+
+    fn apply(&self, package: Option<&str>, used_item_map: &mut UsedItemMap) {
+        if let Some(enabled_features) = &self.enabled_features {
+            let no_features = HashSet::new();
+            let features = package
+                .and_then(|package| enabled_features.get(package))
+                .unwrap_or(&no_features);
+            let enabled = |cfgs: &[String]| {
+                cfgs.iter()
+                    .all(|cfg| cfg::evaluate(cfg, features) != Some(false))
+            };
+            // The cfgs of the `mod` declarations leading to the file gate all of it.
+            if enabled(used_item_map.cfgs()) {
+                used_item_map.retain(|item| enabled(item.cfgs()));
+            } else {
+                used_item_map.retain(|_| false);
+            }
+        }
+        if let Some((negated, predicate)) = &self.cfg_filter {
+            used_item_map.retain(|item| item.cfgs().contains(predicate) != *negated);
+        }
+        for &filter in &self.options.origin_filters {
+            used_item_map.retain_origin(filter);
+        }
+        if !self.options.locations {
+            used_item_map.clear_locations();
+        }
+        if !self.options.definitions {
+            used_item_map.clear_definitions();
+        }
+        if !self.options.kinds.is_empty() {
+            used_item_map.retain_kinds(&self.options.kinds);
+        }
+        if self.options.only_reexports {
+            used_item_map.retain(|item| item.is_reexport());
+        }
+        if self.options.no_reexports {
+            used_item_map.retain(|item| !item.is_reexport());
+        }
+        if self.options.exclude_tests || self.options.only_tests {
+            let test_file = used_item_map.target() == Some(TargetKind::Test)
+                || used_item_map
+                    .cfgs()
+                    .iter()
+                    .any(|cfg| cfg::requires_test(cfg));
+            let only_tests = self.options.only_tests;
+            used_item_map.retain(|item| {
+                let test = test_file || item.cfgs().iter().any(|cfg| cfg::requires_test(cfg));
+                test == only_tests
+            });
+        }
+        used_item_map.resolve_reexports(self.options.resolve_reexports);
+    }
+}
+
+/// What the files of a dump are keyed by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keys {
+    /// The path of the file, e.g. `foo/src/parser/expr.rs`.
+    File,
+    /// The module path of the file, e.g. `foo::parser::expr`, or `UNREACHABLE_KEY` for the
+    /// files outside of every module tree.
+    Module,
+}
+
+impl Keys {
+    pub const NAMES: &'static [&'static str] = &["file", "module"];
+}
+
+impl Default for Keys {
+    fn default() -> Self {
+        Keys::File
+    }
+}
+
+impl FromStr for Keys {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "file" => Ok(Keys::File),
+            "module" => Ok(Keys::Module),
+            _ => Err(format!(
+                "unknown keys `{}`, expected one of: {}",
+                s,
+                Keys::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Which path the used items which are re-exported, e.g. by a facade module, are listed
+/// under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResolveReexports {
+    /// The path as imported, e.g. `tokio::sync::Mutex`.
+    Surface,
+    /// The path of the definition the re-exports lead to, e.g. `tokio::sync::mutex::Mutex`.
+    Definition,
+    /// The path of the definition, with the path as imported as `surface_path`.
+    Both,
+}
+
+impl ResolveReexports {
+    pub const NAMES: &'static [&'static str] = &["surface", "definition", "both"];
+}
+
+impl Default for ResolveReexports {
+    fn default() -> Self {
+        ResolveReexports::Definition
+    }
+}
+
+impl FromStr for ResolveReexports {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "surface" => Ok(ResolveReexports::Surface),
+            "definition" => Ok(ResolveReexports::Definition),
+            "both" => Ok(ResolveReexports::Both),
+            _ => Err(format!(
+                "unknown re-export resolution `{}`, expected one of: {}",
+                s,
+                ResolveReexports::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// What to do with generated files, i.e. the ones the members `include!` from outside of
+/// their directories, e.g. from the `OUT_DIR` build scripts write to. rust-analyzer does
+/// not load them, so their use trees are only listed from their syntax.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GeneratedMode {
+    /// Leaves them out of the dump.
+    Skip,
+    /// Dumps them like the other files.
+    Include,
+    /// Dumps them under the reserved `generated` key of the `CrateMap`.
+    Separate,
+}
+
+impl GeneratedMode {
+    pub const NAMES: &'static [&'static str] = &["skip", "include", "separate"];
+}
+
+impl Default for GeneratedMode {
+    fn default() -> Self {
+        GeneratedMode::Include
+    }
+}
+
+impl FromStr for GeneratedMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(GeneratedMode::Skip),
+            "include" => Ok(GeneratedMode::Include),
+            "separate" => Ok(GeneratedMode::Separate),
+            _ => Err(format!(
+                "unknown generated mode `{}`, expected one of: {}",
+                s,
+                GeneratedMode::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// The features to activate when loading the workspace, as in `cargo build`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct CargoFeatures {
+    pub(crate) features: Vec<String>,
+    pub(crate) all_features: bool,
+    pub(crate) no_default_features: bool,
+}
+
+/// How the workspace is loaded, see `load_workspace`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct LoadConfig {
+    pub(crate) features: CargoFeatures,
+    pub(crate) sysroot: SysrootConfig,
+    pub(crate) offline: bool,
+    pub(crate) locked: bool,
+    /// The target triple whose cfgs the crates are analyzed with, the host when `None`.
+    pub(crate) target_triple: Option<String>,
+}
+
+/// The features cargo enabled for each workspace member, keyed by package name.
+fn enabled_features(metadata: &Metadata) -> HashMap<String, HashSet<String>> {
+    let nodes = metadata.resolve.iter().flat_map(|resolve| &resolve.nodes);
+    nodes
+        .filter_map(|node| {
+            let package = metadata.packages.iter().find(|p| p.id == node.id)?;
+            Some((
+                package.name.clone(),
+                node.features.iter().cloned().collect(),
+            ))
+        })
+        .collect()
+}
+
+/// The workspace members and files to analyze, and the number of threads to do it on.
+#[derive(Debug, Default)]
+pub(crate) struct AnalysisConfig {
+    /// Names of the members to analyze. Every member is analyzed when empty.
+    pub(crate) packages: Vec<String>,
+    /// Only analyzes the files of these kinds of targets, every file when `None`.
+    pub(crate) targets: Option<BTreeSet<TargetKind>>,
+    pub(crate) filter: FileFilter,
+    /// Uses rayon's global thread pool when `None`.
+    pub(crate) jobs: Option<usize>,
+    /// Keys files by their relative path only, without the package name.
+    pub(crate) flat_paths: bool,
+    /// Keys files by their absolute path.
+    pub(crate) absolute_paths: bool,
+    pub(crate) keys: Keys,
+    pub(crate) generated: GeneratedMode,
+    pub(crate) progress: Option<ProgressHandler>,
+    pub(crate) cache: CacheConfig,
+    /// Describes how the workspace was loaded, e.g. with which features, so that the cache
+    /// written with another load is dropped.
+    pub(crate) cache_key: String,
+    /// Only analyzes these files of the selected members when set.
+    pub(crate) files: Option<HashSet<FileId>>,
+    /// Skips the remaining files once set.
+    pub(crate) cancel: Option<Arc<AtomicBool>>,
+    pub(crate) file_timeout: Option<Duration>,
+    /// Resolves the derives of `#[derive(...)]` attributes, which costs a `goto_definition`
+    /// call each, and lists them.
+    pub(crate) include_derives: bool,
+}
+
+/// Where the used items of unchanged files are cached, if anywhere.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum CacheConfig {
+    Disabled,
+    /// In `.usedump-cache` under the target directory.
+    Default,
+    In(PathBuf),
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig::Disabled
+    }
+}
@@ -0,0 +1,267 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use cargo_metadata::Metadata;
+use ra_db::{FileId, SourceDatabaseExt};
+use ra_project_model::PackageRoot;
+use serde::{Deserialize, Serialize};
+
+use crate::{output::normalize_path, used_item::SourceMap};
+
+/// The crate an imported item is defined in.
+#[derive(Debug, Clone, PartialOrd, PartialEq, Eq, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "kind", content = "crate")]
+pub enum Origin {
+    /// A crate of the sysroot, e.g. `std`, `core` or `alloc`.
+    Std(String),
+    /// A dependency which is not a member of the workspace.
+    External(String),
+    /// A member of the workspace.
+    Local(String),
+}
+
+impl Origin {
+    pub fn crate_name(&self) -> &str {
+        match self {
+            Origin::Std(name) | Origin::External(name) | Origin::Local(name) => name,
+        }
+    }
+
+    pub fn kind(&self) -> OriginKind {
+        match self {
+            Origin::Std(_) => OriginKind::Std,
+            Origin::External(_) => OriginKind::External,
+            Origin::Local(_) => OriginKind::Local,
+        }
+    }
+}
+
+/// The file an imported item is defined in.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct DefinedIn {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    /// The version of the package, unknown for the sysroot crates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The path of the file relative to the workspace root for members, and prefixed by
+    /// the directory of the package otherwise, e.g. `serde-1.0.103/src/ser/mod.rs`.
+    pub file: String,
+}
+
+/// The kind of an `Origin`, regardless of the crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OriginKind {
+    Std,
+    External,
+    Local,
+}
+
+impl OriginKind {
+    pub const NAMES: &'static [&'static str] = &["std", "external", "local"];
+}
+
+impl FromStr for OriginKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "std" => Ok(OriginKind::Std),
+            "external" => Ok(OriginKind::External),
+            "local" => Ok(OriginKind::Local),
+            _ => Err(format!(
+                "unknown origin `{}`, expected one of: {}",
+                s,
+                OriginKind::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Selects used items by the kind of crate they are defined in.
+///
+/// The origin is that of the definition an import resolves to, so a `HashMap` re-exported
+/// by a facade crate still counts as `std`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OriginFilter {
+    /// Keeps the items of the given origin only, dropping those of unknown origin.
+    Only(OriginKind),
+    /// Keeps everything but the items of the given origin.
+    Exclude(OriginKind),
+}
+
+impl OriginFilter {
+    pub fn matches(self, origin: Option<&Origin>) -> bool {
+        let kind = origin.map(Origin::kind);
+        match self {
+            OriginFilter::Only(only) => kind == Some(only),
+            OriginFilter::Exclude(excluded) => kind != Some(excluded),
+        }
+    }
+}
+
+/// Maps every loaded file to the crate it belongs to.
+#[derive(Default)]
+pub struct Origins {
+    by_file: HashMap<FileId, Origin>,
+    defined_in: HashMap<FileId, DefinedIn>,
+}
+
+impl Origins {
+    pub fn new(
+        db: &impl SourceDatabaseExt,
+        source_map: &SourceMap,
+        metadata: Option<&Metadata>,
+    ) -> Self {
+        let package_names = package_names(metadata);
+        let package_versions: HashMap<&Path, String> = metadata
+            .iter()
+            .flat_map(|metadata| &metadata.packages)
+            .filter_map(|package| {
+                let dir = package.manifest_path.parent()?;
+                Some((dir, package.version.to_string()))
+            })
+            .collect();
+        let workspace_root = metadata.map(|metadata| metadata.workspace_root.as_path());
+        let member_dirs = member_dirs(metadata);
+
+        let mut by_file = HashMap::new();
+        let mut defined_in = HashMap::new();
+        for (source_root_id, package_root) in source_map {
+            let origin = classify(package_root, &package_names, &member_dirs);
+            let path: &PathBuf = package_root.path();
+            let version = match origin {
+                Origin::Std(_) => None,
+                _ => package_versions.get(path.as_path()).cloned(),
+            };
+            // Never leak where the registry cache or the sysroot live.
+            let prefix = match workspace_root.and_then(|root| path.strip_prefix(root).ok()) {
+                Some(relative) if is_member(package_root, &member_dirs) => normalize_path(relative),
+                _ => dir_name(path),
+            };
+            for file_id in db.source_root(*source_root_id).walk() {
+                let relative = db.file_relative_path(file_id).to_string();
+                let file = if prefix.is_empty() {
+                    relative
+                } else {
+                    format!("{}/{}", prefix, relative)
+                };
+                defined_in.insert(
+                    file_id,
+                    DefinedIn {
+                        krate: origin.crate_name().to_owned(),
+                        version: version.clone(),
+                        file,
+                    },
+                );
+                by_file.insert(file_id, origin.clone());
+            }
+        }
+
+        Origins {
+            by_file,
+            defined_in,
+        }
+    }
+
+    pub fn of(&self, file_id: FileId) -> Option<&Origin> {
+        self.by_file.get(&file_id)
+    }
+
+    pub fn defined_in(&self, file_id: FileId) -> Option<&DefinedIn> {
+        self.defined_in.get(&file_id)
+    }
+
+    /// The version of the package of `file_id` when it is an external crate. Each copy of
+    /// a package depended on at several versions is a source root of its own, so this is
+    /// the version of the copy the file is in.
+    pub fn external_version(&self, file_id: FileId) -> Option<&str> {
+        match self.of(file_id)? {
+            Origin::External(_) => self
+                .defined_in(file_id)?
+                .version
+                .as_ref()
+                .map(String::as_str),
+            _ => None,
+        }
+    }
+}
+
+/// Maps the directories of the packages in `metadata` to their names.
+pub(crate) fn package_names(metadata: Option<&Metadata>) -> HashMap<&Path, &str> {
+    metadata
+        .iter()
+        .flat_map(|metadata| &metadata.packages)
+        .filter_map(|package| {
+            let dir = package.manifest_path.parent()?;
+            Some((dir, package.name.as_str()))
+        })
+        .collect()
+}
+
+/// The directories of the workspace members.
+pub(crate) fn member_dirs(metadata: Option<&Metadata>) -> HashSet<&Path> {
+    metadata
+        .iter()
+        .flat_map(|metadata| {
+            metadata
+                .packages
+                .iter()
+                .filter(move |package| metadata.workspace_members.contains(&package.id))
+        })
+        .filter_map(|package| package.manifest_path.parent())
+        .collect()
+}
+
+/// Whether `package_root` is a workspace member. rust-analyzer does not always mark the
+/// members of virtual workspaces, which the metadata knows about.
+pub(crate) fn is_member(package_root: &PackageRoot, member_dirs: &HashSet<&Path>) -> bool {
+    package_root.is_member() || member_dirs.contains(package_root.path().as_path())
+}
+
+/// The name of the package in `package_root`, falling back to its directory name when it is
+/// not in `package_names`.
+pub(crate) fn package_name(
+    package_root: &PackageRoot,
+    package_names: &HashMap<&Path, &str>,
+) -> String {
+    let path: &PathBuf = package_root.path();
+    package_names
+        .get(path.as_path())
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| strip_version(&dir_name(path)).to_owned())
+}
+
+fn classify(
+    package_root: &PackageRoot,
+    package_names: &HashMap<&Path, &str>,
+    member_dirs: &HashSet<&Path>,
+) -> Origin {
+    let path: &PathBuf = package_root.path();
+    let name = package_name(package_root, package_names);
+
+    if is_member(package_root, member_dirs) {
+        Origin::Local(name)
+    } else if path.components().any(|c| c.as_os_str() == "rustlib") {
+        Origin::Std(dir_name(path).trim_start_matches("lib").to_owned())
+    } else {
+        Origin::External(name)
+    }
+}
+
+/// Strips the `-1.0.103` suffix of directories in the registry cache.
+fn strip_version(dir_name: &str) -> &str {
+    match dir_name.rfind('-') {
+        Some(i) if dir_name[i + 1..].starts_with(|c: char| c.is_ascii_digit()) => &dir_name[..i],
+        _ => dir_name,
+    }
+}
+
+fn dir_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
@@ -0,0 +1,660 @@
+use std::{
+    collections::BTreeMap,
+    env, fmt,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    dot::FileGraph,
+    html::render_html,
+    markdown::{render_markdown, MarkdownOptions},
+    sqlite::write_sqlite,
+    stats::DumpStats,
+    used_item::CrateMap,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Json,
+    Yaml,
+    Toml,
+    /// Human-readable text, only supported by the commands which say so.
+    Text,
+    /// One JSON object per line. Dumps stream a line per file, other values take a single
+    /// line.
+    Ndjson,
+    /// A Graphviz graph, only supported by the commands which say so.
+    Dot,
+    /// A row per used item, only supported by the commands which say so.
+    Csv,
+    /// A SARIF 2.1.0 log, only supported by the commands which say so.
+    Sarif,
+    /// A report to read, only supported by the commands which say so.
+    Markdown,
+    /// A self-contained page to browse, only supported by the commands which say so.
+    Html,
+    /// An SQLite database, only supported by the commands which say so.
+    Sqlite,
+}
+
+impl OutputFormat {
+    pub const NAMES: &'static [&'static str] = &[
+        "json", "yaml", "toml", "text", "ndjson", "dot", "csv", "sarif", "markdown", "html",
+        "sqlite",
+    ];
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::Json
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(OutputFormat::Json),
+            "yaml" => Ok(OutputFormat::Yaml),
+            "toml" => Ok(OutputFormat::Toml),
+            "text" => Ok(OutputFormat::Text),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            "dot" => Ok(OutputFormat::Dot),
+            "csv" => Ok(OutputFormat::Csv),
+            "sarif" => Ok(OutputFormat::Sarif),
+            "markdown" => Ok(OutputFormat::Markdown),
+            "html" => Ok(OutputFormat::Html),
+            "sqlite" => Ok(OutputFormat::Sqlite),
+            _ => Err(format!(
+                "unknown format `{}`, expected one of: {}",
+                s,
+                OutputFormat::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Yaml => write!(f, "yaml"),
+            OutputFormat::Toml => write!(f, "toml"),
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Ndjson => write!(f, "ndjson"),
+            OutputFormat::Dot => write!(f, "dot"),
+            OutputFormat::Csv => write!(f, "csv"),
+            OutputFormat::Sarif => write!(f, "sarif"),
+            OutputFormat::Markdown => write!(f, "markdown"),
+            OutputFormat::Html => write!(f, "html"),
+            OutputFormat::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+pub fn write_crate_map<W: Write>(
+    map: &CrateMap,
+    format: OutputFormat,
+    pretty: bool,
+    w: W,
+) -> io::Result<()> {
+    write_value(map, format, pretty, w)
+}
+
+/// Writes anything shaped like a `CrateMap`, e.g. several of them keyed by project.
+///
+/// `pretty` only affects JSON, the other formats are always indented.
+pub fn write_value<T, W>(value: &T, format: OutputFormat, pretty: bool, mut w: W) -> io::Result<()>
+where
+    T: Serialize + ?Sized,
+    W: Write,
+{
+    match format {
+        OutputFormat::Json if pretty => {
+            serde_json::to_writer_pretty(&mut w, value).map_err(io::Error::from)?;
+            w.write_all(b"\n")
+        }
+        OutputFormat::Json => serde_json::to_writer(w, value).map_err(io::Error::from),
+        OutputFormat::Ndjson => {
+            serde_json::to_writer(&mut w, value).map_err(io::Error::from)?;
+            w.write_all(b"\n")
+        }
+        OutputFormat::Yaml => serde_yaml::to_writer(w, value).map_err(other_error),
+        OutputFormat::Toml => {
+            // Going through `toml::Value` moves tables after plain values, which
+            // `toml::to_string` would otherwise reject. Keys such as `src/lib.rs` get quoted.
+            let value = toml::Value::try_from(value).map_err(other_error)?;
+            let s = toml::to_string(&value).map_err(other_error)?;
+            w.write_all(s.as_bytes())
+        }
+        OutputFormat::Text
+        | OutputFormat::Dot
+        | OutputFormat::Csv
+        | OutputFormat::Sarif
+        | OutputFormat::Markdown
+        | OutputFormat::Html
+        | OutputFormat::Sqlite => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("this command does not support the {} format", format),
+        )),
+    }
+}
+
+/// The columns `write_csv` writes.
+pub const CSV_HEADER: [&str; 6] = ["file", "kind", "name", "path", "origin", "alias"];
+
+/// Writes a row per used item of `map` under a `CSV_HEADER` row, ordered by file then item,
+/// leaving `origin` and `alias` empty when unknown.
+pub fn write_csv<W: Write>(map: &CrateMap, w: W) -> io::Result<()> {
+    write_csv_with_delimiter(map, b',', w)
+}
+
+/// Same as `write_csv`, separating the fields with `delimiter`, e.g. `b'\t'`.
+pub fn write_csv_with_delimiter<W: Write>(map: &CrateMap, delimiter: u8, w: W) -> io::Result<()> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(w);
+    writer.write_record(&CSV_HEADER)?;
+    for (file, kind, item) in map.iter() {
+        writer.write_record(&[
+            file,
+            kind.name(),
+            item.name(),
+            item.path(),
+            item.origin().map_or("", |origin| origin.crate_name()),
+            item.alias().unwrap_or_default(),
+        ])?;
+    }
+    writer.flush()
+}
+
+/// Formats `path` the way every emitted path is, with `/` separators on every platform so
+/// that dumps compare equal across them.
+pub fn normalize_path<P: AsRef<Path>>(path: P) -> String {
+    path.as_ref().to_string_lossy().replace('\\', "/")
+}
+
+/// The version of the shape dumps are wrapped in by `Envelope`, bumped whenever the shape
+/// changes. Version 1 is the bare map, which `--no-envelope` still emits.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Wraps a dump with what generated it, so that parsers can tell its shape apart.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub usedump_version: String,
+    pub format_version: u32,
+    /// When the dump was generated, in RFC 3339 in UTC, or at `SOURCE_DATE_EPOCH`.
+    pub generated_at: String,
+    /// The workspace root, for dumps of a single project.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    /// The sysroot the standard library was loaded from, for dumps of a single project
+    /// which loaded one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sysroot: Option<String>,
+    pub files: T,
+}
+
+impl<T> Envelope<T> {
+    /// Wraps `files` as generated now by this version of usedump.
+    pub fn new(root: Option<String>, files: T) -> Self {
+        Envelope {
+            usedump_version: env!("CARGO_PKG_VERSION").to_owned(),
+            format_version: FORMAT_VERSION,
+            generated_at: rfc3339(generation_time()),
+            root,
+            sysroot: None,
+            files,
+        }
+    }
+}
+
+/// Writes the maps of the projects of a dump as `usedump dump` does: a single project as
+/// is, several keyed by their path, wrapped in an `Envelope` and summarized or grouped as
+/// asked.
+///
+/// ```no_run
+/// # use std::collections::BTreeMap;
+/// use usedump::output::{DumpWriter, OutputFormat};
+///
+/// let mut maps = BTreeMap::new();
+/// maps.insert(".".to_owned(), usedump::DumpOptions::new(".").run()?);
+/// let mut buf = vec![];
+/// DumpWriter::new(OutputFormat::Json).pretty(true).write(&maps, None, &mut buf)?;
+/// # Ok::<(), usedump::UsedumpError>(())
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct DumpWriter {
+    format: OutputFormat,
+    pretty: bool,
+    envelope: bool,
+    summary: bool,
+    top: usize,
+    by_origin: bool,
+    legacy_names: bool,
+    csv_delimiter: u8,
+    graph: FileGraph,
+    markdown: MarkdownOptions,
+}
+
+/// How `DumpWriter` emits a dump.
+enum Wrapping {
+    /// As is, as format version 1 did.
+    Bare,
+    /// In an `Envelope`, with the root and the sysroot of its project when there is a
+    /// single one.
+    Envelope(Option<String>, Option<String>),
+}
+
+impl DumpWriter {
+    pub fn new(format: OutputFormat) -> Self {
+        DumpWriter {
+            format,
+            pretty: false,
+            envelope: true,
+            summary: false,
+            top: 10,
+            by_origin: false,
+            legacy_names: false,
+            csv_delimiter: b',',
+            graph: FileGraph::new(),
+            markdown: MarkdownOptions::new(),
+        }
+    }
+
+    /// Indents JSON, see `write_value`.
+    pub fn pretty(mut self, yes: bool) -> Self {
+        self.pretty = yes;
+        self
+    }
+
+    /// Wraps the dump in an `Envelope`, which is the default. Without it, the maps are
+    /// emitted bare, as format version 1 did.
+    pub fn envelope(mut self, yes: bool) -> Self {
+        self.envelope = yes;
+        self
+    }
+
+    /// Writes a `Summary` of every map instead of its files, never wrapped.
+    pub fn summary(mut self, yes: bool) -> Self {
+        self.summary = yes;
+        self
+    }
+
+    /// The number of most imported items a summary lists, 10 by default.
+    pub fn top(mut self, n: usize) -> Self {
+        self.top = n;
+        self
+    }
+
+    /// Groups the files by the crates they import from, see `CrateMap::invert_by_origin`.
+    pub fn by_origin(mut self, yes: bool) -> Self {
+        self.by_origin = yes;
+        self
+    }
+
+    /// Names the sets of items as format version 1 did, see `CrateMap::legacy_names`.
+    pub fn legacy_names(mut self, yes: bool) -> Self {
+        self.legacy_names = yes;
+        self
+    }
+
+    /// Separates the fields of CSV with `delimiter`, `b','` by default.
+    pub fn csv_delimiter(mut self, delimiter: u8) -> Self {
+        self.csv_delimiter = delimiter;
+        self
+    }
+
+    /// How the dot format draws the files.
+    pub fn graph(mut self, graph: FileGraph) -> Self {
+        self.graph = graph;
+        self
+    }
+
+    /// How the Markdown format lays out its report.
+    pub fn markdown(mut self, options: MarkdownOptions) -> Self {
+        self.markdown = options;
+        self
+    }
+
+    /// Whether the format renders the files of a single `CrateMap` rather than serializing
+    /// the dump as is, so that it renders every map after the other, with neither the
+    /// summary, the grouping nor the stats.
+    pub fn renders_single_maps(&self) -> bool {
+        match self.format {
+            OutputFormat::Dot
+            | OutputFormat::Csv
+            | OutputFormat::Markdown
+            | OutputFormat::Html
+            | OutputFormat::Sqlite => true,
+            _ => false,
+        }
+    }
+
+    /// Writes `maps`, keyed by project, to `w`. `stats`, keyed by the same projects, are
+    /// embedded into their maps as `stats`.
+    pub fn write<W: Write>(
+        &self,
+        maps: &BTreeMap<String, CrateMap>,
+        stats: Option<&BTreeMap<String, DumpStats>>,
+        mut w: W,
+    ) -> io::Result<()> {
+        if self.renders_single_maps() {
+            for map in maps.values() {
+                self.write_single_map(map, &mut w)?;
+            }
+            return Ok(());
+        }
+        // The root and the sysroot of a single project move to the envelope.
+        let (single_root, single_sysroot) = match maps.values().next() {
+            Some(map) if maps.len() == 1 => (
+                map.root().map(str::to_owned),
+                map.sysroot().map(str::to_owned),
+            ),
+            _ => (None, None),
+        };
+        let moved = single_root.is_some() || single_sysroot.is_some();
+        let wrapping = if self.envelope {
+            Wrapping::Envelope(single_root, single_sysroot)
+        } else {
+            Wrapping::Bare
+        };
+        if self.summary {
+            let summaries: BTreeMap<_, _> = maps
+                .iter()
+                .map(|(k, v)| (k, v.summarize().top(self.top)))
+                .collect();
+            // Summaries are no dump of files.
+            self.write_maps(&summaries, stats, Wrapping::Bare, w)
+        } else if self.by_origin {
+            let maps: BTreeMap<_, _> = maps
+                .iter()
+                .map(|(k, v)| (k, v.invert_by_origin()))
+                .collect();
+            self.write_maps(&maps, stats, wrapping, w)
+        } else if self.legacy_names {
+            let maps: BTreeMap<_, _> = maps.iter().map(|(k, v)| (k, v.legacy_names())).collect();
+            self.write_maps(&maps, stats, wrapping, w)
+        } else if let (Wrapping::Envelope(..), true) = (&wrapping, moved) {
+            let maps: BTreeMap<_, _> = maps.iter().map(|(k, v)| (k, v.without_root())).collect();
+            self.write_maps(&maps, stats, wrapping, w)
+        } else {
+            self.write_maps(maps, stats, wrapping, w)
+        }
+    }
+
+    fn write_single_map<W: Write>(&self, map: &CrateMap, mut w: W) -> io::Result<()> {
+        match self.format {
+            OutputFormat::Dot => w.write_all(self.graph.render(map).as_bytes()),
+            OutputFormat::Csv => write_csv_with_delimiter(map, self.csv_delimiter, w),
+            OutputFormat::Markdown => w.write_all(render_markdown(map, &self.markdown).as_bytes()),
+            OutputFormat::Html => w.write_all(render_html(map).as_bytes()),
+            OutputFormat::Sqlite => write_sqlite(map, w),
+            format => write_value(map, format, self.pretty, w),
+        }
+    }
+
+    /// Emits the map of a single project as is, and keys several projects by their path.
+    fn write_maps<K, V, W>(
+        &self,
+        maps: &BTreeMap<K, V>,
+        stats: Option<&BTreeMap<String, DumpStats>>,
+        wrapping: Wrapping,
+        w: W,
+    ) -> io::Result<()>
+    where
+        K: Serialize + Ord,
+        V: Serialize,
+        W: Write,
+    {
+        #[derive(Serialize)]
+        struct WithStats<'a, V> {
+            #[serde(flatten)]
+            map: &'a V,
+            stats: &'a DumpStats,
+        }
+
+        if let Some(stats) = stats {
+            // Both are ordered by project.
+            let maps: BTreeMap<&K, WithStats<'_, V>> = maps
+                .iter()
+                .zip(stats.values())
+                .map(|((k, map), stats)| (k, WithStats { map, stats }))
+                .collect();
+            return self.write_projects(&maps, wrapping, w);
+        }
+        self.write_projects(maps, wrapping, w)
+    }
+
+    fn write_projects<K, V, W>(
+        &self,
+        maps: &BTreeMap<K, V>,
+        wrapping: Wrapping,
+        w: W,
+    ) -> io::Result<()>
+    where
+        K: Serialize + Ord,
+        V: Serialize,
+        W: Write,
+    {
+        match maps.values().next() {
+            Some(map) if maps.len() == 1 => self.write_wrapped(map, wrapping, w),
+            _ => self.write_wrapped(maps, wrapping, w),
+        }
+    }
+
+    fn write_wrapped<T: Serialize, W: Write>(
+        &self,
+        value: &T,
+        wrapping: Wrapping,
+        w: W,
+    ) -> io::Result<()> {
+        match wrapping {
+            Wrapping::Bare => write_value(value, self.format, self.pretty, w),
+            Wrapping::Envelope(root, sysroot) => {
+                let mut envelope = Envelope::new(root, value);
+                envelope.sysroot = sysroot;
+                write_value(&envelope, self.format, self.pretty, w)
+            }
+        }
+    }
+}
+
+/// Reads a `CrateMap` written by `write_value`, whether wrapped in an `Envelope` or bare as
+/// format version 1 and `--no-envelope` emit it.
+pub fn read_crate_map(path: &Path) -> io::Result<CrateMap> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Versioned {
+        Enveloped(Envelope<CrateMap>),
+        Bare(CrateMap),
+    }
+
+    match read_value(path)? {
+        Versioned::Enveloped(envelope) => {
+            if envelope.format_version > FORMAT_VERSION {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "`{}` has format version {}, which is newer than {}",
+                        path.display(),
+                        envelope.format_version,
+                        FORMAT_VERSION
+                    ),
+                ));
+            }
+            Ok(envelope
+                .files
+                .with_root(envelope.root)
+                .with_sysroot(envelope.sysroot))
+        }
+        Versioned::Bare(map) => Ok(map),
+    }
+}
+
+/// Seconds since the epoch, taken from `SOURCE_DATE_EPOCH` when set for reproducible output.
+fn generation_time() -> u64 {
+    env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs())
+        })
+}
+
+/// Formats `secs` since the epoch as an RFC 3339 date and time in UTC.
+fn rfc3339(secs: u64) -> String {
+    let (days, secs_of_day) = (secs / 86_400, secs % 86_400);
+    // The civil date of a day count, as in Howard Hinnant's `civil_from_days`.
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60
+    )
+}
+
+/// Reads a value written by `write_value`, guessing the format from the extension of
+/// `path` and falling back to JSON.
+pub fn read_value<T: DeserializeOwned>(path: &Path) -> io::Result<T> {
+    let contents = fs::read_to_string(path)?;
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+    match extension {
+        "yaml" | "yml" => serde_yaml::from_str(&contents).map_err(other_error),
+        "toml" => toml::from_str(&contents).map_err(other_error),
+        _ => serde_json::from_str(&contents).map_err(io::Error::from),
+    }
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it into place, so that
+/// readers never observe a partially written file.
+pub fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut file = AtomicFile::create(path)?;
+    file.write_all(contents)?;
+    file.commit()
+}
+
+/// A file written like `write_atomically`, but bit by bit, e.g. a stream of lines. It
+/// only replaces `path` once committed, and is removed when dropped before.
+pub struct AtomicFile {
+    file: File,
+    tmp_path: PathBuf,
+    path: PathBuf,
+    committed: bool,
+}
+
+impl AtomicFile {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let tmp_path = tmp_path_of(path)?;
+        let file = File::create(&tmp_path)?;
+        Ok(AtomicFile {
+            file,
+            tmp_path,
+            path: path.to_owned(),
+            committed: false,
+        })
+    }
+
+    /// Renames the file into place once everything written reached the disk.
+    pub fn commit(mut self) -> io::Result<()> {
+        self.file.sync_all()?;
+        fs::rename(&self.tmp_path, &self.path)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.tmp_path);
+        }
+    }
+}
+
+/// The temporary file next to `path` which `AtomicFile` writes to.
+fn tmp_path_of(path: &Path) -> io::Result<PathBuf> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if !dir.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("output directory `{}` does not exist", dir.display()),
+        ));
+    }
+    let file_name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("`{}` is not a file path", path.display()),
+        )
+    })?;
+    Ok(dir.join(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        process::id()
+    )))
+}
+
+fn other_error<E>(e: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_separators_are_normalized() {
+        assert_eq!(
+            normalize_path(r"C:\Users\me\project\src\lib.rs"),
+            "C:/Users/me/project/src/lib.rs"
+        );
+    }
+
+    #[test]
+    fn mixed_separators_are_normalized() {
+        assert_eq!(
+            normalize_path(r"C:\Users\me/project\src/shapes/mod.rs"),
+            "C:/Users/me/project/src/shapes/mod.rs"
+        );
+        assert_eq!(normalize_path("src/lib.rs"), "src/lib.rs");
+    }
+}
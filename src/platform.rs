@@ -0,0 +1,131 @@
+use std::{collections::HashMap, path::Path};
+
+use cargo_metadata::Metadata;
+use ra_cfg::CfgOptions;
+use ra_db::{CrateGraph, Env, SourceDatabase, SourceDatabaseExt};
+use ra_ide::{AnalysisChange, AnalysisHost};
+
+use crate::{
+    error::{Result, UsedumpError},
+    sysroot,
+    used_item::SourceMap,
+};
+
+/// The cfgs of the host, as `rustc --print cfg` prints them for the toolchain of the
+/// workspace in `dir`. None are known when rustc fails, as when rust-analyzer asks it.
+pub(crate) fn host_cfgs(dir: &Path) -> Vec<(String, Option<String>)> {
+    sysroot::rustc(dir)
+        .args(&["--print", "cfg"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map_or_else(Vec::new, |output| {
+            parse_cfgs(&String::from_utf8_lossy(&output.stdout))
+        })
+}
+
+/// The cfgs of the target `triple`, e.g. `target_os="none"`, as `rustc --print cfg` prints
+/// them for the toolchain of the workspace in `dir`, the value of the ones set to one.
+pub(crate) fn target_cfgs(dir: &Path, triple: &str) -> Result<Vec<(String, Option<String>)>> {
+    let unknown = |reason: String| UsedumpError::UnknownTarget {
+        triple: triple.to_owned(),
+        reason,
+    };
+    let output = sysroot::rustc(dir)
+        .args(&["--print", "cfg", "--target", triple])
+        .output()
+        .map_err(|e| unknown(format!("failed to run rustc: {}", e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(unknown(
+            stderr.trim().trim_start_matches("error:").trim().to_owned(),
+        ));
+    }
+    Ok(parse_cfgs(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_cfgs(output: &str) -> Vec<(String, Option<String>)> {
+    output
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| match line.find('=') {
+            Some(i) => (
+                line[..i].trim().to_owned(),
+                Some(line[i + 1..].trim().trim_matches('"').to_owned()),
+            ),
+            None => (line.trim().to_owned(), None),
+        })
+        .collect()
+}
+
+/// Replaces the cfgs of every crate of `host`, which rust-analyzer sets for the host and
+/// the default features, with `cfgs` and the features cargo enabled for its package in
+/// `metadata`, so that the modules gated on the target or on the features, e.g.
+/// `#[cfg(target_os = "none")]` or `#[cfg(feature = "serde")]`, resolve as they build.
+///
+/// `metadata` must come from a `cargo metadata` run with the features of the dump. As when
+/// rust-analyzer loads the workspace, `test` and `debug_assertions` are set too.
+pub(crate) fn reconfigure(
+    host: &mut AnalysisHost,
+    source_map: &SourceMap,
+    metadata: &Metadata,
+    cfgs: &[(String, Option<String>)],
+) {
+    // The features cargo enabled for each package, by directory.
+    let mut features: HashMap<&Path, &[String]> = HashMap::new();
+    for node in metadata.resolve.iter().flat_map(|resolve| &resolve.nodes) {
+        let package = metadata
+            .packages
+            .iter()
+            .find(|package| package.id == node.id);
+        if let Some(dir) = package.and_then(|package| package.manifest_path.parent()) {
+            features.insert(dir, &node.features);
+        }
+    }
+
+    let db = host.raw_database();
+    let crate_graph = db.crate_graph();
+    let mut reconfigured = CrateGraph::default();
+    let mut ids = HashMap::new();
+    for crate_id in crate_graph.iter() {
+        let root = crate_graph.crate_root(crate_id);
+        let mut cfg_options = CfgOptions::default();
+        for (key, value) in cfgs {
+            match value {
+                Some(value) => {
+                    cfg_options.insert_key_value(key.as_str().into(), value.as_str().into())
+                }
+                None => cfg_options.insert_atom(key.as_str().into()),
+            }
+        }
+        cfg_options.insert_atom("test".into());
+        cfg_options.insert_atom("debug_assertions".into());
+        let package_features = source_map
+            .get(&db.file_source_root(root))
+            .and_then(|package_root| features.get::<Path>(package_root.path()));
+        for feature in package_features.into_iter().copied().flatten() {
+            cfg_options.insert_key_value("feature".into(), feature.as_str().into());
+        }
+        let id = reconfigured.add_crate_root(
+            root,
+            crate_graph.edition(crate_id),
+            cfg_options,
+            Env::default(),
+        );
+        ids.insert(crate_id, id);
+    }
+    for crate_id in crate_graph.iter() {
+        for dependency in crate_graph.dependencies(crate_id) {
+            // The graph is the one rust-analyzer built, which has no cycles.
+            let _ = reconfigured.add_dep(
+                ids[&crate_id],
+                dependency.name.clone(),
+                ids[&dependency.crate_id()],
+            );
+        }
+    }
+
+    let mut change = AnalysisChange::new();
+    change.set_crate_graph(reconfigured);
+    host.apply_change(change);
+}
@@ -0,0 +1,37 @@
+use std::{fmt, sync::Arc};
+
+/// What a dump is busy with, as reported to the handler set with `DumpOptions::progress`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Progress<'a> {
+    /// Running `cargo metadata` and loading the sources of the workspace and its
+    /// dependencies, which takes a while before anything else happens.
+    LoadingWorkspace,
+    /// The workspace is loaded, and `total` files are about to be analyzed.
+    Analyzing { total: usize },
+    /// `file` has been analyzed, successfully or not, as the `done`th of `total` files.
+    Analyzed {
+        file: &'a str,
+        done: usize,
+        total: usize,
+    },
+}
+
+/// A progress handler shared by the threads analyzing files.
+#[derive(Clone)]
+pub(crate) struct ProgressHandler(Arc<dyn Fn(Progress<'_>) + Send + Sync>);
+
+impl ProgressHandler {
+    pub(crate) fn new<F: Fn(Progress<'_>) + Send + Sync + 'static>(f: F) -> Self {
+        ProgressHandler(Arc::new(f))
+    }
+
+    pub(crate) fn report(&self, progress: Progress<'_>) {
+        (self.0)(progress)
+    }
+}
+
+impl fmt::Debug for ProgressHandler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressHandler")
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::BTreeMap;
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::{
+    error::{Result, UsedumpError},
+    used_item::{UsedItem, UsedItemKind},
+};
+
+/// Matches used items by their name or their fully qualified path.
+#[derive(Debug, Clone)]
+pub struct ItemQuery {
+    matcher: Matcher,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Exact(String),
+    Regex(Regex),
+}
+
+impl ItemQuery {
+    /// Matches items named `query`, e.g. `Serialize`, or whose path is `query`, e.g.
+    /// `serde::ser::Serialize`.
+    pub fn exact<S: Into<String>>(query: S) -> Self {
+        ItemQuery {
+            matcher: Matcher::Exact(query.into()),
+        }
+    }
+
+    /// Matches items whose name or path matches the regular expression `pattern`.
+    pub fn regex(pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern).map_err(|source| UsedumpError::InvalidRegex {
+            pattern: pattern.to_owned(),
+            source,
+        })?;
+        Ok(ItemQuery {
+            matcher: Matcher::Regex(regex),
+        })
+    }
+
+    pub fn matches(&self, item: &UsedItem) -> bool {
+        match &self.matcher {
+            Matcher::Exact(query) => item.name() == query || item.path() == query,
+            Matcher::Regex(regex) => regex.is_match(item.name()) || regex.is_match(item.path()),
+        }
+    }
+}
+
+/// The used items an `ItemQuery` matched, keyed by the file importing them, as listed by
+/// `usedump who-uses`. Built by `CrateMap::importers`.
+///
+/// Keyed by file rather than a flat list, which TOML cannot represent.
+#[derive(Debug, Serialize)]
+#[serde(transparent)]
+pub struct Importers<'a> {
+    files: BTreeMap<&'a str, Vec<Import<'a>>>,
+}
+
+#[derive(Debug, Serialize)]
+struct Import<'a> {
+    kind: UsedItemKind,
+    name: &'a str,
+    path: &'a str,
+}
+
+impl<'a> Importers<'a> {
+    pub(crate) fn new(importers: Vec<(&'a str, &'a UsedItem)>) -> Self {
+        let mut files: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for (file, item) in importers {
+            files.entry(file).or_default().push(Import {
+                kind: item.kind(),
+                name: item.name(),
+                path: item.path(),
+            });
+        }
+        Importers { files }
+    }
+
+    /// Returns true if no file imports a matching item.
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::{self, Command},
+};
+
+use cargo_metadata::Metadata;
+
+use crate::error::{Result, UsedumpError};
+
+/// A package or a repository copied into a temporary directory, which is removed once
+/// dropped, see `fetch_crate` and `clone_git`.
+#[derive(Debug)]
+pub struct FetchedCrate {
+    /// The temporary directory holding everything fetched.
+    temp_dir: PathBuf,
+    /// The directory of the copied package or of the clone, under `temp_dir`.
+    dir: PathBuf,
+    name: String,
+    version: String,
+    build_script: bool,
+}
+
+impl FetchedCrate {
+    /// Creates the temporary directory anew, so that it is removed whatever goes wrong
+    /// next.
+    fn new(name: &str) -> Result<Self> {
+        let temp_dir = env::temp_dir().join(format!("usedump-{}-{}", name, process::id()));
+        if temp_dir.exists() {
+            fs::remove_dir_all(&temp_dir)?;
+        }
+        fs::create_dir_all(&temp_dir)?;
+        Ok(FetchedCrate {
+            temp_dir,
+            dir: PathBuf::new(),
+            name: name.to_owned(),
+            version: String::new(),
+            build_script: false,
+        })
+    }
+
+    /// The directory of the package or of the clone, to be analyzed like any other.
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The name of the package, or of the repository, e.g. `bar` for `https://foo/bar.git`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The version of the package, or the commit checked out.
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Whether the package has a build script, whose generated code is not analyzed. Never
+    /// told for repositories.
+    pub fn has_build_script(&self) -> bool {
+        self.build_script
+    }
+}
+
+impl Drop for FetchedCrate {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.temp_dir);
+    }
+}
+
+/// Fetches the package `spec` from crates.io, given as `name@version` or as `name` for its
+/// latest version, and copies it into a temporary directory to analyze it on its own.
+///
+/// Cargo does the fetching, through a throwaway package depending on `spec`, so the
+/// archives already in the cache of `CARGO_HOME` are reused. With `offline`, only those
+/// are, and the latest version is the latest one in the local copy of the index. Versions
+/// which do not exist or are yanked, and network failures, are reported with the message
+/// of cargo.
+pub fn fetch_crate(spec: &str, offline: bool) -> Result<FetchedCrate> {
+    let (name, version) = match spec.find('@') {
+        Some(i) => (&spec[..i], Some(&spec[i + 1..])),
+        None => (spec, None),
+    };
+    let fetch_error = |reason: String| UsedumpError::Fetch {
+        spec: spec.to_owned(),
+        reason,
+    };
+    if name.is_empty() || version == Some("") {
+        return Err(fetch_error("expected `name` or `name@version`".to_owned()));
+    }
+
+    let mut fetched = FetchedCrate::new(name)?;
+    let temp_dir = fetched.temp_dir.clone();
+
+    // The `[workspace]` table keeps the package out of any workspace the temporary
+    // directory happens to be in.
+    let fetch_dir = temp_dir.join("fetch");
+    fs::create_dir_all(fetch_dir.join("src"))?;
+    fs::write(fetch_dir.join("src/lib.rs"), "")?;
+    fs::write(
+        fetch_dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"usedump-fetch\"\nversion = \"0.0.0\"\nedition = \"2018\"\n\n\
+             [workspace]\n\n[dependencies]\n{} = \"{}\"\n",
+            name,
+            version.map_or_else(|| "*".to_owned(), |version| format!("={}", version))
+        ),
+    )?;
+    let metadata =
+        cargo_metadata_of(&fetch_dir.join("Cargo.toml"), offline).map_err(fetch_error)?;
+    let package = metadata
+        .packages
+        .iter()
+        .find(|package| package.name == name)
+        .ok_or_else(|| fetch_error(format!("cargo did not resolve `{}`", name)))?;
+    let source_dir = package
+        .manifest_path
+        .parent()
+        .ok_or_else(|| fetch_error("the package has no directory".to_owned()))?;
+
+    fetched.version = package.version.to_string();
+    fetched.build_script = package
+        .targets
+        .iter()
+        .any(|target| target.kind.iter().any(|kind| kind == "custom-build"));
+    // The sources in `CARGO_HOME` are shared, and analyzing them would write a `Cargo.lock`.
+    fetched.dir = temp_dir.join(format!("{}-{}", name, fetched.version));
+    copy_dir(source_dir, &fetched.dir)?;
+    Ok(fetched)
+}
+
+/// Clones the git repository at `url` into a temporary directory, checking out `rev`, a
+/// commit, a tag or a branch, or the default branch when `None`.
+///
+/// The system `git` does the cloning, with the credentials it is configured with. Only the
+/// commit checked out is fetched, unless the server does not let a commit be fetched by
+/// its hash, when the whole history is.
+pub fn clone_git(url: &str, rev: Option<&str>) -> Result<FetchedCrate> {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit(|c| c == '/' || c == ':')
+        .next()
+        .unwrap_or(url)
+        .trim_end_matches(".git");
+    let mut fetched = FetchedCrate::new(if name.is_empty() { "git" } else { name })?;
+    let dir = fetched.temp_dir.join("checkout");
+    let clone_error = |reason: String| UsedumpError::Fetch {
+        spec: url.to_owned(),
+        reason,
+    };
+
+    match rev {
+        None => git(
+            &fetched.temp_dir,
+            &["clone", "--depth", "1", url, "checkout"],
+        )
+        .map_err(clone_error)?,
+        Some(rev) => {
+            fs::create_dir_all(&dir)?;
+            git(&dir, &["init", "--quiet"]).map_err(clone_error)?;
+            git(&dir, &["remote", "add", "origin", url]).map_err(clone_error)?;
+            let shallow = git(&dir, &["fetch", "--quiet", "--depth", "1", "origin", rev]);
+            let checkout = if shallow.is_ok() { "FETCH_HEAD" } else { rev };
+            if shallow.is_err() {
+                git(&dir, &["fetch", "--quiet", "--tags", "origin"]).map_err(clone_error)?;
+            }
+            git(&dir, &["checkout", "--quiet", checkout])
+                .map_err(|reason| clone_error(format!("no revision `{}`: {}", rev, reason)))?;
+        }
+    }
+    fetched.version = git(&dir, &["rev-parse", "HEAD"]).map_err(clone_error)?;
+    fetched.dir = dir;
+    Ok(fetched)
+}
+
+/// Runs git with `args` in `dir`, returning its trimmed output, or its error message when
+/// it fails.
+fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| format!("failed to run git: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(stderr.trim().trim_start_matches("fatal:").trim().to_owned());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+/// Runs `cargo metadata` on `manifest`, returning the error message of cargo when it fails.
+fn cargo_metadata_of(manifest: &Path, offline: bool) -> Result<Metadata, String> {
+    let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut command = Command::new(cargo);
+    command
+        .args(&["metadata", "--format-version", "1", "--manifest-path"])
+        .arg(manifest);
+    if offline {
+        command.arg("--offline");
+    }
+    let output = command
+        .output()
+        .map_err(|e| format!("failed to run cargo: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(stderr.trim().trim_start_matches("error:").trim().to_owned());
+    }
+    serde_json::from_slice(&output.stdout).map_err(|e| e.to_string())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            copy_dir(&path, &to.join(entry.file_name()))?;
+        } else {
+            fs::copy(&path, to.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
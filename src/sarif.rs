@@ -0,0 +1,307 @@
+use serde::Serialize;
+
+use crate::{
+    dead_exports::DeadExports, deps::DepsAudit, file_paths::FilePaths, unused::UnusedImports,
+    used_item::Location,
+};
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const VERSION: &str = "2.1.0";
+const INFORMATION_URI: &str = "https://github.com/topecongiro/usedump-rs";
+/// The base of every artifact URI, which consumers such as code scanning resolve to the
+/// checkout.
+const SRCROOT: &str = "%SRCROOT%";
+
+/// A SARIF 2.1.0 log of the findings of a check, with one result per finding, as
+/// `--format sarif` emits.
+///
+/// Artifact URIs are relative to the workspace root, and regions are only known for the
+/// findings which have a location. Columns are in UTF-16 code units, the SARIF default.
+#[derive(Debug, Serialize)]
+pub struct SarifLog {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run>,
+}
+
+#[derive(Debug, Serialize)]
+struct Run {
+    tool: Tool,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Debug, Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    information_uri: &'static str,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: &'static str,
+    short_description: Message,
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: &'static str,
+    rule_index: usize,
+    level: &'static str,
+    message: Message,
+    locations: Vec<SarifLocation>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifLocation {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<Region>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactLocation {
+    uri: String,
+    uri_base_id: &'static str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: u32,
+    start_column: u32,
+    byte_offset: u32,
+    byte_length: u32,
+}
+
+impl From<Location> for Region {
+    fn from(location: Location) -> Self {
+        let [start, end] = location.range;
+        Region {
+            start_line: location.line,
+            start_column: location.col,
+            byte_offset: start,
+            byte_length: end - start,
+        }
+    }
+}
+
+const UNUSED_IMPORT: &str = "unused-import";
+const DEV_DEPENDENCY_OUTSIDE_TESTS: &str = "dev-dependency-outside-tests";
+const TEST_ONLY_DEPENDENCY: &str = "test-only-dependency";
+const NON_BUILD_DEPENDENCY_IN_BUILD_SCRIPT: &str = "non-build-dependency-in-build-script";
+const DEAD_EXPORT: &str = "dead-export";
+
+/// Every rule, whose index results refer to.
+const RULES: &[(&str, &str)] = &[
+    (UNUSED_IMPORT, "Imported item is never used"),
+    (
+        DEV_DEPENDENCY_OUTSIDE_TESTS,
+        "Dev-dependency is imported outside of tests",
+    ),
+    (TEST_ONLY_DEPENDENCY, "Dependency is only imported by tests"),
+    (
+        NON_BUILD_DEPENDENCY_IN_BUILD_SCRIPT,
+        "Build script imports a crate which is not a build-dependency",
+    ),
+    (DEAD_EXPORT, "Exported item is not used by any other member"),
+];
+
+impl SarifLog {
+    /// The log of `usedump unused`.
+    pub fn from_unused_imports(unused: &UnusedImports) -> Self {
+        let mut log = SarifLog::new(&[UNUSED_IMPORT]);
+        for (file, imports) in unused.files() {
+            for import in imports {
+                log.push_file(
+                    UNUSED_IMPORT,
+                    format!("unused import `{}`", import.name()),
+                    unused.paths(),
+                    file,
+                    import.location(),
+                );
+            }
+        }
+        log
+    }
+
+    /// The log of `usedump deps-audit`. Test-only dependencies are reported on the manifest
+    /// of their member.
+    pub fn from_deps_audit(audit: &DepsAudit) -> Self {
+        let mut log = SarifLog::new(&[
+            DEV_DEPENDENCY_OUTSIDE_TESTS,
+            TEST_ONLY_DEPENDENCY,
+            NON_BUILD_DEPENDENCY_IN_BUILD_SCRIPT,
+        ]);
+        for (member, dependencies) in audit.dev_dependencies_in_non_test_code() {
+            for (dependency, files) in dependencies {
+                for file in files {
+                    log.push_file(
+                        DEV_DEPENDENCY_OUTSIDE_TESTS,
+                        format!(
+                            "dev-dependency `{}` of `{}` is imported outside of tests",
+                            dependency, member
+                        ),
+                        audit.paths(),
+                        file,
+                        None,
+                    );
+                }
+            }
+        }
+        for (member, dependencies) in audit.test_only_dependencies() {
+            let manifest = audit.manifest_of(member).unwrap_or("Cargo.toml");
+            for dependency in dependencies {
+                log.push(
+                    TEST_ONLY_DEPENDENCY,
+                    format!(
+                        "dependency `{}` of `{}` is only imported by tests, consider making it \
+                         a dev-dependency",
+                        dependency, member
+                    ),
+                    manifest,
+                    None,
+                );
+            }
+        }
+        for (member, crates) in audit.non_build_dependencies_in_build_scripts() {
+            for (crate_name, files) in crates {
+                for file in files {
+                    log.push_file(
+                        NON_BUILD_DEPENDENCY_IN_BUILD_SCRIPT,
+                        format!(
+                            "`{}` is imported by the build script of `{}`, but is not a \
+                             build-dependency",
+                            crate_name, member
+                        ),
+                        audit.paths(),
+                        file,
+                        None,
+                    );
+                }
+            }
+        }
+        log
+    }
+
+    /// The log of `usedump dead-exports`.
+    pub fn from_dead_exports(dead: &DeadExports) -> Self {
+        let mut log = SarifLog::new(&[DEAD_EXPORT]);
+        for (member, exports) in dead.members() {
+            for export in exports {
+                let mut message = format!("{} `{}`", export.kind().name(), export.path());
+                if let Some(reexported_as) = export.reexported_as() {
+                    message.push_str(&format!(", re-exported as `{}`,", reexported_as));
+                }
+                message.push_str(&format!(" of `{}` is not used by any other member", member));
+                log.push_file(
+                    DEAD_EXPORT,
+                    message,
+                    dead.paths(),
+                    export.file(),
+                    export.location(),
+                );
+            }
+        }
+        log
+    }
+
+    /// An empty log whose tool declares `rules`.
+    fn new(rules: &[&'static str]) -> Self {
+        let rules = rules
+            .iter()
+            .map(|&id| Rule {
+                id,
+                short_description: Message {
+                    text: RULES
+                        .iter()
+                        .find(|(rule, _)| *rule == id)
+                        .map_or("", |(_, description)| *description)
+                        .to_owned(),
+                },
+            })
+            .collect();
+        SarifLog {
+            schema: SCHEMA,
+            version: VERSION,
+            runs: vec![Run {
+                tool: Tool {
+                    driver: Driver {
+                        name: "usedump",
+                        version: env!("CARGO_PKG_VERSION"),
+                        information_uri: INFORMATION_URI,
+                        rules,
+                    },
+                },
+                results: vec![],
+            }],
+        }
+    }
+
+    /// Pushes a result on the file keyed `file`, whose URI is its path in `paths`.
+    fn push_file(
+        &mut self,
+        rule_id: &'static str,
+        message: String,
+        paths: &FilePaths,
+        file: &str,
+        location: Option<Location>,
+    ) {
+        self.push(rule_id, message, paths.path_of(file), location);
+    }
+
+    fn push(
+        &mut self,
+        rule_id: &'static str,
+        message: String,
+        uri: &str,
+        location: Option<Location>,
+    ) {
+        let run = &mut self.runs[0];
+        let rule_index = run
+            .tool
+            .driver
+            .rules
+            .iter()
+            .position(|rule| rule.id == rule_id)
+            .expect("the rule of a result must be declared");
+        run.results.push(SarifResult {
+            rule_id,
+            rule_index,
+            level: "warning",
+            message: Message { text: message },
+            locations: vec![SarifLocation {
+                physical_location: PhysicalLocation {
+                    artifact_location: ArtifactLocation {
+                        uri: uri.to_owned(),
+                        uri_base_id: SRCROOT,
+                    },
+                    region: location.map(Region::from),
+                },
+            }],
+        });
+    }
+}
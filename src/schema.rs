@@ -0,0 +1,10 @@
+/// The JSON Schema of the output of `usedump dump`, as `usedump schema` prints it.
+///
+/// It is maintained by hand next to the `Serialize` implementations and must follow them,
+/// bumping `FORMAT_VERSION` whenever the shape changes.
+pub const DUMP_SCHEMA: &str = include_str!("schema.json");
+
+/// `DUMP_SCHEMA` as a value, to be written in any format.
+pub fn dump_schema() -> serde_json::Value {
+    serde_json::from_str(DUMP_SCHEMA).expect("the schema must be valid JSON")
+}
@@ -0,0 +1,235 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    error::{Result, UsedumpError},
+    options::DumpOptions,
+    query::ItemQuery,
+    watch::WatchedWorkspace,
+};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Any `UsedumpError`, e.g. a file which could not be read.
+const SERVER_ERROR: i64 = -32000;
+
+/// Answers queries about a workspace loaded once, over newline-delimited JSON-RPC 2.0.
+///
+/// The methods are:
+///
+/// - `dump_file {"path": ...}` reads the file at `path`, absolute or relative to the
+///   workspace root, resolves it again and returns its dump, as `usedump dump` would.
+/// - `who_uses {"item": ..., "regex": false}` returns the files importing the item, as
+///   `usedump who-uses` would.
+/// - `reload` loads the workspace again, e.g. after files were added or removed.
+///
+/// ```no_run
+/// let mut server = usedump::Server::new(usedump::DumpOptions::new("."))?;
+/// let stdin = std::io::stdin();
+/// server.serve(stdin.lock(), std::io::stdout())?;
+/// # Ok::<(), usedump::UsedumpError>(())
+/// ```
+pub struct Server {
+    options: DumpOptions,
+    workspace: WatchedWorkspace,
+}
+
+#[derive(Deserialize)]
+struct Request {
+    jsonrpc: String,
+    /// Missing for notifications, which get no response.
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Serialize)]
+struct Response {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ResponseError>,
+}
+
+#[derive(Serialize)]
+struct ResponseError {
+    code: i64,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct DumpFileParams {
+    path: PathBuf,
+}
+
+#[derive(Deserialize)]
+struct WhoUsesParams {
+    item: String,
+    #[serde(default)]
+    regex: bool,
+}
+
+impl Server {
+    /// Loads and resolves the workspace `options` describes, whose filters then apply to
+    /// every response.
+    pub fn new(options: DumpOptions) -> Result<Self> {
+        let workspace = WatchedWorkspace::load(&options)?;
+        Ok(Server { options, workspace })
+    }
+
+    /// Answers the requests read from `input`, one per line, until it ends.
+    pub fn serve<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line) {
+                output.write_all(response.as_bytes())?;
+                output.write_all(b"\n")?;
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Answers a single request, returning `None` for notifications.
+    pub fn handle(&mut self, request: &str) -> Option<String> {
+        let response = match serde_json::from_str::<Value>(request) {
+            Ok(request) => self.handle_value(request)?,
+            Err(e) => error_response(Value::Null, PARSE_ERROR, e.to_string()),
+        };
+        Some(serde_json::to_string(&response).expect("failed to serialize a response"))
+    }
+
+    fn handle_value(&mut self, request: Value) -> Option<Response> {
+        let request: Request = match serde_json::from_value(request) {
+            Ok(request) => request,
+            Err(e) => return Some(error_response(Value::Null, INVALID_REQUEST, e.to_string())),
+        };
+        if request.jsonrpc != "2.0" {
+            let message = format!("unsupported JSON-RPC version `{}`", request.jsonrpc);
+            return Some(error_response(
+                request.id.unwrap_or(Value::Null),
+                INVALID_REQUEST,
+                message,
+            ));
+        }
+
+        let result = self.call(&request.method, request.params);
+        let id = request.id?;
+        Some(match result {
+            Ok(result) => Response {
+                jsonrpc: "2.0",
+                id,
+                result: Some(result),
+                error: None,
+            },
+            Err(error) => Response {
+                jsonrpc: "2.0",
+                id,
+                result: None,
+                error: Some(error),
+            },
+        })
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, ResponseError> {
+        match method {
+            "dump_file" => {
+                let params: DumpFileParams = parse_params(params)?;
+                self.dump_file(params).map_err(server_error)
+            }
+            "who_uses" => {
+                let params: WhoUsesParams = parse_params(params)?;
+                self.who_uses(params).map_err(server_error)
+            }
+            "reload" => self.reload().map_err(server_error),
+            _ => Err(ResponseError {
+                code: METHOD_NOT_FOUND,
+                message: format!("unknown method `{}`", method),
+            }),
+        }
+    }
+
+    fn dump_file(&mut self, params: DumpFileParams) -> Result<Value> {
+        let path = self.workspace.metadata().workspace_root.join(&params.path);
+        let file_id = self
+            .workspace
+            .file_id(&path)
+            .or_else(|| self.workspace.file_id(&fs::canonicalize(&path).ok()?))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "`{}` is not a file of the workspace members",
+                        params.path.display()
+                    ),
+                )
+            })?;
+
+        let mut changed = HashMap::new();
+        changed.insert(file_id, path);
+        let mut map = self.workspace.resolve(&self.options, &changed)?;
+        // Module keys may be shared with other files, whose used items would be lost.
+        if !self.options.keys_by_module() {
+            self.workspace.replace_files(map.clone());
+        }
+        self.options.filter(&mut map, self.workspace.metadata());
+        Ok(to_value(&map))
+    }
+
+    fn who_uses(&self, params: WhoUsesParams) -> Result<Value> {
+        let query = if params.regex {
+            ItemQuery::regex(&params.item)?
+        } else {
+            ItemQuery::exact(params.item)
+        };
+        let map = self.workspace.dump(&self.options);
+        Ok(to_value(&map.importers(&query)))
+    }
+
+    fn reload(&mut self) -> Result<Value> {
+        self.workspace = WatchedWorkspace::load(&self.options)?;
+        Ok(Value::Null)
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, ResponseError> {
+    serde_json::from_value(params).map_err(|e| ResponseError {
+        code: INVALID_PARAMS,
+        message: e.to_string(),
+    })
+}
+
+fn server_error(e: UsedumpError) -> ResponseError {
+    ResponseError {
+        code: SERVER_ERROR,
+        message: e.to_string(),
+    }
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Response {
+    Response {
+        jsonrpc: "2.0",
+        id,
+        result: None,
+        error: Some(ResponseError { code, message }),
+    }
+}
+
+fn to_value<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).expect("failed to serialize a response")
+}
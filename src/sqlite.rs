@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use rusqlite::{params, Connection};
+
+use crate::used_item::CrateMap;
+
+/// The version of the schema `write_sqlite` creates, stored in the `meta` table under
+/// `schema_version` so that readers can tell older databases apart.
+pub const SQLITE_SCHEMA_VERSION: u32 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+CREATE TABLE files (id INTEGER PRIMARY KEY, path TEXT NOT NULL UNIQUE, package TEXT);
+CREATE TABLE items (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    path TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    origin TEXT
+);
+CREATE TABLE imports (
+    file_id INTEGER NOT NULL REFERENCES files (id),
+    item_id INTEGER NOT NULL REFERENCES items (id),
+    alias TEXT,
+    line INTEGER
+);
+CREATE INDEX items_name ON items (name);
+CREATE INDEX items_path ON items (path);
+CREATE INDEX items_origin ON items (origin);
+CREATE INDEX imports_file_id ON imports (file_id);
+CREATE INDEX imports_item_id ON imports (item_id);
+";
+
+/// Writes `map` as an SQLite database with a row in `files` per file, in `items` per
+/// distinct item and in `imports` per used item, as `--format sqlite` emits.
+///
+/// `origin` is the crate the item comes from and `line` the line it is named on, both
+/// `NULL` when unknown. The database is built in a temporary file, as SQLite needs one, and
+/// then copied to `w`.
+pub fn write_sqlite<W: Write>(map: &CrateMap, mut w: W) -> io::Result<()> {
+    let tmp_path = create_tmp_file()?;
+    let result = build_database(map, &tmp_path)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+        .and_then(|()| fs::read(&tmp_path))
+        .and_then(|contents| w.write_all(&contents));
+    let _ = fs::remove_file(&tmp_path);
+    result
+}
+
+/// Creates an empty file of its own in the temporary directory, which SQLite opens as an
+/// empty database. It is created anew, so that an existing database, e.g. left behind by a
+/// process of the same id, is never reused.
+fn create_tmp_file() -> io::Result<PathBuf> {
+    static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+    loop {
+        let path = env::temp_dir().join(format!(
+            "usedump-{}-{}.db",
+            process::id(),
+            CREATED.fetch_add(1, Ordering::SeqCst)
+        ));
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => return Ok(path),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn build_database(map: &CrateMap, path: &Path) -> rusqlite::Result<()> {
+    let mut connection = Connection::open(path)?;
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(SCHEMA)?;
+    transaction.execute(
+        "INSERT INTO meta (key, value) VALUES ('schema_version', ?1)",
+        params![SQLITE_SCHEMA_VERSION.to_string()],
+    )?;
+    {
+        let mut insert_file =
+            transaction.prepare("INSERT INTO files (id, path, package) VALUES (?1, ?2, ?3)")?;
+        let mut insert_item = transaction.prepare(
+            "INSERT INTO items (id, name, path, kind, origin) VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        let mut insert_import = transaction.prepare(
+            "INSERT INTO imports (file_id, item_id, alias, line) VALUES (?1, ?2, ?3, ?4)",
+        )?;
+
+        let mut item_ids: HashMap<(&str, &str, &str, Option<&str>), i64> = HashMap::new();
+        for (file_id, (file, used_item_map)) in (1i64..).zip(map.files()) {
+            insert_file.execute(params![file_id, file, map.package_of(file)])?;
+            for item in used_item_map.iter() {
+                let origin = item.origin().map(|origin| origin.crate_name());
+                let key = (item.name(), item.path(), item.kind().name(), origin);
+                let next_id = item_ids.len() as i64 + 1;
+                let item_id = match item_ids.get(&key) {
+                    Some(&id) => id,
+                    None => {
+                        insert_item.execute(params![next_id, key.0, key.1, key.2, key.3])?;
+                        item_ids.insert(key, next_id);
+                        next_id
+                    }
+                };
+                let line = item.location().map(|location| location.line);
+                insert_import.execute(params![file_id, item_id, item.alias(), line])?;
+            }
+        }
+    }
+    transaction.commit()
+}
@@ -0,0 +1,137 @@
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+/// Where the time of a dump went, see `DumpOptions::run_with_stats`.
+#[derive(Debug, Clone, Default)]
+pub struct DumpStats {
+    pub(crate) load: Duration,
+    pub(crate) resolution: Duration,
+    /// How long the analysis of each file took, by key.
+    pub(crate) files: BTreeMap<String, Duration>,
+    pub(crate) goto_definition_calls: usize,
+    pub(crate) unresolved: usize,
+    pub(crate) failed_files: usize,
+    /// The number of files whose used items were taken from the cache.
+    pub(crate) cached_files: usize,
+}
+
+impl DumpStats {
+    /// How many of the slowest files are displayed.
+    const SLOWEST: usize = 5;
+
+    /// The time spent running `cargo metadata` and loading the sources.
+    pub fn load(&self) -> Duration {
+        self.load
+    }
+
+    /// The wall-clock time spent analyzing the files.
+    pub fn resolution(&self) -> Duration {
+        self.resolution
+    }
+
+    /// How long the analysis of each file took, by key.
+    pub fn files(&self) -> &BTreeMap<String, Duration> {
+        &self.files
+    }
+
+    /// The number of `goto_definition` calls made, including those building module trees.
+    pub fn goto_definition_calls(&self) -> usize {
+        self.goto_definition_calls
+    }
+
+    /// The number of use trees which could not be resolved.
+    pub fn unresolved(&self) -> usize {
+        self.unresolved
+    }
+
+    /// The number of files whose analysis failed.
+    pub fn failed_files(&self) -> usize {
+        self.failed_files
+    }
+
+    /// The number of files whose used items were taken from the cache rather than resolved.
+    pub fn cached_files(&self) -> usize {
+        self.cached_files
+    }
+
+    /// Adds the times of `other`, a dump of the same workspace for another target, the
+    /// analysis of each file taking the time of both.
+    pub(crate) fn add(&mut self, other: DumpStats) {
+        self.load += other.load;
+        self.resolution += other.resolution;
+        for (file, duration) in other.files {
+            *self.files.entry(file).or_default() += duration;
+        }
+        self.goto_definition_calls += other.goto_definition_calls;
+        self.unresolved += other.unresolved;
+        self.failed_files += other.failed_files;
+        self.cached_files += other.cached_files;
+    }
+
+    /// The slowest files to analyze, slowest first.
+    fn slowest(&self) -> Vec<(&str, Duration)> {
+        let mut files: Vec<(&str, Duration)> = self
+            .files
+            .iter()
+            .map(|(file, duration)| (file.as_str(), *duration))
+            .collect();
+        files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        files
+    }
+}
+
+impl Serialize for DumpStats {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let files: BTreeMap<&str, f64> = self
+            .files
+            .iter()
+            .map(|(file, duration)| (file.as_str(), duration.as_secs_f64()))
+            .collect();
+
+        let mut s = serializer.serialize_struct("DumpStats", 7)?;
+        s.serialize_field("load_seconds", &self.load.as_secs_f64())?;
+        s.serialize_field("resolution_seconds", &self.resolution.as_secs_f64())?;
+        s.serialize_field("goto_definition_calls", &self.goto_definition_calls)?;
+        s.serialize_field("unresolved", &self.unresolved)?;
+        s.serialize_field("failed_files", &self.failed_files)?;
+        s.serialize_field("cached_files", &self.cached_files)?;
+        s.serialize_field("files", &files)?;
+        s.end()
+    }
+}
+
+impl fmt::Display for DumpStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "load                   {:>10.3}s",
+            self.load.as_secs_f64()
+        )?;
+        writeln!(
+            f,
+            "resolution             {:>10.3}s",
+            self.resolution.as_secs_f64()
+        )?;
+        writeln!(f, "files                  {:>10}", self.files.len())?;
+        writeln!(
+            f,
+            "goto_definition calls  {:>10}",
+            self.goto_definition_calls
+        )?;
+        writeln!(f, "unresolved use trees   {:>10}", self.unresolved)?;
+        writeln!(f, "failed files           {:>10}", self.failed_files)?;
+        writeln!(f, "cached files           {:>10}", self.cached_files)?;
+        let slowest = self.slowest();
+        if !slowest.is_empty() {
+            writeln!(f, "slowest files:")?;
+            for (file, duration) in slowest.into_iter().take(DumpStats::SLOWEST) {
+                writeln!(f, "  {:>10.3}s  {}", duration.as_secs_f64(), file)?;
+            }
+        }
+        Ok(())
+    }
+}
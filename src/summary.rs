@@ -0,0 +1,116 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+use crate::{
+    origin::Origin,
+    used_item::{CrateMap, UsedItemKind},
+};
+
+/// Aggregate statistics of a `CrateMap`, see `CrateMap::summarize`.
+#[derive(Debug, Clone)]
+pub struct Summary {
+    files: usize,
+    unique_items: BTreeMap<UsedItemKind, usize>,
+    /// Every imported item with the number of files importing it, most imported first.
+    most_imported: Vec<(String, usize)>,
+    external_crates: BTreeSet<String>,
+    top: usize,
+}
+
+impl Summary {
+    /// How many of the most imported items are serialized by default.
+    pub const DEFAULT_TOP: usize = 10;
+
+    pub(crate) fn new(crate_map: &CrateMap) -> Self {
+        let mut unique: BTreeMap<UsedItemKind, BTreeSet<&str>> = BTreeMap::new();
+        let mut importers: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+        let mut external_crates = BTreeSet::new();
+        for (file, kind, item) in crate_map.iter() {
+            unique.entry(kind).or_default().insert(item.path());
+            importers.entry(item.path()).or_default().insert(file);
+            if let Some(Origin::External(name)) = item.origin() {
+                external_crates.insert(name.clone());
+            }
+        }
+
+        let mut most_imported: Vec<(String, usize)> = importers
+            .into_iter()
+            .map(|(path, files)| (path.to_owned(), files.len()))
+            .collect();
+        most_imported.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        Summary {
+            files: crate_map.files().count(),
+            unique_items: unique
+                .into_iter()
+                .map(|(kind, paths)| (kind, paths.len()))
+                .collect(),
+            most_imported,
+            external_crates,
+            top: Summary::DEFAULT_TOP,
+        }
+    }
+
+    /// Serializes the `n` most imported items instead of `DEFAULT_TOP`.
+    pub fn top(mut self, n: usize) -> Self {
+        self.top = n;
+        self
+    }
+
+    /// The number of analyzed files.
+    pub fn files(&self) -> usize {
+        self.files
+    }
+
+    /// The number of distinct items imported in the workspace for each kind.
+    pub fn unique_items(&self, kind: UsedItemKind) -> usize {
+        self.unique_items.get(&kind).copied().unwrap_or(0)
+    }
+
+    /// Paths of the imported items with the number of files importing them, most imported
+    /// first.
+    pub fn most_imported(&self) -> &[(String, usize)] {
+        &self.most_imported
+    }
+
+    /// The external crates the workspace imports items from.
+    pub fn external_crates(&self) -> &BTreeSet<String> {
+        &self.external_crates
+    }
+}
+
+impl Serialize for Summary {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        #[derive(Serialize)]
+        struct Imported<'a> {
+            path: &'a str,
+            files: usize,
+        }
+
+        let unique_items: BTreeMap<&str, usize> = self
+            .unique_items
+            .iter()
+            .map(|(kind, count)| (kind.set_name(), *count))
+            .collect();
+        let most_imported: Vec<Imported<'_>> = self
+            .most_imported
+            .iter()
+            .take(self.top)
+            .map(|(path, files)| Imported {
+                path,
+                files: *files,
+            })
+            .collect();
+
+        let mut s = serializer.serialize_struct("Summary", 4)?;
+        s.serialize_field("files", &self.files)?;
+        s.serialize_field("external_crates", &self.external_crates.len())?;
+        s.serialize_field("unique_items", &unique_items)?;
+        s.serialize_field("most_imported", &most_imported)?;
+        s.end()
+    }
+}
@@ -0,0 +1,378 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ffi::OsStr,
+    fs, io, mem,
+    path::{Path, PathBuf},
+    sync::atomic::Ordering,
+    time::Instant,
+};
+
+use cargo_metadata::Metadata;
+
+use crate::{
+    error::{Result, UsedumpError},
+    generated,
+    options::{AnalysisConfig, GeneratedMode, Keys},
+    origin::Origin,
+    output::normalize_path,
+    progress::Progress,
+    stats::DumpStats,
+    target::Targets,
+    used_item::{used_items_by_syntax, AnalyzedFiles, CrateMap, Diagnostic},
+};
+
+/// The crates of the sysroot which paths may name without a dependency.
+const STD_CRATES: &[&str] = &["std", "core", "alloc", "proc_macro", "test"];
+
+/// Lists the use trees of every file of the members `config` selects from their syntax
+/// only, see `DumpOptions::no_resolve`, keying the files like `analyze_files`.
+///
+/// The files of a member are those under its directory, but for the ones of the target
+/// directory, of hidden directories and of nested packages. The origin of an item is told
+/// by the first segment of its path: `std`, `core`, `alloc` and the like are the sysroot,
+/// `crate`, `self` and `super` the member itself, and the other names the dependency named so in
+/// the metadata, or just an external crate of that name. Module paths cannot be told
+/// without analysis, so files are always keyed by path. The files they `include!` from
+/// outside of the members are listed per `config.generated`, like with analysis.
+pub(crate) fn resolve_by_syntax(
+    metadata: &Metadata,
+    config: &AnalysisConfig,
+) -> Result<(CrateMap, DumpStats)> {
+    let started = Instant::now();
+    let crates = SyntaxOrigins::new(metadata);
+
+    let mut members: Vec<(&str, &Path)> = metadata
+        .packages
+        .iter()
+        .filter(|package| metadata.workspace_members.contains(&package.id))
+        .filter_map(|package| Some((package.name.as_str(), package.manifest_path.parent()?)))
+        .collect();
+    members.sort();
+    for package in &config.packages {
+        if !members.iter().any(|(name, _)| name == package) {
+            let mut available: Vec<String> =
+                members.iter().map(|(name, _)| name.to_string()).collect();
+            available.dedup();
+            return Err(UsedumpError::UnknownPackage {
+                name: package.clone(),
+                available,
+            });
+        }
+    }
+    if !config.packages.is_empty() {
+        members.retain(|(name, _)| config.packages.iter().any(|package| package == name));
+    }
+
+    let targets = Targets::from_metadata(Some(metadata));
+    let mut analyzed = AnalyzedFiles {
+        values: BTreeMap::new(),
+        diagnostics: vec![],
+        packages: BTreeMap::new(),
+        modules: BTreeMap::new(),
+        definitions: BTreeMap::new(),
+        durations: BTreeMap::new(),
+        targets: BTreeMap::new(),
+        syntax_errors: BTreeMap::new(),
+        generated: BTreeSet::new(),
+        included: vec![],
+        goto_definition_calls: 0,
+        cancelled: false,
+    };
+    let mut files = vec![];
+    for (package, dir) in members {
+        let mut paths = vec![];
+        rust_files(dir, dir, &metadata.target_directory, &mut paths)?;
+        for path in paths {
+            let relative = normalize_path(path.strip_prefix(dir).unwrap_or(&path));
+            let target = targets.kind_by_path(dir, &relative);
+            if let Some(selected) = &config.targets {
+                if !selected.contains(&target) {
+                    continue;
+                }
+            }
+            if !config.filter.is_match(&relative) {
+                continue;
+            }
+            let key = if config.absolute_paths {
+                normalize_path(&path)
+            } else if config.flat_paths {
+                relative
+            } else {
+                format!("{}/{}", package, relative)
+            };
+            analyzed.packages.insert(key.clone(), package.to_owned());
+            analyzed.targets.insert(key.clone(), target);
+            files.push((package, path, key));
+        }
+    }
+
+    let total = files.len();
+    if let Some(progress) = &config.progress {
+        progress.report(Progress::Analyzing { total });
+    }
+    for (done, (package, path, key)) in files.into_iter().enumerate() {
+        let cancelled = config
+            .cancel
+            .as_ref()
+            .map_or(false, |cancel| cancel.load(Ordering::SeqCst));
+        if cancelled {
+            analyzed.cancelled = true;
+            break;
+        }
+        let file_started = Instant::now();
+        let origin_of = |first: &str| crates.origin_of(package, first);
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                let used_item_map = used_items_by_syntax(&text, &origin_of);
+                analyzed.values.insert(key.clone(), used_item_map);
+                if config.generated != GeneratedMode::Skip {
+                    analyzed.included.extend(generated::included_files(
+                        metadata,
+                        &text,
+                        &path,
+                        &key,
+                        package,
+                        analyzed.targets[&key],
+                        config,
+                    ));
+                }
+            }
+            Err(e) => analyzed
+                .diagnostics
+                .push(Diagnostic::new(key.clone(), e.to_string())),
+        }
+        analyzed
+            .durations
+            .insert(key.clone(), file_started.elapsed());
+        if let Some(progress) = &config.progress {
+            progress.report(Progress::Analyzed {
+                file: &key,
+                done: done + 1,
+                total,
+            });
+        }
+    }
+
+    let mut values = mem::take(&mut analyzed.values);
+    generated::add_generated_files(&mut values, &mut analyzed, Some(metadata), config);
+    let files = mem::take(&mut analyzed.durations);
+    let map = CrateMap::from_analyzed(values, analyzed, Some(metadata), Keys::File);
+    let stats = DumpStats {
+        resolution: started.elapsed(),
+        files,
+        unresolved: map.unresolved_count(),
+        failed_files: map.diagnostics().len(),
+        ..DumpStats::default()
+    };
+    Ok((map, stats))
+}
+
+/// Tells the origin of an item from the first segment of its path, as written in a file of
+/// a workspace member, see `resolve_by_syntax`.
+pub(crate) struct SyntaxOrigins<'a> {
+    /// The package of every library crate, by the name paths refer to it by, and whether
+    /// it is a member.
+    crates: HashMap<String, (&'a str, bool)>,
+}
+
+impl<'a> SyntaxOrigins<'a> {
+    pub(crate) fn new(metadata: &'a Metadata) -> Self {
+        let mut crates: HashMap<String, (&str, bool)> = HashMap::new();
+        for package in &metadata.packages {
+            let member = metadata.workspace_members.contains(&package.id);
+            let libs = package.targets.iter().filter(|target| {
+                target
+                    .kind
+                    .iter()
+                    .any(|kind| kind.ends_with("lib") || kind == "proc-macro")
+            });
+            for name in libs
+                .map(|target| target.name.as_str())
+                .chain(Some(package.name.as_str()))
+            {
+                crates
+                    .entry(name.replace('-', "_"))
+                    .or_insert((package.name.as_str(), member));
+            }
+        }
+        SyntaxOrigins { crates }
+    }
+
+    /// The origin of the items whose path starts with `first` in the files of the member
+    /// `package`.
+    pub(crate) fn origin_of(&self, package: &str, first: &str) -> Option<Origin> {
+        Some(match first {
+            "" => return None,
+            "crate" | "self" | "super" => Origin::Local(package.to_owned()),
+            _ if STD_CRATES.contains(&first) => Origin::Std(first.to_owned()),
+            _ => match self.crates.get(first) {
+                Some((name, true)) => Origin::Local(name.to_string()),
+                Some((name, false)) => Origin::External(name.to_string()),
+                None => Origin::External(first.to_owned()),
+            },
+        })
+    }
+}
+
+/// Collects the `.rs` files under `dir`, ordered by path, leaving out the target directory,
+/// hidden directories and the directories of packages other than the one in `root`.
+fn rust_files(
+    dir: &Path,
+    root: &Path,
+    target_dir: &Path,
+    files: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<_>>()?;
+    entries.sort();
+    for path in entries {
+        let hidden = path
+            .file_name()
+            .map_or(false, |name| name.to_string_lossy().starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if path.is_dir() {
+            let nested_package = path != root && path.join("Cargo.toml").is_file();
+            let target = path == target_dir || path.file_name() == Some(OsStr::new("target"));
+            if !target && !nested_package {
+                rust_files(&path, root, target_dir, files)?;
+            }
+        } else if path
+            .extension()
+            .map_or(false, |extension| extension == "rs")
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::used_item::{UsedItem, UsedItemKind, UsedItemMap};
+
+    use super::*;
+
+    const TEXT: &str = r#"
+use std::{collections::HashMap, io::{self, Read}};
+use crate::shapes::Square as Shape;
+use super::*;
+pub use serde::Serialize;
+use ::serde_json::Value;
+use left::UNIT;
+extern crate alloc as _;
+
+fn area() {
+    use self::inner::side;
+}
+"#;
+
+    /// The items of `TEXT` in the member `app`, which depends on the member `left` and on
+    /// `serde`.
+    fn used_items() -> UsedItemMap {
+        let mut crates = HashMap::new();
+        crates.insert("app".to_owned(), ("app", true));
+        crates.insert("left".to_owned(), ("left", true));
+        crates.insert("serde".to_owned(), ("serde", false));
+        let origins = SyntaxOrigins { crates };
+        used_items_by_syntax(TEXT, &|first| origins.origin_of("app", first))
+    }
+
+    fn item<'a>(used_item_map: &'a UsedItemMap, path: &str) -> &'a UsedItem {
+        used_item_map
+            .iter()
+            .find(|item| item.path() == path)
+            .unwrap_or_else(|| panic!("no `{}` in {:?}", path, used_item_map))
+    }
+
+    #[test]
+    fn paths_are_listed_as_written() {
+        let used_item_map = used_items();
+        let mut paths: Vec<&str> = used_item_map.iter().map(UsedItem::path).collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                "::serde_json::Value",
+                "alloc",
+                "crate::shapes::Square",
+                "left::UNIT",
+                "self::inner::side",
+                "serde::Serialize",
+                "std::collections::HashMap",
+                "std::io",
+                "std::io::Read",
+                "super::*",
+            ]
+        );
+        assert!(used_item_map.iter().all(UsedItem::unresolved));
+        assert!(!used_item_map.has_syntax_errors());
+    }
+
+    #[test]
+    fn kinds_are_guessed_from_the_names() {
+        let used_item_map = used_items();
+        let kind = |path| item(&used_item_map, path).kind();
+        assert_eq!(kind("std::collections::HashMap"), UsedItemKind::Struct);
+        assert_eq!(kind("std::io"), UsedItemKind::Module);
+        assert_eq!(kind("left::UNIT"), UsedItemKind::Const);
+        assert_eq!(kind("self::inner::side"), UsedItemKind::Module);
+        assert_eq!(kind("super::*"), UsedItemKind::Other);
+        assert_eq!(item(&used_item_map, "std::io").name(), "io");
+    }
+
+    #[test]
+    fn origins_are_told_by_the_first_segment() {
+        let used_item_map = used_items();
+        let origin = |path| item(&used_item_map, path).origin().cloned();
+        assert_eq!(
+            origin("std::collections::HashMap"),
+            Some(Origin::Std("std".to_owned()))
+        );
+        assert_eq!(origin("alloc"), Some(Origin::Std("alloc".to_owned())));
+        assert_eq!(
+            origin("crate::shapes::Square"),
+            Some(Origin::Local("app".to_owned()))
+        );
+        assert_eq!(origin("super::*"), Some(Origin::Local("app".to_owned())));
+        assert_eq!(origin("left::UNIT"), Some(Origin::Local("left".to_owned())));
+        assert_eq!(
+            origin("serde::Serialize"),
+            Some(Origin::External("serde".to_owned()))
+        );
+        // Not a dependency in the metadata, but still a crate.
+        assert_eq!(
+            origin("::serde_json::Value"),
+            Some(Origin::External("serde_json".to_owned()))
+        );
+    }
+
+    #[test]
+    fn use_items_keep_what_the_syntax_tells() {
+        let used_item_map = used_items();
+        assert_eq!(
+            item(&used_item_map, "crate::shapes::Square").alias(),
+            Some("Shape")
+        );
+        assert_eq!(
+            item(&used_item_map, "serde::Serialize").visibility(),
+            Some("pub")
+        );
+        assert!(item(&used_item_map, "super::*").via_glob());
+        assert_eq!(
+            item(&used_item_map, "self::inner::side").scope(),
+            Some("area()")
+        );
+        let alloc = item(&used_item_map, "alloc");
+        assert!(alloc.extern_crate());
+        assert!(alloc.anonymous());
+    }
+
+    #[test]
+    fn syntax_errors_are_counted() {
+        let used_item_map = used_items_by_syntax("use std::{io;\n", &|_| None);
+        assert!(used_item_map.has_syntax_errors());
+    }
+}
@@ -0,0 +1,176 @@
+use std::{
+    env,
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+};
+
+use lazy_static::lazy_static;
+
+use crate::output::normalize_path;
+
+/// The variable rust-analyzer reads the path of the sources of the standard library from
+/// when loading a workspace, the only way to give it one.
+const RUST_SRC_PATH: &str = "RUST_SRC_PATH";
+
+lazy_static! {
+    /// Held while `RUST_SRC_PATH` is set for a load, so that loads on other threads see
+    /// their own value.
+    static ref LOAD_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Which standard library the workspace is loaded with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SysrootConfig {
+    /// The sources `RUST_SRC_PATH` points at, else the sysroot of the toolchain which a
+    /// `rust-toolchain` or `rust-toolchain.toml` file of the workspace names, else that of
+    /// `rustc`.
+    Discover,
+    /// The sysroot in the directory, or the sources of the standard library themselves.
+    Path(PathBuf),
+    /// No standard library.
+    Disabled,
+}
+
+impl Default for SysrootConfig {
+    fn default() -> Self {
+        SysrootConfig::Discover
+    }
+}
+
+/// The standard library a workspace is loaded with, see `resolve`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Sysroot {
+    /// The directory holding the sources of `core`, `std` and the like, empty when there
+    /// is none.
+    pub(crate) sources: PathBuf,
+    /// The sysroot the sources are in, normalized, `None` when there is none.
+    pub(crate) sysroot: Option<String>,
+}
+
+/// Finds the standard library `config` selects for the workspace in `dir`.
+///
+/// When there is no sysroot or its sources are missing, e.g. as the `rust-src` component
+/// is not installed, the sources are an empty directory, so that the workspace still loads
+/// with the items of the standard library unresolved, and the sysroot is `None`.
+pub(crate) fn resolve(dir: &Path, config: &SysrootConfig) -> io::Result<Sysroot> {
+    let found = match config {
+        SysrootConfig::Disabled => None,
+        SysrootConfig::Path(path) => Some((path.clone(), sources_of(path))),
+        SysrootConfig::Discover => match env::var_os(RUST_SRC_PATH) {
+            Some(sources) => Some((PathBuf::from(&sources), PathBuf::from(sources))),
+            None => discover(dir).map(|sysroot| {
+                let sources = sources_of(&sysroot);
+                (sysroot, sources)
+            }),
+        },
+    };
+    match found {
+        Some((sysroot, sources)) if sources.is_dir() => Ok(Sysroot {
+            sources,
+            sysroot: Some(normalize_path(sysroot)),
+        }),
+        _ => {
+            let empty = empty_dir();
+            fs::create_dir_all(&empty)?;
+            Ok(Sysroot {
+                sources: empty,
+                sysroot: None,
+            })
+        }
+    }
+}
+
+/// Runs `load` with rust-analyzer pointed at the sources of `sysroot`, restoring
+/// `RUST_SRC_PATH` as it was right after, so that nothing else in the process sees it.
+pub(crate) fn load_with<T, F: FnOnce() -> T>(sysroot: &Sysroot, load: F) -> T {
+    /// Restores the variable on drop, were `load` to panic.
+    struct Restore(Option<OsString>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            match self.0.take() {
+                Some(previous) => env::set_var(RUST_SRC_PATH, previous),
+                None => env::remove_var(RUST_SRC_PATH),
+            }
+        }
+    }
+
+    // A load which panicked held the lock, which still serializes the others.
+    let _lock = LOAD_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let _restore = Restore(env::var_os(RUST_SRC_PATH));
+    env::set_var(RUST_SRC_PATH, &sysroot.sources);
+    load()
+}
+
+fn empty_dir() -> PathBuf {
+    env::temp_dir().join("usedump-no-sysroot")
+}
+
+/// The sources of the standard library of `sysroot`, which may be the sources themselves.
+fn sources_of(sysroot: &Path) -> PathBuf {
+    let sources = sysroot.join("lib/rustlib/src/rust/src");
+    if sources.is_dir() {
+        sources
+    } else {
+        sysroot.to_owned()
+    }
+}
+
+/// The sysroot of the toolchain of the workspace in `dir`, as `rustc --print sysroot`
+/// prints it.
+fn discover(dir: &Path) -> Option<PathBuf> {
+    let output = rustc(dir).args(&["--print", "sysroot"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let sysroot = String::from_utf8(output.stdout).ok()?;
+    Some(PathBuf::from(sysroot.trim()))
+}
+
+/// The `rustc` of the toolchain of the workspace in `dir`, run through rustup when a
+/// toolchain file names the toolchain, else `$RUSTC` or the one in `PATH`.
+pub(crate) fn rustc(dir: &Path) -> Command {
+    let mut command = match toolchain_of(dir) {
+        Some(toolchain) => {
+            let mut command = Command::new("rustup");
+            command.args(&["run", toolchain.as_str(), "rustc"]);
+            command
+        }
+        None => Command::new(env::var_os("RUSTC").unwrap_or_else(|| "rustc".into())),
+    };
+    command.current_dir(dir);
+    command
+}
+
+/// The toolchain a `rust-toolchain` or `rust-toolchain.toml` file in `dir` or one of its
+/// parents names, as rustup looks them up. The legacy `rust-toolchain` file holds either
+/// the bare name of the toolchain or the same table as `rust-toolchain.toml`.
+fn toolchain_of(dir: &Path) -> Option<String> {
+    for ancestor in dir.ancestors() {
+        for name in &["rust-toolchain", "rust-toolchain.toml"] {
+            let contents = match fs::read_to_string(ancestor.join(name)) {
+                Ok(contents) => contents,
+                Err(_) => continue,
+            };
+            if let Ok(value) = contents.parse::<toml::Value>() {
+                let channel = value
+                    .get("toolchain")
+                    .and_then(|toolchain| toolchain.get("channel"))
+                    .and_then(toml::Value::as_str);
+                if let Some(channel) = channel {
+                    return Some(channel.to_owned());
+                }
+            }
+            let toolchain = contents.lines().next().unwrap_or("").trim();
+            if !toolchain.is_empty() && !toolchain.starts_with('[') {
+                return Some(toolchain.to_owned());
+            }
+        }
+    }
+    None
+}
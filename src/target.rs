@@ -0,0 +1,221 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use cargo_metadata::Metadata;
+use ra_db::{FileId, SourceDatabase, SourceDatabaseExt};
+use ra_ide::{Analysis, AnalysisHost};
+use serde::{Deserialize, Serialize};
+
+use crate::used_item::SourceMap;
+
+/// The kind of cargo target a file belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TargetKind {
+    /// The library, whatever its crate type, e.g. a proc-macro.
+    Lib,
+    Bin,
+    /// An integration test under `tests/`.
+    Test,
+    Bench,
+    Example,
+    /// A build script, e.g. `build.rs`, and the modules it declares.
+    BuildScript,
+}
+
+impl TargetKind {
+    pub const ALL: &'static [TargetKind] = &[
+        TargetKind::Lib,
+        TargetKind::Bin,
+        TargetKind::Test,
+        TargetKind::Bench,
+        TargetKind::Example,
+        TargetKind::BuildScript,
+    ];
+
+    /// The kinds analyzed unless asked otherwise.
+    pub const DEFAULT: &'static [TargetKind] = &[TargetKind::Lib, TargetKind::Bin];
+
+    pub const NAMES: &'static [&'static str] =
+        &["lib", "bin", "test", "bench", "example", "build-script"];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TargetKind::Lib => "lib",
+            TargetKind::Bin => "bin",
+            TargetKind::Test => "test",
+            TargetKind::Bench => "bench",
+            TargetKind::Example => "example",
+            TargetKind::BuildScript => "build-script",
+        }
+    }
+
+    /// The kind of a target of `cargo metadata`, given by its `kind`.
+    fn of_metadata(kinds: &[String]) -> Option<Self> {
+        match kinds.first().map(String::as_str)? {
+            "bin" => Some(TargetKind::Bin),
+            "test" => Some(TargetKind::Test),
+            "bench" => Some(TargetKind::Bench),
+            "example" => Some(TargetKind::Example),
+            "custom-build" => Some(TargetKind::BuildScript),
+            _ => Some(TargetKind::Lib),
+        }
+    }
+}
+
+impl FromStr for TargetKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TargetKind::ALL
+            .iter()
+            .find(|kind| kind.name() == s)
+            .copied()
+            .ok_or_else(|| {
+                format!(
+                    "unknown target kind `{}`, expected one of: {}",
+                    s,
+                    TargetKind::NAMES.join(", ")
+                )
+            })
+    }
+}
+
+impl fmt::Display for TargetKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The targets of every package, to tell which one a file belongs to.
+pub(crate) struct Targets {
+    /// The root file and the kind of every target, by package directory.
+    by_package: HashMap<PathBuf, Vec<(PathBuf, TargetKind)>>,
+    /// The root files of the build scripts' crates.
+    build_script_roots: HashSet<FileId>,
+}
+
+impl Targets {
+    /// The targets of the packages of `metadata`. The crates of `host` rooted at a build
+    /// script are looked up, as their modules may be anywhere in the package.
+    pub(crate) fn new(
+        metadata: Option<&Metadata>,
+        host: &AnalysisHost,
+        source_map: &SourceMap,
+    ) -> Self {
+        let mut targets = Targets::from_metadata(metadata);
+        let db = host.raw_database();
+        let crate_graph = db.crate_graph();
+        for crate_id in crate_graph.iter() {
+            let root = crate_graph.crate_root(crate_id);
+            let package_root = match source_map.get(&db.file_source_root(root)) {
+                Some(package_root) => package_root,
+                None => continue,
+            };
+            let path = db.file_relative_path(root).to_string();
+            if targets.kind_by_path(package_root.path(), &path) == TargetKind::BuildScript {
+                targets.build_script_roots.insert(root);
+            }
+        }
+        targets
+    }
+
+    /// The targets of the packages of `metadata`, without analysis. The modules of build
+    /// scripts are then attributed like any other file, by `kind_by_path`.
+    pub(crate) fn from_metadata(metadata: Option<&Metadata>) -> Self {
+        let mut by_package: HashMap<PathBuf, Vec<(PathBuf, TargetKind)>> = HashMap::new();
+        for package in metadata.iter().flat_map(|metadata| &metadata.packages) {
+            let dir = match package.manifest_path.parent() {
+                Some(dir) => dir,
+                None => continue,
+            };
+            let targets = package.targets.iter().filter_map(|target| {
+                let kind = TargetKind::of_metadata(&target.kind)?;
+                Some((target.src_path.clone(), kind))
+            });
+            by_package
+                .entry(dir.to_owned())
+                .or_default()
+                .extend(targets);
+        }
+        Targets {
+            by_package,
+            build_script_roots: HashSet::new(),
+        }
+    }
+
+    /// The kind of the target the file `file_id` at `path`, relative to `package_dir`,
+    /// belongs to.
+    ///
+    /// The files of a build script's crate, and of no other crate, belong to the build
+    /// script. The other files are attributed by `kind_by_path`.
+    pub(crate) fn kind_of(
+        &self,
+        analysis: &Analysis,
+        file_id: FileId,
+        package_dir: &Path,
+        path: &str,
+    ) -> TargetKind {
+        if !self.build_script_roots.is_empty() {
+            let crates = analysis.crate_for(file_id).unwrap_or_default();
+            let in_build_script = !crates.is_empty()
+                && crates.iter().all(|&crate_id| {
+                    analysis
+                        .crate_root(crate_id)
+                        .map_or(false, |root| self.build_script_roots.contains(&root))
+                });
+            if in_build_script {
+                return TargetKind::BuildScript;
+            }
+        }
+        self.kind_by_path(package_dir, path)
+    }
+
+    /// The kind of the target the file at `path`, relative to `package_dir`, belongs to,
+    /// going by paths only.
+    ///
+    /// Files are attributed to the target they are the root of, then to the target whose
+    /// root is in the closest parent directory, e.g. `tests/common/mod.rs` to the tests,
+    /// and otherwise by cargo's conventional layout. Without metadata, only the layout is
+    /// known.
+    pub(crate) fn kind_by_path(&self, package_dir: &Path, path: &str) -> TargetKind {
+        let targets = self
+            .by_package
+            .get(package_dir)
+            .map_or(&[][..], Vec::as_slice);
+        let file = package_dir.join(path);
+        if let Some((_, kind)) = targets.iter().find(|(root, _)| *root == file) {
+            return *kind;
+        }
+
+        // The library and the binaries share `src`, so their modules are told apart below.
+        // Build scripts usually sit in the package directory, which holds every file.
+        let src = package_dir.join("src");
+        let closest = targets
+            .iter()
+            .filter(|(_, kind)| *kind != TargetKind::BuildScript)
+            .filter_map(|(root, kind)| Some((root.parent()?, *kind)))
+            .filter(|(dir, _)| *dir != src && file.starts_with(dir))
+            .max_by_key(|(dir, _)| dir.components().count());
+        if let Some((_, kind)) = closest {
+            return kind;
+        }
+
+        let first = path.split('/').next().unwrap_or("");
+        match first {
+            "tests" => TargetKind::Test,
+            "benches" => TargetKind::Bench,
+            "examples" => TargetKind::Example,
+            "build.rs" if targets.is_empty() => TargetKind::BuildScript,
+            _ if path.starts_with("src/bin/") => TargetKind::Bin,
+            _ if targets.is_empty() || targets.iter().any(|(_, k)| *k == TargetKind::Lib) => {
+                TargetKind::Lib
+            }
+            _ => TargetKind::Bin,
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::Path,
+};
+
+use ra_db::{Cancelable, FileId};
+use ra_ide::{Analysis, FilePosition};
+use ra_syntax::{
+    ast::{self, NameOwner},
+    AstNode, SourceFile, SyntaxKind, SyntaxNode,
+};
+
+use serde::Serialize;
+
+use crate::{
+    definition_path::covering_node,
+    error::Result,
+    file_paths::FilePaths,
+    options::{AnalysisConfig, LoadConfig},
+    used_item::{analyze_files, load_workspace, used_items_in_file, Location, UsedItem},
+    UsedItemKind, UsedItemMap,
+};
+
+/// Finds the imports of every workspace member file which are never referenced in it,
+/// keyed by file. Files without unused imports are omitted.
+pub fn find_unused_imports_in_cargo<Q: AsRef<Path>>(
+    dir: Q,
+) -> Result<BTreeMap<String, BTreeSet<String>>> {
+    let unused = find_unused_imports_with_locations(dir)?;
+    Ok(unused
+        .files
+        .into_iter()
+        .map(|(file, imports)| {
+            let names = imports.into_iter().map(|import| import.name).collect();
+            (file, names)
+        })
+        .collect())
+}
+
+/// The imports `find_unused_imports_with_locations` found, keyed by file.
+#[derive(Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct UnusedImports {
+    files: BTreeMap<String, Vec<UnusedImport>>,
+    #[serde(skip)]
+    paths: FilePaths,
+}
+
+/// An import which is never referenced in its file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UnusedImport {
+    /// The local name of the item.
+    name: String,
+    kind: UsedItemKind,
+    /// Where the item is named in the use item.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+}
+
+impl UnusedImports {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    pub fn files(&self) -> &BTreeMap<String, Vec<UnusedImport>> {
+        &self.files
+    }
+
+    /// The local names of the unused imports, as `find_unused_imports_in_cargo` lists them.
+    pub fn names(&self) -> BTreeMap<&str, BTreeSet<&str>> {
+        self.files
+            .iter()
+            .map(|(file, imports)| {
+                let names = imports.iter().map(|import| import.name.as_str()).collect();
+                (file.as_str(), names)
+            })
+            .collect()
+    }
+
+    pub fn paths(&self) -> &FilePaths {
+        &self.paths
+    }
+
+    /// Keeps only the imports `f` returns `true` for, dropping the files left without any.
+    pub(crate) fn retain<F: FnMut(&str, &UnusedImport) -> bool>(&mut self, mut f: F) {
+        for (file, imports) in &mut self.files {
+            imports.retain(|import| f(file, import));
+        }
+        self.files.retain(|_, imports| !imports.is_empty());
+        let files = &self.files;
+        self.paths.retain(|file| files.contains_key(file));
+    }
+}
+
+impl UnusedImport {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> UsedItemKind {
+        self.kind
+    }
+
+    pub fn location(&self) -> Option<Location> {
+        self.location
+    }
+}
+
+/// Same as `find_unused_imports_in_cargo`, also recording where each unused import is.
+pub fn find_unused_imports_with_locations<Q: AsRef<Path>>(dir: Q) -> Result<UnusedImports> {
+    let mut loaded = load_workspace(dir.as_ref(), &LoadConfig::default())?;
+    let analyzed = analyze_files(
+        &mut loaded.host,
+        &loaded.source_map,
+        Some(&loaded.metadata),
+        &AnalysisConfig::default(),
+        |analysis, workspace, file_id| {
+            let used_item_map = used_items_in_file(analysis, workspace, file_id)?;
+            let imports = unused_items(analysis, file_id, &used_item_map)?
+                .map(|item| UnusedImport {
+                    name: item.local_name().to_owned(),
+                    kind: item.kind(),
+                    location: item.location(),
+                })
+                .collect::<Vec<_>>();
+            Ok(imports)
+        },
+    )?;
+    let files: BTreeMap<String, Vec<UnusedImport>> = analyzed
+        .values
+        .into_iter()
+        .filter(|(_, imports)| !imports.is_empty())
+        .collect();
+    let paths = analyzed
+        .definitions
+        .into_iter()
+        .filter(|(file, _)| files.contains_key(file))
+        .collect();
+    Ok(UnusedImports {
+        files,
+        paths: FilePaths::new(paths),
+    })
+}
+
+/// Returns the local names of the items in `used_item_map`, as listed for `file_id`, which
+/// are never referenced in the rest of the file.
+///
+/// Items imported as `_` are always considered used, and so are traits whose methods are
+/// called in the file. Items imported through globs and `#[macro_use]` crates, whose
+/// macros may be used anywhere in the crate, are not reported.
+pub fn find_unused_imports(
+    analysis: &Analysis,
+    file_id: FileId,
+    used_item_map: &UsedItemMap,
+) -> Cancelable<BTreeSet<String>> {
+    Ok(unused_items(analysis, file_id, used_item_map)?
+        .map(|item| item.local_name().to_owned())
+        .collect())
+}
+
+fn unused_items<'a>(
+    analysis: &Analysis,
+    file_id: FileId,
+    used_item_map: &'a UsedItemMap,
+) -> Cancelable<impl Iterator<Item = &'a UsedItem>> {
+    let references = References::collect(analysis, file_id, true)?;
+    Ok(used_item_map
+        .iter()
+        .filter(|item| item.kind() != UsedItemKind::Derive)
+        .filter(|item| !item.via_glob() && !item.macro_use() && !item.anonymous())
+        .filter(|item| item.count() == 0)
+        .filter(move |item| {
+            item.kind() != UsedItemKind::Trait || !references.calls_methods_of(item.name())
+        }))
+}
+
+/// The identifiers a file refers to outside of its use items.
+pub(crate) struct References {
+    names: HashMap<String, usize>,
+    /// Names of the traits defining (or implemented by the impls defining) the methods
+    /// called in the file.
+    method_traits: HashSet<String>,
+}
+
+impl References {
+    /// Resolving the traits of method calls needs a `goto_definition` per call, so it is only
+    /// done when `with_method_traits` is set.
+    pub(crate) fn collect(
+        analysis: &Analysis,
+        file_id: FileId,
+        with_method_traits: bool,
+    ) -> Cancelable<Self> {
+        let source_file = analysis.parse(file_id)?;
+        let mut names = HashMap::new();
+        let mut method_traits = HashSet::new();
+        let mut definition_files: HashMap<FileId, SourceFile> = HashMap::new();
+
+        for element in source_file.syntax().descendants_with_tokens() {
+            let token = match element.into_token() {
+                Some(token) => token,
+                None => continue,
+            };
+            if token.kind() != SyntaxKind::IDENT || is_in_use_item(&token.parent()) {
+                continue;
+            }
+            let parent = token.parent();
+            // Identifiers in macro arguments and attributes are only tokens, not name refs.
+            if parent.kind() != SyntaxKind::NAME_REF && parent.kind() != SyntaxKind::TOKEN_TREE {
+                continue;
+            }
+            // Raw identifiers count for the name without their `r#`, as imports are named.
+            let name = token.text().trim_start_matches("r#");
+            *names.entry(name.to_owned()).or_insert(0) += 1;
+
+            let is_method_call = parent
+                .parent()
+                .map_or(false, |node| node.kind() == SyntaxKind::METHOD_CALL_EXPR);
+            if with_method_traits && is_method_call {
+                let file_position = FilePosition {
+                    file_id,
+                    offset: token.text_range().start(),
+                };
+                let targets = match analysis.goto_definition(file_position)? {
+                    Some(range_info) => range_info.info,
+                    None => continue,
+                };
+                for target in targets {
+                    let definition_file = match definition_files.get(&target.file_id()) {
+                        Some(definition_file) => definition_file.clone(),
+                        None => {
+                            let parsed = analysis.parse(target.file_id())?;
+                            definition_files.insert(target.file_id(), parsed.clone());
+                            parsed
+                        }
+                    };
+                    let node = covering_node(definition_file.syntax(), target.full_range());
+                    if let Some(trait_name) = method_trait_name(&node) {
+                        method_traits.insert(trait_name);
+                    }
+                }
+            }
+        }
+
+        Ok(References {
+            names,
+            method_traits,
+        })
+    }
+
+    /// Every distinct identifier referred to.
+    pub(crate) fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.keys().map(String::as_str)
+    }
+
+    pub(crate) fn count(&self, name: &str) -> usize {
+        self.names.get(name).copied().unwrap_or(0)
+    }
+
+    pub(crate) fn calls_methods_of(&self, trait_name: &str) -> bool {
+        self.method_traits.contains(trait_name)
+    }
+}
+
+pub(crate) fn is_in_use_item(node: &SyntaxNode) -> bool {
+    node.ancestors().any(|node| {
+        node.kind() == SyntaxKind::USE_ITEM || node.kind() == SyntaxKind::EXTERN_CRATE_ITEM
+    })
+}
+
+/// The name of the trait a method definition belongs to, either directly or through the
+/// `impl Trait for Type` block it is defined in.
+fn method_trait_name(node: &SyntaxNode) -> Option<String> {
+    for ancestor in node.ancestors() {
+        if let Some(trait_def) = ast::TraitDef::cast(ancestor.clone()) {
+            return Some(trait_def.name()?.text().to_string());
+        }
+        if let Some(impl_block) = ast::ImplBlock::cast(ancestor) {
+            let target_trait = impl_block.target_trait()?.syntax().text().to_string();
+            let without_generics = target_trait.split('<').next().unwrap_or("");
+            let name = without_generics.rsplit("::").next().unwrap_or("").trim();
+            return Some(name.to_owned());
+        }
+    }
+    None
+}
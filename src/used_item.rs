@@ -1,48 +1,1842 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::{
+    any::Any,
+    cmp::Ordering,
+    collections::{btree_map, BTreeMap, BTreeSet, HashSet},
+    fmt, fs, io, mem,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{self, AtomicBool, AtomicUsize},
+        mpsc::{self, RecvTimeoutError},
+    },
+    time::{Duration, Instant},
+};
 
-use ra_db::{FileId, SourceDatabaseExt};
-use ra_ide::{Analysis, FilePosition, NavigationTarget};
+use cargo_metadata::{CargoOpt, Metadata};
+use ra_db::{Cancelable, FileId, SourceDatabase, SourceDatabaseExt, SourceRootId};
+use ra_ide::{Analysis, AnalysisHost, FilePosition, LineIndex, NavigationTarget, RangeInfo};
+use ra_project_model::PackageRoot;
 use ra_syntax::{
-    ast::{ModuleItem, ModuleItemOwner, UseItem, UseTree},
-    AstNode, SyntaxKind,
+    ast::{
+        self, AttrsOwner, ExternCrateItem, ModuleItem, ModuleItemOwner, NameOwner, UseItem, UseTree,
+    },
+    AstNode, SourceFile, SyntaxKind, SyntaxNode, SyntaxToken, TextRange,
+};
+use rayon::prelude::*;
+use rustc_hash::FxHashMap;
+use serde::{ser::SerializeMap, Deserialize, Serialize, Serializer};
+
+use crate::{
+    analysis::CommonImports,
+    cache::{content_hash, Cache, CacheEntry},
+    crates::CrateUsages,
+    definition_path::{
+        covering_node, deprecation_of, is_keyword, is_pub, parent_enum_name, proc_macro_of,
+        unstable_feature_of, visibility_of, DefinitionPaths,
+    },
+    deprecated::DeprecatedImports,
+    error::{MergeConflict, Result, UsedumpError},
+    generated::{self, Included},
+    globs::GlobImport,
+    lints::Lints,
+    options::{
+        AnalysisConfig, CacheConfig, CargoFeatures, DumpOptions, GeneratedMode, Keys, LoadConfig,
+        ResolveReexports,
+    },
+    origin::{
+        is_member, member_dirs, package_name, package_names, DefinedIn, Origin, OriginFilter,
+        Origins,
+    },
+    output::normalize_path,
+    platform,
+    progress::Progress,
+    query::{Importers, ItemQuery},
+    stats::DumpStats,
+    summary::Summary,
+    sysroot,
+    target::{TargetKind, Targets},
+    unused::{is_in_use_item, References},
 };
-use serde::{Serialize, Serializer};
 
-pub fn list_used_items_in_cargo<Q: AsRef<std::path::Path>>(
-    dir: Q,
-) -> Result<CrateMap, Box<dyn std::error::Error + Send + Sync>> {
-    let (analysis_host, source_map) = ra_batch::load_cargo(dir.as_ref())?;
+/// Finds the `Cargo.toml` that cargo would use for `dir`, searching parent directories.
+/// `dir` may also be the manifest itself, as given to `--manifest-path`.
+pub fn find_manifest(dir: &Path) -> Option<PathBuf> {
+    if dir.is_file() {
+        return if dir.file_name().map_or(false, |name| name == "Cargo.toml") {
+            Some(dir.to_owned())
+        } else {
+            None
+        };
+    }
+    dir.ancestors()
+        .map(|ancestor| ancestor.join("Cargo.toml"))
+        .find(|manifest| manifest.is_file())
+}
+
+/// Finds the `Cargo.lock` of the workspace of `dir`, in the directory of its manifest or in
+/// one of their parents, where the root of the workspace is.
+pub fn find_lockfile(dir: &Path) -> Option<PathBuf> {
+    let manifest = find_manifest(dir)?;
+    manifest
+        .parent()?
+        .ancestors()
+        .map(|ancestor| ancestor.join("Cargo.lock"))
+        .find(|lockfile| lockfile.is_file())
+}
+
+/// Fails unless `manifest` describes a package or a workspace, which may be virtual.
+fn check_manifest(manifest: &Path) -> Result<()> {
+    let invalid = |reason: String| UsedumpError::InvalidManifest {
+        path: manifest.to_owned(),
+        reason,
+    };
+    let contents = fs::read_to_string(manifest)?;
+    let value: toml::Value = toml::from_str(&contents).map_err(|e| invalid(e.to_string()))?;
+    if value.get("package").is_none() && value.get("workspace").is_none() {
+        return Err(invalid(
+            "it has neither a `[package]` nor a `[workspace]` section".to_owned(),
+        ));
+    }
+    Ok(())
+}
+
+/// The source roots of a loaded cargo workspace, as returned by `ra_batch::load_cargo`.
+pub type SourceMap = FxHashMap<SourceRootId, PackageRoot>;
+
+/// Lists the used items of every file of the workspace containing `dir`, omitting the files
+/// which import nothing. See `DumpOptions` to select what is analyzed.
+pub fn list_used_items_in_cargo<Q: AsRef<Path>>(dir: Q) -> Result<CrateMap> {
+    DumpOptions::new(dir).run()
+}
+
+/// Lists the used items of a workspace which the caller has already loaded into `host`.
+///
+/// `host` must contain the whole crate graph of the workspace, including its dependencies
+/// and the sysroot, and `source_map` must describe the source roots of that same load with
+/// the workspace members marked, as `ra_batch::load_cargo` does. Only member roots are
+/// analyzed. As no cargo metadata is available, dependencies are named after the
+/// directories they live in. `host` is borrowed mutably as a cancelled analysis cancels
+/// its snapshots.
+pub fn list_used_items_with_host(
+    host: &mut AnalysisHost,
+    source_map: &SourceMap,
+) -> Result<CrateMap> {
+    let config = AnalysisConfig {
+        include_derives: true,
+        ..AnalysisConfig::default()
+    };
+    resolve_workspace(host, source_map, None, &config)
+}
+
+pub(crate) struct LoadedWorkspace {
+    pub(crate) host: AnalysisHost,
+    pub(crate) source_map: SourceMap,
+    pub(crate) metadata: Metadata,
+    /// The sysroot the standard library was loaded from, if any.
+    pub(crate) sysroot: Option<String>,
+}
+
+/// Loads the workspace of `dir`, a directory or a manifest. A virtual manifest loads every
+/// member of its workspace.
+pub(crate) fn load_workspace(dir: &Path, config: &LoadConfig) -> Result<LoadedWorkspace> {
+    let manifest = checked_manifest(dir)?;
+    let dir = manifest.parent().unwrap_or(dir);
+    // Before rust-analyzer runs cargo on its own, failing on what it would fetch or lock.
+    // Once this run resolved the dependencies offline, and wrote the lockfile it needed,
+    // that of rust-analyzer finds everything locally and does not access the network
+    // either.
+    let metadata = metadata_of(&manifest, config)?;
+    // rust-analyzer loads the crates with the cfgs of the host and the default features.
+    let cfgs = match &config.target_triple {
+        Some(triple) => Some(platform::target_cfgs(dir, triple)?),
+        None if config.features != CargoFeatures::default() => Some(platform::host_cfgs(dir)),
+        None => None,
+    };
+    let sysroot = sysroot::resolve(dir, &config.sysroot)?;
+    let (mut host, source_map) = sysroot::load_with(&sysroot, || ra_batch::load_cargo(dir))
+        .map_err(|source| UsedumpError::WorkspaceLoad {
+            path: dir.to_owned(),
+            source,
+        })?;
+    if let Some(cfgs) = &cfgs {
+        platform::reconfigure(&mut host, &source_map, &metadata, cfgs);
+    }
+
+    Ok(LoadedWorkspace {
+        host,
+        source_map,
+        metadata,
+        sysroot: sysroot.sysroot,
+    })
+}
+
+/// Runs `cargo metadata` for the workspace of `dir` like `load_workspace`, without loading
+/// its sources.
+pub(crate) fn load_metadata(dir: &Path, config: &LoadConfig) -> Result<Metadata> {
+    metadata_of(&checked_manifest(dir)?, config)
+}
+
+fn checked_manifest(dir: &Path) -> Result<PathBuf> {
+    let manifest = find_manifest(dir).ok_or_else(|| UsedumpError::ManifestNotFound {
+        path: dir.to_owned(),
+    })?;
+    check_manifest(&manifest)?;
+    Ok(manifest)
+}
+
+/// Runs `cargo metadata` on `manifest`, with `--offline` and `--locked` when `config` asks
+/// for them, telling the failures they cause apart.
+fn metadata_of(manifest: &Path, config: &LoadConfig) -> Result<Metadata> {
+    let dir = manifest.parent().unwrap_or(manifest);
+    let lockfile = find_lockfile(dir);
+    if config.locked && lockfile.is_none() {
+        return Err(UsedumpError::MissingLockfile {
+            path: dir.to_owned(),
+        });
+    }
+    let features = &config.features;
+    let mut command = cargo_metadata::MetadataCommand::new();
+    command.current_dir(dir).manifest_path(manifest);
+    let mut options = vec![];
+    if config.offline {
+        options.push("--offline".to_owned());
+    }
+    if config.locked {
+        options.push("--locked".to_owned());
+    }
+    command.other_options(options);
+    if features.all_features {
+        command.features(CargoOpt::AllFeatures);
+    }
+    if features.no_default_features {
+        command.features(CargoOpt::NoDefaultFeatures);
+    }
+    if !features.features.is_empty() {
+        command.features(CargoOpt::SomeFeatures(features.features.clone()));
+    }
+    command.exec().map_err(|e| {
+        let stderr = match &e {
+            cargo_metadata::Error::CargoMetadata { stderr } => stderr.clone(),
+            _ => return UsedumpError::Metadata(e),
+        };
+        if config.locked && stderr.contains("--locked") {
+            let path = lockfile.unwrap_or_else(|| dir.join("Cargo.lock"));
+            let dependency =
+                failing_dependency(&stderr).or_else(|| missing_from_lockfile(manifest, &path));
+            UsedumpError::LockfileOutOfDate { path, dependency }
+        } else if config.offline && stderr.contains("offline") {
+            UsedumpError::Offline {
+                dependency: failing_dependency(&stderr),
+                reason: stderr.trim().trim_start_matches("error:").trim().to_owned(),
+            }
+        } else {
+            UsedumpError::Metadata(e)
+        }
+    })
+}
+
+/// The dependency cargo names in the error message `stderr` as the one it failed to get.
+fn failing_dependency(stderr: &str) -> Option<String> {
+    const PREFIXES: &[&str] = &[
+        "no matching package named `",
+        "failed to get `",
+        "failed to download `",
+        "failed to load source for dependency `",
+        "failed to select a version for the requirement `",
+    ];
+    PREFIXES.iter().find_map(|prefix| {
+        let start = stderr.find(prefix)? + prefix.len();
+        let quoted = &stderr[start..];
+        let name = quoted[..quoted.find('`')?].split_whitespace().next()?;
+        Some(name.to_owned())
+    })
+}
+
+/// A dependency of the members of the workspace of `manifest` which has no package in
+/// `lockfile`, as when it was added to a manifest last.
+fn missing_from_lockfile(manifest: &Path, lockfile: &Path) -> Option<String> {
+    let lock: toml::Value = fs::read_to_string(lockfile).ok()?.parse().ok()?;
+    let locked: HashSet<&str> = lock
+        .get("package")?
+        .as_array()?
+        .iter()
+        .filter_map(|package| package.get("name")?.as_str())
+        .collect();
+    // Without resolving anything, which would write the lockfile.
+    let metadata = cargo_metadata::MetadataCommand::new()
+        .manifest_path(manifest)
+        .no_deps()
+        .exec()
+        .ok()?;
+    let members = metadata
+        .packages
+        .iter()
+        .filter(|package| metadata.workspace_members.contains(&package.id));
+    let missing = members
+        .flat_map(|package| &package.dependencies)
+        .find(|dependency| !locked.contains(dependency.name.as_str()))?;
+    Some(missing.name.clone())
+}
+
+pub(crate) fn resolve_workspace(
+    host: &mut AnalysisHost,
+    source_map: &SourceMap,
+    metadata: Option<&Metadata>,
+    config: &AnalysisConfig,
+) -> Result<CrateMap> {
+    resolve_workspace_with_stats(host, source_map, metadata, config).map(|(map, _)| map)
+}
+
+/// Resolves the workspace like `resolve_workspace`, also timing it. The load time of the
+/// returned stats is left to the caller.
+pub(crate) fn resolve_workspace_with_stats(
+    host: &mut AnalysisHost,
+    source_map: &SourceMap,
+    metadata: Option<&Metadata>,
+    config: &AnalysisConfig,
+) -> Result<(CrateMap, DumpStats)> {
+    let started = Instant::now();
+    let mut cache = load_cache(metadata, config);
+    let cached_files = AtomicUsize::new(0);
+    let mut analyzed = analyze_files(
+        host,
+        source_map,
+        metadata,
+        config,
+        |analysis, workspace, file_id| {
+            used_items_in_file_cached(analysis, workspace, file_id, cache.as_ref(), &cached_files)
+        },
+    )?;
+    let mut values = BTreeMap::new();
+    for (file, (mut used_item_map, cache_entry)) in mem::take(&mut analyzed.values) {
+        if let Some(&syntax_errors) = analyzed.syntax_errors.get(&file) {
+            used_item_map.set_syntax_errors(syntax_errors);
+        }
+        if let (Some(cache), Some(cache_entry)) = (&mut cache, cache_entry) {
+            cache.insert(cache_entry, used_item_map.clone());
+        }
+        values.insert(file, used_item_map);
+    }
+    if let Some(cache) = &cache {
+        cache.save()?;
+    }
+    generated::add_generated_files(&mut values, &mut analyzed, metadata, config);
+
+    let files = mem::take(&mut analyzed.durations);
+    let goto_definition_calls = analyzed.goto_definition_calls;
+    let map = CrateMap::from_analyzed(values, analyzed, metadata, config.keys);
+    let stats = DumpStats {
+        load: Duration::default(),
+        resolution: started.elapsed(),
+        files,
+        goto_definition_calls,
+        unresolved: map.unresolved_count,
+        failed_files: map.diagnostics.len(),
+        cached_files: cached_files.into_inner(),
+    };
+    Ok((map, stats))
+}
+
+/// Lists the items every file of the selected members exports, see
+/// `DumpOptions::run_exports`.
+pub(crate) fn resolve_exports(
+    host: &mut AnalysisHost,
+    source_map: &SourceMap,
+    metadata: Option<&Metadata>,
+    config: &AnalysisConfig,
+) -> Result<CrateMap> {
+    let mut analyzed = analyze_files(host, source_map, metadata, config, exports_in_file)?;
+    let values = mem::take(&mut analyzed.values);
+    Ok(CrateMap::from_analyzed(
+        values,
+        analyzed,
+        metadata,
+        config.keys,
+    ))
+}
+
+/// Loads the cache `config` asks for, which needs cargo metadata to be located and
+/// fingerprinted.
+pub(crate) fn load_cache(metadata: Option<&Metadata>, config: &AnalysisConfig) -> Option<Cache> {
+    let metadata = metadata?;
+    let dir = match &config.cache {
+        CacheConfig::Disabled => return None,
+        CacheConfig::Default => Cache::default_dir(metadata),
+        CacheConfig::In(dir) => dir.clone(),
+    };
+    Some(Cache::load(
+        &dir,
+        metadata,
+        config.include_derives,
+        &config.cache_key,
+    ))
+}
+
+/// Lists the used items of a file like `used_items_in_file`, taking them from `cache` when
+/// neither the file nor the member files its crate can see have changed since they were
+/// cached. Files resolved anew come with the entry to cache their items under.
+pub(crate) fn used_items_in_file_cached(
+    analysis: &Analysis,
+    workspace: &Workspace,
+    file_id: FileId,
+    cache: Option<&Cache>,
+    cached_files: &AtomicUsize,
+) -> Cancelable<(UsedItemMap, Option<CacheEntry>)> {
+    let cache = match cache {
+        Some(cache) => cache,
+        None => return Ok((used_items_in_file(analysis, workspace, file_id)?, None)),
+    };
+    let (defined_in, dependencies) = match (
+        workspace.origins.defined_in(file_id),
+        workspace.dependency_hashes.get(&file_id),
+    ) {
+        (Some(defined_in), Some(dependencies)) => (defined_in, dependencies),
+        _ => return Ok((used_items_in_file(analysis, workspace, file_id)?, None)),
+    };
+    let entry = CacheEntry {
+        path: defined_in.file.clone(),
+        hash: content_hash(&analysis.file_text(file_id)?),
+        dependencies: dependencies.clone(),
+    };
+    if let Some(used_item_map) = cache.get(&entry) {
+        cached_files.fetch_add(1, atomic::Ordering::Relaxed);
+        return Ok((used_item_map.clone(), None));
+    }
+    let used_item_map = used_items_in_file(analysis, workspace, file_id)?;
+    Ok((used_item_map, Some(entry)))
+}
+
+/// The results of `analyze_files`.
+pub(crate) struct AnalyzedFiles<T> {
+    pub(crate) values: BTreeMap<String, T>,
+    pub(crate) diagnostics: Vec<Diagnostic>,
+    /// The package name of every selected file, whether its analysis succeeded or not.
+    pub(crate) packages: BTreeMap<String, String>,
+    /// The module path of every selected file, only computed with `Keys::Module`.
+    pub(crate) modules: BTreeMap<String, String>,
+    /// The `DefinedIn::file` of every selected file, which the items defined in it refer to
+    /// it by.
+    pub(crate) definitions: BTreeMap<String, String>,
+    /// How long the analysis of every selected file took.
+    pub(crate) durations: BTreeMap<String, Duration>,
+    /// The kind of the target every selected file belongs to.
+    pub(crate) targets: BTreeMap<String, TargetKind>,
+    /// The number of syntax errors of every selected file.
+    pub(crate) syntax_errors: BTreeMap<String, usize>,
+    /// The keys of the generated files, only told apart with `GeneratedMode::Separate`.
+    pub(crate) generated: BTreeSet<String>,
+    /// The generated files the selected files include, see `generated::included_files`.
+    pub(crate) included: Vec<Included>,
+    pub(crate) goto_definition_calls: usize,
+    /// Whether some files were skipped as the analysis was cancelled, or lost to a panic.
+    pub(crate) cancelled: bool,
+}
+
+/// The key of the files `Keys::Module` finds no module path for, as no crate root reaches
+/// them.
+pub const UNREACHABLE_KEY: &str = "<unreachable>";
+
+/// The key `CrateMap::aggregate_by_package` merges the files whose workspace member is
+/// unknown under, rather than passing them off as those of a package.
+pub const UNKNOWN_PACKAGE_KEY: &str = "<unknown package>";
+
+/// Runs `analyze` on every file of the workspace members which `config` selects, in
+/// parallel, keying the results by the package name and the relative path of the file,
+/// e.g. `foo/src/lib.rs`. Files on which it fails are reported as diagnostics.
+///
+/// With `config.flat_paths` the keys are only the relative paths, so the files of members
+/// sharing a layout overwrite each other, and with `config.absolute_paths` they are the
+/// absolute paths.
+pub(crate) fn analyze_files<T, F>(
+    host: &mut AnalysisHost,
+    source_map: &SourceMap,
+    metadata: Option<&Metadata>,
+    config: &AnalysisConfig,
+    analyze: F,
+) -> Result<AnalyzedFiles<T>>
+where
+    T: Send,
+    F: Fn(&Analysis, &Workspace, FileId) -> Cancelable<T> + Sync,
+{
+    let mut values = BTreeMap::new();
+    let mut diagnostics = vec![];
+    let mut packages = BTreeMap::new();
+    let mut modules = BTreeMap::new();
+    let mut definitions = BTreeMap::new();
+    let mut durations = BTreeMap::new();
+    let mut targets = BTreeMap::new();
+    let mut syntax_errors = BTreeMap::new();
+    let visited = for_each_file(host, source_map, metadata, config, analyze, |file| {
+        packages.insert(file.key.clone(), file.package);
+        targets.insert(file.key.clone(), file.target);
+        syntax_errors.insert(file.key.clone(), file.syntax_errors);
+        durations.insert(file.key.clone(), file.duration);
+        if let Some(module) = file.module {
+            modules.insert(file.key.clone(), module);
+        }
+        if let Some(definition) = file.definition {
+            definitions.insert(file.key.clone(), definition);
+        }
+        match file.result {
+            Ok(value) => {
+                values.insert(file.key, value);
+            }
+            Err(error) => diagnostics.push(Diagnostic::new(file.key, error)),
+        }
+    })?;
+    diagnostics.sort_by(|a, b| a.file.cmp(&b.file));
+    Ok(AnalyzedFiles {
+        values,
+        diagnostics,
+        packages,
+        modules,
+        definitions,
+        durations,
+        targets,
+        syntax_errors,
+        generated: BTreeSet::new(),
+        included: visited.included,
+        goto_definition_calls: visited.goto_definition_calls,
+        cancelled: visited.cancelled,
+    })
+}
+
+/// A file analyzed by `for_each_file`.
+pub(crate) struct AnalyzedFile<T> {
+    pub(crate) key: String,
+    pub(crate) package: String,
+    /// The module path of the file, only computed with `Keys::Module`.
+    pub(crate) module: Option<String>,
+    /// The `DefinedIn::file` of the file.
+    pub(crate) definition: Option<String>,
+    pub(crate) target: TargetKind,
+    /// The value `analyze` returned, or why it failed.
+    pub(crate) result: Result<T, String>,
+    /// How long `analyze` took.
+    pub(crate) duration: Duration,
+    /// The number of syntax errors of the file, as rust-analyzer parsed it.
+    pub(crate) syntax_errors: usize,
+}
+
+/// What `for_each_file` reports besides the files themselves.
+pub(crate) struct Visited {
+    /// The generated files the visited files include, unless `config.generated` skips
+    /// them or only `config.files` are visited.
+    pub(crate) included: Vec<Included>,
+    pub(crate) goto_definition_calls: usize,
+    /// Whether some files were skipped as `config.cancel` was set, or lost to a panic.
+    pub(crate) cancelled: bool,
+}
+
+/// The streaming version of `analyze_files`, passing every file to `emit` instead.
+///
+/// The members are analyzed one after the other, ordered by package name, and the files of
+/// a member are emitted ordered by key once all of them are analyzed. Once `config.cancel`
+/// is set, the snapshots of `host` are cancelled, so that the files being analyzed are
+/// given up on at their next call into rust-analyzer, and the others are skipped. Should
+/// the analysis of a member panic, its files are emitted as failed and the others are
+/// still analyzed.
+pub(crate) fn for_each_file<T, F, E>(
+    host: &mut AnalysisHost,
+    source_map: &SourceMap,
+    metadata: Option<&Metadata>,
+    config: &AnalysisConfig,
+    analyze: F,
+    mut emit: E,
+) -> Result<Visited>
+where
+    T: Send,
+    F: Fn(&Analysis, &Workspace, FileId) -> Cancelable<T> + Sync,
+    E: FnMut(AnalyzedFile<T>),
+{
+    let db = host.raw_database();
+    let workspace = Workspace::new(host, source_map, metadata, config);
+    let skipped = AtomicBool::new(false);
+    let is_cancelled = || {
+        config
+            .cancel
+            .as_ref()
+            .map_or(false, |cancel| cancel.load(atomic::Ordering::SeqCst))
+    };
 
-    let analysis = analysis_host.analysis();
-    let mut map = CrateMap::default();
+    let mut members = selected_members(source_map, metadata, &config.packages)?;
+    members.sort_by(|(a_id, a_name), (b_id, b_name)| {
+        (a_name, source_map[a_id].path()).cmp(&(b_name, source_map[b_id].path()))
+    });
 
-    for (source_root_id, package_root) in source_map {
-        if !package_root.is_member() {
+    let pool = match config.jobs {
+        Some(jobs) => Some(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(jobs)
+                .build()
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?,
+        ),
+        None => None,
+    };
+    let threads = pool
+        .as_ref()
+        .map_or_else(rayon::current_num_threads, |pool| {
+            pool.current_num_threads()
+        });
+
+    // Every file is listed up front, so that progress can be reported against the total.
+    let targets = Targets::new(metadata, host, source_map);
+    let analysis = host.analysis();
+    let mut target_of: FxHashMap<FileId, TargetKind> = FxHashMap::default();
+    // The generated files are not in the source roots, but reached through `include!`.
+    // Only some files being visited, e.g. by `watch`, the ones they include are kept.
+    let follow_includes =
+        metadata.filter(|_| config.generated != GeneratedMode::Skip && config.files.is_none());
+    let mut included = vec![];
+    let mut files_by_member: Vec<(String, Vec<(FileId, String)>)> = vec![];
+    for (source_root_id, package) in members {
+        let mut files: Vec<(FileId, String)> = vec![];
+        for file_id in db.source_root(source_root_id).walk() {
+            if let Some(files) = &config.files {
+                if !files.contains(&file_id) {
+                    continue;
+                }
+            }
+            let path = db.file_relative_path(file_id).to_string();
+            let package_dir = source_map[&source_root_id].path();
+            let target = targets.kind_of(&analysis, file_id, package_dir, &path);
+            if let Some(selected) = &config.targets {
+                if !selected.contains(&target) {
+                    continue;
+                }
+            }
+            target_of.insert(file_id, target);
+            if config.filter.is_match(&path) {
+                let absolute = package_dir.join(&path);
+                let key = if config.absolute_paths {
+                    normalize_path(&absolute)
+                } else if config.flat_paths {
+                    path
+                } else {
+                    format!("{}/{}", package, path)
+                };
+                if let Some(metadata) = follow_includes {
+                    included.extend(generated::included_files(
+                        metadata,
+                        &db.file_text(file_id),
+                        &absolute,
+                        &key,
+                        &package,
+                        target,
+                        config,
+                    ));
+                }
+                files.push((file_id, key));
+            }
+        }
+        files_by_member.push((package, files));
+    }
+    // Cancelling waits for every snapshot to be dropped.
+    drop(analysis);
+    let total = files_by_member.iter().map(|(_, files)| files.len()).sum();
+    let done = AtomicUsize::new(0);
+    if let Some(progress) = &config.progress {
+        progress.report(Progress::Analyzing { total });
+    }
+
+    for (package, files) in files_by_member {
+        if is_cancelled() {
+            skipped.store(true, atomic::Ordering::Relaxed);
             continue;
         }
+        // `AnalysisHost` cannot be shared between threads, so every job gets its own
+        // snapshot up front and resolves an interleaved share of the files with it.
+        let jobs = threads.max(1).min(files.len().max(1));
+        let mut chunks: Vec<Vec<(FileId, String)>> = vec![vec![]; jobs];
+        for (i, file) in files.into_iter().enumerate() {
+            chunks[i % jobs].push(file);
+        }
+        let snapshots: Vec<Analysis> = (0..jobs).map(|_| host.analysis()).collect();
+        // The files reported as failed should a panic lose their results.
+        let selected: Vec<(FileId, String)> = chunks.iter().flatten().cloned().collect();
 
-        for file_id in analysis_host
-            .raw_database()
-            .source_root(source_root_id)
-            .walk()
-        {
-            let resolver = UsedItemResolver::new(&analysis, file_id);
-            let path = analysis_host.raw_database().file_relative_path(file_id);
-            map.source_map
-                .insert(path.to_string(), resolver.used_items());
+        let crate_name = package.replace('-', "_");
+        let module_of = |analysis: &Analysis, file_id: FileId| -> Option<String> {
+            if config.keys != Keys::Module {
+                return None;
+            }
+            let module = panic::catch_unwind(AssertUnwindSafe(|| {
+                workspace
+                    .definition_paths
+                    .module_path_of_file(analysis, file_id, &crate_name)
+            }));
+            Some(
+                module
+                    .ok()
+                    .and_then(|module| module)
+                    .unwrap_or_else(|| UNREACHABLE_KEY.to_owned()),
+            )
+        };
+        type Resolved<T> = (FileId, Result<T, String>, Option<String>, Duration);
+        let resolve_chunks = || -> BTreeMap<String, Resolved<T>> {
+            chunks
+                .into_par_iter()
+                .zip(snapshots)
+                .flat_map(|(chunk, analysis)| {
+                    chunk
+                        .into_iter()
+                        .filter_map(|(file_id, key)| {
+                            if is_cancelled() {
+                                skipped.store(true, atomic::Ordering::Relaxed);
+                                return None;
+                            }
+                            let started = Instant::now();
+                            // A panic inside rust-analyzer only costs us the file it
+                            // happened in.
+                            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                                analyze(&analysis, &workspace, file_id)
+                            }));
+                            let result = match result {
+                                // Only `cancel_snapshots` cancels the snapshots, and the
+                                // calls which return `Canceled` may have left the file
+                                // incomplete.
+                                Ok(Err(_)) => None,
+                                Ok(Ok(_)) if is_cancelled() => None,
+                                Ok(Ok(value)) => Some(Ok(value)),
+                                Err(payload) => match payload.downcast_ref::<TimedOut>() {
+                                    Some(TimedOut(timeout)) => Some(Err(format!(
+                                        "timed out after {} seconds",
+                                        timeout.as_secs_f64()
+                                    ))),
+                                    None => Some(Err(panic_message(&*payload))),
+                                },
+                            };
+                            let result = match result {
+                                Some(result) => result,
+                                None => {
+                                    skipped.store(true, atomic::Ordering::Relaxed);
+                                    return None;
+                                }
+                            };
+                            let module = module_of(&analysis, file_id);
+                            let duration = started.elapsed();
+                            if let Some(progress) = &config.progress {
+                                progress.report(Progress::Analyzed {
+                                    file: &key,
+                                    done: done.fetch_add(1, atomic::Ordering::Relaxed) + 1,
+                                    total,
+                                });
+                            }
+                            Some((key, (file_id, result, module, duration)))
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .collect()
+        };
+        let resolve = || match &pool {
+            Some(pool) => pool.install(resolve_chunks),
+            None => resolve_chunks(),
+        };
+        let results = match &config.cancel {
+            Some(cancel) => cancel_snapshots(host, cancel, &package, resolve),
+            None => panic::catch_unwind(AssertUnwindSafe(resolve))
+                .map_err(|payload| analysis_panicked(&package, &*payload)),
+        };
+        // The files of the member then are diagnostics, and the others are still analyzed.
+        let results = results.unwrap_or_else(|error| {
+            skipped.store(true, atomic::Ordering::Relaxed);
+            let error = error.to_string();
+            selected
+                .into_iter()
+                .map(|(file_id, key)| {
+                    let result = (file_id, Err(error.clone()), None, Duration::default());
+                    (key, result)
+                })
+                .collect()
+        });
+
+        // The snapshots parsed the files into the database, which keeps the errors
+        // `Analysis::parse` leaves out.
+        let db = host.raw_database();
+        for (key, (file_id, result, module, duration)) in results {
+            let definition = workspace
+                .origins
+                .defined_in(file_id)
+                .map(|defined_in| defined_in.file.clone());
+            emit(AnalyzedFile {
+                key,
+                package: package.clone(),
+                module,
+                definition,
+                target: target_of[&file_id],
+                result,
+                duration,
+                syntax_errors: db.parse(file_id).errors().len(),
+            });
         }
     }
+    Ok(Visited {
+        included,
+        goto_definition_calls: workspace
+            .goto_definition_calls
+            .load(atomic::Ordering::Relaxed)
+            + workspace.definition_paths.goto_definition_calls(),
+        cancelled: skipped.into_inner(),
+    })
+}
 
-    Ok(map)
+/// How often `cancel_snapshots` checks whether to cancel.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Runs `resolve`, the analysis of the files of `package`, on another thread, as `host`
+/// cannot be shared between threads, while this one requests the cancellation of the
+/// snapshots of `host` once `cancel` is set. The calls into rust-analyzer made with them
+/// then return `Canceled`, and `resolve` is expected to return soon after. A panic of
+/// `resolve` is returned as `UsedumpError::AnalysisPanicked`.
+fn cancel_snapshots<T, F>(
+    host: &mut AnalysisHost,
+    cancel: &AtomicBool,
+    package: &str,
+    resolve: F,
+) -> Result<T>
+where
+    T: Send,
+    F: FnOnce() -> T + Send,
+{
+    let result = crossbeam_utils::thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        // Unwinding drops the snapshots, so that a pending cancellation still returns.
+        scope.spawn(move |_| sender.send(panic::catch_unwind(AssertUnwindSafe(resolve))));
+        let mut cancelled = false;
+        loop {
+            match receiver.recv_timeout(CANCEL_POLL_INTERVAL) {
+                Ok(result) => return Some(result),
+                Err(RecvTimeoutError::Timeout) => {
+                    if !cancelled && cancel.load(atomic::Ordering::SeqCst) {
+                        // Blocks until the snapshots are dropped, which `resolve` does
+                        // once every file has returned `Canceled` or been skipped.
+                        host.request_cancellation();
+                        cancelled = true;
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => return None,
+            }
+        }
+    });
+    match result {
+        Ok(Some(Ok(result))) => Ok(result),
+        Ok(Some(Err(payload))) => Err(analysis_panicked(package, &*payload)),
+        _ => Err(UsedumpError::AnalysisPanicked {
+            package: package.to_owned(),
+            reason: "panicked".to_owned(),
+        }),
+    }
 }
 
-#[derive(Default, Serialize)]
+fn analysis_panicked(package: &str, payload: &(dyn Any + Send)) -> UsedumpError {
+    UsedumpError::AnalysisPanicked {
+        package: package.to_owned(),
+        reason: panic_message(payload),
+    }
+}
+
+/// Lists the exports of every file of the dependency `package`, like `exports_in_file`,
+/// passing them to `emit` one file at a time, so that large dependencies are never held
+/// in memory at once. Files are keyed by their path in the package, and their order is
+/// unspecified.
+pub(crate) fn for_each_dependency_export<E>(
+    host: &AnalysisHost,
+    source_map: &SourceMap,
+    metadata: Option<&Metadata>,
+    package: &str,
+    mut emit: E,
+) -> Result<()>
+where
+    E: FnMut(String, Result<UsedItemMap, String>),
+{
+    let package_names = package_names(metadata);
+    let member_dirs = member_dirs(metadata);
+    let dependencies: Vec<(SourceRootId, String)> = source_map
+        .iter()
+        .filter(|(_, package_root)| !is_member(package_root, &member_dirs))
+        .map(|(source_root_id, package_root)| {
+            (*source_root_id, package_name(package_root, &package_names))
+        })
+        .collect();
+    if !dependencies.iter().any(|(_, name)| name == package) {
+        let mut available: Vec<String> = dependencies.into_iter().map(|(_, name)| name).collect();
+        available.sort();
+        available.dedup();
+        return Err(UsedumpError::UnknownDependency {
+            name: package.to_owned(),
+            available,
+        });
+    }
+
+    let db = host.raw_database();
+    let workspace = Workspace::new(host, source_map, metadata, &AnalysisConfig::default());
+    let analysis = host.analysis();
+    for (source_root_id, _) in dependencies.iter().filter(|(_, name)| name == package) {
+        for file_id in db.source_root(*source_root_id).walk() {
+            let key = db.file_relative_path(file_id).to_string();
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                exports_in_file(&analysis, &workspace, file_id)
+            }));
+            let result = match result {
+                Ok(Ok(exports)) => Ok(exports),
+                Ok(Err(canceled)) => Err(canceled.to_string()),
+                Err(payload) => Err(panic_message(&*payload)),
+            };
+            emit(key, result);
+        }
+    }
+    Ok(())
+}
+
+/// The source roots and package names of the workspace members named `packages`, or of
+/// every member when it is empty.
+fn selected_members(
+    source_map: &SourceMap,
+    metadata: Option<&Metadata>,
+    packages: &[String],
+) -> Result<Vec<(SourceRootId, String)>> {
+    let package_names = package_names(metadata);
+    let member_dirs = member_dirs(metadata);
+    let members: Vec<(SourceRootId, String)> = source_map
+        .iter()
+        .filter(|(_, package_root)| is_member(package_root, &member_dirs))
+        .map(|(source_root_id, package_root)| {
+            (*source_root_id, package_name(package_root, &package_names))
+        })
+        .collect();
+
+    if packages.is_empty() {
+        return Ok(members);
+    }
+    for package in packages {
+        if !members.iter().any(|(_, name)| name == package) {
+            let mut available: Vec<String> = members.iter().map(|(_, name)| name.clone()).collect();
+            available.sort();
+            available.dedup();
+            return Err(UsedumpError::UnknownPackage {
+                name: package.clone(),
+                available,
+            });
+        }
+    }
+    Ok(members
+        .into_iter()
+        .filter(|(_, name)| packages.contains(name))
+        .collect())
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        format!("panicked: {}", s)
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        format!("panicked: {}", s)
+    } else {
+        "panicked".to_owned()
+    }
+}
+
+/// Lists the items used by a single file of an already loaded `analysis`.
+///
+/// Without the workspace information `list_used_items_in_cargo` gathers, the paths of the
+/// used items are not prefixed by their crate name and their origin is unknown.
+pub fn list_used_items_in_file(analysis: &Analysis, file_id: FileId) -> UsedItemMap {
+    let workspace = Workspace::default();
+    let mut used_item_map = used_items_in_file(analysis, &workspace, file_id).unwrap_or_default();
+    // `Analysis::parse` leaves the errors out, and there is no database to take them from.
+    if let Ok(text) = analysis.file_text(file_id) {
+        used_item_map.set_syntax_errors(SourceFile::parse(&text).errors().len());
+    }
+    used_item_map
+}
+
+pub(crate) fn used_items_in_file(
+    analysis: &Analysis,
+    workspace: &Workspace,
+    file_id: FileId,
+) -> Cancelable<UsedItemMap> {
+    UsedItemResolver::new(analysis, workspace, file_id).used_items()
+}
+
+pub(crate) fn exports_in_file(
+    analysis: &Analysis,
+    workspace: &Workspace,
+    file_id: FileId,
+) -> Cancelable<UsedItemMap> {
+    UsedItemResolver::new(analysis, workspace, file_id).exports()
+}
+
+/// The glob imports of `file_id`, with the names the file refers to through them.
+pub(crate) fn glob_imports_in_file(
+    analysis: &Analysis,
+    workspace: &Workspace,
+    file_id: FileId,
+) -> Cancelable<Vec<GlobImport>> {
+    UsedItemResolver::new(analysis, workspace, file_id).glob_imports()
+}
+
+/// The definition paths of the items named one of `names` which `file_id` refers to with a
+/// qualified path outside of use items, such as `foo::Bar` in `foo::Bar::new()` or
+/// `foo::m!()`, which need no import.
+pub(crate) fn qualified_references(
+    analysis: &Analysis,
+    workspace: &Workspace,
+    file_id: FileId,
+    names: &HashSet<String>,
+) -> Cancelable<BTreeSet<String>> {
+    UsedItemResolver::new(analysis, workspace, file_id).qualified_references(names)
+}
+
+/// Lists the items used by `text`, analyzed as a standalone file without dependencies.
+///
+/// Only the items defined in `text` itself resolve. The other use trees, e.g. those
+/// importing from `std`, are kept with their path as written and marked unresolved, their
+/// kind guessed from the casing of their last segment: `SCREAMING_CASE` names are taken
+/// as constants, `CamelCase` ones as structs and `snake_case` ones as modules. Glob imports
+/// which do not resolve are only listed as unresolved.
+pub fn list_used_items_in_source(text: &str) -> UsedItemMap {
+    let (analysis, file_id) = Analysis::from_single_file(text.to_owned());
+    let workspace = Workspace {
+        guess_unresolved: true,
+        include_derives: true,
+        ..Workspace::default()
+    };
+    let mut used_item_map = used_items_in_file(&analysis, &workspace, file_id).unwrap_or_default();
+    used_item_map.set_syntax_errors(SourceFile::parse(text).errors().len());
+    used_item_map
+}
+
+/// Lists the use trees and the `extern crate` items of `text` from its syntax only, for
+/// `DumpOptions::no_resolve`.
+///
+/// Every item is marked unresolved, with its path as written, e.g. `super::Foo`, and its
+/// kind guessed as in `list_used_items_in_source`. A glob is listed as an item named `*`,
+/// e.g. with the path `std::io::prelude::*`. `origin_of` tells the origin of an item from
+/// the first segment of its path. Neither derives nor the references to the items are
+/// counted.
+pub(crate) fn used_items_by_syntax(
+    text: &str,
+    origin_of: &dyn Fn(&str) -> Option<Origin>,
+) -> UsedItemMap {
+    let parse = SourceFile::parse(text);
+    let line_index = LineIndex::new(text);
+    let location = |range: TextRange| {
+        let line_col = line_index.line_col(range.start());
+        Location {
+            line: line_col.line + 1,
+            col: line_col.col_utf16 + 1,
+            range: [u32::from(range.start()), u32::from(range.end())],
+        }
+    };
+
+    let mut used_item_map = UsedItemMap::default();
+    used_item_map.syntax_errors = parse.errors().len();
+    used_item_map.has_syntax_errors = used_item_map.syntax_errors > 0;
+    for node in parse.tree().syntax().descendants() {
+        let mut items = vec![];
+        if let Some(use_item) = UseItem::cast(node.clone()) {
+            let visibility = visibility_of(use_item.syntax());
+            let cfgs = cfgs_of(use_item.syntax());
+            let leaves = use_item
+                .syntax()
+                .descendants()
+                .filter_map(UseTree::cast)
+                .filter(|use_tree| use_tree.use_tree_list().is_none());
+            for use_tree in leaves {
+                let written = full_path_text(&use_tree);
+                let path = written.trim_end_matches("::self");
+                let is_self_import = path.len() < written.len();
+                let (name, kind) = if use_tree.has_star() {
+                    ("*".to_owned(), UsedItemKind::Other)
+                } else {
+                    let name = match referenced_name(&use_tree) {
+                        Some(name) => name,
+                        None => continue,
+                    };
+                    let kind = if is_self_import {
+                        UsedItemKind::Module
+                    } else {
+                        UsedItemKind::guess(&name)
+                    };
+                    (name, kind)
+                };
+                let mut item = UsedItem::new(name, path.to_owned(), kind, None);
+                item.alias = use_tree.alias().as_ref().and_then(alias_name);
+                item.anonymous = item.alias() == Some("_");
+                item.via_glob = use_tree.has_star();
+                item.visibility = visibility.clone();
+                item.cfgs = cfgs.clone();
+                item.location = Some(location(use_tree.syntax().text_range()));
+                items.push(item);
+            }
+        } else if let Some(extern_crate_item) = ExternCrateItem::cast(node.clone()) {
+            let name = match extern_crate_item.name_ref() {
+                Some(name_ref) => name_ref.text().to_string(),
+                None => continue,
+            };
+            let mut item = UsedItem::new(name.clone(), name, UsedItemKind::Module, None);
+            item.alias = extern_crate_item.alias().as_ref().and_then(alias_name);
+            item.anonymous = item.alias() == Some("_");
+            item.cfgs = cfgs_of(extern_crate_item.syntax());
+            item.visibility = visibility_of(extern_crate_item.syntax());
+            item.extern_crate = true;
+            item.location = Some(location(extern_crate_item.syntax().text_range()));
+            item.macro_use = extern_crate_item
+                .attrs()
+                .any(|attr| attr.simple_name().map_or(false, |name| name == "macro_use"));
+            items.push(item);
+        } else {
+            continue;
+        }
+
+        let scope = scope_of(&node);
+        let use_item_location = location(node.text_range());
+        for mut item in items {
+            let first = item.path.trim_start_matches("::");
+            let first = first.split("::").next().unwrap_or(first);
+            item.origin = origin_of(first);
+            item.scope = scope.clone();
+            item.use_item_location = Some(use_item_location);
+            item.unresolved = true;
+            used_item_map.insert(item);
+        }
+    }
+    used_item_map
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct CrateMap {
+    /// The workspace root, against which the keys of files can be re-anchored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    root: Option<String>,
+    /// The sysroot the standard library was loaded from, unknown when it was not.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sysroot: Option<String>,
+    /// The versions of every package of the dependency graph which is not a member, by
+    /// name, oldest first.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    dependencies: BTreeMap<String, Vec<String>>,
     #[serde(flatten)]
     source_map: BTreeMap<String, UsedItemMap>,
+    /// The used items of the generated files, with `GeneratedMode::Separate`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    generated: BTreeMap<String, UsedItemMap>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    diagnostics: Vec<Diagnostic>,
+    /// The number of use trees which could not be resolved, over all files.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    unresolved_count: usize,
+    /// Whether the dump was cancelled before every file was analyzed, or the analysis of
+    /// a member panicked.
+    #[serde(default, skip_serializing_if = "is_false")]
+    partial: bool,
+    /// The package each file belongs to.
+    #[serde(skip)]
+    packages: BTreeMap<String, String>,
+    /// The key of each file by its `DefinedIn::file`.
+    #[serde(skip)]
+    definitions: BTreeMap<String, String>,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
 }
 
-#[derive(Debug, PartialOrd, PartialEq, Eq, Ord)]
+/// The versions of the packages of `metadata` which are not members, see
+/// `CrateMap::dependencies`.
+fn dependency_versions(metadata: Option<&Metadata>) -> BTreeMap<String, Vec<String>> {
+    let mut packages: Vec<&cargo_metadata::Package> = metadata
+        .iter()
+        .flat_map(|metadata| {
+            metadata
+                .packages
+                .iter()
+                .filter(move |package| !metadata.workspace_members.contains(&package.id))
+        })
+        .collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.version.cmp(&b.version)));
+    let mut dependencies: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for package in packages {
+        let versions = dependencies.entry(package.name.clone()).or_default();
+        let version = package.version.to_string();
+        if !versions.contains(&version) {
+            versions.push(version);
+        }
+    }
+    dependencies
+}
+
+/// Adds the versions of `other` to `dependencies`, keeping the versions of each package
+/// ordered.
+fn merge_versions(
+    dependencies: &mut BTreeMap<String, Vec<String>>,
+    other: BTreeMap<String, Vec<String>>,
+) {
+    for (name, versions) in other {
+        let merged = dependencies.entry(name).or_default();
+        for version in versions {
+            if !merged.contains(&version) {
+                merged.push(version);
+            }
+        }
+        merged.sort_by(|a, b| compare_versions(a, b));
+    }
+}
+
+/// Compares versions such as `1.0.10` by their numeric components, then as text.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let numbers = |version: &str| -> Vec<u64> {
+        let release = version.split(|c| c == '-' || c == '+').next().unwrap_or("");
+        release
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    numbers(a).cmp(&numbers(b)).then_with(|| a.cmp(b))
+}
+
+/// Compares what is serialized, so that a map read back from its dump equals it. The
+/// packages and definitions of files are only known while dumping.
+impl PartialEq for CrateMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.root == other.root
+            && self.sysroot == other.sysroot
+            && self.dependencies == other.dependencies
+            && self.source_map == other.source_map
+            && self.generated == other.generated
+            && self.diagnostics == other.diagnostics
+            && self.unresolved_count == other.unresolved_count
+            && self.partial == other.partial
+    }
+}
+
+impl Eq for CrateMap {}
+
+/// A file whose analysis failed, and which is therefore missing from the `CrateMap`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagnostic {
+    file: String,
+    error: String,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(file: String, error: String) -> Self {
+        Diagnostic { file, error }
+    }
+
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    pub fn error(&self) -> &str {
+        &self.error
+    }
+}
+
+/// The used items of a single file, as passed to the callback of `dump_streaming`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FileEntry {
+    file: String,
+    items: UsedItemMap,
+    #[serde(default, skip_serializing_if = "is_false")]
+    generated: bool,
+}
+
+impl FileEntry {
+    pub(crate) fn new(file: String, items: UsedItemMap, generated: bool) -> Self {
+        FileEntry {
+            file,
+            items,
+            generated,
+        }
+    }
+
+    /// The key of the file, as in a `CrateMap`.
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    pub fn items(&self) -> &UsedItemMap {
+        &self.items
+    }
+
+    pub fn into_items(self) -> UsedItemMap {
+        self.items
+    }
+
+    /// Whether the file is generated, only told with `GeneratedMode::Separate`.
+    pub fn is_generated(&self) -> bool {
+        self.generated
+    }
+}
+
+/// What `dump_streaming` returns once every file is passed on.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StreamedDump {
+    diagnostics: Vec<Diagnostic>,
+    partial: bool,
+}
+
+impl StreamedDump {
+    pub(crate) fn new(diagnostics: Vec<Diagnostic>, partial: bool) -> Self {
+        StreamedDump {
+            diagnostics,
+            partial,
+        }
+    }
+
+    /// The files whose analysis failed.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Whether the dump was cancelled, leaving out the files which were not analyzed yet,
+    /// or the analysis of a member panicked, leaving its files as diagnostics.
+    pub fn is_partial(&self) -> bool {
+        self.partial
+    }
+}
+
+impl CrateMap {
+    /// The map of the used items `analyze_files` listed into `values`, keyed as `keys`
+    /// asks.
+    pub(crate) fn from_analyzed<T>(
+        mut values: BTreeMap<String, UsedItemMap>,
+        analyzed: AnalyzedFiles<T>,
+        metadata: Option<&Metadata>,
+        keys: Keys,
+    ) -> Self {
+        for (key, used_item_map) in &mut values {
+            used_item_map.target = analyzed.targets.get(key).copied();
+        }
+        let unresolved_count = values
+            .values()
+            .map(|used_item_map| used_item_map.unresolved.len())
+            .sum();
+        let generated = analyzed
+            .generated
+            .iter()
+            .filter_map(|key| Some((key.clone(), values.remove(key)?)))
+            .collect();
+        let mut map = CrateMap {
+            root: metadata.map(|metadata| normalize_path(&metadata.workspace_root)),
+            sysroot: None,
+            dependencies: dependency_versions(metadata),
+            source_map: values,
+            generated,
+            diagnostics: analyzed.diagnostics,
+            unresolved_count,
+            partial: analyzed.cancelled,
+            packages: analyzed.packages,
+            definitions: analyzed
+                .definitions
+                .into_iter()
+                .map(|(key, definition)| (definition, key))
+                .collect(),
+        };
+        if keys == Keys::Module {
+            map.rekey(&analyzed.modules);
+        }
+        map
+    }
+
+    /// Iterates over the analyzed files and their used items, ordered by file.
+    pub fn files(&self) -> impl Iterator<Item = (&str, &UsedItemMap)> {
+        self.source_map
+            .iter()
+            .map(|(file, used_item_map)| (file.as_str(), used_item_map))
+    }
+
+    /// Iterates over the generated files and their used items, ordered by file, when they
+    /// are dumped separately.
+    pub fn generated_files(&self) -> impl Iterator<Item = (&str, &UsedItemMap)> {
+        self.generated
+            .iter()
+            .map(|(file, used_item_map)| (file.as_str(), used_item_map))
+    }
+
+    /// The root of the analyzed workspace, unknown without cargo metadata.
+    pub fn root(&self) -> Option<&str> {
+        self.root.as_ref().map(String::as_str)
+    }
+
+    /// The sysroot the standard library was loaded from, e.g.
+    /// `~/.rustup/toolchains/stable-x86_64-unknown-linux-gnu`, so that dumps made with
+    /// different toolchains can be told apart. `None` when none was loaded, leaving the
+    /// items of the standard library unresolved.
+    pub fn sysroot(&self) -> Option<&str> {
+        self.sysroot.as_ref().map(String::as_str)
+    }
+
+    /// The versions of every package of the dependency graph which is not a member, by
+    /// name, e.g. `["0.2.17", "0.4.11"]` for a package whose two versions are depended on.
+    pub fn dependencies(&self) -> &BTreeMap<String, Vec<String>> {
+        &self.dependencies
+    }
+
+    /// The map without its root and its sysroot, for `Envelope`s which hold them.
+    pub fn without_root(&self) -> CrateMap {
+        CrateMap {
+            root: None,
+            sysroot: None,
+            ..self.clone()
+        }
+    }
+
+    /// Sets the sysroot to `sysroot` unless the map already has one.
+    pub(crate) fn with_sysroot(mut self, sysroot: Option<String>) -> Self {
+        if self.sysroot.is_none() {
+            self.sysroot = sysroot;
+        }
+        self
+    }
+
+    /// Sets the root to `root` unless the map already has one.
+    pub(crate) fn with_root(mut self, root: Option<String>) -> Self {
+        if self.root.is_none() {
+            self.root = root;
+        }
+        self
+    }
+
+    pub fn get(&self, file: &str) -> Option<&UsedItemMap> {
+        self.source_map.get(file)
+    }
+
+    /// The files which could not be analyzed, ordered by file.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// The number of use trees which could not be resolved, over all files.
+    pub fn unresolved_count(&self) -> usize {
+        self.unresolved_count
+    }
+
+    /// The files with syntax errors.
+    pub fn files_with_syntax_errors(&self) -> impl Iterator<Item = &str> {
+        self.files()
+            .chain(self.generated_files())
+            .filter(|(_, used_item_map)| used_item_map.has_syntax_errors)
+            .map(|(file, _)| file)
+    }
+
+    /// Whether the dump was cancelled, leaving out the files which were not analyzed yet,
+    /// or the analysis of a member panicked, leaving its files as diagnostics.
+    pub fn is_partial(&self) -> bool {
+        self.partial
+    }
+
+    /// Iterates over every used item of the workspace together with its file and kind.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, UsedItemKind, &UsedItem)> {
+        self.files().flat_map(|(file, used_item_map)| {
+            used_item_map
+                .iter()
+                .map(move |item| (file, item.kind, item))
+        })
+    }
+
+    /// Keeps only the used items for which `f` returns `true`, in every file.
+    pub fn retain_items<F: FnMut(&str, &UsedItem) -> bool>(&mut self, mut f: F) {
+        for (file, used_item_map) in &mut self.source_map {
+            used_item_map.retain(|item| f(file, item));
+        }
+    }
+
+    /// Keeps only the used items of the given kinds, in every file.
+    pub fn retain_kinds(&mut self, kinds: &[UsedItemKind]) {
+        for used_item_map in self.source_map.values_mut() {
+            used_item_map.retain_kinds(kinds);
+        }
+    }
+
+    /// The name of the workspace member `file` belongs to.
+    pub fn package_of(&self, file: &str) -> Option<&str> {
+        self.packages.get(file).map(String::as_str)
+    }
+
+    /// The key of the analyzed file whose `DefinedIn::file` is `definition`, unknown for
+    /// deserialized maps.
+    pub fn key_of_definition(&self, definition: &str) -> Option<&str> {
+        self.definitions.get(definition).map(String::as_str)
+    }
+
+    /// Re-keys the files by `keys`, merging the used items of the files which end up under
+    /// the same key like `aggregate_by_package` does.
+    fn rekey(&mut self, keys: &BTreeMap<String, String>) {
+        let mut grouped: BTreeMap<String, Vec<UsedItemMap>> = BTreeMap::new();
+        let mut packages = BTreeMap::new();
+        for (file, used_item_map) in mem::take(&mut self.source_map) {
+            let key = keys.get(&file).cloned().unwrap_or_else(|| file.clone());
+            if let Some(package) = self.packages.get(&file) {
+                packages
+                    .entry(key.clone())
+                    .or_insert_with(|| package.clone());
+            }
+            grouped.entry(key).or_default().push(used_item_map);
+        }
+        for key in self.definitions.values_mut() {
+            if let Some(new_key) = keys.get(key) {
+                *key = new_key.clone();
+            }
+        }
+        self.source_map = grouped
+            .into_iter()
+            .map(|(key, mut maps)| {
+                let used_item_map = if maps.len() == 1 {
+                    maps.remove(0)
+                } else {
+                    UsedItemMap::merged(&maps)
+                };
+                (key, used_item_map)
+            })
+            .collect();
+        self.packages = packages;
+    }
+
+    /// Merges the used items of the files of each workspace member into a single entry
+    /// keyed by the package name, as `UsedItemMap::merged` merges them. The files whose
+    /// package is unknown are merged under `UNKNOWN_PACKAGE_KEY`.
+    pub fn aggregate_by_package(&self) -> CrateMap {
+        let mut by_package: BTreeMap<&str, Vec<&UsedItemMap>> = BTreeMap::new();
+        for (file, used_item_map) in &self.source_map {
+            let package = self.package_of(file).unwrap_or(UNKNOWN_PACKAGE_KEY);
+            by_package.entry(package).or_default().push(used_item_map);
+        }
+        let packages = by_package
+            .keys()
+            .filter(|package| **package != UNKNOWN_PACKAGE_KEY)
+            .map(|package| (package.to_string(), package.to_string()))
+            .collect();
+        let source_map: BTreeMap<String, UsedItemMap> = by_package
+            .into_iter()
+            .map(|(package, maps)| (package.to_owned(), UsedItemMap::merged(maps)))
+            .collect();
+        let definitions = self
+            .definitions
+            .iter()
+            .filter_map(|(definition, file)| {
+                Some((definition.clone(), self.package_of(file)?.to_owned()))
+            })
+            .collect();
+        CrateMap {
+            root: self.root.clone(),
+            sysroot: self.sysroot.clone(),
+            dependencies: self.dependencies.clone(),
+            source_map,
+            generated: self.generated.clone(),
+            diagnostics: self.diagnostics.clone(),
+            unresolved_count: self.unresolved_count,
+            partial: self.partial,
+            packages,
+            definitions,
+        }
+    }
+
+    /// Combines the maps of the same workspace analyzed for each of the target triples of
+    /// `maps`, in order, into one listing every import of any of them. Imports missing for
+    /// some of the targets are annotated with the ones they are there for, see
+    /// `UsedItem::targets`.
+    pub(crate) fn by_target(maps: Vec<(String, CrateMap)>) -> CrateMap {
+        let total = maps.len();
+        let mut source_maps: BTreeMap<&str, Vec<(&str, &UsedItemMap)>> = BTreeMap::new();
+        let mut generated: BTreeMap<&str, Vec<(&str, &UsedItemMap)>> = BTreeMap::new();
+        for (triple, map) in &maps {
+            for (file, used_item_map) in &map.source_map {
+                source_maps
+                    .entry(file)
+                    .or_default()
+                    .push((triple, used_item_map));
+            }
+            for (file, used_item_map) in &map.generated {
+                generated
+                    .entry(file)
+                    .or_default()
+                    .push((triple, used_item_map));
+            }
+        }
+        let combine = |maps: BTreeMap<&str, Vec<(&str, &UsedItemMap)>>| {
+            maps.into_iter()
+                .map(|(file, maps)| (file.to_owned(), UsedItemMap::by_target(&maps, total)))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let source_map = combine(source_maps);
+        let generated = combine(generated);
+
+        let mut result = CrateMap::default();
+        for (_, map) in &maps {
+            result.diagnostics.extend(map.diagnostics.iter().cloned());
+            result.partial |= map.partial;
+            result.packages.extend(map.packages.clone());
+            result.definitions.extend(map.definitions.clone());
+            if result.root.is_none() {
+                result.root = map.root.clone();
+            }
+            if result.sysroot.is_none() {
+                result.sysroot = map.sysroot.clone();
+            }
+            merge_versions(&mut result.dependencies, map.dependencies.clone());
+        }
+        result.diagnostics.sort_by(|a, b| a.file.cmp(&b.file));
+        result.diagnostics.dedup();
+        result.unresolved_count = source_map
+            .values()
+            .chain(generated.values())
+            .map(|used_item_map| used_item_map.unresolved.len())
+            .sum();
+        result.source_map = source_map;
+        result.generated = generated;
+        result
+    }
+
+    /// Inverts the map so that it is keyed by the crate the items come from, then by the
+    /// files importing them. Items of unknown origin are keyed by an empty crate name.
+    pub fn invert_by_origin(&self) -> BTreeMap<String, BTreeMap<String, BTreeSet<UsedItem>>> {
+        let mut result: BTreeMap<String, BTreeMap<String, BTreeSet<UsedItem>>> = BTreeMap::new();
+        for (file, _, item) in self.iter() {
+            let crate_name = item
+                .origin
+                .as_ref()
+                .map(|origin| origin.crate_name().to_owned())
+                .unwrap_or_default();
+            result
+                .entry(crate_name)
+                .or_default()
+                .entry(file.to_owned())
+                .or_default()
+                .insert(item.clone());
+        }
+        result
+    }
+
+    /// The used items matching `query` together with the files importing them, ordered by
+    /// file.
+    pub fn find_importers(&self, query: &ItemQuery) -> Vec<(&str, &UsedItem)> {
+        self.iter()
+            .filter(|(_, _, item)| query.matches(item))
+            .map(|(file, _, item)| (file, item))
+            .collect()
+    }
+
+    /// Groups the results of `find_importers` by file, as `usedump who-uses` lists them.
+    pub fn importers(&self, query: &ItemQuery) -> Importers<'_> {
+        Importers::new(self.find_importers(query))
+    }
+
+    /// Counts the files, the distinct items of each kind, the most imported items and the
+    /// external crates of the map.
+    pub fn summarize(&self) -> Summary {
+        Summary::new(self)
+    }
+
+    /// Finds the imports of every file which bind the same name, and those of unstable
+    /// items of the standard library, see `Lints`. Shadowing by the imports of functions is
+    /// only reported with `shadowing`. Duplicates are only told apart by their locations,
+    /// which the map must be dumped with.
+    pub fn lints(&self, shadowing: bool) -> Lints {
+        Lints::new(self, shadowing)
+    }
+
+    /// Lists the imports of deprecated items, see `DeprecatedImports`. Deprecation is only
+    /// known for resolved imports, not for those listed with `DumpOptions::no_resolve`.
+    pub fn deprecated_imports(&self) -> DeprecatedImports {
+        DeprecatedImports::new(self)
+    }
+
+    /// Finds the sets of items which at least `min_files` files import together, see
+    /// `CommonImports`.
+    pub fn common_imports(&self, min_files: usize) -> CommonImports {
+        CommonImports::new(self, min_files)
+    }
+
+    /// Counts the files and the distinct items importing from each crate, see
+    /// `CrateUsages`.
+    pub fn crate_usages(&self) -> CrateUsages {
+        CrateUsages::new(self)
+    }
+
+    /// Keeps only the used items whose origin `filter` matches, in every file.
+    pub fn retain_origin(&mut self, filter: OriginFilter) {
+        for used_item_map in self.source_map.values_mut() {
+            used_item_map.retain_origin(filter);
+        }
+    }
+
+    /// Replaces the files of `other`, whether their analysis succeeded or not, by the ones
+    /// it has.
+    pub(crate) fn replace_files(&mut self, other: CrateMap) {
+        let files: Vec<&String> = other
+            .source_map
+            .keys()
+            .chain(other.generated.keys())
+            .chain(other.diagnostics.iter().map(|diagnostic| &diagnostic.file))
+            .collect();
+        for file in files {
+            let removed = self.source_map.remove(file).into_iter();
+            for used_item_map in removed.chain(self.generated.remove(file)) {
+                self.unresolved_count -= used_item_map.unresolved.len();
+            }
+            self.diagnostics
+                .retain(|diagnostic| diagnostic.file != *file);
+        }
+        self.unresolved_count += other.unresolved_count;
+        self.source_map.extend(other.source_map);
+        self.generated.extend(other.generated);
+        self.diagnostics.extend(other.diagnostics);
+        self.diagnostics.sort_by(|a, b| a.file.cmp(&b.file));
+        self.packages.extend(other.packages);
+        self.definitions.extend(other.definitions);
+    }
+
+    /// Adds the files of `other`, e.g. a dump of another shard of the workspace. Files both
+    /// maps have must have the same used items, otherwise nothing is merged.
+    pub fn merge(&mut self, other: CrateMap) -> std::result::Result<(), MergeConflict> {
+        let conflict = other.source_map.iter().find(|(key, used_item_map)| {
+            self.source_map
+                .get(*key)
+                .map_or(false, |mine| mine != *used_item_map)
+        });
+        let conflict = conflict.or_else(|| {
+            other.generated.iter().find(|(key, used_item_map)| {
+                self.generated
+                    .get(*key)
+                    .map_or(false, |mine| mine != *used_item_map)
+            })
+        });
+        if let Some((key, _)) = conflict {
+            return Err(MergeConflict::new(key.clone()));
+        }
+        self.merge_preferring_last(other);
+        Ok(())
+    }
+
+    /// Adds the files of `other`, whose used items win for the files both maps have.
+    /// Diagnostics are concatenated.
+    pub fn merge_preferring_last(&mut self, other: CrateMap) {
+        for (key, used_item_map) in other.source_map {
+            self.unresolved_count += used_item_map.unresolved.len();
+            if let Some(replaced) = self.source_map.insert(key, used_item_map) {
+                self.unresolved_count -= replaced.unresolved.len();
+            }
+        }
+        for (key, used_item_map) in other.generated {
+            self.unresolved_count += used_item_map.unresolved.len();
+            if let Some(replaced) = self.generated.insert(key, used_item_map) {
+                self.unresolved_count -= replaced.unresolved.len();
+            }
+        }
+        self.diagnostics.extend(other.diagnostics);
+        self.diagnostics.sort_by(|a, b| a.file.cmp(&b.file));
+        self.diagnostics.dedup();
+        self.partial |= other.partial;
+        if self.root.is_none() {
+            self.root = other.root;
+        }
+        if self.sysroot.is_none() {
+            self.sysroot = other.sysroot;
+        }
+        merge_versions(&mut self.dependencies, other.dependencies);
+        self.packages.extend(other.packages);
+        self.definitions.extend(other.definitions);
+    }
+
+    /// Applies `f` to the package and the used items of every file.
+    pub(crate) fn update_files<F: FnMut(Option<&str>, &mut UsedItemMap)>(&mut self, mut f: F) {
+        for (file, used_item_map) in self.source_map.iter_mut().chain(&mut self.generated) {
+            f(self.packages.get(file).map(String::as_str), used_item_map);
+        }
+    }
+
+    /// Drops the files which have nothing left to report, e.g. after filtering.
+    pub fn remove_empty_files(&mut self) {
+        let files: Vec<String> = self
+            .source_map
+            .iter()
+            .filter(|(_, used_item_map)| used_item_map.is_empty())
+            .map(|(file, _)| file.clone())
+            .collect();
+        for file in files {
+            self.source_map.remove(&file);
+        }
+        self.generated
+            .retain(|_, used_item_map| !used_item_map.is_empty());
+    }
+
+    pub fn legacy_names(&self) -> LegacyNames<'_> {
+        LegacyNames(self)
+    }
+
+    /// Regroups every used item of the workspace by the name of the crate it comes from.
+    pub fn by_origin(&self) -> BTreeMap<String, UsedItemMap> {
+        let mut result: BTreeMap<String, UsedItemMap> = BTreeMap::new();
+        for used_item_map in self.source_map.values() {
+            for (_, items) in used_item_map.sets() {
+                for item in items {
+                    let crate_name = item
+                        .origin
+                        .as_ref()
+                        .map(|origin| origin.crate_name().to_owned())
+                        .unwrap_or_default();
+                    result.entry(crate_name).or_default().insert(item.clone());
+                }
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+impl CrateMap {
+    /// The map `files` deserializes to, as unit tests build it: every file belongs to the
+    /// package its key starts with, and is the `DefinedIn::file` of the items defined in it.
+    pub(crate) fn from_json(files: serde_json::Value) -> Self {
+        let mut map: CrateMap = serde_json::from_value(files).unwrap();
+        for file in map.source_map.keys() {
+            let package = file.split('/').next().unwrap_or_default();
+            map.packages.insert(file.clone(), package.to_owned());
+            map.definitions.insert(file.clone(), file.clone());
+        }
+        map
+    }
+}
+
+/// Serializes a `CrateMap` in the old shape, where each used item is only its name.
+pub struct LegacyNames<'a>(&'a CrateMap);
+
+impl Serialize for LegacyNames<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.0.source_map.len()))?;
+        for (path, used_item_map) in &self.0.source_map {
+            map.serialize_entry(path, &LegacyUsedItemMap(used_item_map))?;
+        }
+        map.end()
+    }
+}
+
+struct LegacyUsedItemMap<'a>(&'a UsedItemMap);
+
+impl Serialize for LegacyUsedItemMap<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+        for (name, items) in self.0.sets() {
+            if items.is_empty() {
+                continue;
+            }
+            let names: Vec<&str> = items.iter().map(|item| item.name.as_str()).collect();
+            map.serialize_entry(name, &names)?;
+        }
+        map.end()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialOrd, PartialEq, Eq, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum UsedItemKind {
     Module,
     Trait,
@@ -50,11 +1844,79 @@ pub enum UsedItemKind {
     Enum,
     Fn,
     Const,
+    Static,
+    TypeAlias,
+    Union,
+    EnumVariant,
     Macro,
+    /// A trait or derive macro named in `#[derive(...)]`, which is not a `use`.
+    Derive,
     Other,
 }
 
 impl UsedItemKind {
+    pub const ALL: &'static [UsedItemKind] = &[
+        UsedItemKind::Module,
+        UsedItemKind::Trait,
+        UsedItemKind::Struct,
+        UsedItemKind::Enum,
+        UsedItemKind::Fn,
+        UsedItemKind::Const,
+        UsedItemKind::Static,
+        UsedItemKind::TypeAlias,
+        UsedItemKind::Union,
+        UsedItemKind::EnumVariant,
+        UsedItemKind::Macro,
+        UsedItemKind::Derive,
+        UsedItemKind::Other,
+    ];
+
+    /// The name of the kind as serialized, e.g. `type_alias`.
+    pub fn name(self) -> &'static str {
+        match self {
+            UsedItemKind::Module => "module",
+            UsedItemKind::Trait => "trait",
+            UsedItemKind::Struct => "struct",
+            UsedItemKind::Enum => "enum",
+            UsedItemKind::Fn => "fn",
+            UsedItemKind::Const => "const",
+            UsedItemKind::Static => "static",
+            UsedItemKind::TypeAlias => "type_alias",
+            UsedItemKind::Union => "union",
+            UsedItemKind::EnumVariant => "enum_variant",
+            UsedItemKind::Macro => "macro",
+            UsedItemKind::Derive => "derive",
+            UsedItemKind::Other => "other",
+        }
+    }
+
+    /// The name of the `UsedItemMap` field holding items of this kind, e.g. `traits`.
+    pub fn set_name(self) -> &'static str {
+        match self {
+            UsedItemKind::Module => "modules",
+            UsedItemKind::Trait => "traits",
+            UsedItemKind::Struct => "structs",
+            UsedItemKind::Enum => "enums",
+            UsedItemKind::Fn => "fns",
+            UsedItemKind::Const => "consts",
+            UsedItemKind::Static => "statics",
+            UsedItemKind::TypeAlias => "type_aliases",
+            UsedItemKind::Union => "unions",
+            UsedItemKind::EnumVariant => "enum_variants",
+            UsedItemKind::Macro => "macros",
+            UsedItemKind::Derive => "derives",
+            UsedItemKind::Other => "others",
+        }
+    }
+
+    /// The kind whose `UsedItemMap` field is named `set_name`, e.g. `traits`.
+    pub fn from_set_name(set_name: &str) -> Option<Self> {
+        UsedItemKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.set_name() == set_name)
+    }
+
     fn from_syntax_kind(syntax_kind: SyntaxKind) -> Self {
         match syntax_kind {
             SyntaxKind::SOURCE_FILE => UsedItemKind::Module,
@@ -63,43 +1925,648 @@ impl UsedItemKind {
             SyntaxKind::ENUM_DEF => UsedItemKind::Enum,
             SyntaxKind::FN_DEF => UsedItemKind::Fn,
             SyntaxKind::CONST_DEF => UsedItemKind::Const,
+            SyntaxKind::STATIC_DEF => UsedItemKind::Static,
+            SyntaxKind::TYPE_ALIAS_DEF => UsedItemKind::TypeAlias,
+            SyntaxKind::UNION_DEF => UsedItemKind::Union,
+            SyntaxKind::ENUM_VARIANT => UsedItemKind::EnumVariant,
             SyntaxKind::MACRO_CALL => UsedItemKind::Macro,
             _ => UsedItemKind::Other,
         }
     }
+
+    /// The kind of an item named `name`, going by the naming conventions of Rust only.
+    fn guess(name: &str) -> Self {
+        let name = name.trim_start_matches("r#");
+        let has_lowercase = name.chars().any(char::is_lowercase);
+        let starts_uppercase = name.chars().next().map_or(false, char::is_uppercase);
+        if starts_uppercase && !has_lowercase && name.len() > 1 {
+            UsedItemKind::Const
+        } else if starts_uppercase {
+            UsedItemKind::Struct
+        } else if has_lowercase {
+            UsedItemKind::Module
+        } else {
+            UsedItemKind::Other
+        }
+    }
+}
+
+impl FromStr for UsedItemKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        UsedItemKind::from_set_name(s).ok_or_else(|| {
+            let names: Vec<&str> = UsedItemKind::ALL.iter().map(|k| k.set_name()).collect();
+            format!(
+                "unknown kind `{}`, expected one of: {}",
+                s,
+                names.join(", ")
+            )
+        })
+    }
+}
+
+/// How a macro is invoked.
+#[derive(Debug, Clone, Copy, PartialOrd, PartialEq, Eq, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MacroKind {
+    /// `foo!(...)`, defined by `macro_rules!` or by a `#[proc_macro]` function.
+    Bang,
+    /// `#[derive(Foo)]`, defined by a `#[proc_macro_derive(Foo)]` function.
+    Derive,
+    /// `#[foo]`, defined by a `#[proc_macro_attribute]` function.
+    Attribute,
+}
+
+impl MacroKind {
+    pub const NAMES: &'static [&'static str] = &["bang", "derive", "attribute"];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MacroKind::Bang => "bang",
+            MacroKind::Derive => "derive",
+            MacroKind::Attribute => "attribute",
+        }
+    }
 }
 
-#[derive(Debug, PartialOrd, PartialEq, Eq, Ord)]
+impl fmt::Display for MacroKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct UsedItem {
+    /// The name, without the `r#` of raw identifiers.
     name: String,
+    /// Whether the name is a raw identifier, e.g. `r#type`.
+    #[serde(default, skip_serializing_if = "is_false")]
+    raw: bool,
+    path: String,
     kind: UsedItemKind,
+    /// How the macro is invoked, for macros.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    macro_kind: Option<MacroKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    alias: Option<String>,
+    /// The visibility of the use item, e.g. `pub` for a re-export or `pub(crate)` for a
+    /// restricted one, `None` for a private import.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    visibility: Option<String>,
+    /// Whether the item is imported as `_`, which brings no name into scope.
+    #[serde(default, skip_serializing_if = "is_false")]
+    anonymous: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    origin: Option<Origin>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    via_glob: bool,
+    /// How many times the item is referenced in the importing file, outside of use items.
+    #[serde(default)]
+    count: usize,
+    /// The items enclosing the use item when it is not at the top level of the file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+    /// The predicates of the `#[cfg(...)]` attributes on the use item and the items
+    /// enclosing it, e.g. `test`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    cfgs: Vec<String>,
+    /// Whether the item is a crate imported with `extern crate`.
+    #[serde(default, skip_serializing_if = "is_false")]
+    extern_crate: bool,
+    /// Whether the `extern crate` item carries `#[macro_use]`.
+    #[serde(default, skip_serializing_if = "is_false")]
+    macro_use: bool,
+    /// Whether the item could not be resolved, so that `path` is only the text as written.
+    #[serde(default, skip_serializing_if = "is_false")]
+    unresolved: bool,
+    /// Where the leaf use tree importing the item is, e.g. `Serialize` in
+    /// `use serde::{Serialize, Deserialize};`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    location: Option<Location>,
+    /// Where the whole use item is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    use_item_location: Option<Location>,
+    /// The file the item is defined in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    defined_in: Option<DefinedIn>,
+    /// The path a `pub use` re-exports the item under, in the exports of a crate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reexported_as: Option<String>,
+    /// The path the item is imported as, when it differs from `path` as the item is
+    /// re-exported, e.g. `tokio::sync::Mutex`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    surface_path: Option<String>,
+    /// The version of the package defining the item, for external crates, that of the
+    /// copy the definition is in when several versions are depended on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    /// Whether the item, or an item enclosing it, carries `#[deprecated]`.
+    #[serde(default, skip_serializing_if = "is_false")]
+    deprecated: bool,
+    /// The `since` of the `#[deprecated]` attribute, e.g. `1.2.0`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deprecated_since: Option<String>,
+    /// The `note` of the `#[deprecated]` attribute.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deprecated_note: Option<String>,
+    /// The feature of the `#[unstable(feature = "...")]` attribute gating the item, for the
+    /// items of the standard library.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    unstable_feature: Option<String>,
+    /// The target triples the item is imported for, when the workspace was analyzed for
+    /// several and the import is not there for all of them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    targets: Vec<String>,
 }
 
-impl Serialize for UsedItem {
-    fn serialize<S>(&self, serializer: S) -> Result<<S as Serializer>::Ok, <S as Serializer>::Error>
-    where
-        S: Serializer,
-    {
-        serializer.serialize_str(&self.name)
+/// A position in the importing file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Location {
+    /// 1-based line of the start.
+    pub line: u32,
+    /// 1-based column of the start, in UTF-16 code units.
+    pub col: u32,
+    /// Byte offsets of the start and the end.
+    pub range: [u32; 2],
+}
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
+
+impl PartialOrd for UsedItem {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for UsedItem {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.path
+            .cmp(&other.path)
+            .then_with(|| self.kind.cmp(&other.kind))
+            .then_with(|| self.macro_kind.cmp(&other.macro_kind))
+            .then_with(|| self.name.cmp(&other.name))
+            .then_with(|| self.raw.cmp(&other.raw))
+            .then_with(|| self.alias.cmp(&other.alias))
+            .then_with(|| self.visibility.cmp(&other.visibility))
+            .then_with(|| self.anonymous.cmp(&other.anonymous))
+            .then_with(|| self.origin.cmp(&other.origin))
+            .then_with(|| self.via_glob.cmp(&other.via_glob))
+            .then_with(|| self.count.cmp(&other.count))
+            .then_with(|| self.scope.cmp(&other.scope))
+            .then_with(|| self.cfgs.cmp(&other.cfgs))
+            .then_with(|| self.extern_crate.cmp(&other.extern_crate))
+            .then_with(|| self.macro_use.cmp(&other.macro_use))
+            .then_with(|| self.unresolved.cmp(&other.unresolved))
+            .then_with(|| self.location.cmp(&other.location))
+            .then_with(|| self.use_item_location.cmp(&other.use_item_location))
+            .then_with(|| self.defined_in.cmp(&other.defined_in))
+            .then_with(|| self.reexported_as.cmp(&other.reexported_as))
+            .then_with(|| self.surface_path.cmp(&other.surface_path))
+            .then_with(|| self.version.cmp(&other.version))
+            .then_with(|| self.deprecated.cmp(&other.deprecated))
+            .then_with(|| self.deprecated_since.cmp(&other.deprecated_since))
+            .then_with(|| self.deprecated_note.cmp(&other.deprecated_note))
+            .then_with(|| self.unstable_feature.cmp(&other.unstable_feature))
+            .then_with(|| self.targets.cmp(&other.targets))
+    }
+}
+
+impl UsedItem {
+    /// The name of the item, e.g. `Serialize`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the name is a raw identifier, which `name` leaves the `r#` out of.
+    pub fn is_raw(&self) -> bool {
+        self.raw
+    }
+
+    /// The fully qualified path of the definition, e.g. `serde::ser::Serialize`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn kind(&self) -> UsedItemKind {
+        self.kind
+    }
+
+    /// The local name given with `use ... as alias`.
+    pub fn alias(&self) -> Option<&str> {
+        self.alias.as_ref().map(String::as_str)
+    }
+
+    /// Whether the item is imported as `_`, e.g. `use std::io::Write as _;` to call the
+    /// methods of a trait without bringing its name into scope. Such imports have `_` as
+    /// their alias and are never unused.
+    pub fn anonymous(&self) -> bool {
+        self.anonymous
+    }
+
+    /// The name the item is known by in the importing file.
+    pub fn local_name(&self) -> &str {
+        match &self.alias {
+            Some(alias) => alias,
+            // Enum variants are named `Enum::Variant`.
+            None => self.name.rsplit("::").next().unwrap_or(&self.name),
+        }
+    }
+
+    pub fn origin(&self) -> Option<&Origin> {
+        self.origin.as_ref()
+    }
+
+    /// Whether the item was only imported through a glob import.
+    pub fn via_glob(&self) -> bool {
+        self.via_glob
+    }
+
+    /// How many times the item is referenced in the importing file, outside of use items.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// The items enclosing the use item, e.g. `tests` or `main()`, if it is not top-level.
+    pub fn scope(&self) -> Option<&str> {
+        self.scope.as_ref().map(String::as_str)
+    }
+
+    /// The predicates of the `#[cfg(...)]` attributes on the use item and the items
+    /// enclosing it, such as a `#[cfg(test)] mod tests`, e.g. `test` or `feature = "foo"`.
+    pub fn cfgs(&self) -> &[String] {
+        &self.cfgs
+    }
+
+    /// Whether the item is a crate imported with `extern crate`.
+    pub fn extern_crate(&self) -> bool {
+        self.extern_crate
+    }
+
+    /// Whether the item is a crate imported with `#[macro_use] extern crate`, which brings
+    /// its exported macros into scope.
+    pub fn macro_use(&self) -> bool {
+        self.macro_use
+    }
+
+    /// Whether the item could not be resolved, in which case `path` is the path as written.
+    pub fn unresolved(&self) -> bool {
+        self.unresolved
+    }
+
+    /// Where the leaf use tree importing the item is. Only recorded with
+    /// `DumpOptions::locations`.
+    pub fn location(&self) -> Option<Location> {
+        self.location
+    }
+
+    /// Where the use item importing the item is. Only recorded with
+    /// `DumpOptions::locations`.
+    pub fn use_item_location(&self) -> Option<Location> {
+        self.use_item_location
+    }
+
+    /// The file the item is defined in. Only recorded with `DumpOptions::definitions`.
+    pub fn defined_in(&self) -> Option<&DefinedIn> {
+        self.defined_in.as_ref()
+    }
+
+    /// The path under which the item is re-exported, e.g. `foo::Bar` for a
+    /// `pub use self::bar::Bar;` in the root of `foo`, whose `path` is `foo::bar::Bar`.
+    /// Only set in the maps listed by `DumpOptions::run_exports`.
+    pub fn reexported_as(&self) -> Option<&str> {
+        self.reexported_as.as_ref().map(String::as_str)
+    }
+
+    /// The path the item is imported as, when it is re-exported under another one than
+    /// `path`. Only kept with `ResolveReexports::Both`.
+    pub fn surface_path(&self) -> Option<&str> {
+        self.surface_path.as_ref().map(String::as_str)
+    }
+
+    /// The version of the package defining the item, only known for external crates, e.g.
+    /// `1.0.103` for `serde::Serialize`.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_ref().map(String::as_str)
+    }
+
+    /// Whether the item is deprecated, by a `#[deprecated]` attribute on its definition or
+    /// on a module or an enum enclosing it. Never told for the items imported
+    /// through globs, which rustc does not warn about either.
+    pub fn deprecated(&self) -> bool {
+        self.deprecated
+    }
+
+    /// The version the item is deprecated since, as its `#[deprecated]` attribute gives it.
+    pub fn deprecated_since(&self) -> Option<&str> {
+        self.deprecated_since.as_ref().map(String::as_str)
+    }
+
+    /// Why the item is deprecated, or what to use instead, as its `#[deprecated]` attribute
+    /// gives it.
+    pub fn deprecated_note(&self) -> Option<&str> {
+        self.deprecated_note.as_ref().map(String::as_str)
+    }
+
+    /// The nightly feature the item is gated behind, e.g. `try_trait` for `std::ops::Try`,
+    /// only told for the items of the crates of the sysroot.
+    pub fn unstable_feature(&self) -> Option<&str> {
+        self.unstable_feature.as_ref().map(String::as_str)
+    }
+
+    /// The target triples the item is imported for, empty unless the workspace was analyzed
+    /// for several and the item is not imported for all of them.
+    pub fn targets(&self) -> &[String] {
+        &self.targets
+    }
+
+    /// The visibility of the use item importing the item, e.g. `pub(crate)`, `None` when it
+    /// is private.
+    pub fn visibility(&self) -> Option<&str> {
+        self.visibility.as_ref().map(String::as_str)
+    }
+
+    /// Whether the item is re-exported by the use item importing it, a plain `pub use`.
+    /// Restricted ones, e.g. `pub(crate) use`, keep the item out of the API of the crate.
+    pub fn is_reexport(&self) -> bool {
+        self.visibility
+            .as_ref()
+            .map_or(false, |visibility| visibility == "pub")
+    }
+
+    /// How the macro is invoked, `None` for other items.
+    pub fn macro_kind(&self) -> Option<MacroKind> {
+        self.macro_kind
+    }
+
+    fn new(name: String, path: String, kind: UsedItemKind, origin: Option<Origin>) -> Self {
+        // Whether rust-analyzer and the source spell the `r#` or not, raw identifiers are
+        // named without it.
+        let raw = name.starts_with("r#") || is_keyword(&name);
+        let name = name.trim_start_matches("r#").to_owned();
+        UsedItem {
+            name,
+            raw,
+            path,
+            kind,
+            macro_kind: None,
+            alias: None,
+            visibility: None,
+            anonymous: false,
+            origin,
+            via_glob: false,
+            count: 0,
+            scope: None,
+            cfgs: vec![],
+            extern_crate: false,
+            macro_use: false,
+            unresolved: false,
+            location: None,
+            use_item_location: None,
+            defined_in: None,
+            reexported_as: None,
+            surface_path: None,
+            version: None,
+            deprecated: false,
+            deprecated_since: None,
+            deprecated_note: None,
+            unstable_feature: None,
+            targets: vec![],
+        }
+    }
+
+    fn from_navigation_target(
+        navigation_target: &NavigationTarget,
+        path: String,
+        origin: Option<Origin>,
+    ) -> Self {
+        let name = navigation_target.name().to_string();
+        let kind = UsedItemKind::from_syntax_kind(navigation_target.kind());
+        let mut item = UsedItem::new(name, path, kind, origin);
+        // `macro_rules!` definitions are macro calls themselves.
+        if kind == UsedItemKind::Macro {
+            item.macro_kind = Some(MacroKind::Bang);
+        }
+        item
+    }
+
+    /// Merges `other`, another import of the item, into this one: the counts are summed,
+    /// the flags telling something about the item, such as `deprecated` or `unresolved`,
+    /// hold when they hold for either import, and `via_glob` and `anonymous` only when
+    /// they hold for both. The cfgs common to both imports and the targets of either are
+    /// kept, and the other attributes when both imports agree on them. The imports no
+    /// longer have a single scope or location.
+    fn merge(&mut self, other: &UsedItem) {
+        fn agreed<T: PartialEq>(mine: &mut Option<T>, other: &Option<T>) {
+            if *mine != *other {
+                *mine = None;
+            }
+        }
+        fn or_other<T: Clone>(mine: &mut Option<T>, other: &Option<T>) {
+            if mine.is_none() {
+                *mine = other.clone();
+            }
+        }
+        self.count += other.count;
+        self.scope = None;
+        self.location = None;
+        self.use_item_location = None;
+        self.raw |= other.raw;
+        self.extern_crate |= other.extern_crate;
+        self.macro_use |= other.macro_use;
+        self.unresolved |= other.unresolved;
+        self.deprecated |= other.deprecated;
+        self.via_glob &= other.via_glob;
+        self.anonymous &= other.anonymous;
+        self.cfgs.retain(|cfg| other.cfgs.contains(cfg));
+        // No targets means every target.
+        if self.targets.is_empty() || other.targets.is_empty() {
+            self.targets.clear();
+        } else {
+            for target in &other.targets {
+                if !self.targets.contains(target) {
+                    self.targets.push(target.clone());
+                }
+            }
+        }
+        agreed(&mut self.alias, &other.alias);
+        agreed(&mut self.visibility, &other.visibility);
+        agreed(&mut self.reexported_as, &other.reexported_as);
+        agreed(&mut self.surface_path, &other.surface_path);
+        agreed(&mut self.version, &other.version);
+        // These describe the item rather than the import.
+        or_other(&mut self.macro_kind, &other.macro_kind);
+        or_other(&mut self.origin, &other.origin);
+        or_other(&mut self.defined_in, &other.defined_in);
+        or_other(&mut self.deprecated_since, &other.deprecated_since);
+        or_other(&mut self.deprecated_note, &other.deprecated_note);
+        or_other(&mut self.unstable_feature, &other.unstable_feature);
+    }
+}
+
+/// Workspace-wide information shared by the resolvers of every file.
+#[derive(Default)]
+pub(crate) struct Workspace {
+    definition_paths: DefinitionPaths,
+    origins: Origins,
+    /// The number of `goto_definition` calls made by the resolvers.
+    goto_definition_calls: AtomicUsize,
+    /// How long the resolution of a single file may take.
+    file_timeout: Option<Duration>,
+    /// Whether the use trees which do not resolve are kept as written, with a kind guessed
+    /// from their casing, rather than only listed as unresolved.
+    guess_unresolved: bool,
+    /// Whether the derives of `#[derive(...)]` attributes are resolved and listed.
+    include_derives: bool,
+    /// For every member file, a hash of the member files its crate can see: those of its
+    /// package and of the members the package depends on, directly or not. Only computed
+    /// with the cache, whose entries it keys.
+    dependency_hashes: FxHashMap<FileId, String>,
+}
+
+impl Workspace {
+    /// Gathers what the resolvers need to know about the workspace loaded into `host`.
+    fn new(
+        host: &AnalysisHost,
+        source_map: &SourceMap,
+        metadata: Option<&Metadata>,
+        config: &AnalysisConfig,
+    ) -> Self {
+        let db = host.raw_database();
+        let files = source_map
+            .iter()
+            .flat_map(|(source_root_id, package_root)| {
+                db.source_root(*source_root_id)
+                    .walk()
+                    .map(move |file_id| {
+                        let path = db.file_relative_path(file_id).to_string();
+                        (package_root.path().join(path), file_id)
+                    })
+                    .collect::<Vec<_>>()
+            });
+        let origins = Origins::new(db, source_map, metadata);
+        let dependency_hashes = match config.cache {
+            CacheConfig::Disabled => FxHashMap::default(),
+            _ => dependency_hashes(host, source_map, metadata, &origins),
+        };
+        Workspace {
+            definition_paths: DefinitionPaths::new(&db.crate_graph()).with_files(files),
+            origins,
+            goto_definition_calls: AtomicUsize::new(0),
+            file_timeout: config.file_timeout,
+            guess_unresolved: false,
+            include_derives: config.include_derives,
+            dependency_hashes,
+        }
+    }
+
+    /// Whether the resolution of a file started at `started` has taken longer than
+    /// `file_timeout`.
+    fn timed_out(&self, started: Instant) -> Option<TimedOut> {
+        match self.file_timeout {
+            Some(timeout) if started.elapsed() > timeout => Some(TimedOut(timeout)),
+            _ => None,
+        }
     }
 }
 
-impl UsedItem {
-    fn from_navigation_target(navigation_target: &NavigationTarget) -> Self {
-        let name = navigation_target.name().to_string();
-        let kind = UsedItemKind::from_syntax_kind(navigation_target.kind());
-        UsedItem { name, kind }
+/// Computes `Workspace::dependency_hashes`.
+///
+/// A package stands for all of its targets, so that the files of a library are also
+/// resolved again when an integration test of it changes.
+fn dependency_hashes(
+    host: &AnalysisHost,
+    source_map: &SourceMap,
+    metadata: Option<&Metadata>,
+    origins: &Origins,
+) -> FxHashMap<FileId, String> {
+    let db = host.raw_database();
+    let member_dirs = member_dirs(metadata);
+    let members: Vec<SourceRootId> = source_map
+        .iter()
+        .filter(|(_, package_root)| is_member(package_root, &member_dirs))
+        .map(|(&source_root_id, _)| source_root_id)
+        .collect();
+
+    let crate_graph = db.crate_graph();
+    let mut dependencies: FxHashMap<SourceRootId, HashSet<SourceRootId>> = FxHashMap::default();
+    for crate_id in crate_graph.iter() {
+        let package = db.file_source_root(crate_graph.crate_root(crate_id));
+        for dependency in crate_graph.dependencies(crate_id) {
+            let dependency = db.file_source_root(crate_graph.crate_root(dependency.crate_id()));
+            if dependency != package && members.contains(&dependency) {
+                dependencies.entry(package).or_default().insert(dependency);
+            }
+        }
+    }
+
+    let mut hashes = FxHashMap::default();
+    for &member in &members {
+        // The packages the member can see, itself included.
+        let mut seen = HashSet::new();
+        let mut pending = vec![member];
+        while let Some(package) = pending.pop() {
+            if seen.insert(package) {
+                pending.extend(dependencies.get(&package).into_iter().flatten().copied());
+            }
+        }
+        let file_hashes: BTreeMap<&str, String> = seen
+            .iter()
+            .flat_map(|&package| db.source_root(package).walk().collect::<Vec<_>>())
+            .filter_map(|file_id| {
+                let file = &origins.defined_in(file_id)?.file;
+                Some((file.as_str(), content_hash(&db.file_text(file_id))))
+            })
+            .collect();
+        let mut text = String::new();
+        for (file, hash) in file_hashes {
+            text.push_str(file);
+            text.push(' ');
+            text.push_str(&hash);
+            text.push('\n');
+        }
+        let hash = content_hash(&text);
+        for file_id in db.source_root(member).walk() {
+            hashes.insert(file_id, hash.clone());
+        }
     }
+    hashes
 }
 
+/// The payload a resolver unwinds with to give up on a file which took longer than
+/// `Workspace::file_timeout`, caught by `for_each_file`.
+///
+/// Cancelling the snapshots would give up on every file, so resolvers check for it between
+/// `goto_definition` calls instead, and a single call which never returns still blocks
+/// the dump.
+#[derive(Debug)]
+struct TimedOut(Duration);
+
 struct UsedItemResolver<'a> {
     analysis: &'a Analysis,
+    workspace: &'a Workspace,
     file_id: FileId,
     used_item_map: UsedItemMap,
+    glob_depth: usize,
+    /// When the resolution of the file started, to enforce `Workspace::file_timeout`.
+    started: Instant,
 }
 
-#[derive(Default, Debug, Serialize)]
+/// How many levels of `pub use` re-exports are followed when expanding a glob import.
+const MAX_GLOB_DEPTH: usize = 4;
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct UsedItemMap {
+    /// The kind of the target the file belongs to, unknown for maps merged over several.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target: Option<TargetKind>,
+    /// The predicates of the `#[cfg(...)]` attributes of the `mod` declarations leading to
+    /// the file, e.g. `test` for `#[cfg(test)] mod tests;`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cfgs: Vec<String>,
     #[serde(skip_serializing_if = "BTreeSet::is_empty")]
     modules: BTreeSet<UsedItem>,
     #[serde(skip_serializing_if = "BTreeSet::is_empty")]
@@ -113,56 +2580,715 @@ pub struct UsedItemMap {
     #[serde(skip_serializing_if = "BTreeSet::is_empty")]
     consts: BTreeSet<UsedItem>,
     #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    statics: BTreeSet<UsedItem>,
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    type_aliases: BTreeSet<UsedItem>,
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    unions: BTreeSet<UsedItem>,
+    /// Enum variants, named after their enum like `Ordering::Less`.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    enum_variants: BTreeSet<UsedItem>,
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
     macros: BTreeSet<UsedItem>,
+    /// Derives named in `#[derive(...)]` attributes.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    derives: BTreeSet<UsedItem>,
     #[serde(skip_serializing_if = "BTreeSet::is_empty")]
     others: BTreeSet<UsedItem>,
+    /// Glob imports whose module could not be resolved.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    globs: BTreeSet<String>,
+    /// Use trees which `goto_definition` could not resolve, as written in the source.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    unresolved: BTreeSet<String>,
+    /// The candidates of the use trees which resolve to several items that nothing tells
+    /// apart, e.g. a macro and a struct of the same name both used in the file.
+    #[serde(skip_serializing_if = "BTreeSet::is_empty")]
+    ambiguous: BTreeSet<UsedItem>,
+    /// Whether the file failed to parse. The parser recovers from errors, so the use items
+    /// it still finds are listed regardless.
+    #[serde(skip_serializing_if = "is_false")]
+    has_syntax_errors: bool,
+    /// The number of syntax errors in the file.
+    #[serde(skip_serializing_if = "is_zero")]
+    syntax_errors: usize,
+}
+
+impl UsedItemMap {
+    /// The used items of the given kind.
+    pub fn get(&self, kind: UsedItemKind) -> &BTreeSet<UsedItem> {
+        use UsedItemKind::*;
+        match kind {
+            Module => &self.modules,
+            Trait => &self.traits,
+            Struct => &self.structs,
+            Enum => &self.enums,
+            Fn => &self.fns,
+            Const => &self.consts,
+            Static => &self.statics,
+            TypeAlias => &self.type_aliases,
+            Union => &self.unions,
+            EnumVariant => &self.enum_variants,
+            Macro => &self.macros,
+            Derive => &self.derives,
+            Other => &self.others,
+        }
+    }
+
+    fn get_mut(&mut self, kind: UsedItemKind) -> &mut BTreeSet<UsedItem> {
+        use UsedItemKind::*;
+        match kind {
+            Module => &mut self.modules,
+            Trait => &mut self.traits,
+            Struct => &mut self.structs,
+            Enum => &mut self.enums,
+            Fn => &mut self.fns,
+            Const => &mut self.consts,
+            Static => &mut self.statics,
+            TypeAlias => &mut self.type_aliases,
+            Union => &mut self.unions,
+            EnumVariant => &mut self.enum_variants,
+            Macro => &mut self.macros,
+            Derive => &mut self.derives,
+            Other => &mut self.others,
+        }
+    }
+
+    /// Iterates over the used items of every kind.
+    pub fn iter(&self) -> impl Iterator<Item = &UsedItem> {
+        self.sets().flat_map(|(_, items)| items)
+    }
+
+    /// Glob imports whose module could not be resolved, as written in the source.
+    pub fn globs(&self) -> &BTreeSet<String> {
+        &self.globs
+    }
+
+    /// Use trees which could not be resolved, as written in the source.
+    pub fn unresolved(&self) -> &BTreeSet<String> {
+        &self.unresolved
+    }
+
+    /// The candidates of the use trees which resolve to several items, which are not
+    /// listed with the other used items.
+    pub fn ambiguous(&self) -> &BTreeSet<UsedItem> {
+        &self.ambiguous
+    }
+
+    /// Whether the file has syntax errors, in which case its used items may be incomplete.
+    pub fn has_syntax_errors(&self) -> bool {
+        self.has_syntax_errors
+    }
+
+    /// The number of syntax errors in the file.
+    pub fn syntax_errors(&self) -> usize {
+        self.syntax_errors
+    }
+
+    fn insert(&mut self, item: UsedItem) {
+        self.get_mut(item.kind).insert(item);
+    }
+
+    /// The sets of used items keyed by their field name, in declaration order.
+    pub fn sets(&self) -> impl Iterator<Item = (&'static str, &BTreeSet<UsedItem>)> {
+        UsedItemKind::ALL
+            .iter()
+            .map(move |&kind| (kind.set_name(), self.get(kind)))
+    }
+
+    /// Keeps only the used items of the given kinds.
+    pub fn retain_kinds(&mut self, kinds: &[UsedItemKind]) {
+        for &kind in UsedItemKind::ALL {
+            if !kinds.contains(&kind) {
+                self.get_mut(kind).clear();
+            }
+        }
+    }
+
+    /// Keeps only the used items whose origin `filter` matches.
+    pub fn retain_origin(&mut self, filter: OriginFilter) {
+        self.retain(|item| filter.matches(item.origin()));
+    }
+
+    pub fn target(&self) -> Option<TargetKind> {
+        self.target
+    }
+
+    /// The predicates of the `#[cfg(...)]` attributes of the `mod` declarations leading to
+    /// the file, outermost first.
+    pub fn cfgs(&self) -> &[String] {
+        &self.cfgs
+    }
+
+    pub(crate) fn set_target(&mut self, target: TargetKind) {
+        self.target = Some(target);
+    }
+
+    pub(crate) fn set_syntax_errors(&mut self, syntax_errors: usize) {
+        self.has_syntax_errors = syntax_errors > 0;
+        self.syntax_errors = syntax_errors;
+    }
+
+    /// Whether the map has no used items, unresolved globs or unresolved use trees.
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+            && self.globs.is_empty()
+            && self.unresolved.is_empty()
+            && self.ambiguous.is_empty()
+    }
+
+    /// Combines the maps of a file analyzed for each of `total` target triples, of which
+    /// `maps` has those the file was analyzed for, see `CrateMap::by_target`.
+    fn by_target(maps: &[(&str, &UsedItemMap)], total: usize) -> UsedItemMap {
+        let mut targets: BTreeMap<UsedItem, Vec<String>> = BTreeMap::new();
+        let mut result = UsedItemMap::default();
+        for (triple, map) in maps {
+            result.target = result.target.or(map.target);
+            if result.cfgs.is_empty() {
+                result.cfgs = map.cfgs.clone();
+            }
+            for item in map.iter() {
+                targets
+                    .entry(item.clone())
+                    .or_default()
+                    .push((*triple).to_owned());
+            }
+            result.globs.extend(map.globs.iter().cloned());
+            result.unresolved.extend(map.unresolved.iter().cloned());
+            result.ambiguous.extend(map.ambiguous.iter().cloned());
+            result.has_syntax_errors |= map.has_syntax_errors;
+            result.syntax_errors = result.syntax_errors.max(map.syntax_errors);
+        }
+        for (mut item, triples) in targets {
+            if triples.len() < total {
+                item.targets = triples;
+            }
+            result.insert(item);
+        }
+        result
+    }
+
+    /// Merges `maps` into one, the imports of an item of the same kind and path collapsing
+    /// into one item, see `UsedItem::merge`.
+    fn merged<'a, I: IntoIterator<Item = &'a UsedItemMap>>(maps: I) -> UsedItemMap {
+        let mut items: BTreeMap<(UsedItemKind, &str), UsedItem> = BTreeMap::new();
+        let mut targets = BTreeSet::new();
+        let mut result = UsedItemMap::default();
+        for map in maps {
+            targets.insert(map.target);
+            for item in map.iter() {
+                match items.entry((item.kind, &item.path)) {
+                    btree_map::Entry::Occupied(mut entry) => entry.get_mut().merge(item),
+                    btree_map::Entry::Vacant(entry) => {
+                        let mut item = item.clone();
+                        item.scope = None;
+                        item.location = None;
+                        item.use_item_location = None;
+                        entry.insert(item);
+                    }
+                }
+            }
+            result.globs.extend(map.globs.iter().cloned());
+            result.unresolved.extend(map.unresolved.iter().cloned());
+            result.ambiguous.extend(map.ambiguous.iter().cloned());
+            result.has_syntax_errors |= map.has_syntax_errors;
+            result.syntax_errors += map.syntax_errors;
+        }
+        for (_, item) in items {
+            result.insert(item);
+        }
+        if targets.len() == 1 {
+            result.target = targets.into_iter().next().unwrap_or_default();
+        }
+        result
+    }
+
+    /// Applies `f` to every used item.
+    pub(crate) fn update_items<F: FnMut(&mut UsedItem)>(&mut self, mut f: F) {
+        for &kind in UsedItemKind::ALL {
+            let set = self.get_mut(kind);
+            *set = std::mem::take(set)
+                .into_iter()
+                .map(|mut item| {
+                    f(&mut item);
+                    item
+                })
+                .collect();
+        }
+    }
+
+    /// Forgets where the used items are imported.
+    pub(crate) fn clear_locations(&mut self) {
+        self.update_items(|item| {
+            item.location = None;
+            item.use_item_location = None;
+        });
+    }
+
+    /// Lists the re-exported items under the path `resolve_reexports` asks for.
+    pub(crate) fn resolve_reexports(&mut self, resolve_reexports: ResolveReexports) {
+        match resolve_reexports {
+            ResolveReexports::Surface => self.update_items(|item| {
+                if let Some(surface_path) = item.surface_path.take() {
+                    item.path = surface_path;
+                }
+            }),
+            ResolveReexports::Definition => self.update_items(|item| item.surface_path = None),
+            ResolveReexports::Both => {}
+        }
+    }
+
+    /// Forgets where the used items are defined.
+    pub(crate) fn clear_definitions(&mut self) {
+        self.update_items(|item| item.defined_in = None);
+    }
+
+    /// Keeps only the used items for which `f` returns `true`.
+    pub fn retain<F: FnMut(&UsedItem) -> bool>(&mut self, mut f: F) {
+        for &kind in UsedItemKind::ALL {
+            let set = self.get_mut(kind);
+            *set = std::mem::take(set)
+                .into_iter()
+                .filter(|item| f(item))
+                .collect();
+        }
+    }
 }
 
 impl<'a> UsedItemResolver<'a> {
-    fn new(analysis: &'a Analysis, file_id: FileId) -> Self {
+    fn new(analysis: &'a Analysis, workspace: &'a Workspace, file_id: FileId) -> Self {
         UsedItemResolver {
             file_id,
             analysis,
+            workspace,
             used_item_map: Default::default(),
+            glob_depth: 0,
+            started: Instant::now(),
         }
     }
 
-    fn used_items(mut self) -> UsedItemMap {
-        let source_file = match self.analysis.parse(self.file_id) {
-            Ok(s) => s,
-            Err(_) => return UsedItemMap::default(),
-        };
+    fn used_items(mut self) -> Cancelable<UsedItemMap> {
+        let source_file = self.analysis.parse(self.file_id)?;
+        self.used_item_map.cfgs = self
+            .workspace
+            .definition_paths
+            .file_cfgs(self.analysis, self.file_id);
+
+        let mut imported_items = vec![];
+        for node in source_file.syntax().descendants() {
+            let mut items = if let Some(use_item) = UseItem::cast(node.clone()) {
+                self.used_items_in_use_item(&use_item)
+            } else if let Some(extern_crate_item) = ExternCrateItem::cast(node.clone()) {
+                self.used_item_in_extern_crate(&extern_crate_item)
+                    .map(|item| vec![item])
+            } else {
+                continue;
+            };
+            if let Some(items) = &mut items {
+                let scope = scope_of(&node);
+                let use_item_location = self.location(node.text_range());
+                for item in items.iter_mut() {
+                    item.scope = scope.clone();
+                    item.use_item_location = use_item_location;
+                }
+                imported_items.append(items);
+            }
+        }
+
+        let references = References::collect(self.analysis, self.file_id, false)?;
+        for item in &mut imported_items {
+            // `_` would count the wildcard patterns.
+            if !item.anonymous {
+                item.count = references.count(item.local_name());
+            }
+        }
+        self.add_imported_items(imported_items);
+
+        // Each derive takes a `goto_definition` call.
+        if self.workspace.include_derives {
+            for attr in source_file
+                .syntax()
+                .descendants()
+                .filter_map(ast::Attr::cast)
+            {
+                for item in self.derives_in_attr(&attr) {
+                    self.used_item_map.insert(item);
+                }
+            }
+        }
+
+        Ok(self.used_item_map)
+    }
+
+    /// The items the file exports: its `pub` items and those of its `pub` inline modules,
+    /// including `pub use` re-exports, and its `#[macro_export]` macros. Only the macros are
+    /// listed for the files which other crates cannot reach.
+    fn exports(mut self) -> Cancelable<UsedItemMap> {
+        let source_file = self.analysis.parse(self.file_id)?;
+        let public = self
+            .workspace
+            .definition_paths
+            .is_public_file(self.analysis, self.file_id);
+        let items = self.exports_in(source_file.syntax(), public);
+        self.add_imported_items(items);
+        Ok(self.used_item_map)
+    }
+
+    /// The exports among the items in `parent`, a source file or the item list of an inline
+    /// module, which is reachable from other crates if `public`.
+    fn exports_in(&mut self, parent: &SyntaxNode, public: bool) -> Vec<UsedItem> {
+        let mut result = vec![];
+        for node in parent.children() {
+            if let Some(macro_call) = ast::MacroCall::cast(node.clone()) {
+                result.extend(self.exported_macro(&macro_call));
+                continue;
+            }
+            if let Some(module) = ast::Module::cast(node.clone()) {
+                let public = public && is_pub(&node);
+                match module.name() {
+                    Some(name) if public => {
+                        result.push(self.exported_item(&node, &name, UsedItemKind::Module))
+                    }
+                    _ => {}
+                }
+                // Exported macros are exported from private modules too.
+                if let Some(item_list) = module.item_list() {
+                    result.append(&mut self.exports_in(item_list.syntax(), public));
+                }
+                continue;
+            }
+            if !public || !is_pub(&node) {
+                continue;
+            }
+
+            let reexports = if let Some(use_item) = UseItem::cast(node.clone()) {
+                self.used_items_in_use_item(&use_item).unwrap_or_default()
+            } else if let Some(extern_crate_item) = ExternCrateItem::cast(node.clone()) {
+                self.used_item_in_extern_crate(&extern_crate_item)
+                    .into_iter()
+                    .collect()
+            } else {
+                if let Some(name) = node.children().find_map(ast::Name::cast) {
+                    let kind = UsedItemKind::from_syntax_kind(node.kind());
+                    result.push(self.exported_item(&node, &name, kind));
+                }
+                continue;
+            };
+            for mut item in reexports {
+                if item.anonymous {
+                    continue;
+                }
+                item.reexported_as = Some(self.workspace.definition_paths.path_of_node(
+                    self.analysis,
+                    self.file_id,
+                    &node,
+                    item.local_name(),
+                ));
+                result.push(item);
+            }
+        }
+        result
+    }
+
+    /// The glob imports of the file, each with the module it resolves to and, when it can
+    /// be expanded, the names of its items which the file refers to.
+    fn glob_imports(&self) -> Cancelable<Vec<GlobImport>> {
+        let source_file = self.analysis.parse(self.file_id)?;
+        let globs: Vec<UseTree> = source_file
+            .syntax()
+            .descendants()
+            .filter_map(UseTree::cast)
+            .filter(|use_tree| use_tree.has_star())
+            .collect();
+        if globs.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let references = References::collect(self.analysis, self.file_id, true)?;
+        let mut result = vec![];
+        for use_tree in globs {
+            let target = use_tree.path().and_then(|path| self.glob_target(&path));
+            let module = target.as_ref().map(|target| {
+                self.workspace
+                    .definition_paths
+                    .path_of(self.analysis, target)
+            });
+            let referenced = target
+                .as_ref()
+                .and_then(|target| self.glob_items_of(target))
+                .map(|items| self.referenced_glob_items(&source_file, &items, &references));
+            result.push(GlobImport::new(
+                full_path_text(&use_tree),
+                self.location(use_tree.syntax().text_range()),
+                module,
+                referenced,
+            ));
+        }
+        Ok(result)
+    }
 
-        for use_item in source_file.items().filter_map(item_to_use_item) {
-            if let Some(imported_items) = self.used_items_in_use_item(&use_item) {
-                self.add_imported_items(imported_items);
+    /// The names of the `items` of a glob import which the file refers to outside of use
+    /// items, resolving every reference by the same name until one reaches the item. Traits
+    /// count as referred to when the file calls their methods. Names only written in macro
+    /// arguments cannot be resolved and are left out.
+    fn referenced_glob_items(
+        &self,
+        source_file: &SourceFile,
+        items: &[UsedItem],
+        references: &References,
+    ) -> BTreeSet<String> {
+        let mut referenced = BTreeSet::new();
+        for item in items {
+            let name = item.local_name();
+            if item.kind == UsedItemKind::Trait && references.calls_methods_of(name) {
+                referenced.insert(name.to_owned());
+                continue;
             }
+            if references.count(name) == 0 {
+                continue;
+            }
+            let is_referenced = source_file
+                .syntax()
+                .descendants()
+                .filter_map(ast::NameRef::cast)
+                .filter(|name_ref| {
+                    name_ref.text().trim_start_matches("r#") == name
+                        && !is_in_use_item(name_ref.syntax())
+                })
+                .any(|name_ref| {
+                    let file_position = FilePosition {
+                        file_id: self.file_id,
+                        offset: name_ref.syntax().text_range().start(),
+                    };
+                    let targets = match self.goto_definition(file_position) {
+                        Ok(Some(range_info)) => range_info.info,
+                        _ => return false,
+                    };
+                    targets.iter().any(|target| {
+                        self.workspace
+                            .definition_paths
+                            .path_of(self.analysis, target)
+                            == item.path
+                    })
+                });
+            if is_referenced {
+                referenced.insert(name.to_owned());
+            }
+        }
+        referenced
+    }
+
+    fn qualified_references(&self, names: &HashSet<String>) -> Cancelable<BTreeSet<String>> {
+        let source_file = self.analysis.parse(self.file_id)?;
+        let mut paths = BTreeSet::new();
+        for path in source_file
+            .syntax()
+            .descendants()
+            .filter_map(ast::Path::cast)
+        {
+            if path.qualifier().is_none() || is_in_use_item(path.syntax()) {
+                continue;
+            }
+            let name_ref = match path.segment().and_then(|segment| segment.name_ref()) {
+                Some(name_ref) if names.contains(name_ref.text().as_str()) => name_ref,
+                _ => continue,
+            };
+            let file_position = FilePosition {
+                file_id: self.file_id,
+                offset: name_ref.syntax().text_range().start(),
+            };
+            if let Some(range_info) = self.goto_definition(file_position)? {
+                for navigation_target in &range_info.info {
+                    paths.insert(
+                        self.workspace
+                            .definition_paths
+                            .path_of(self.analysis, navigation_target),
+                    );
+                }
+            }
+        }
+        Ok(paths)
+    }
+
+    /// The item named `name` which `node` defines in the file.
+    fn exported_item(&self, node: &SyntaxNode, name: &ast::Name, kind: UsedItemKind) -> UsedItem {
+        let text = name.text().to_string();
+        let path =
+            self.workspace
+                .definition_paths
+                .path_of_node(self.analysis, self.file_id, node, &text);
+        let origin = self.workspace.origins.of(self.file_id).cloned();
+        let mut item = UsedItem::new(text, path, kind, origin);
+        item.defined_in = self.workspace.origins.defined_in(self.file_id).cloned();
+        item.location = self.location(name.syntax().text_range());
+        item.cfgs = cfgs_of(node);
+        item
+    }
+
+    /// The macro a `#[macro_export] macro_rules!` defines, which is exported from the root
+    /// of the crate wherever it is.
+    fn exported_macro(&self, macro_call: &ast::MacroCall) -> Option<UsedItem> {
+        let exported = macro_call.attrs().any(|attr| {
+            attr.simple_name()
+                .map_or(false, |name| name == "macro_export")
+        });
+        if !exported || macro_call.path()?.syntax().text() != "macro_rules" {
+            return None;
         }
+        let name = macro_call.name()?;
+        let mut item = self.exported_item(macro_call.syntax(), &name, UsedItemKind::Macro);
+        item.path = format!(
+            "{}::{}",
+            self.workspace
+                .definition_paths
+                .crate_path(self.analysis, self.file_id),
+            item.name
+        );
+        Some(item)
+    }
+
+    fn goto_definition(
+        &self,
+        file_position: FilePosition,
+    ) -> Cancelable<Option<RangeInfo<Vec<NavigationTarget>>>> {
+        self.workspace
+            .goto_definition_calls
+            .fetch_add(1, atomic::Ordering::Relaxed);
+        if let Some(timed_out) = self.workspace.timed_out(self.started) {
+            panic::resume_unwind(Box::new(timed_out));
+        }
+        self.analysis.goto_definition(file_position)
+    }
 
-        self.used_item_map
+    fn location(&self, range: TextRange) -> Option<Location> {
+        let line_index = self.analysis.file_line_index(self.file_id).ok()?;
+        let line_col = line_index.line_col(range.start());
+        Some(Location {
+            line: line_col.line + 1,
+            col: line_col.col_utf16 + 1,
+            range: [u32::from(range.start()), u32::from(range.end())],
+        })
     }
 
     fn add_imported_items(&mut self, used_items: Vec<UsedItem>) {
         for item in used_items {
-            use UsedItemKind::*;
-            match item.kind {
-                Module => self.used_item_map.modules.insert(item),
-                Trait => self.used_item_map.traits.insert(item),
-                Struct => self.used_item_map.structs.insert(item),
-                Enum => self.used_item_map.enums.insert(item),
-                Fn => self.used_item_map.fns.insert(item),
-                Const => self.used_item_map.consts.insert(item),
-                Macro => self.used_item_map.macros.insert(item),
-                Other => self.used_item_map.others.insert(item),
-            };
+            self.used_item_map.insert(item);
+        }
+    }
+
+    fn used_items_in_use_item(&mut self, use_item: &UseItem) -> Option<Vec<UsedItem>> {
+        let mut items = self.used_items_in_use_tree(&use_item.use_tree()?)?;
+        let cfgs = cfgs_of(use_item.syntax());
+        // The items a glob expands to through the `pub use` of another module come with
+        // its visibility, which is not that of this use item.
+        let visibility = visibility_of(use_item.syntax());
+        for item in &mut items {
+            if !cfgs.is_empty() {
+                item.cfgs = cfgs.clone();
+            }
+            item.visibility = visibility.clone();
         }
+        Some(items)
+    }
+
+    /// The crate imported by `extern crate foo;`, with `#[macro_use]` noted.
+    fn used_item_in_extern_crate(&self, extern_crate_item: &ExternCrateItem) -> Option<UsedItem> {
+        let name_ref = extern_crate_item.name_ref()?;
+        let file_position = FilePosition {
+            file_id: self.file_id,
+            offset: name_ref.syntax().text_range().end(),
+        };
+        let target = match self.goto_definition(file_position) {
+            Ok(Some(range_info)) => range_info.info.into_iter().next(),
+            _ => None,
+        };
+        let mut item = match target {
+            Some(navigation_target) => self.used_item(&navigation_target),
+            // `extern crate` of a crate which is not a dependency, e.g. `proc_macro`.
+            None => {
+                let name = name_ref.text().to_string();
+                UsedItem::new(name.clone(), name, UsedItemKind::Module, None)
+            }
+        };
+        // The crate root is named after its file, e.g. `lib`.
+        item.name = name_ref.text().to_string();
+        item.kind = UsedItemKind::Module;
+        item.alias = extern_crate_item.alias().as_ref().and_then(alias_name);
+        item.anonymous = item.alias() == Some("_");
+        item.cfgs = cfgs_of(extern_crate_item.syntax());
+        item.visibility = visibility_of(extern_crate_item.syntax());
+        item.extern_crate = true;
+        item.location = self.location(extern_crate_item.syntax().text_range());
+        item.macro_use = extern_crate_item
+            .attrs()
+            .any(|attr| attr.simple_name().map_or(false, |name| name == "macro_use"));
+        Some(item)
     }
 
-    fn used_items_in_use_item(&self, use_item: &UseItem) -> Option<Vec<UsedItem>> {
-        self.used_items_in_use_tree(&use_item.use_tree()?)
+    /// The derives named in `#[derive(A, b::C)]`. Derives which `goto_definition` cannot
+    /// resolve, as is common with proc macros, are kept as written and marked unresolved.
+    fn derives_in_attr(&self, attr: &ast::Attr) -> Vec<UsedItem> {
+        let token_tree = match attr.as_simple_call() {
+            Some((name, token_tree)) if name == "derive" => token_tree,
+            _ => return vec![],
+        };
+        let scope = scope_of(attr.syntax());
+
+        // Split the tokens between the parentheses at the commas.
+        let mut paths: Vec<Vec<SyntaxToken>> = vec![vec![]];
+        for token in token_tree
+            .syntax()
+            .children_with_tokens()
+            .filter_map(|element| element.into_token())
+        {
+            match token.kind() {
+                SyntaxKind::COMMA => paths.push(vec![]),
+                SyntaxKind::L_PAREN | SyntaxKind::R_PAREN | SyntaxKind::WHITESPACE => {}
+                _ => paths.last_mut().unwrap().push(token),
+            }
+        }
+
+        let mut result = vec![];
+        for tokens in paths {
+            let last_ident = match tokens.iter().rev().find(|t| t.kind() == SyntaxKind::IDENT) {
+                Some(token) => token,
+                None => continue,
+            };
+            let text: String = tokens.iter().map(|token| token.text().as_str()).collect();
+            let file_position = FilePosition {
+                file_id: self.file_id,
+                offset: last_ident.text_range().start(),
+            };
+            let target = match self.goto_definition(file_position) {
+                Ok(Some(range_info)) => range_info.info.into_iter().next(),
+                _ => None,
+            };
+            let mut item = match target {
+                Some(navigation_target) => self.used_item(&navigation_target),
+                None => {
+                    let mut item = UsedItem::new(
+                        last_ident.text().to_string(),
+                        text,
+                        UsedItemKind::Derive,
+                        None,
+                    );
+                    item.unresolved = true;
+                    item
+                }
+            };
+            item.kind = UsedItemKind::Derive;
+            item.scope = scope.clone();
+            item.location = self.location(attr.syntax().text_range());
+            result.push(item);
+        }
+        result
     }
 
-    fn used_items_in_use_tree(&self, use_tree: &UseTree) -> Option<Vec<UsedItem>> {
+    fn used_items_in_use_tree(&mut self, use_tree: &UseTree) -> Option<Vec<UsedItem>> {
+        if use_tree.has_star() {
+            return self.used_items_in_glob(use_tree);
+        }
+
         match use_tree.use_tree_list() {
             Some(use_tree_list) => {
                 let mut result = vec![];
@@ -175,30 +3301,462 @@ impl<'a> UsedItemResolver<'a> {
                 Some(result)
             }
             None => {
-                let offset = use_tree.syntax().text_range().end();
+                // With an alias (`use foo::Bar as Baz`), the end of the tree is the alias,
+                // so always point at the last segment of the path instead.
+                let path = use_tree.path();
+                let is_self_import = path.as_ref().map_or(false, is_self_path);
+                let offset = match path {
+                    // `use foo::{self}` imports `foo` itself.
+                    Some(_) if is_self_import => parent_use_tree(use_tree)?
+                        .path()?
+                        .syntax()
+                        .text_range()
+                        .end(),
+                    Some(path) => path.syntax().text_range().end(),
+                    None => use_tree.syntax().text_range().end(),
+                };
+                let alias = use_tree.alias().as_ref().and_then(alias_name);
+                let anonymous = alias.as_ref().map_or(false, |alias| alias == "_");
                 let file_position = FilePosition {
                     file_id: self.file_id,
                     offset,
                 };
-                if let Ok(Some(range_info)) = self.analysis.goto_definition(file_position) {
-                    Some(
-                        range_info
-                            .info
+                let location = self.location(use_tree.syntax().text_range());
+                let written = full_path_text(use_tree);
+                let written = written.trim_end_matches("::self");
+                let surface_path = self.workspace.definition_paths.absolute_path(
+                    self.analysis,
+                    self.file_id,
+                    use_tree.syntax(),
+                    written,
+                );
+                match self.goto_definition(file_position) {
+                    Ok(Some(range_info)) if !range_info.info.is_empty() => {
+                        let name = referenced_name(use_tree);
+                        let local_name = alias.clone().or_else(|| name.clone());
+                        let (targets, ambiguous) =
+                            self.disambiguate(range_info.info, name, local_name);
+                        let mut items: Vec<UsedItem> = targets
                             .iter()
-                            .map(UsedItem::from_navigation_target)
-                            .collect(),
-                    )
+                            .map(|navigation_target| {
+                                let mut item = self.used_item(navigation_target);
+                                item.alias = alias.clone();
+                                item.anonymous = anonymous;
+                                item.location = location;
+                                if item.path != surface_path {
+                                    item.surface_path = Some(surface_path.clone());
+                                }
+                                item
+                            })
+                            .collect();
+                        // `self` can only import a module or an enum, not the items of
+                        // the value namespace sharing their name.
+                        if is_self_import {
+                            items.retain(|item| match item.kind {
+                                UsedItemKind::Module | UsedItemKind::Enum => true,
+                                _ => false,
+                            });
+                        }
+                        // Definitions under exclusive cfgs are the same item.
+                        let mut seen = HashSet::new();
+                        items.retain(|item| seen.insert((item.kind.name(), item.path.clone())));
+                        if ambiguous && items.len() > 1 {
+                            self.used_item_map.ambiguous.extend(items);
+                            return Some(vec![]);
+                        }
+                        Some(items)
+                    }
+                    _ if self.workspace.guess_unresolved => {
+                        let name = referenced_name(use_tree)?;
+                        let kind = if is_self_import {
+                            UsedItemKind::Module
+                        } else {
+                            UsedItemKind::guess(&name)
+                        };
+                        let mut item = UsedItem::new(name, written.to_owned(), kind, None);
+                        item.alias = alias;
+                        item.anonymous = anonymous;
+                        item.location = location;
+                        item.unresolved = true;
+                        Some(vec![item])
+                    }
+                    _ => {
+                        self.used_item_map
+                            .unresolved
+                            .insert(full_path_text(use_tree));
+                        None
+                    }
+                }
+            }
+        }
+    }
+
+    /// Narrows down the targets a use tree resolves to, ordered by where they are defined,
+    /// and tells whether several are left which nothing tells apart.
+    ///
+    /// Targets named like the imported segment `name` win, then the macros or the other
+    /// items if the file only uses `local_name` as a macro or only as something else.
+    fn disambiguate(
+        &self,
+        mut targets: Vec<NavigationTarget>,
+        name: Option<String>,
+        local_name: Option<String>,
+    ) -> (Vec<NavigationTarget>, bool) {
+        targets.sort_by_key(|target| (target.file_id().0, target.full_range().start()));
+        targets.dedup_by_key(|target| (target.file_id(), target.full_range()));
+        if targets.len() <= 1 {
+            return (targets, false);
+        }
+
+        if let Some(name) = &name {
+            if targets.iter().any(|target| target.name() == name.as_str()) {
+                targets.retain(|target| target.name() == name.as_str());
+            }
+        }
+        let is_macro = |target: &NavigationTarget| {
+            UsedItemKind::from_syntax_kind(target.kind()) == UsedItemKind::Macro
+        };
+        let macros = targets.iter().filter(|target| is_macro(target)).count();
+        if macros > 0 && macros < targets.len() {
+            let (as_macro, as_other) = local_name
+                .as_ref()
+                .map_or((false, false), |local_name| self.name_usage(local_name));
+            if as_macro != as_other {
+                targets.retain(|target| is_macro(target) == as_macro);
+            }
+        }
+        let ambiguous = targets.len() > 1;
+        (targets, ambiguous)
+    }
+
+    /// Whether the file uses `name` outside of use items as a macro, and as something else.
+    fn name_usage(&self, name: &str) -> (bool, bool) {
+        let source_file = match self.analysis.parse(self.file_id) {
+            Ok(source_file) => source_file,
+            Err(_) => return (false, false),
+        };
+        let mut as_macro = false;
+        let mut as_other = false;
+        for path in source_file
+            .syntax()
+            .descendants()
+            .filter_map(ast::Path::cast)
+        {
+            // An import brings the first segment of a path in scope.
+            if path.qualifier().is_some() || is_in_use_item(path.syntax()) {
+                continue;
+            }
+            let matches = path
+                .segment()
+                .and_then(|segment| segment.name_ref())
+                .map_or(false, |name_ref| name_ref.text() == name);
+            if !matches {
+                continue;
+            }
+            let in_macro_call = path
+                .syntax()
+                .parent()
+                .map_or(false, |parent| parent.kind() == SyntaxKind::MACRO_CALL);
+            if in_macro_call {
+                as_macro = true;
+            } else {
+                as_other = true;
+            }
+        }
+        (as_macro, as_other)
+    }
+
+    fn used_items_in_glob(&mut self, use_tree: &UseTree) -> Option<Vec<UsedItem>> {
+        let mut items = use_tree.path().and_then(|path| self.glob_items(&path));
+        match &mut items {
+            Some(items) => {
+                let location = self.location(use_tree.syntax().text_range());
+                for item in items {
+                    item.location = location;
+                }
+            }
+            None => {
+                self.used_item_map.globs.insert(full_path_text(use_tree));
+            }
+        }
+        items
+    }
+
+    /// The module (or the enum) the path of a glob import points at.
+    fn glob_target(&self, path: &ast::Path) -> Option<NavigationTarget> {
+        let file_position = FilePosition {
+            file_id: self.file_id,
+            offset: path.syntax().text_range().end(),
+        };
+        self.goto_definition(file_position)
+            .ok()??
+            .info
+            .into_iter()
+            .next()
+    }
+
+    /// Enumerates the public items of the module (or the variants of the enum) `path`
+    /// points at.
+    fn glob_items(&self, path: &ast::Path) -> Option<Vec<UsedItem>> {
+        let target = self.glob_target(path)?;
+        self.glob_items_of(&target)
+    }
+
+    /// Enumerates the public items of the module (or the variants of the enum) `target`.
+    fn glob_items_of(&self, target: &NavigationTarget) -> Option<Vec<UsedItem>> {
+        let target_path = self
+            .workspace
+            .definition_paths
+            .path_of(self.analysis, target);
+        let origin = self.workspace.origins.of(target.file_id()).cloned();
+        let version = self.workspace.origins.external_version(target.file_id());
+        let source_file = self.analysis.parse(target.file_id()).ok()?;
+        let node = covering_node(source_file.syntax(), target.full_range());
+
+        let glob_item = |name: String, kind: UsedItemKind| {
+            let path = format!("{}::{}", target_path, name);
+            let mut item = UsedItem::new(name, path, kind, origin.clone());
+            item.via_glob = true;
+            item.version = version.map(str::to_owned);
+            item
+        };
+
+        let mut result = vec![];
+
+        match target.kind() {
+            SyntaxKind::ENUM_DEF => {
+                let enum_def = node.ancestors().find_map(ast::EnumDef::cast)?;
+                let enum_name = enum_def.name()?.text().to_string();
+                for variant in enum_def.variant_list()?.variants() {
+                    if let Some(name) = variant.name() {
+                        let path = format!("{}::{}", target_path, name.text());
+                        let name = format!("{}::{}", enum_name, name.text());
+                        let mut item =
+                            UsedItem::new(name, path, UsedItemKind::EnumVariant, origin.clone());
+                        item.via_glob = true;
+                        result.push(item);
+                    }
+                }
+            }
+            SyntaxKind::SOURCE_FILE | SyntaxKind::MODULE => {
+                let items: Vec<ModuleItem> = if target.kind() == SyntaxKind::SOURCE_FILE {
+                    source_file.items().collect()
                 } else {
-                    None
+                    let module = node.ancestors().find_map(ast::Module::cast)?;
+                    module.item_list()?.items().collect()
+                };
+                for item in items {
+                    if !item
+                        .syntax()
+                        .children()
+                        .any(|child| child.kind() == SyntaxKind::VISIBILITY)
+                    {
+                        continue;
+                    }
+                    match item {
+                        ModuleItem::UseItem(use_item) => {
+                            if self.glob_depth >= MAX_GLOB_DEPTH {
+                                continue;
+                            }
+                            let mut resolver = UsedItemResolver::new(
+                                self.analysis,
+                                self.workspace,
+                                target.file_id(),
+                            );
+                            resolver.glob_depth = self.glob_depth + 1;
+                            resolver.started = self.started;
+                            if let Some(items) = resolver.used_items_in_use_item(&use_item) {
+                                for mut item in items {
+                                    item.via_glob = true;
+                                    result.push(item);
+                                }
+                            }
+                        }
+                        item => {
+                            if let Some(name) = item.syntax().children().find_map(ast::Name::cast) {
+                                let kind = UsedItemKind::from_syntax_kind(item.syntax().kind());
+                                result.push(glob_item(name.text().to_string(), kind));
+                            }
+                        }
+                    }
                 }
             }
+            _ => return None,
+        }
+
+        Some(result)
+    }
+
+    fn used_item(&self, navigation_target: &NavigationTarget) -> UsedItem {
+        let path = self
+            .workspace
+            .definition_paths
+            .path_of(self.analysis, navigation_target);
+        let origin = self
+            .workspace
+            .origins
+            .of(navigation_target.file_id())
+            .cloned();
+        let mut item = UsedItem::from_navigation_target(navigation_target, path, origin);
+        item.defined_in = self
+            .workspace
+            .origins
+            .defined_in(navigation_target.file_id())
+            .cloned();
+        item.version = self
+            .workspace
+            .origins
+            .external_version(navigation_target.file_id())
+            .map(str::to_owned);
+        if let Some((since, note)) = deprecation_of(self.analysis, navigation_target) {
+            item.deprecated = true;
+            item.deprecated_since = since;
+            item.deprecated_note = note;
+        }
+        // Other crates may have attributes named so of their own.
+        if let Some(Origin::Std(_)) = item.origin {
+            item.unstable_feature = unstable_feature_of(self.analysis, navigation_target);
+        }
+        if let Some(enum_name) = parent_enum_name(self.analysis, navigation_target) {
+            item.name = format!("{}::{}", enum_name, item.name);
+        }
+        // Proc macros are functions of the crate defining them, but are used under the
+        // name their attribute gives, from the root of the crate.
+        if let Some((macro_kind, name)) = proc_macro_of(self.analysis, navigation_target) {
+            item.kind = UsedItemKind::Macro;
+            item.macro_kind = Some(macro_kind);
+            item.path = format!(
+                "{}::{}",
+                self.workspace
+                    .definition_paths
+                    .crate_path(self.analysis, navigation_target.file_id()),
+                name
+            );
+            item.name = name;
+        }
+        item
+    }
+}
+
+fn is_self_path(path: &ast::Path) -> bool {
+    if path.qualifier().is_some() {
+        return false;
+    }
+    match path.segment().and_then(|segment| segment.kind()) {
+        Some(ast::PathSegmentKind::SelfKw) => true,
+        _ => false,
+    }
+}
+
+/// The name given by `as alias`, which is `_` for anonymous imports. Raw identifiers are
+/// named without their `r#`, like the names of items.
+fn alias_name(alias: &ast::Alias) -> Option<String> {
+    match alias.name() {
+        Some(name) => Some(name.text().trim_start_matches("r#").to_owned()),
+        // `as _` has no name but an underscore token.
+        None if alias.syntax().text().to_string().trim_end().ends_with('_') => Some("_".to_owned()),
+        None => None,
+    }
+}
+
+/// The name of the last segment of the path `use_tree` imports, that of its parent for
+/// `self`.
+fn referenced_name(use_tree: &UseTree) -> Option<String> {
+    let path = use_tree.path()?;
+    let path = if is_self_path(&path) {
+        parent_use_tree(use_tree)?.path()?
+    } else {
+        path
+    };
+    Some(path.segment()?.name_ref()?.text().to_string())
+}
+
+fn parent_use_tree(use_tree: &UseTree) -> Option<UseTree> {
+    use_tree
+        .syntax()
+        .ancestors()
+        .skip(1)
+        .find_map(UseTree::cast)
+}
+
+/// The textual path of `use_tree`, including the prefixes of the enclosing use trees.
+fn full_path_text(use_tree: &UseTree) -> String {
+    let mut segments: Vec<String> = use_tree
+        .syntax()
+        .ancestors()
+        .filter_map(UseTree::cast)
+        .filter_map(|tree| tree.path())
+        .map(|path| path.syntax().text().to_string())
+        .collect();
+    segments.reverse();
+    if use_tree.has_star() {
+        segments.push("*".to_owned());
+    }
+    segments.join("::")
+}
+
+/// The predicates of the `#[cfg(...)]` attributes of `node` and of the items enclosing it,
+/// outermost first, with normalized whitespace.
+pub(crate) fn cfgs_of(node: &SyntaxNode) -> Vec<String> {
+    let mut ancestors: Vec<SyntaxNode> = node.ancestors().collect();
+    ancestors.reverse();
+    ancestors
+        .iter()
+        .flat_map(|ancestor| ancestor.children().filter_map(ast::Attr::cast))
+        .filter_map(|attr| attr.as_simple_call())
+        .filter(|(name, _)| name == "cfg")
+        .map(|(_, token_tree)| {
+            let text = token_tree.syntax().text().to_string();
+            let text = text.trim();
+            let predicate = if text.starts_with('(') && text.ends_with(')') {
+                &text[1..text.len() - 1]
+            } else {
+                text
+            };
+            normalize_cfg(predicate)
+        })
+        .collect()
+}
+
+/// Spells a cfg predicate the way rustfmt would, so that `feature="a"` and
+/// `feature = "a"` compare equal.
+pub fn normalize_cfg(predicate: &str) -> String {
+    let mut result = String::new();
+    let mut in_string = false;
+    for c in predicate.trim().chars() {
+        match c {
+            '"' => {
+                in_string = !in_string;
+                result.push(c);
+            }
+            _ if in_string => result.push(c),
+            c if c.is_whitespace() => {}
+            '=' => result.push_str(" = "),
+            ',' => result.push_str(", "),
+            c => result.push(c),
         }
     }
+    result.replace(", )", ")").trim_end_matches(", ").to_owned()
 }
 
-fn item_to_use_item(item: ModuleItem) -> Option<UseItem> {
-    match item {
-        ModuleItem::UseItem(use_item) => Some(use_item),
-        _ => None,
+/// The items enclosing a nested use item, e.g. `tests` for an inline `mod tests` or
+/// `tests::helper()` for a function inside it. `None` for top-level use items.
+fn scope_of(node: &SyntaxNode) -> Option<String> {
+    let mut segments = vec![];
+    for ancestor in node.ancestors() {
+        if let Some(module) = ast::Module::cast(ancestor.clone()) {
+            if let Some(name) = module.name() {
+                segments.push(name.text().to_string());
+            }
+        } else if let Some(fn_def) = ast::FnDef::cast(ancestor) {
+            if let Some(name) = fn_def.name() {
+                segments.push(format!("{}()", name.text()));
+            }
+        }
+    }
+    if segments.is_empty() {
+        return None;
     }
+    segments.reverse();
+    Some(segments.join("::"))
 }
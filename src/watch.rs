@@ -0,0 +1,225 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    time::Duration,
+};
+
+use cargo_metadata::Metadata;
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use ra_db::{FileId, SourceDatabaseExt};
+use ra_ide::AnalysisChange;
+
+use crate::{
+    error::Result,
+    options::DumpOptions,
+    origin::{is_member, member_dirs},
+    used_item::{resolve_workspace, CrateMap, LoadedWorkspace},
+};
+
+/// How long a file has to stay untouched before its changes are picked up, so that an
+/// editor saving several files at once triggers a single dump.
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// How often `stop` is checked while no file changes.
+const POLL: Duration = Duration::from_millis(200);
+
+/// Implements `DumpOptions::watch`.
+pub(crate) fn watch<F>(options: &DumpOptions, stop: &AtomicBool, mut on_change: F) -> Result<()>
+where
+    F: FnMut(&CrateMap, Option<&BTreeSet<String>>) -> Result<()>,
+{
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher = notify::watcher(tx, DEBOUNCE).map_err(watch_error)?;
+    let mut workspace = WatchedWorkspace::load(options)?;
+    workspace.watch(&mut watcher)?;
+    on_change(&workspace.dump(options), None)?;
+
+    while !stop.load(Ordering::SeqCst) {
+        let first = match rx.recv_timeout(POLL) {
+            Ok(event) => event,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+
+        let mut changed = HashMap::new();
+        let mut rescan = false;
+        for event in Some(first).into_iter().chain(rx.try_iter()) {
+            match event {
+                DebouncedEvent::Write(path) if is_rust_file(&path) => {
+                    match workspace.files.get(&path) {
+                        Some(&file_id) => {
+                            changed.insert(file_id, path);
+                        }
+                        None => rescan = true,
+                    }
+                }
+                DebouncedEvent::Create(path) | DebouncedEvent::Remove(path)
+                    if is_rust_file(&path) =>
+                {
+                    rescan = true
+                }
+                DebouncedEvent::Rename(from, to) if is_rust_file(&from) || is_rust_file(&to) => {
+                    rescan = true
+                }
+                DebouncedEvent::Rescan => rescan = true,
+                DebouncedEvent::Error(e, _) => return Err(watch_error(e)),
+                _ => {}
+            }
+        }
+
+        if rescan || (!changed.is_empty() && options.keys_by_module()) {
+            workspace.unwatch(&mut watcher);
+            workspace = WatchedWorkspace::load(options)?;
+            workspace.watch(&mut watcher)?;
+            on_change(&workspace.dump(options), None)?;
+        } else if !changed.is_empty() {
+            let files = workspace.update(options, &changed)?;
+            on_change(&workspace.dump(options), Some(&files))?;
+        }
+    }
+    Ok(())
+}
+
+/// A loaded workspace along with the used items of its files, before filtering, which is
+/// kept up to date as files change. Also backs `Server`.
+pub(crate) struct WatchedWorkspace {
+    loaded: LoadedWorkspace,
+    /// The workspace member files, by absolute path.
+    files: HashMap<PathBuf, FileId>,
+    map: CrateMap,
+}
+
+impl WatchedWorkspace {
+    pub(crate) fn load(options: &DumpOptions) -> Result<Self> {
+        let mut loaded = options.load_workspace()?;
+        let config = options.analysis_config_for(&loaded)?;
+        let map = resolve_workspace(
+            &mut loaded.host,
+            &loaded.source_map,
+            Some(&loaded.metadata),
+            &config,
+        )?;
+
+        let db = loaded.host.raw_database();
+        let member_dirs = member_dirs(Some(&loaded.metadata));
+        let mut files = HashMap::new();
+        for (source_root_id, package_root) in &loaded.source_map {
+            if !is_member(package_root, &member_dirs) {
+                continue;
+            }
+            for file_id in db.source_root(*source_root_id).walk() {
+                let path = db.file_relative_path(file_id).to_path(package_root.path());
+                files.insert(path, file_id);
+            }
+        }
+
+        Ok(WatchedWorkspace { loaded, files, map })
+    }
+
+    fn member_roots(&self) -> impl Iterator<Item = &PathBuf> {
+        let member_dirs = member_dirs(Some(&self.loaded.metadata));
+        self.loaded
+            .source_map
+            .values()
+            .filter(move |package_root| is_member(package_root, &member_dirs))
+            .map(|package_root| package_root.path())
+    }
+
+    fn watch(&self, watcher: &mut RecommendedWatcher) -> Result<()> {
+        for root in self.member_roots() {
+            watcher
+                .watch(root, RecursiveMode::Recursive)
+                .map_err(watch_error)?;
+        }
+        Ok(())
+    }
+
+    fn unwatch(&self, watcher: &mut RecommendedWatcher) {
+        for root in self.member_roots() {
+            // The root may well be gone.
+            let _ = watcher.unwatch(root);
+        }
+    }
+
+    /// The workspace member file at `path`, if any.
+    pub(crate) fn file_id(&self, path: &Path) -> Option<FileId> {
+        self.files.get(path).copied()
+    }
+
+    pub(crate) fn metadata(&self) -> &Metadata {
+        &self.loaded.metadata
+    }
+
+    /// Applies the new contents of the `changed` files and resolves them again, returning
+    /// their used items before filtering. The files are not updated in the dump.
+    pub(crate) fn resolve(
+        &mut self,
+        options: &DumpOptions,
+        changed: &HashMap<FileId, PathBuf>,
+    ) -> Result<CrateMap> {
+        let mut change = AnalysisChange::new();
+        for (&file_id, path) in changed {
+            let text = fs::read_to_string(path)?;
+            change.change_file(file_id, Arc::new(text));
+        }
+        self.loaded.host.apply_change(change);
+
+        let mut config = options.analysis_config_for(&self.loaded)?;
+        config.files = Some(changed.keys().copied().collect::<HashSet<_>>());
+        resolve_workspace(
+            &mut self.loaded.host,
+            &self.loaded.source_map,
+            Some(&self.loaded.metadata),
+            &config,
+        )
+    }
+
+    /// Resolves the `changed` files again and updates them in the dump, returning their
+    /// keys.
+    pub(crate) fn update(
+        &mut self,
+        options: &DumpOptions,
+        changed: &HashMap<FileId, PathBuf>,
+    ) -> Result<BTreeSet<String>> {
+        let updated = self.resolve(options, changed)?;
+        let files = updated
+            .files()
+            .map(|(file, _)| file.to_owned())
+            .chain(
+                updated
+                    .diagnostics()
+                    .iter()
+                    .map(|diagnostic| diagnostic.file().to_owned()),
+            )
+            .collect();
+        self.replace_files(updated);
+        Ok(files)
+    }
+
+    /// Replaces the used items of the files `map` has, as returned by `resolve`.
+    pub(crate) fn replace_files(&mut self, map: CrateMap) {
+        self.map.replace_files(map);
+    }
+
+    /// The used items of every file, filtered as `options` asks.
+    pub(crate) fn dump(&self, options: &DumpOptions) -> CrateMap {
+        let mut map = self.map.clone();
+        options.filter(&mut map, &self.loaded.metadata);
+        map
+    }
+}
+
+fn is_rust_file(path: &Path) -> bool {
+    path.extension()
+        .map_or(false, |extension| extension == "rs")
+}
+
+fn watch_error(e: notify::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
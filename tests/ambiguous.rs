@@ -0,0 +1,61 @@
+mod common;
+
+use serde_json::Value;
+
+use crate::common::{paths, Fixture};
+
+const AS_STRUCT: &str = "collision/src/as_struct.rs";
+const AS_MACRO: &str = "collision/src/as_macro.rs";
+const AS_BOTH: &str = "collision/src/as_both.rs";
+const NEVER: &str = "collision/src/never.rs";
+
+fn kinds(items: &Value) -> Vec<&str> {
+    items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["kind"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn the_use_of_the_name_picks_the_macro_or_the_struct() {
+    let fixture = Fixture::new("collision");
+    let files = fixture.dump(&["--no-sysroot"]);
+
+    assert_eq!(paths(&files, AS_STRUCT, "structs"), ["collision::Format"]);
+    assert_eq!(files[AS_STRUCT]["macros"], Value::Null);
+    assert_eq!(files[AS_STRUCT]["ambiguous"], Value::Null);
+
+    assert_eq!(paths(&files, AS_MACRO, "macros"), ["collision::Format"]);
+    assert_eq!(files[AS_MACRO]["macros"][0]["macro_kind"], "bang");
+    assert_eq!(files[AS_MACRO]["structs"], Value::Null);
+    assert_eq!(files[AS_MACRO]["ambiguous"], Value::Null);
+}
+
+#[test]
+fn names_used_both_ways_or_not_at_all_are_ambiguous() {
+    let fixture = Fixture::new("collision");
+    let files = fixture.dump(&["--no-sysroot"]);
+    for file in &[AS_BOTH, NEVER] {
+        // Both candidates, ordered like the sets, and in no other set.
+        assert_eq!(
+            kinds(&files[file]["ambiguous"]),
+            ["struct", "macro"],
+            "{}",
+            file
+        );
+        assert_eq!(files[file]["structs"], Value::Null, "{}", file);
+        assert_eq!(files[file]["macros"], Value::Null, "{}", file);
+    }
+}
+
+#[test]
+fn candidates_do_not_depend_on_the_resolution_order() {
+    let fixture = Fixture::new("collision");
+    let serial = fixture.dump(&["--no-sysroot", "--no-cache", "--jobs", "1"]);
+    for _ in 0..3 {
+        let parallel = fixture.dump(&["--no-sysroot", "--no-cache", "--jobs", "4"]);
+        assert_eq!(parallel, serial);
+    }
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use serde_json::Value;
+
+use crate::common::{names, paths, Fixture};
+
+const SHAPES: &str = "right/src/shapes.rs";
+const RIGHT_LIB: &str = "right/src/lib.rs";
+
+/// The stats of a dump of `fixture`, along with its files.
+fn dump_with_stats(fixture: &Fixture, args: &[&str]) -> (Value, Value) {
+    let mut all_args = vec!["--no-sysroot", "--stats=json"];
+    all_args.extend(args);
+    let mut files = fixture.dump(&all_args);
+    let stats = files["stats"].take();
+    (files, stats)
+}
+
+fn count(stats: &Value, name: &str) -> u64 {
+    stats[name].as_u64().unwrap()
+}
+
+#[test]
+fn only_the_modified_package_is_resolved_again() {
+    let fixture = Fixture::new("members");
+    let (first, stats) = dump_with_stats(&fixture, &[]);
+    let total = stats["files"].as_object().unwrap().len() as u64;
+    assert!(total >= 4);
+    assert_eq!(count(&stats, "cached_files"), 0);
+    let first_calls = count(&stats, "goto_definition_calls");
+    assert!(first_calls > 0);
+
+    let (second, stats) = dump_with_stats(&fixture, &[]);
+    assert_eq!(second, first);
+    assert_eq!(count(&stats, "cached_files"), total);
+
+    fixture.write(
+        SHAPES,
+        "use left::shapes::Square;\n\npub fn side(square: &Square) -> f64 {\n    square.side\n}\n",
+    );
+    let (third, stats) = dump_with_stats(&fixture, &[]);
+    // Both files of `right` are resolved again, those of `left` come from the cache.
+    assert_eq!(count(&stats, "cached_files"), total - 2);
+    assert!(count(&stats, "goto_definition_calls") < first_calls);
+    assert_eq!(paths(&third, SHAPES, "structs"), ["left::shapes::Square"]);
+    assert_eq!(paths(&third, SHAPES, "fns"), Vec::<String>::new());
+    for file in &["left/src/lib.rs", "left/src/shapes.rs", "right/src/lib.rs"] {
+        assert_eq!(third[file], first[file], "{}", file);
+    }
+}
+
+#[test]
+fn a_modified_dependency_resolves_its_dependents_again() {
+    let fixture = Fixture::new("members");
+    let (first, _) = dump_with_stats(&fixture, &[]);
+    assert_eq!(
+        paths(&first, RIGHT_LIB, "structs"),
+        ["left::shapes::Square"]
+    );
+
+    // `right/src/lib.rs` did not change, but the struct it imports is gone.
+    fixture.write(
+        "left/src/shapes.rs",
+        "pub struct Rectangle {\n    pub side: f64,\n}\n",
+    );
+    let (second, stats) = dump_with_stats(&fixture, &[]);
+    assert_eq!(count(&stats, "cached_files"), 0);
+    assert_eq!(paths(&second, RIGHT_LIB, "structs"), Vec::<String>::new());
+    let unresolved = second[RIGHT_LIB]["unresolved"].as_array().unwrap();
+    assert!(
+        unresolved
+            .iter()
+            .any(|path| path.as_str().unwrap().ends_with("Square")),
+        "{:?}",
+        unresolved
+    );
+}
+
+#[test]
+fn no_cache_resolves_everything() {
+    let fixture = Fixture::new("members");
+    let (first, _) = dump_with_stats(&fixture, &[]);
+    let (second, stats) = dump_with_stats(&fixture, &["--no-cache"]);
+    assert_eq!(second, first);
+    assert_eq!(count(&stats, "cached_files"), 0);
+}
+
+#[test]
+fn cache_dir_keeps_the_cache_elsewhere() {
+    let fixture = Fixture::new("members");
+    let dir = fixture.join("elsewhere");
+    let dir_arg = dir.to_str().unwrap();
+    dump_with_stats(&fixture, &["--cache-dir", dir_arg]);
+    assert!(dir.join("cache.json").is_file());
+    assert!(!fixture.join("target").join(".usedump-cache").exists());
+
+    let (_, stats) = dump_with_stats(&fixture, &["--cache-dir", dir_arg]);
+    assert!(count(&stats, "cached_files") > 0);
+    // Nothing was cached where the default cache is kept.
+    let (_, stats) = dump_with_stats(&fixture, &[]);
+    assert_eq!(count(&stats, "cached_files"), 0);
+}
+
+#[test]
+fn other_features_do_not_reuse_the_cache() {
+    let fixture = Fixture::new("features");
+    let extension = "app/src/extension.rs";
+    // The path comes first, as `--features` takes every value after it.
+    let (_, stats) = dump_with_stats(&fixture, &["app", "--no-default-features"]);
+    assert_eq!(count(&stats, "cached_files"), 0);
+
+    // Without the feature, `extra` is no dependency and `extension.rs` was cached with
+    // its import unresolved.
+    let (files, stats) = dump_with_stats(&fixture, &["app", "--all-features"]);
+    assert_eq!(count(&stats, "cached_files"), 0);
+    assert_eq!(paths(&files, extension, "structs"), ["extra::Widget"]);
+    assert_eq!(files[extension]["unresolved"], Value::Null);
+
+    let (_, stats) = dump_with_stats(&fixture, &["app", "--all-features"]);
+    assert!(count(&stats, "cached_files") > 0);
+}
+
+#[test]
+fn another_sysroot_does_not_reuse_the_cache() {
+    let fixture = Fixture::new("kinds");
+    let file = "kinds/src/selves.rs";
+    let mut dump = fixture.dump_envelope(&[]);
+    // The items of the standard library resolve only with its sources.
+    if dump["sysroot"].is_string() {
+        let files = dump["files"].take();
+        assert!(names(&files, file, "structs").contains(&"HashMap".to_owned()));
+
+        // The file was cached with `std` resolved, which it no longer is.
+        let (files, stats) = dump_with_stats(&fixture, &[]);
+        assert_eq!(count(&stats, "cached_files"), 0);
+        assert!(!names(&files, file, "structs").contains(&"HashMap".to_owned()));
+    }
+}
@@ -0,0 +1,181 @@
+//! What the integration tests share: they run the `usedump` binary on copies of the
+//! workspaces under `tests/fixtures`, so that nothing they write ends up in the tree.
+
+// Every test crate uses its own share of the helpers.
+#![allow(dead_code)]
+
+pub mod schema;
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{self, Command, Output},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use serde_json::Value;
+
+static COPIES: AtomicUsize = AtomicUsize::new(0);
+
+/// A copy of a fixture workspace in a temporary directory, removed when dropped.
+pub struct Fixture {
+    dir: PathBuf,
+}
+
+impl Fixture {
+    /// Copies `tests/fixtures/<name>`.
+    pub fn new(name: &str) -> Self {
+        let source = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(name);
+        let dir = env::temp_dir().join(format!(
+            "usedump-test-{}-{}-{}",
+            process::id(),
+            COPIES.fetch_add(1, Ordering::SeqCst),
+            name
+        ));
+        copy_dir(&source, &dir);
+        Fixture { dir }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.dir.join(path)
+    }
+
+    pub fn read(&self, path: &str) -> String {
+        fs::read_to_string(self.join(path)).unwrap()
+    }
+
+    /// Writes `contents` to the file `path` of the fixture, creating its directories.
+    pub fn write(&self, path: &str, contents: &str) {
+        let path = self.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    /// `usedump` run in the fixture, without the user's configuration.
+    pub fn usedump(&self) -> Command {
+        let mut command = usedump();
+        command.current_dir(&self.dir);
+        command
+    }
+
+    /// `usedump` run in the fixture like `usedump`, printing its warnings.
+    pub fn usedump_with_warnings(&self) -> Command {
+        let mut command = usedump_with_warnings();
+        command.current_dir(&self.dir);
+        command
+    }
+
+    /// Dumps the fixture with `args` on top of `dump`, returning the files of the dump.
+    pub fn dump(&self, args: &[&str]) -> Value {
+        self.dump_envelope(args)["files"].take()
+    }
+
+    /// Dumps the fixture like `dump`, returning the whole envelope.
+    pub fn dump_envelope(&self, args: &[&str]) -> Value {
+        let output = self.usedump().arg("dump").args(args).output().unwrap();
+        serde_json::from_str(&success(output)).unwrap()
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// `usedump_with_warnings` with `--quiet`, printing nothing but its output.
+pub fn usedump() -> Command {
+    let mut command = usedump_with_warnings();
+    command.arg("--quiet");
+    command
+}
+
+/// The `usedump` binary cargo built along with the tests, in the parent directory of
+/// `target/<profile>/deps` where they run from, without the variables of the user.
+pub fn usedump_with_warnings() -> Command {
+    let mut dir = env::current_exe().unwrap();
+    dir.pop();
+    if dir.ends_with("deps") {
+        dir.pop();
+    }
+    let mut command = Command::new(dir.join(format!("usedump{}", env::consts::EXE_SUFFIX)));
+    for (name, _) in env::vars_os() {
+        if name.to_string_lossy().starts_with("USEDUMP_") {
+            command.env_remove(name);
+        }
+    }
+    // The same output whenever it runs.
+    command.env("SOURCE_DATE_EPOCH", "0");
+    command
+}
+
+/// The stdout of `output`, which must have succeeded.
+pub fn success(output: Output) -> String {
+    assert!(
+        output.status.success(),
+        "usedump failed with {}: {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// The files of the JSON dump `stdout`, inside its envelope.
+pub fn files(stdout: &str) -> Value {
+    let mut dump: Value = serde_json::from_str(stdout).unwrap();
+    dump["files"].take()
+}
+
+/// The paths of the items of kind `set`, e.g. `structs`, which `file` of `files` imports.
+pub fn paths(files: &Value, file: &str, set: &str) -> Vec<String> {
+    files[file][set]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .map(|item| item["path"].as_str().unwrap().to_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The names of the items of kind `set` which `file` of `files` imports.
+pub fn names(files: &Value, file: &str, set: &str) -> Vec<String> {
+    files[file][set]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .map(|item| item["name"].as_str().unwrap().to_owned())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The item of kind `set` with the path `path` which `file` of `files` imports.
+pub fn item<'a>(files: &'a Value, file: &str, set: &str, path: &str) -> &'a Value {
+    files[file][set]
+        .as_array()
+        .and_then(|items| items.iter().find(|item| item["path"] == path))
+        .unwrap_or_else(|| panic!("`{}` imports no {} `{}`: {}", file, set, path, files[file]))
+}
+
+fn copy_dir(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        let path = entry.path();
+        if path.is_dir() {
+            copy_dir(&path, &to.join(entry.file_name()));
+        } else {
+            fs::copy(&path, to.join(entry.file_name())).unwrap();
+        }
+    }
+}
@@ -0,0 +1,168 @@
+//! A validator of the JSON Schemas the tests check output against.
+
+use serde_json::Value;
+
+/// Checks values against the keywords of JSON Schema draft 7 which `usedump schema` and the
+/// vendored schemas use, failing on any other so that they cannot outgrow it unnoticed.
+pub struct Validator {
+    root: Value,
+}
+
+impl Validator {
+    pub fn new(root: Value) -> Self {
+        Validator { root }
+    }
+
+    /// Why `value` does not match the root schema, nothing when it does.
+    pub fn errors(&self, value: &Value) -> Vec<String> {
+        let mut errors = vec![];
+        self.check(&self.root, value, "", &mut errors);
+        errors
+    }
+
+    fn check(&self, schema: &Value, value: &Value, at: &str, errors: &mut Vec<String>) {
+        let schema = match schema {
+            Value::Bool(true) => return,
+            Value::Bool(false) => return errors.push(format!("{}: no value is allowed", at)),
+            Value::Object(schema) => schema,
+            _ => panic!("invalid schema at {}: {}", at, schema),
+        };
+        for (keyword, argument) in schema {
+            match keyword.as_str() {
+                "$schema" | "$id" | "title" | "description" | "format" | "default" => {}
+                // Only reached through `$ref`.
+                "definitions" => {}
+                "$ref" => {
+                    let pointer = argument.as_str().unwrap().trim_start_matches('#');
+                    let target = self.root.pointer(pointer).expect("dangling $ref");
+                    self.check(target, value, at, errors);
+                }
+                "type" => {
+                    let types: Vec<&str> = match argument {
+                        Value::String(name) => vec![name],
+                        Value::Array(names) => names.iter().map(|n| n.as_str().unwrap()).collect(),
+                        _ => panic!("invalid type at {}", at),
+                    };
+                    if !types.iter().any(|name| has_type(value, name)) {
+                        errors.push(format!("{}: {} is not of type {:?}", at, value, types));
+                    }
+                }
+                "const" => {
+                    if value != argument {
+                        errors.push(format!("{}: {} is not {}", at, value, argument));
+                    }
+                }
+                "enum" => {
+                    if !argument.as_array().unwrap().contains(value) {
+                        errors.push(format!("{}: {} is not one of {}", at, value, argument));
+                    }
+                }
+                "minimum" => {
+                    if let Some(number) = value.as_f64() {
+                        if number < argument.as_f64().unwrap() {
+                            errors.push(format!("{}: {} is below {}", at, number, argument));
+                        }
+                    }
+                }
+                "required" => {
+                    if let Some(object) = value.as_object() {
+                        for name in argument.as_array().unwrap() {
+                            if !object.contains_key(name.as_str().unwrap()) {
+                                errors.push(format!("{}: `{}` is missing", at, name));
+                            }
+                        }
+                    }
+                }
+                "properties" => {}
+                "additionalProperties" => {}
+                "items" => {
+                    if let Some(items) = value.as_array() {
+                        for (i, item) in items.iter().enumerate() {
+                            self.check(argument, item, &format!("{}/{}", at, i), errors);
+                        }
+                    }
+                }
+                "minItems" | "maxItems" => {
+                    if let Some(items) = value.as_array() {
+                        let bound = argument.as_u64().unwrap() as usize;
+                        let fits = if keyword == "minItems" {
+                            items.len() >= bound
+                        } else {
+                            items.len() <= bound
+                        };
+                        if !fits {
+                            errors.push(format!("{}: {} items break {}", at, items.len(), keyword));
+                        }
+                    }
+                }
+                "uniqueItems" => {
+                    if let (Some(items), Some(true)) = (value.as_array(), argument.as_bool()) {
+                        for (i, item) in items.iter().enumerate() {
+                            if items[..i].contains(item) {
+                                errors.push(format!("{}/{}: {} is repeated", at, i, item));
+                            }
+                        }
+                    }
+                }
+                "anyOf" | "oneOf" => {
+                    let matching = argument
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .filter(|schema| {
+                            let mut errors = vec![];
+                            self.check(schema, value, at, &mut errors);
+                            errors.is_empty()
+                        })
+                        .count();
+                    let fits = if keyword == "anyOf" {
+                        matching > 0
+                    } else {
+                        matching == 1
+                    };
+                    if !fits {
+                        errors.push(format!(
+                            "{}: {} of the {} schemas match {}",
+                            at, matching, keyword, value
+                        ));
+                    }
+                }
+                _ => panic!("unsupported keyword `{}` at {}", keyword, at),
+            }
+        }
+        if let Some(object) = value.as_object() {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            let additional = schema.get("additionalProperties");
+            for (name, property) in object {
+                let at = format!("{}/{}", at, name);
+                match properties.and_then(|properties| properties.get(name)) {
+                    Some(schema) => self.check(schema, property, &at, errors),
+                    None => {
+                        if let Some(schema) = additional {
+                            self.check(schema, property, &at, errors);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "integer" => value.is_u64() || value.is_i64(),
+        "number" => value.is_number(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        _ => panic!("unknown type `{}`", name),
+    }
+}
+
+/// Fails with every error when `value` does not match the schema of `validator`.
+pub fn assert_valid(validator: &Validator, value: &Value) {
+    let errors = validator.errors(value);
+    assert!(errors.is_empty(), "{}\n{:#}", errors.join("\n"), value);
+}
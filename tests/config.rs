@@ -0,0 +1,160 @@
+mod common;
+
+use serde_json::Value;
+
+use crate::common::{success, Fixture};
+
+/// Makes every dump of the `members` fixture skip the sysroot, list only structs and come
+/// out in YAML.
+const CONFIG: &str = "format = \"yaml\"\n\n[dump]\nno-sysroot = true\nkind = [\"structs\"]\n";
+
+/// The files of the dump `usedump` printed in `format`.
+fn files(stdout: &str, format: &str) -> Value {
+    let mut dump: Value = match format {
+        "json" => serde_json::from_str(stdout).unwrap(),
+        "yaml" => serde_yaml::from_str(stdout).unwrap(),
+        _ => unreachable!(),
+    };
+    dump["files"].take()
+}
+
+/// The kinds of items `file` lists.
+fn sets(files: &Value, file: &str) -> Vec<String> {
+    files[file]
+        .as_object()
+        .unwrap()
+        .keys()
+        .filter(|key| key.as_str() != "target")
+        .cloned()
+        .collect()
+}
+
+#[test]
+fn config_in_a_parent_directory_of_the_member() {
+    let fixture = Fixture::new("members");
+    fixture.write("usedump.toml", CONFIG);
+    // Run from the member, without a path: the configuration of the workspace root applies.
+    let output = fixture
+        .usedump()
+        .current_dir(fixture.join("right"))
+        .arg("dump")
+        .output()
+        .unwrap();
+    let files = files(&success(output), "yaml");
+    assert_eq!(sets(&files, "right/src/lib.rs"), ["structs"]);
+    assert_eq!(files["right/src/shapes.rs"], Value::Null);
+
+    // The same with the member given as the path.
+    let output = fixture.usedump().args(&["dump", "right"]).output().unwrap();
+    assert_eq!(self::files(&success(output), "yaml"), files);
+}
+
+#[test]
+fn the_command_line_wins_over_the_config() {
+    let fixture = Fixture::new("members");
+    fixture.write("usedump.toml", CONFIG);
+    let output = fixture
+        .usedump()
+        .args(&["--format", "json", "dump", "--kind", "fns"])
+        .output()
+        .unwrap();
+    let files = files(&success(output), "json");
+    assert_eq!(sets(&files, "right/src/lib.rs"), ["fns"]);
+    assert_eq!(sets(&files, "right/src/shapes.rs"), ["fns"]);
+    // `no-sysroot` still comes from the configuration, as the command line leaves it unset.
+    let output = fixture
+        .usedump()
+        .args(&["--format", "json", "dump"])
+        .output()
+        .unwrap();
+    let dump: Value = serde_json::from_str(&success(output)).unwrap();
+    assert_eq!(dump["sysroot"], Value::Null);
+}
+
+#[test]
+fn config_overrides_the_defaults() {
+    let fixture = Fixture::new("members");
+    let output = fixture
+        .usedump()
+        .args(&["dump", "--no-sysroot"])
+        .output()
+        .unwrap();
+    let defaults = files(&success(output), "json");
+    assert_eq!(sets(&defaults, "right/src/lib.rs"), ["fns", "structs"]);
+
+    fixture.write("usedump.toml", CONFIG);
+    let output = fixture.usedump().arg("dump").output().unwrap();
+    let configured = files(&success(output), "yaml");
+    assert_eq!(sets(&configured, "right/src/lib.rs"), ["structs"]);
+}
+
+#[test]
+fn config_path_replaces_the_found_config() {
+    let fixture = Fixture::new("members");
+    fixture.write("usedump.toml", CONFIG);
+    fixture.write(
+        "other.toml",
+        "[dump]\nno-sysroot = true\nkind = [\"fns\"]\n",
+    );
+    let output = fixture
+        .usedump()
+        .args(&["--config", "other.toml", "dump"])
+        .output()
+        .unwrap();
+    let files = files(&success(output), "json");
+    assert_eq!(sets(&files, "right/src/lib.rs"), ["fns"]);
+}
+
+#[test]
+fn unset_keys_of_the_config_are_ignored() {
+    let fixture = Fixture::new("members");
+    fixture.write("usedump.toml", CONFIG);
+    let output = fixture
+        .usedump()
+        .args(&[
+            "--unset",
+            "format",
+            "dump",
+            "--unset",
+            "kind",
+            "--no-sysroot",
+        ])
+        .output()
+        .unwrap();
+    let files = files(&success(output), "json");
+    assert_eq!(sets(&files, "right/src/lib.rs"), ["fns", "structs"]);
+
+    let output = fixture
+        .usedump()
+        .args(&["dump", "--unset", "no-such-key"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no-such-key"));
+}
+
+#[test]
+fn unknown_keys_are_named_in_a_warning() {
+    let fixture = Fixture::new("members");
+    fixture.write(
+        "usedump.toml",
+        "colour = \"red\"\n\n[dump]\nno-sysroot = true\nexclude-sdt = true\n",
+    );
+    let output = fixture
+        .usedump_with_warnings()
+        .arg("dump")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    success(output);
+    assert!(
+        stderr.contains("warning: unknown key `colour`"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("unknown key `dump.exclude-sdt`"),
+        "{}",
+        stderr
+    );
+}
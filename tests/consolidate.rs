@@ -0,0 +1,119 @@
+mod common;
+
+use std::{collections::BTreeMap, env, fs, path::Path};
+
+use serde_json::Value;
+
+use crate::common::{success, Fixture};
+
+/// The files of the `consolidate` fixture which have something to merge, each compared to
+/// `tests/snapshots/consolidate/<file>` once patched.
+const SNAPSHOTS: &[&str] = &[
+    "aliases.rs",
+    "attributes.rs",
+    "globs.rs",
+    "prefixes.rs",
+    "scopes.rs",
+];
+
+/// The patches `usedump consolidate` suggests for the fixture, by file.
+fn patches(fixture: &Fixture) -> BTreeMap<String, Vec<Value>> {
+    let output = fixture
+        .usedump()
+        .args(&["consolidate", "--format", "json"])
+        .output()
+        .unwrap();
+    let consolidations: Value = serde_json::from_str(&success(output)).unwrap();
+    assert_eq!(consolidations["diagnostics"], Value::Null);
+    let mut patches: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    for patch in consolidations["patches"].as_array().unwrap() {
+        let file = patch["file"].as_str().unwrap().to_owned();
+        patches.entry(file).or_default().push(patch.clone());
+    }
+    patches
+}
+
+/// `text` with `patches` applied, which must be ordered and must not overlap.
+fn apply(text: &str, patches: &[Value]) -> String {
+    let mut result = String::new();
+    let mut end = 0;
+    for patch in patches {
+        let range = &patch["range"];
+        let (start, patch_end) = (
+            range[0].as_u64().unwrap() as usize,
+            range[1].as_u64().unwrap() as usize,
+        );
+        assert!(start >= end, "overlapping patches: {:?}", patches);
+        result.push_str(&text[end..start]);
+        result.push_str(patch["replacement"].as_str().unwrap());
+        end = patch_end;
+    }
+    result.push_str(&text[end..]);
+    result
+}
+
+/// `SNAPSHOTS` patched like `consolidate` suggests. `USEDUMP_BLESS=1` rewrites the
+/// snapshots with the current suggestions instead.
+#[test]
+fn suggestions_match_the_snapshots() {
+    let fixture = Fixture::new("consolidate");
+    let patches = patches(&fixture);
+    let files: Vec<&str> = patches.keys().map(String::as_str).collect();
+    let expected: Vec<String> = SNAPSHOTS
+        .iter()
+        .map(|file| format!("consolidate/src/{}", file))
+        .collect();
+    assert_eq!(files, expected);
+
+    let snapshots = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("snapshots")
+        .join("consolidate");
+    let bless = env::var_os("USEDUMP_BLESS").is_some();
+    let mut failures = vec![];
+    for file in SNAPSHOTS {
+        let source = fixture.read(&format!("src/{}", file));
+        let patched = apply(&source, &patches[&format!("consolidate/src/{}", file)]);
+        let snapshot = snapshots.join(file);
+        if bless {
+            fs::write(&snapshot, &patched).unwrap();
+        }
+        if fs::read_to_string(&snapshot).unwrap() != patched {
+            failures.push(format!("--- {}, once patched:\n{}", file, patched));
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "the suggestions differ from tests/snapshots/consolidate, run with USEDUMP_BLESS=1 \
+         to update them:\n{}",
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn patched_files_have_nothing_left_to_merge() {
+    let fixture = Fixture::new("consolidate");
+    for (file, file_patches) in patches(&fixture) {
+        let path = file.trim_start_matches("consolidate/");
+        let patched = apply(&fixture.read(path), &file_patches);
+        fixture.write(path, &patched);
+    }
+    assert!(patches(&fixture).is_empty());
+}
+
+#[test]
+fn text_lists_the_patches_by_line() {
+    let fixture = Fixture::new("consolidate");
+    let output = fixture
+        .usedump()
+        .args(&["consolidate", "--format", "text"])
+        .output()
+        .unwrap();
+    let text = success(output);
+    assert!(text.contains(
+        "consolidate/src/prefixes.rs:1: replace with \
+         `use std::{collections::HashMap, fmt::{self, Debug}};`\n"
+    ));
+    assert!(text.contains("consolidate/src/prefixes.rs:2: remove, merged above\n"));
+    assert!(text.contains("consolidate/src/prefixes.rs:4: remove, merged above\n"));
+}
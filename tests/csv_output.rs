@@ -0,0 +1,94 @@
+mod common;
+
+use usedump::output::{self, CSV_HEADER};
+
+use crate::common::{success, Fixture};
+
+const COMMA: &str = "odd_paths/src/with, comma.rs";
+
+/// The rows of `text` read back with `delimiter`, the header first.
+fn read_rows(text: &str, delimiter: u8) -> Vec<Vec<String>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .from_reader(text.as_bytes());
+    reader
+        .records()
+        .map(|record| record.unwrap().iter().map(str::to_owned).collect())
+        .collect()
+}
+
+fn dump_csv(fixture: &Fixture, args: &[&str]) -> String {
+    let output = fixture
+        .usedump()
+        .args(&["dump", "--no-sysroot", "--format", "csv"])
+        .args(args)
+        .output()
+        .unwrap();
+    success(output)
+}
+
+/// The rows `write_csv` is documented to write for the JSON dump of `fixture`.
+fn expected_rows(fixture: &Fixture) -> Vec<Vec<String>> {
+    let path = fixture.join("dump.json");
+    let output = fixture
+        .usedump()
+        .args(&["dump", "--no-sysroot", "--output"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    success(output);
+    let map = output::read_crate_map(&path).unwrap();
+    let mut rows = vec![CSV_HEADER.iter().map(|field| field.to_string()).collect()];
+    for (file, kind, item) in map.iter() {
+        rows.push(vec![
+            file.to_owned(),
+            kind.name().to_owned(),
+            item.name().to_owned(),
+            item.path().to_owned(),
+            item.origin()
+                .map_or(String::new(), |origin| origin.crate_name().to_owned()),
+            item.alias().unwrap_or_default().to_owned(),
+        ]);
+    }
+    rows
+}
+
+#[test]
+fn csv_reads_back_as_the_map() {
+    let fixture = Fixture::new("odd_paths");
+    let rows = read_rows(&dump_csv(&fixture, &[]), b',');
+    assert_eq!(rows, expected_rows(&fixture));
+    // The paths with commas are quoted rather than split.
+    let disc = rows
+        .iter()
+        .find(|row| row[2] == "Circle" && row[5] == "Disc")
+        .unwrap();
+    assert_eq!(disc[0], COMMA);
+    assert_eq!(disc[3], "odd_paths::shapes::Circle");
+    assert_eq!(disc[4], "odd_paths");
+    assert!(rows
+        .iter()
+        .any(|row| row[0].ends_with("with space/ünïcode.rs")));
+}
+
+#[test]
+fn csv_rows_are_ordered_by_file() {
+    let fixture = Fixture::new("odd_paths");
+    let rows = read_rows(&dump_csv(&fixture, &[]), b',');
+    let files: Vec<&String> = rows[1..].iter().map(|row| &row[0]).collect();
+    let mut sorted = files.clone();
+    sorted.sort();
+    assert_eq!(files, sorted);
+    assert_eq!(read_rows(&dump_csv(&fixture, &[]), b','), rows);
+}
+
+#[test]
+fn csv_delimiter_tab() {
+    let fixture = Fixture::new("odd_paths");
+    let text = dump_csv(&fixture, &["--csv-delimiter", "tab"]);
+    assert!(text.starts_with("file\tkind\tname\tpath\torigin\talias\n"));
+    // A comma needs no quoting between tabs.
+    assert!(text.contains(&format!("\n{}\t", COMMA)));
+    assert_eq!(read_rows(&text, b'\t'), expected_rows(&fixture));
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use serde_json::{json, Value};
+
+use crate::common::{success, Fixture};
+
+/// `usedump deps-audit app` run on the `deps` fixture in `format`, which exits with 1 as
+/// it finds misplaced dependencies.
+fn audit(fixture: &Fixture, format: &str) -> String {
+    let output = fixture
+        .usedump()
+        .args(&["deps-audit", "app", "--format", format])
+        .output()
+        .unwrap();
+    assert_eq!(
+        output.status.code(),
+        Some(1),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn misplaced_dependencies_are_found() {
+    let fixture = Fixture::new("deps");
+    let audit: Value = serde_json::from_str(&audit(&fixture, "json")).unwrap();
+    // `shapes` is imported by both the library and a test, and `mock` only under
+    // `#[cfg(test)]`, which is where they belong.
+    assert_eq!(
+        audit,
+        json!({
+            "dev_dependencies_in_non_test_code": {"app": {"checks": ["app/src/lib.rs"]}},
+            "test_only_dependencies": {"app": ["colors"]},
+            "non_build_dependencies_in_build_scripts": {"app": {"shapes": ["app/build.rs"]}}
+        })
+    );
+}
+
+#[test]
+fn text_tells_what_to_move() {
+    let fixture = Fixture::new("deps");
+    let text = audit(&fixture, "text");
+    assert_eq!(
+        text,
+        "app: dev-dependency `checks` is imported outside of tests in app/src/lib.rs\n\
+         app: dependency `colors` is only imported by tests, consider making it a \
+         dev-dependency\n\
+         app: `shapes` is imported by the build script in app/build.rs, but is not a \
+         build-dependency\n"
+    );
+}
+
+#[test]
+fn well_placed_dependencies_are_not_reported() {
+    let fixture = Fixture::new("deps");
+    fixture.write(
+        "app/Cargo.toml",
+        &fixture
+            .read("app/Cargo.toml")
+            .replace(
+                "[dev-dependencies]\n",
+                "[dev-dependencies]\ncolors = { path = \"../colors\" }\n",
+            )
+            .replace(
+                "[dependencies]\n",
+                "[dependencies]\nchecks = { path = \"../checks\" }\n",
+            )
+            .replace(
+                "[build-dependencies]\n",
+                "[build-dependencies]\nshapes = { path = \"../shapes\" }\n",
+            ),
+    );
+    let output = fixture
+        .usedump()
+        .args(&["deps-audit", "app", "--format", "json"])
+        .output()
+        .unwrap();
+    assert_eq!(success(output).trim(), "{}");
+}
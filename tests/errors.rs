@@ -0,0 +1,98 @@
+mod common;
+
+use serde_json::Value;
+
+use crate::common::{paths, success, Fixture};
+
+const BROKEN: &str = "broken/src/broken.rs";
+const FINE: &str = "broken/src/fine.rs";
+
+#[test]
+fn files_with_syntax_errors_do_not_stop_the_dump() {
+    let fixture = Fixture::new("broken");
+    let files = fixture.dump(&["--no-sysroot"]);
+    assert_eq!(paths(&files, FINE, "structs"), ["broken::Point"]);
+    assert_eq!(files[FINE]["has_syntax_errors"], Value::Null);
+    // The parser recovers, so the use item before the garbage is still listed.
+    assert_eq!(paths(&files, BROKEN, "structs"), ["broken::Point"]);
+    assert_eq!(files[BROKEN]["has_syntax_errors"], true);
+    assert!(files[BROKEN]["syntax_errors"].as_u64().unwrap() > 0);
+    assert_eq!(files["diagnostics"], Value::Null);
+}
+
+#[test]
+fn deny_syntax_errors_fails_after_the_dump() {
+    let fixture = Fixture::new("broken");
+    let output = fixture
+        .usedump()
+        .args(&["dump", "--no-sysroot", "--deny-syntax-errors"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(3));
+    let files = common::files(&String::from_utf8(output.stdout).unwrap());
+    assert_eq!(paths(&files, FINE, "structs"), ["broken::Point"]);
+    assert_eq!(files[BROKEN]["has_syntax_errors"], true);
+}
+
+#[test]
+fn syntax_errors_are_counted_from_the_cache_and_in_streams() {
+    let fixture = Fixture::new("broken");
+    let count = fixture.dump(&["--no-sysroot"])[BROKEN]["syntax_errors"].clone();
+    assert!(count.as_u64().unwrap() > 0);
+    // The second dump takes the file from the cache.
+    let files = fixture.dump(&["--no-sysroot"]);
+    assert_eq!(files[BROKEN]["syntax_errors"], count);
+
+    let output = fixture
+        .usedump()
+        .args(&["dump", "--no-sysroot", "--format", "ndjson"])
+        .output()
+        .unwrap();
+    let stdout = success(output);
+    let broken: Value = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .find(|entry| entry["file"] == BROKEN)
+        .unwrap();
+    assert_eq!(broken["items"]["syntax_errors"], count);
+}
+
+/// A timeout of zero makes every file which imports anything fail like a panic inside
+/// rust-analyzer would.
+fn dump_failing_files(fixture: &Fixture, strict: bool) -> std::process::Output {
+    let mut command = fixture.usedump();
+    command.args(&["dump", "--no-sysroot", "--no-cache", "--file-timeout", "0"]);
+    if strict {
+        command.arg("--strict");
+    }
+    command.output().unwrap()
+}
+
+#[test]
+fn failed_files_are_reported_as_diagnostics() {
+    let fixture = Fixture::new("broken");
+    let files = common::files(&success(dump_failing_files(&fixture, false)));
+    let diagnostics = files["diagnostics"].as_array().unwrap();
+    let failed: Vec<&str> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic["file"].as_str().unwrap())
+        .collect();
+    assert!(
+        failed.contains(&BROKEN) && failed.contains(&FINE),
+        "{:?}",
+        failed
+    );
+    for diagnostic in diagnostics {
+        assert!(diagnostic["error"].as_str().unwrap().contains("timed out"));
+    }
+    assert_eq!(files[FINE], Value::Null);
+}
+
+#[test]
+fn strict_fails_on_diagnostics_after_the_dump() {
+    let fixture = Fixture::new("broken");
+    let output = dump_failing_files(&fixture, true);
+    assert_eq!(output.status.code(), Some(3));
+    let files = common::files(&String::from_utf8(output.stdout).unwrap());
+    assert!(!files["diagnostics"].as_array().unwrap().is_empty());
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use std::process::Output;
+
+use crate::common::Fixture;
+
+const FINDINGS: i32 = 1;
+const USAGE_ERROR: i32 = 2;
+const ANALYSIS_ERROR: i32 = 3;
+
+fn code(output: &Output) -> i32 {
+    output.status.code().expect("usedump was killed")
+}
+
+fn run(fixture: &Fixture, args: &[&str]) -> Output {
+    fixture.usedump().args(args).output().unwrap()
+}
+
+#[test]
+fn success_is_zero() {
+    let fixture = Fixture::new("golden");
+    assert_eq!(code(&run(&fixture, &["dump", "--no-sysroot"])), 0);
+    assert_eq!(
+        code(&run(
+            &fixture,
+            &["dump", "--no-sysroot", "--check-unresolved"]
+        )),
+        0
+    );
+    let output = run(&fixture, &["dump", "--no-sysroot", "--output", "a.json"]);
+    assert_eq!(code(&output), 0);
+    assert_eq!(code(&run(&fixture, &["diff", "a.json", "a.json"])), 0);
+    assert_eq!(code(&run(&fixture, &["schema"])), 0);
+}
+
+#[test]
+fn findings_are_one() {
+    let fixture = Fixture::new("unused");
+    assert_eq!(code(&run(&fixture, &["unused"])), FINDINGS);
+
+    // Without a sysroot, the imports of `std` cannot be resolved.
+    let fixture = Fixture::new("kinds");
+    let output = run(&fixture, &["dump", "--no-sysroot", "--check-unresolved"]);
+    assert_eq!(code(&output), FINDINGS);
+    // The dump is still written.
+    assert!(!output.stdout.is_empty());
+    let output = run(&fixture, &["dump", "--no-sysroot", "--fail-on-unresolved"]);
+    assert_eq!(code(&output), FINDINGS);
+}
+
+#[test]
+fn differing_dumps_are_one() {
+    let fixture = Fixture::new("golden");
+    assert_eq!(
+        code(&run(
+            &fixture,
+            &["dump", "--no-sysroot", "--output", "a.json"]
+        )),
+        0
+    );
+    fixture.write(
+        "src/lib.rs",
+        "mod shapes;\n\npub use crate::shapes::Circle;\n",
+    );
+    assert_eq!(
+        code(&run(
+            &fixture,
+            &["dump", "--no-sysroot", "--output", "b.json"]
+        )),
+        0
+    );
+    let output = run(&fixture, &["diff", "a.json", "b.json"]);
+    assert_eq!(code(&output), FINDINGS);
+    assert!(!output.stdout.is_empty());
+}
+
+#[test]
+fn misplaced_dependencies_are_one() {
+    let fixture = Fixture::new("same_names");
+    assert_eq!(code(&run(&fixture, &["deps-audit", "app"])), 0);
+    // `beta` stays a normal dependency, but only a test imports it.
+    fixture.write(
+        "app/src/lib.rs",
+        "use alpha::Thing;\n\npub fn thing() -> Thing {\n    Thing\n}\n",
+    );
+    fixture.write("app/tests/beta.rs", "use beta::things::Thing;\n");
+    assert_eq!(code(&run(&fixture, &["deps-audit", "app"])), FINDINGS);
+}
+
+#[test]
+fn usage_errors_are_two() {
+    let fixture = Fixture::new("golden");
+    for args in &[
+        &["dump", "--frobnicate"][..],
+        &["frobnicate"],
+        &["dump", "--format", "xml"],
+        &["dump", "--no-sysroot", "--package", "nowhere"],
+        &["dump", "--no-sysroot", "--include", "src/[lib.rs"],
+        &["dump", "--format", "ndjson", "--summary"],
+        &["who-uses", "--regex", "(", "--no-sysroot"],
+    ] {
+        let output = run(&fixture, args);
+        assert_eq!(
+            code(&output),
+            USAGE_ERROR,
+            "{:?}: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    // Help and the version are not errors.
+    assert_eq!(code(&run(&fixture, &["--help"])), 0);
+    assert_eq!(code(&run(&fixture, &["--version"])), 0);
+}
+
+#[test]
+fn analysis_errors_are_three() {
+    let fixture = Fixture::new("golden");
+    // A project whose manifest cannot be read.
+    fixture.write("invalid/Cargo.toml", "[package\n");
+    let output = run(&fixture, &["dump", "--no-sysroot", "invalid"]);
+    assert_eq!(code(&output), ANALYSIS_ERROR);
+    // A dump which cannot be read.
+    fixture.write("garbage.json", "{ not a dump");
+    assert_eq!(
+        code(&run(&fixture, &["diff", "garbage.json", "garbage.json"])),
+        ANALYSIS_ERROR
+    );
+
+    let fixture = Fixture::new("broken");
+    let output = run(&fixture, &["dump", "--no-sysroot", "--deny-syntax-errors"]);
+    assert_eq!(code(&output), ANALYSIS_ERROR);
+    let output = run(
+        &fixture,
+        &[
+            "dump",
+            "--no-sysroot",
+            "--no-cache",
+            "--strict",
+            "--file-timeout",
+            "0",
+        ],
+    );
+    assert_eq!(code(&output), ANALYSIS_ERROR);
+}
+
+#[test]
+fn help_documents_the_codes() {
+    let fixture = Fixture::new("golden");
+    let output = run(&fixture, &["--help"]);
+    let help = String::from_utf8(output.stdout).unwrap();
+    assert!(help.contains("EXIT CODES:"), "{}", help);
+    for code in &["0", "1", "2", "3", "130"] {
+        assert!(
+            help.lines().any(|line| line.trim_start().starts_with(code)),
+            "{}",
+            code
+        );
+    }
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use serde_json::Value;
+
+use crate::common::{paths, Fixture};
+
+const LIB: &str = "app/src/lib.rs";
+const EXTENSION: &str = "app/src/extension.rs";
+
+fn dump(fixture: &Fixture, features: &[&str]) -> Value {
+    // The path comes first, as `--features` takes every value after it.
+    let mut args = vec!["--no-sysroot", "app"];
+    args.extend(features);
+    fixture.dump(&args)
+}
+
+#[test]
+fn default_features() {
+    let fixture = Fixture::new("features");
+    let files = dump(&fixture, &[]);
+    assert_eq!(
+        paths(&files, LIB, "structs"),
+        ["app::shapes::Fancy", "app::shapes::Plain"]
+    );
+    assert_eq!(files[EXTENSION], Value::Null);
+}
+
+#[test]
+fn no_default_features_drop_what_they_gate() {
+    let fixture = Fixture::new("features");
+    let files = dump(&fixture, &["--no-default-features"]);
+    assert_eq!(paths(&files, LIB, "structs"), ["app::shapes::Plain"]);
+    assert_eq!(files[EXTENSION], Value::Null);
+    assert_eq!(files[LIB]["unresolved"], Value::Null);
+}
+
+#[test]
+fn all_features_enable_the_optional_dependency() {
+    let fixture = Fixture::new("features");
+    let all = dump(&fixture, &["--all-features"]);
+    assert_eq!(
+        paths(&all, LIB, "structs"),
+        ["app::shapes::Fancy", "app::shapes::Plain"]
+    );
+    assert_eq!(paths(&all, EXTENSION, "structs"), ["extra::Widget"]);
+    assert_eq!(
+        all[EXTENSION]["structs"][0]["origin"],
+        serde_json::json!({"kind": "external", "crate": "extra"})
+    );
+    assert_ne!(all, dump(&fixture, &["--no-default-features"]));
+}
+
+#[test]
+fn features_enable_the_optional_dependency() {
+    let fixture = Fixture::new("features");
+    let files = dump(&fixture, &["--no-default-features", "--features", "extra"]);
+    assert_eq!(paths(&files, LIB, "structs"), ["app::shapes::Plain"]);
+    assert_eq!(paths(&files, EXTENSION, "structs"), ["extra::Widget"]);
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use serde_json::Value;
+
+use crate::common::{success, Fixture};
+
+fn keys(files: &Value) -> Vec<&str> {
+    files
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .filter(|key| key.ends_with(".rs"))
+        .collect()
+}
+
+#[test]
+fn include_keeps_the_matching_files() {
+    let fixture = Fixture::new("imports");
+    // Without a slash, a pattern matches at any depth.
+    let files = fixture.dump(&[
+        "--no-sysroot",
+        "--include",
+        "globs.rs",
+        "--include",
+        "scopes.rs",
+    ]);
+    assert_eq!(
+        keys(&files),
+        ["imports/src/globs.rs", "imports/src/scopes.rs"]
+    );
+}
+
+#[test]
+fn exclude_drops_the_matching_files() {
+    let fixture = Fixture::new("imports");
+    let files = fixture.dump(&["--no-sysroot", "--exclude", "scopes.rs"]);
+    let keys = keys(&files);
+    assert!(!keys.contains(&"imports/src/scopes.rs"), "{:?}", keys);
+    assert!(keys.contains(&"imports/src/aliases.rs"), "{:?}", keys);
+    assert!(keys.contains(&"imports/src/globs.rs"), "{:?}", keys);
+}
+
+#[test]
+fn exclude_wins_over_include() {
+    let fixture = Fixture::new("imports");
+    let files = fixture.dump(&[
+        "--no-sysroot",
+        "--include",
+        "src/**",
+        "--exclude",
+        "/src/[cg]*.rs",
+    ]);
+    let keys = keys(&files);
+    assert!(keys.contains(&"imports/src/aliases.rs"), "{:?}", keys);
+    assert!(keys.contains(&"imports/src/scopes.rs"), "{:?}", keys);
+    assert!(!keys.contains(&"imports/src/cfgs.rs"), "{:?}", keys);
+    assert!(!keys.contains(&"imports/src/globs.rs"), "{:?}", keys);
+}
+
+#[test]
+fn files_without_imports_are_left_out() {
+    let fixture = Fixture::new("imports");
+    let files = fixture.dump(&["--no-sysroot"]);
+    let keys = keys(&files);
+    assert!(!keys.contains(&"imports/src/shapes.rs"), "{:?}", keys);
+    assert!(keys.contains(&"imports/src/aliases.rs"), "{:?}", keys);
+}
+
+#[test]
+fn include_empty_keeps_files_without_imports() {
+    let fixture = Fixture::new("imports");
+    let files = fixture.dump(&["--no-sysroot", "--include-empty"]);
+    let keys = keys(&files);
+    assert!(keys.contains(&"imports/src/shapes.rs"), "{:?}", keys);
+    assert!(keys.contains(&"imports/src/aliases.rs"), "{:?}", keys);
+    // An empty file has none of the sets rather than empty ones.
+    let shapes = files["imports/src/shapes.rs"].as_object().unwrap();
+    assert!(shapes.keys().all(|key| key == "target"), "{:?}", shapes);
+}
+
+#[test]
+fn include_empty_keeps_files_emptied_by_kind() {
+    let fixture = Fixture::new("imports");
+    // `visibility.rs` imports no traits.
+    let files = fixture.dump(&["--no-sysroot", "--kind", "traits"]);
+    assert!(!keys(&files).contains(&"imports/src/visibility.rs"));
+    let files = fixture.dump(&["--no-sysroot", "--kind", "traits", "--include-empty"]);
+    assert!(keys(&files).contains(&"imports/src/visibility.rs"));
+}
+
+#[test]
+fn include_empty_applies_to_ndjson() {
+    let fixture = Fixture::new("imports");
+    let streamed = |args: &[&str]| -> Vec<String> {
+        let output = fixture
+            .usedump()
+            .args(&["dump", "--no-sysroot", "--format", "ndjson"])
+            .args(args)
+            .output()
+            .unwrap();
+        success(output)
+            .lines()
+            .filter_map(|line| {
+                let entry: Value = serde_json::from_str(line).unwrap();
+                entry["file"].as_str().map(str::to_owned)
+            })
+            .collect()
+    };
+    let shapes = "imports/src/shapes.rs".to_owned();
+    assert!(!streamed(&[]).contains(&shapes));
+    assert!(streamed(&["--include-empty"]).contains(&shapes));
+}
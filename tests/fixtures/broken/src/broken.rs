@@ -0,0 +1,3 @@
+use crate::Point;
+
+fn broken(point: Point -> {{ ] let let ;;; impl
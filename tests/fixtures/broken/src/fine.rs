@@ -0,0 +1,5 @@
+use crate::Point;
+
+pub fn origin() -> Point {
+    Point
+}
@@ -0,0 +1,4 @@
+mod broken;
+mod fine;
+
+pub struct Point;
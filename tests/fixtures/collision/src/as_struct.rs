@@ -0,0 +1,5 @@
+use crate::Format;
+
+pub fn format() -> Format {
+    Format
+}
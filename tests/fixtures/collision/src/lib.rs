@@ -0,0 +1,14 @@
+mod as_both;
+mod as_macro;
+mod as_struct;
+mod never;
+
+/// Makes a `Format`, like `format!` next to `fmt::Formatter`, but sharing its name.
+#[macro_export]
+macro_rules! Format {
+    () => {
+        $crate::Format
+    };
+}
+
+pub struct Format;
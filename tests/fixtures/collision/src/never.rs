@@ -0,0 +1 @@
+use crate::Format;
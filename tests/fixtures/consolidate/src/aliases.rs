@@ -0,0 +1,10 @@
+use crate::shapes::Circle as Round;
+use crate::shapes::{Circle, Square};
+use crate::shapes::Circle;
+use std::io as stdio;
+use std::io;
+
+pub fn shapes(_: Circle, _: Round, _: Square) -> io::Result<()> {
+    stdio::stdout();
+    Ok(())
+}
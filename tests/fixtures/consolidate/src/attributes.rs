@@ -0,0 +1,13 @@
+#[cfg(test)]
+use std::fmt;
+#[cfg(test)]
+use std::fmt::Display;
+use std::fmt::Write;
+pub(crate) use std::io;
+pub(crate) use std::io::Read;
+#[cfg(all(unix, not(test)))]
+use std::env;
+#[cfg(all( unix, not(test) ))]
+use std::env::args;
+use std::{/* not merged */ mem, rc::Rc};
+use std::rc::Weak;
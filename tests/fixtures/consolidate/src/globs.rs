@@ -0,0 +1,4 @@
+use std::io::prelude::*;
+use ::std::fmt;
+use std::io::{self, BufRead};
+use std::fmt::Debug;
@@ -0,0 +1,6 @@
+mod aliases;
+mod attributes;
+mod globs;
+mod prefixes;
+mod scopes;
+pub mod shapes;
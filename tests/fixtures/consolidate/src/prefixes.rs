@@ -0,0 +1,8 @@
+use std::fmt;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fmt::Debug;
+
+pub fn show(_: &dyn Debug, _: &HashMap<u8, u8>) -> fmt::Result {
+    Ok(())
+}
@@ -0,0 +1,16 @@
+use crate::shapes::Circle;
+
+pub struct Thing;
+
+mod inner {
+    use super::Thing;
+    use crate::shapes;
+    use super::Circle;
+
+    fn body() {
+        use std::mem;
+        use std::ptr;
+    }
+}
+
+use crate::shapes::Square;
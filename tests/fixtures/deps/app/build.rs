@@ -0,0 +1,6 @@
+use builder::Builder;
+use shapes::Square;
+
+fn main() {
+    let _ = (Builder, Square);
+}
@@ -0,0 +1,16 @@
+use checks::Check;
+use shapes::Square;
+
+pub fn square() -> (Square, Check) {
+    (Square, Check)
+}
+
+#[cfg(test)]
+mod tests {
+    use mock::Mock;
+
+    #[test]
+    fn mocks() {
+        let _ = Mock;
+    }
+}
@@ -0,0 +1,7 @@
+use colors::Red;
+use shapes::Square;
+
+#[test]
+fn red_square() {
+    let _ = (Red, Square);
+}
@@ -0,0 +1,5 @@
+use extra::Widget;
+
+pub fn widget() -> Widget {
+    Widget
+}
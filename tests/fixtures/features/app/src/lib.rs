@@ -0,0 +1,7 @@
+#[cfg(feature = "extra")]
+mod extension;
+mod shapes;
+
+#[cfg(feature = "fancy")]
+pub use crate::shapes::Fancy;
+pub use crate::shapes::Plain;
@@ -0,0 +1,3 @@
+pub struct Plain;
+
+pub struct Fancy;
@@ -0,0 +1,3 @@
+// Stands for `prost_build::compile_protos`, which the tests replace by writing its output
+// to the `OUT_DIR` of a build themselves.
+fn main() {}
@@ -0,0 +1,11 @@
+pub mod shapes;
+
+pub mod proto {
+    include!(concat!(env!("OUT_DIR"), "/shapes.v1.rs"));
+}
+
+use crate::shapes::Unit;
+
+pub fn unit() -> Unit {
+    Unit
+}
@@ -0,0 +1,8 @@
+mod shapes;
+
+pub use crate::shapes::Circle;
+use crate::shapes::UNIT;
+
+pub fn unit_circle() -> Circle {
+    Circle { radius: UNIT }
+}
@@ -0,0 +1,11 @@
+use crate::unit_circle;
+
+pub struct Circle {
+    pub radius: f64,
+}
+
+pub const UNIT: f64 = 1.0;
+
+pub fn is_unit(circle: &Circle) -> bool {
+    circle.radius == unit_circle().radius
+}
@@ -0,0 +1,5 @@
+use crate::shapes::area;
+#[cfg(test)]
+use crate::shapes::Circle;
+#[cfg(any(test, doc))]
+use crate::shapes::Direction;
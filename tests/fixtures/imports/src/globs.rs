@@ -0,0 +1,10 @@
+use crate::shapes::Direction::*;
+use crate::shapes::*;
+
+pub fn facing(circle: &Circle) -> Direction {
+    if area(circle) > 1.0 {
+        North
+    } else {
+        South
+    }
+}
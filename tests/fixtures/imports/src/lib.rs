@@ -0,0 +1,6 @@
+mod aliases;
+mod cfgs;
+mod globs;
+mod scopes;
+mod shapes;
+mod visibility;
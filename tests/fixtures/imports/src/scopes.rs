@@ -0,0 +1,33 @@
+use crate::shapes::Circle;
+
+pub fn unit_area() -> f64 {
+    use crate::shapes::area;
+
+    area(&Circle { radius: 1.0 })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shapes::Direction;
+
+    #[test]
+    fn faces_north() {
+        let _ = Direction::North;
+    }
+}
+
+pub mod inner {
+    pub fn twice(x: f64) -> f64 {
+        x * 2.0
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::shapes::Direction::South;
+
+        #[test]
+        fn faces_south() {
+            let _ = South;
+        }
+    }
+}
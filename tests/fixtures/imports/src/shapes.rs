@@ -0,0 +1,16 @@
+pub struct Circle {
+    pub radius: f64,
+}
+
+pub enum Direction {
+    North,
+    South,
+}
+
+pub fn area(circle: &Circle) -> f64 {
+    circle.radius * circle.radius * secret()
+}
+
+fn secret() -> f64 {
+    2.0
+}
@@ -0,0 +1,4 @@
+pub use crate::shapes::Circle;
+pub(crate) use crate::shapes::Direction;
+pub(in crate) use crate::shapes::area;
+pub(super) use crate::shapes::Direction::North;
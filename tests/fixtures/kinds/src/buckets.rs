@@ -0,0 +1,6 @@
+use crate::defs::{Bits, Meters, ORIGIN};
+
+pub fn origin() -> Meters {
+    let bits = Bits { float: 0.0 };
+    unsafe { bits.float as Meters + ORIGIN }
+}
@@ -0,0 +1,15 @@
+pub type Meters = f64;
+
+pub static ORIGIN: Meters = 0.0;
+
+pub union Bits {
+    pub int: u32,
+    pub float: f32,
+}
+
+pub enum Direction {
+    North,
+    South,
+    East,
+    West,
+}
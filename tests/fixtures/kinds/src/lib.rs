@@ -0,0 +1,5 @@
+pub mod defs;
+pub mod shapes;
+mod buckets;
+mod selves;
+mod variants;
@@ -0,0 +1,7 @@
+use crate::shapes::{self, Circle};
+use std::collections::{self, HashMap};
+
+pub fn circles() -> HashMap<&'static str, Circle> {
+    let _ = shapes::Circle;
+    collections::HashMap::new()
+}
@@ -0,0 +1,17 @@
+use crate::defs::Direction::{self, East, North, West};
+use std::cmp::Ordering::{Greater, Less};
+
+pub fn turn(direction: Direction) -> Direction {
+    match direction {
+        North => East,
+        _ => West,
+    }
+}
+
+pub fn flip(less: bool) -> std::cmp::Ordering {
+    if less {
+        Greater
+    } else {
+        Less
+    }
+}
@@ -0,0 +1,12 @@
+use helpers::shout;
+use shapes_derive::{polygon, shaped, Shape};
+
+#[shaped]
+#[derive(Shape)]
+pub struct Square;
+
+polygon!();
+
+pub fn quiet() -> &'static str {
+    shout!("hello")
+}
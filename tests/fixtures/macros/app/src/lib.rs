@@ -0,0 +1,8 @@
+#[macro_use]
+extern crate helpers;
+
+mod kinds;
+
+pub fn loud() -> &'static str {
+    shout!("hello")
+}
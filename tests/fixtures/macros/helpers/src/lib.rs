@@ -0,0 +1,6 @@
+#[macro_export]
+macro_rules! shout {
+    ($text:expr) => {
+        $text
+    };
+}
@@ -0,0 +1,12 @@
+mod shapes;
+
+use crate::shapes::Shape as Figure;
+use crate::shapes::Sides;
+use crate::shapes::{
+    area, circle, diamond, ellipse, hexagon, kite, octagon, pentagon, rhombus, square, triangle,
+};
+
+pub enum Polygon {
+    Convex,
+    Concave,
+}
@@ -0,0 +1,21 @@
+use crate::Polygon;
+
+pub struct Shape {
+    pub polygon: Polygon,
+}
+
+pub trait Sides {
+    fn sides(&self) -> usize;
+}
+
+pub fn area() {}
+pub fn circle() {}
+pub fn diamond() {}
+pub fn ellipse() {}
+pub fn hexagon() {}
+pub fn kite() {}
+pub fn octagon() {}
+pub fn pentagon() {}
+pub fn rhombus() {}
+pub fn square() {}
+pub fn triangle() {}
@@ -0,0 +1,9 @@
+pub mod shapes;
+
+use left::{shapes::Square, unit_square};
+
+pub fn double_square() -> Square {
+    Square {
+        side: unit_square().side * 2.0,
+    }
+}
@@ -0,0 +1,5 @@
+use left::unit_square as unit;
+
+pub fn unit_side() -> f64 {
+    unit().side
+}
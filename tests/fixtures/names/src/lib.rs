@@ -0,0 +1,8 @@
+mod shapes;
+
+pub use crate::shapes::{Circle, Shape};
+use crate::shapes::{area, UNIT};
+
+pub fn unit_area() -> f64 {
+    area(&Circle { radius: UNIT })
+}
@@ -0,0 +1,13 @@
+pub trait Shape {}
+
+pub struct Circle {
+    pub radius: f64,
+}
+
+impl Shape for Circle {}
+
+pub const UNIT: f64 = 1.0;
+
+pub fn area(circle: &Circle) -> f64 {
+    circle.radius * circle.radius
+}
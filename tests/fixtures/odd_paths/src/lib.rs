@@ -0,0 +1,8 @@
+#[path = "with, comma.rs"]
+mod comma;
+mod empty;
+mod shapes;
+#[path = "with space/ünïcode.rs"]
+mod unicode;
+
+pub use crate::shapes::Circle as Round;
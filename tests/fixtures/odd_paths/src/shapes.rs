@@ -0,0 +1,7 @@
+pub struct Circle;
+
+pub const UNIT: f64 = 1.0;
+
+pub fn unit() -> Circle {
+    Circle
+}
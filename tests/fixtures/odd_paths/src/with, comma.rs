@@ -0,0 +1,6 @@
+use crate::shapes::{unit, Circle as Disc, UNIT};
+use crate::unicode::Größe as Size;
+
+pub fn disc() -> (Disc, f64, Size) {
+    (unit(), UNIT, Size)
+}
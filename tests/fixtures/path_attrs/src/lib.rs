@@ -0,0 +1,9 @@
+#[path = "sys/linux.rs"]
+mod imp;
+mod shapes;
+
+mod outer {
+    // Relative to `src/outer`, where the module's own file would be.
+    #[path = "handles.rs"]
+    mod helper;
+}
@@ -0,0 +1,5 @@
+use crate::imp::Handle;
+
+pub fn handle(handle: Handle) -> Handle {
+    handle
+}
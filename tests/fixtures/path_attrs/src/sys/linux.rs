@@ -0,0 +1,3 @@
+use crate::shapes::Square;
+
+pub struct Handle(pub Square);
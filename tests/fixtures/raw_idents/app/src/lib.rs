@@ -0,0 +1,11 @@
+mod local;
+
+use crate::local::r#match as r#loop;
+use keywords::r#type;
+use keywords::r#type::{r#match, Kind as r#struct};
+
+pub fn all() -> (r#type::Kind, r#struct) {
+    r#loop();
+    r#match();
+    (r#type::Kind, r#struct)
+}
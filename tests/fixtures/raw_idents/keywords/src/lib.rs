@@ -0,0 +1,5 @@
+pub mod r#type {
+    pub struct Kind;
+
+    pub fn r#match() {}
+}
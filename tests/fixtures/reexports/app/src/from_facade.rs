@@ -0,0 +1 @@
+use facade::Square;
@@ -0,0 +1 @@
+use inner::shapes::Square;
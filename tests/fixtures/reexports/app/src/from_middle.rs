@@ -0,0 +1 @@
+use middle::Square;
@@ -0,0 +1,9 @@
+mod from_facade;
+mod from_inner;
+mod from_middle;
+
+use facade::Circle;
+
+pub fn unit() -> Circle {
+    Circle
+}
@@ -0,0 +1,2 @@
+pub use inner::shapes::Circle;
+pub use middle::Square;
@@ -0,0 +1,4 @@
+pub mod shapes {
+    pub struct Circle;
+    pub struct Square;
+}
@@ -0,0 +1,7 @@
+pub mod shapes;
+
+use crate::shapes::Square;
+
+pub fn unit_square() -> Square {
+    Square { side: 1.0 }
+}
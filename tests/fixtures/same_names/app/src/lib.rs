@@ -0,0 +1,6 @@
+use alpha::Thing;
+use beta::things::Thing as OtherThing;
+
+pub fn both() -> (Thing, OtherThing) {
+    (Thing, OtherThing)
+}
@@ -0,0 +1,3 @@
+pub mod things {
+    pub struct Thing;
+}
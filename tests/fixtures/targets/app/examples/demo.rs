@@ -0,0 +1,5 @@
+use demo::Demo;
+
+fn main() {
+    let _ = Demo;
+}
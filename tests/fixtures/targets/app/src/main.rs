@@ -0,0 +1,5 @@
+use colors::Red;
+
+fn main() {
+    let _ = Red;
+}
@@ -0,0 +1,8 @@
+mod common;
+
+use checks::Check;
+
+#[test]
+fn checks() {
+    let _ = (Check, common::square());
+}
@@ -0,0 +1,5 @@
+use app::Square;
+
+pub fn square() -> Square {
+    Square
+}
@@ -0,0 +1,7 @@
+use crate::shapes::unit as one;
+use crate::shapes::Circle as Round;
+use crate::shapes::Named as _;
+
+pub fn round() -> Round {
+    Round
+}
@@ -0,0 +1,5 @@
+use crate::shapes::{unit, Area, Circle, Square};
+
+pub fn side_of(square: &Square) -> f64 {
+    square.side
+}
@@ -0,0 +1,4 @@
+mod aliases;
+mod imports;
+mod methods;
+mod shapes;
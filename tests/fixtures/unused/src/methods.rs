@@ -0,0 +1,9 @@
+use crate::shapes::{Area, Named as _, Square};
+
+pub fn area_of(square: &Square) -> f64 {
+    square.area()
+}
+
+pub fn name_of(square: &Square) -> &'static str {
+    square.name()
+}
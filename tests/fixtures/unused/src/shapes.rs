@@ -0,0 +1,29 @@
+pub trait Area {
+    fn area(&self) -> f64;
+}
+
+pub trait Named {
+    fn name(&self) -> &'static str;
+}
+
+pub struct Square {
+    pub side: f64,
+}
+
+impl Area for Square {
+    fn area(&self) -> f64 {
+        self.side * self.side
+    }
+}
+
+impl Named for Square {
+    fn name(&self) -> &'static str {
+        "square"
+    }
+}
+
+pub struct Circle;
+
+pub fn unit() -> f64 {
+    1.0
+}
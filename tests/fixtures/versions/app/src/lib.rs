@@ -0,0 +1,6 @@
+use foo_v1::Widget as OldWidget;
+use foo_v2::{make, Widget};
+
+pub fn both() -> (OldWidget, Widget) {
+    (OldWidget, make())
+}
@@ -0,0 +1,5 @@
+pub struct Widget;
+
+pub fn make() -> Widget {
+    Widget
+}
@@ -0,0 +1,79 @@
+mod common;
+
+use std::{env, fs, path::Path};
+
+use serde_json::Value;
+
+use crate::common::{success, Fixture};
+
+/// Dumps the `golden` fixture in `format` and compares it to `tests/golden/golden.<format>`,
+/// the root of the workspace written `$ROOT`. YAML and TOML are compared once parsed, so
+/// that the layout their serializers pick does not matter, only what they say.
+///
+/// `USEDUMP_BLESS=1` rewrites the golden files with the current output instead.
+fn check_golden(format: &str) -> Value {
+    let fixture = Fixture::new("golden");
+    let output = fixture
+        .usedump()
+        .args(&["dump", "--no-sysroot", "--no-envelope", "--pretty"])
+        .args(&["--format", format])
+        .output()
+        .unwrap();
+    let text = success(output);
+    let mut dump = parse(format, &text);
+    let root = dump["root"].as_str().unwrap().to_owned();
+    assert_eq!(
+        Path::new(&root).canonicalize().unwrap(),
+        fixture.path().canonicalize().unwrap()
+    );
+    dump["root"] = Value::from("$ROOT");
+
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(format!("golden.{}", format));
+    if env::var_os("USEDUMP_BLESS").is_some() {
+        fs::write(&golden, text.replace(&root, "$ROOT")).unwrap();
+    }
+    let expected = parse(format, &fs::read_to_string(&golden).unwrap());
+    assert_eq!(
+        dump,
+        expected,
+        "the {} dump differs from {}, run with USEDUMP_BLESS=1 to update it:\n{}",
+        format,
+        golden.display(),
+        text
+    );
+    dump
+}
+
+fn parse(format: &str, text: &str) -> Value {
+    match format {
+        "json" => serde_json::from_str(text).unwrap(),
+        "yaml" => serde_yaml::from_str(text).unwrap(),
+        "toml" => toml::from_str(text).unwrap(),
+        _ => unreachable!("no golden file in {}", format),
+    }
+}
+
+#[test]
+fn json_matches_the_golden_file() {
+    check_golden("json");
+}
+
+#[test]
+fn yaml_matches_the_golden_file() {
+    check_golden("yaml");
+}
+
+#[test]
+fn toml_matches_the_golden_file() {
+    check_golden("toml");
+}
+
+#[test]
+fn formats_say_the_same() {
+    let json = check_golden("json");
+    assert_eq!(check_golden("yaml"), json);
+    assert_eq!(check_golden("toml"), json);
+}
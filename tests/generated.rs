@@ -0,0 +1,124 @@
+mod common;
+
+use serde_json::Value;
+
+use crate::common::{paths, Fixture};
+
+const LIB: &str = "protos/src/lib.rs";
+const GENERATED: &str = "protos/OUT_DIR/shapes.v1.rs";
+
+/// What prost writes for a `shapes.v1` package, give or take the derives.
+const SHAPES_V1: &str = "\
+use crate::shapes::Unit;
+use std::collections::HashMap;
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Circle {
+    #[prost(double, tag = \"1\")]
+    pub radius: f64,
+}
+
+pub struct Registry {
+    pub circles: HashMap<String, Circle>,
+    pub unit: Unit,
+}
+";
+
+/// The `generated` fixture with `shapes.v1.rs` in the `OUT_DIR` of a build, as if its build
+/// script ran.
+fn built_fixture() -> Fixture {
+    let fixture = Fixture::new("generated");
+    fixture.write(
+        "target/debug/build/protos-0123456789abcdef/out/shapes.v1.rs",
+        SHAPES_V1,
+    );
+    // Not the `OUT_DIR` of a build, its name lacking the hash.
+    fixture.write(
+        "target/debug/build/protos-stale/out/shapes.v1.rs",
+        "use nowhere::Stale;\n",
+    );
+    fixture
+}
+
+fn dump(fixture: &Fixture, mode: &str) -> Value {
+    let output = fixture
+        .usedump()
+        // Wherever the tests are built, the fixture is built in its own target directory.
+        .env("CARGO_TARGET_DIR", fixture.join("target"))
+        .args(&["dump", "--no-sysroot", "--generated", mode])
+        .output()
+        .unwrap();
+    common::files(&common::success(output))
+}
+
+/// The names of the items `file` of `files` imports, of every kind.
+fn all_names(files: &Value, file: &str) -> Vec<String> {
+    let mut names: Vec<String> = files[file]
+        .as_object()
+        .unwrap()
+        .values()
+        .filter_map(Value::as_array)
+        .flatten()
+        .filter_map(|item| item["name"].as_str())
+        .map(str::to_owned)
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn generated_files_are_included_by_default() {
+    let fixture = built_fixture();
+    let files = dump(&fixture, "include");
+    assert_eq!(all_names(&files, GENERATED), ["HashMap", "Unit"]);
+    // Listed from their syntax only, as rust-analyzer does not load them.
+    let unit = files[GENERATED]["structs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|item| item["name"] == "Unit")
+        .unwrap();
+    assert_eq!(unit["path"], "crate::shapes::Unit");
+    assert_eq!(unit["unresolved"], true);
+    assert_eq!(files["generated"], Value::Null);
+    assert_eq!(paths(&files, LIB, "structs"), ["protos::shapes::Unit"]);
+    assert_eq!(
+        fixture
+            .usedump()
+            .env("CARGO_TARGET_DIR", fixture.join("target"))
+            .args(&["dump", "--no-sysroot"])
+            .output()
+            .map(|output| common::files(&common::success(output)))
+            .unwrap(),
+        files
+    );
+}
+
+#[test]
+fn generated_files_are_skipped() {
+    let fixture = built_fixture();
+    let files = dump(&fixture, "skip");
+    assert_eq!(files[GENERATED], Value::Null);
+    assert_eq!(files["generated"], Value::Null);
+    assert_eq!(paths(&files, LIB, "structs"), ["protos::shapes::Unit"]);
+}
+
+#[test]
+fn generated_files_are_kept_apart() {
+    let fixture = built_fixture();
+    let files = dump(&fixture, "separate");
+    assert_eq!(files[GENERATED], Value::Null);
+    assert_eq!(
+        all_names(&files["generated"], GENERATED),
+        ["HashMap", "Unit"]
+    );
+    assert_eq!(paths(&files, LIB, "structs"), ["protos::shapes::Unit"]);
+}
+
+#[test]
+fn nothing_is_included_before_a_build() {
+    let fixture = Fixture::new("generated");
+    let files = dump(&fixture, "include");
+    assert_eq!(files[GENERATED], Value::Null);
+    assert_eq!(paths(&files, LIB, "structs"), ["protos::shapes::Unit"]);
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use serde_json::Value;
+
+use crate::common::{success, Fixture};
+
+/// The payload of the `<script id="data">` block of `page`, parsed.
+fn data(page: &str) -> Value {
+    let start = r#"<script id="data" type="application/json">"#;
+    let data = &page[page.find(start).expect("no data block") + start.len()..];
+    let data = &data[..data.find("</script>").expect("unterminated data block")];
+    serde_json::from_str(data).unwrap()
+}
+
+#[test]
+fn the_report_is_a_whole_page() {
+    let fixture = Fixture::new("members");
+    let output = fixture
+        .usedump()
+        .args(&["dump", "--no-sysroot", "--format", "html"])
+        .output()
+        .unwrap();
+    let page = success(output);
+    assert!(page.starts_with("<!DOCTYPE html>"), "{}", page);
+    assert!(page.trim_end().ends_with("</html>"), "{}", page);
+    assert!(!page.contains("/*DATA*/"), "{}", page);
+    // The data block and the script, nothing in the data closing either early.
+    assert_eq!(page.matches("<script").count(), 2, "{}", page);
+    assert_eq!(page.matches("</script>").count(), 2, "{}", page);
+
+    let data = data(&page);
+    let items: Vec<(&str, &str)> = data["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| (item[0].as_str().unwrap(), item[1].as_str().unwrap()))
+        .collect();
+    assert!(
+        items.contains(&("Square", "left::shapes::Square")),
+        "{:?}",
+        items
+    );
+    assert!(
+        items.contains(&("unit_square", "left::unit_square")),
+        "{:?}",
+        items
+    );
+    assert_eq!(data["packages"]["right"].as_object().unwrap().len(), 2);
+}
@@ -0,0 +1,185 @@
+mod common;
+
+use crate::common::{item, names, paths, Fixture};
+
+#[test]
+fn aliases_are_kept_along_the_resolved_paths() {
+    let fixture = Fixture::new("imports");
+    let dump = fixture.dump_envelope(&["--resolve-reexports", "both"]);
+    let files = &dump["files"];
+    let file = "imports/src/aliases.rs";
+    let geometry = item(files, file, "modules", "imports::shapes");
+    assert_eq!(geometry["alias"], "geometry");
+    // Imported through the alias, the item is resolved all the same.
+    let circle = item(files, file, "structs", "imports::shapes::Circle");
+    assert!(circle["alias"].is_null());
+    // The items of the standard library resolve only with its sources.
+    if dump["sysroot"].is_string() {
+        let map = files[file]["structs"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|item| item["name"] == "HashMap")
+            .unwrap();
+        assert_eq!(map["alias"], "Map");
+        assert_eq!(map["surface_path"], "std::collections::HashMap");
+        assert!(map["path"]
+            .as_str()
+            .unwrap()
+            .starts_with("std::collections::"));
+        assert_eq!(map["origin"]["kind"], "std");
+    } else {
+        assert_eq!(paths(files, file, "structs"), ["imports::shapes::Circle"]);
+    }
+}
+
+#[test]
+fn globs_expand_to_the_public_items() {
+    let fixture = Fixture::new("imports");
+    let files = fixture.dump(&["--no-sysroot"]);
+    let file = "imports/src/globs.rs";
+    assert_eq!(paths(&files, file, "structs"), ["imports::shapes::Circle"]);
+    assert_eq!(paths(&files, file, "enums"), ["imports::shapes::Direction"]);
+    // Not the private `secret`.
+    assert_eq!(paths(&files, file, "fns"), ["imports::shapes::area"]);
+    assert_eq!(
+        paths(&files, file, "enum_variants"),
+        [
+            "imports::shapes::Direction::North",
+            "imports::shapes::Direction::South"
+        ]
+    );
+    assert_eq!(
+        names(&files, file, "enum_variants"),
+        ["Direction::North", "Direction::South"]
+    );
+    for set in &["structs", "enums", "fns", "enum_variants"] {
+        for item in files[file][set].as_array().unwrap() {
+            assert_eq!(item["via_glob"], true, "{}", item);
+        }
+    }
+    assert!(files[file]["globs"].is_null());
+}
+
+#[test]
+fn nested_imports_belong_to_the_enclosing_file() {
+    let fixture = Fixture::new("imports");
+    let files = fixture.dump(&["--no-sysroot"]);
+    let file = "imports/src/scopes.rs";
+    // No key of their own for the function or the module.
+    let keys: Vec<&String> = files
+        .as_object()
+        .unwrap()
+        .keys()
+        .filter(|key| key.contains("scopes"))
+        .collect();
+    assert_eq!(keys, [file]);
+    let circle = item(&files, file, "structs", "imports::shapes::Circle");
+    assert!(circle["scope"].is_null());
+    let area = item(&files, file, "fns", "imports::shapes::area");
+    assert_eq!(area["scope"], "unit_area()");
+    let direction = item(&files, file, "enums", "imports::shapes::Direction");
+    assert_eq!(direction["scope"], "tests");
+    assert_eq!(direction["cfgs"], serde_json::json!(["test"]));
+    let south = item(
+        &files,
+        file,
+        "enum_variants",
+        "imports::shapes::Direction::South",
+    );
+    assert_eq!(south["scope"], "inner::tests");
+    assert_eq!(south["cfgs"], serde_json::json!(["test"]));
+}
+
+#[test]
+fn exclude_tests_drops_the_test_modules() {
+    let fixture = Fixture::new("imports");
+    let files = fixture.dump(&["--no-sysroot", "--exclude-tests"]);
+    let file = "imports/src/scopes.rs";
+    assert_eq!(paths(&files, file, "structs"), ["imports::shapes::Circle"]);
+    assert_eq!(paths(&files, file, "fns"), ["imports::shapes::area"]);
+    assert!(files[file]["enums"].is_null());
+    assert!(files[file]["enum_variants"].is_null());
+}
+
+#[test]
+fn only_tests_keeps_the_test_modules() {
+    let fixture = Fixture::new("imports");
+    let files = fixture.dump(&["--no-sysroot", "--only-tests"]);
+    let file = "imports/src/scopes.rs";
+    assert_eq!(paths(&files, file, "enums"), ["imports::shapes::Direction"]);
+    assert_eq!(
+        paths(&files, file, "enum_variants"),
+        ["imports::shapes::Direction::South"]
+    );
+    assert!(files[file]["structs"].is_null());
+    assert!(files[file]["fns"].is_null());
+    assert!(files["imports/src/aliases.rs"]["modules"].is_null());
+}
+
+#[test]
+fn cfg_filter_keeps_the_imports_under_the_predicate() {
+    let fixture = Fixture::new("imports");
+    let file = "imports/src/cfgs.rs";
+    let files = fixture.dump(&["--no-sysroot"]);
+    let circle = item(&files, file, "structs", "imports::shapes::Circle");
+    assert_eq!(circle["cfgs"], serde_json::json!(["test"]));
+    let direction = item(&files, file, "enums", "imports::shapes::Direction");
+    assert_eq!(direction["cfgs"], serde_json::json!(["any(test, doc)"]));
+    assert!(item(&files, file, "fns", "imports::shapes::area")["cfgs"].is_null());
+
+    let files = fixture.dump(&["--no-sysroot", "--cfg-filter", "test"]);
+    assert_eq!(paths(&files, file, "structs"), ["imports::shapes::Circle"]);
+    assert!(files[file]["enums"].is_null());
+    assert!(files[file]["fns"].is_null());
+
+    // Predicates are compared once their whitespace is normalized.
+    let files = fixture.dump(&["--no-sysroot", "--cfg-filter", "any(test,doc)"]);
+    assert_eq!(paths(&files, file, "enums"), ["imports::shapes::Direction"]);
+    assert!(files[file]["structs"].is_null());
+}
+
+#[test]
+fn negated_cfg_filter_drops_the_imports_under_the_predicate() {
+    let fixture = Fixture::new("imports");
+    let file = "imports/src/cfgs.rs";
+    let files = fixture.dump(&["--no-sysroot", "--cfg-filter", "!test"]);
+    assert_eq!(paths(&files, file, "fns"), ["imports::shapes::area"]);
+    assert_eq!(paths(&files, file, "enums"), ["imports::shapes::Direction"]);
+    assert!(files[file]["structs"].is_null());
+}
+
+#[test]
+fn restricted_use_items_are_no_reexports() {
+    let fixture = Fixture::new("imports");
+    let file = "imports/src/visibility.rs";
+    let files = fixture.dump(&["--no-sysroot"]);
+    let visibilities = [
+        ("structs", "imports::shapes::Circle", "pub"),
+        ("enums", "imports::shapes::Direction", "pub(crate)"),
+        ("fns", "imports::shapes::area", "pub(in crate)"),
+        (
+            "enum_variants",
+            "imports::shapes::Direction::North",
+            "pub(super)",
+        ),
+    ];
+    for (set, path, visibility) in &visibilities {
+        assert_eq!(item(&files, file, set, path)["visibility"], *visibility);
+    }
+
+    let files = fixture.dump(&["--no-sysroot", "--only-reexports"]);
+    assert_eq!(paths(&files, file, "structs"), ["imports::shapes::Circle"]);
+    for set in &["enums", "fns", "enum_variants"] {
+        assert!(files[file][set].is_null(), "{}", files[file]);
+    }
+
+    let files = fixture.dump(&["--no-sysroot", "--no-reexports"]);
+    assert!(files[file]["structs"].is_null(), "{}", files[file]);
+    assert_eq!(paths(&files, file, "enums"), ["imports::shapes::Direction"]);
+    assert_eq!(paths(&files, file, "fns"), ["imports::shapes::area"]);
+    assert_eq!(
+        paths(&files, file, "enum_variants"),
+        ["imports::shapes::Direction::North"]
+    );
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::common::{paths, Fixture};
+
+fn dump_with_jobs(fixture: &Fixture, jobs: &str) -> Value {
+    fixture.dump(&["--no-sysroot", "--no-cache", "--jobs", jobs])
+}
+
+#[test]
+fn jobs_do_not_change_the_dump() {
+    let fixture = Fixture::new("members");
+    let serial = dump_with_jobs(&fixture, "1");
+    assert_eq!(
+        paths(&serial, "right/src/lib.rs", "structs"),
+        ["left::shapes::Square"]
+    );
+    assert_eq!(
+        paths(&serial, "left/src/lib.rs", "structs"),
+        ["left::shapes::Square"]
+    );
+    for jobs in &["2", "4", "16"] {
+        assert_eq!(dump_with_jobs(&fixture, jobs), serial, "--jobs {}", jobs);
+    }
+    // The files come in the same order too, not only the same map.
+    assert_eq!(
+        serde_json::to_string(&dump_with_jobs(&fixture, "4")).unwrap(),
+        serde_json::to_string(&serial).unwrap()
+    );
+}
+
+/// Adds `count` modules to each member, every one of them importing from the next, so that
+/// resolving takes long enough to be timed.
+fn grow(fixture: &Fixture, count: usize) {
+    for member in &["left", "right"] {
+        let mut lib = fixture.read(&format!("{}/src/lib.rs", member));
+        for i in 0..count {
+            lib.push_str(&format!("pub mod generated{};\n", i));
+            fixture.write(
+                &format!("{}/src/generated{}.rs", member, i),
+                &format!(
+                    "use std::collections::HashMap;\n\
+                     use crate::generated{next}::Item{next};\n\n\
+                     pub struct Item{i};\n\n\
+                     pub fn items() -> HashMap<u32, Item{next}> {{\n    \
+                         let mut items = HashMap::new();\n    \
+                         items.insert({i}, Item{next});\n    \
+                         items\n\
+                     }}\n",
+                    i = i,
+                    next = (i + 1) % count,
+                ),
+            );
+        }
+        fixture.write(&format!("{}/src/lib.rs", member), &lib);
+    }
+}
+
+fn time_with_jobs(fixture: &Fixture, jobs: &str) -> (Duration, Value) {
+    let start = Instant::now();
+    let dump = dump_with_jobs(fixture, jobs);
+    (start.elapsed(), dump)
+}
+
+/// Run with `cargo test --release -- --ignored` on a machine with several CPUs.
+#[test]
+#[ignore]
+fn jobs_resolve_files_faster() {
+    let threads = rayon::current_num_threads();
+    assert!(threads > 1, "only one CPU to run on");
+    let fixture = Fixture::new("members");
+    grow(&fixture, 300);
+
+    let (serial, serial_dump) = time_with_jobs(&fixture, "1");
+    let (parallel, parallel_dump) = time_with_jobs(&fixture, &threads.to_string());
+    println!("--jobs 1: {:?}, --jobs {}: {:?}", serial, threads, parallel);
+    assert_eq!(parallel_dump, serial_dump);
+    assert!(
+        parallel < serial,
+        "--jobs {} took {:?}, --jobs 1 {:?}",
+        threads,
+        parallel,
+        serial
+    );
+}
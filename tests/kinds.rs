@@ -0,0 +1,46 @@
+mod common;
+
+use crate::common::{names, paths, Fixture};
+
+#[test]
+fn self_imports_are_modules() {
+    let fixture = Fixture::new("kinds");
+    let dump = fixture.dump_envelope(&[]);
+    let files = &dump["files"];
+    let file = "kinds/src/selves.rs";
+    assert!(paths(files, file, "modules").contains(&"kinds::shapes".to_owned()));
+    assert!(paths(files, file, "structs").contains(&"kinds::shapes::Circle".to_owned()));
+    // The items of the standard library resolve only with its sources.
+    if dump["sysroot"].is_string() {
+        assert!(names(files, file, "modules").contains(&"collections".to_owned()));
+        assert!(names(files, file, "structs").contains(&"HashMap".to_owned()));
+    }
+}
+
+#[test]
+fn type_aliases_statics_and_unions_have_their_own_sets() {
+    let fixture = Fixture::new("kinds");
+    let files = fixture.dump(&["--no-sysroot"]);
+    let file = "kinds/src/buckets.rs";
+    assert_eq!(paths(&files, file, "type_aliases"), ["kinds::defs::Meters"]);
+    assert_eq!(paths(&files, file, "statics"), ["kinds::defs::ORIGIN"]);
+    assert_eq!(paths(&files, file, "unions"), ["kinds::defs::Bits"]);
+    assert!(files[file]["others"].is_null());
+}
+
+#[test]
+fn enum_variants_are_named_after_their_enum() {
+    let fixture = Fixture::new("kinds");
+    let dump = fixture.dump_envelope(&[]);
+    let files = &dump["files"];
+    let file = "kinds/src/variants.rs";
+    assert_eq!(paths(files, file, "enums"), ["kinds::defs::Direction"]);
+    let variants = names(files, file, "enum_variants");
+    for variant in &["Direction::East", "Direction::North", "Direction::West"] {
+        assert!(variants.contains(&variant.to_string()), "{:?}", variants);
+    }
+    if dump["sysroot"].is_string() {
+        assert!(variants.contains(&"Ordering::Greater".to_owned()));
+        assert!(variants.contains(&"Ordering::Less".to_owned()));
+    }
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use serde_json::json;
+use usedump::{output, UsedItemKind};
+
+use crate::common::{names, Fixture};
+
+const LIB: &str = "names/src/lib.rs";
+
+#[test]
+fn items_have_a_kind() {
+    let fixture = Fixture::new("names");
+    let files = fixture.dump(&["--no-sysroot"]);
+    let circle = &files[LIB]["structs"][0];
+    assert_eq!(circle["name"], "Circle");
+    assert_eq!(circle["path"], "names::shapes::Circle");
+    assert_eq!(circle["kind"], "struct");
+    assert_eq!(files[LIB]["traits"][0]["kind"], "trait");
+    assert_eq!(files[LIB]["fns"][0]["kind"], "fn");
+    assert_eq!(files[LIB]["consts"][0]["kind"], "const");
+}
+
+#[test]
+fn legacy_names_keep_only_the_names() {
+    let fixture = Fixture::new("names");
+    let structured = fixture.dump(&["--no-sysroot"]);
+    let legacy = fixture.dump(&["--no-sysroot", "--legacy-names"]);
+    for set in &["traits", "structs", "fns", "consts"] {
+        assert_eq!(
+            legacy[LIB][set],
+            json!(names(&structured, LIB, set)),
+            "{}",
+            set
+        );
+    }
+    assert_eq!(legacy[LIB]["structs"], json!(["Circle"]));
+}
+
+#[test]
+fn structured_items_read_back() {
+    let fixture = Fixture::new("names");
+    let path = fixture.join("dump.json");
+    let status = fixture
+        .usedump()
+        .args(&["dump", "--no-sysroot", "--output"])
+        .arg(&path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let map = output::read_crate_map(&path).unwrap();
+    let structs = map.get(LIB).unwrap().get(UsedItemKind::Struct);
+    let circle = structs.iter().next().unwrap();
+    assert_eq!(circle.name(), "Circle");
+    assert_eq!(circle.path(), "names::shapes::Circle");
+    assert_eq!(circle.kind(), UsedItemKind::Struct);
+
+    let written: serde_json::Value = serde_json::from_str(&fixture.read("dump.json")).unwrap();
+    assert_eq!(
+        serde_json::to_value(map.without_root()).unwrap(),
+        written["files"]
+    );
+}
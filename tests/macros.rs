@@ -0,0 +1,48 @@
+mod common;
+
+use crate::common::{item, paths, Fixture};
+
+const LIB: &str = "app/src/lib.rs";
+
+#[test]
+fn macro_use_extern_crates_are_modules() {
+    let fixture = Fixture::new("macros");
+    let files = fixture.dump(&["--no-sysroot", "app"]);
+    assert_eq!(paths(&files, LIB, "modules"), ["helpers"]);
+    let helpers = item(&files, LIB, "modules", "helpers");
+    assert_eq!(helpers["name"], "helpers");
+    assert_eq!(helpers["extern_crate"], true);
+    assert_eq!(helpers["macro_use"], true);
+    assert_eq!(
+        helpers["origin"],
+        serde_json::json!({"kind": "external", "crate": "helpers"})
+    );
+}
+
+#[test]
+fn macros_of_every_kind_are_told_apart() {
+    let fixture = Fixture::new("macros");
+    let files = fixture.dump(&["--no-sysroot", "app"]);
+    let file = "app/src/kinds.rs";
+    let macros = [
+        ("helpers::shout", "bang", "helpers"),
+        ("shapes_derive::Shape", "derive", "shapes_derive"),
+        ("shapes_derive::polygon", "bang", "shapes_derive"),
+        ("shapes_derive::shaped", "attribute", "shapes_derive"),
+    ];
+    assert_eq!(
+        paths(&files, file, "macros"),
+        macros.iter().map(|(path, _, _)| *path).collect::<Vec<_>>()
+    );
+    for (path, macro_kind, krate) in &macros {
+        let used = item(&files, file, "macros", path);
+        assert_eq!(used["kind"], "macro", "{}", used);
+        assert_eq!(used["macro_kind"], *macro_kind, "{}", used);
+        assert_eq!(used["origin"]["crate"], *krate, "{}", used);
+    }
+    // Named after the derive, not after the function defining it.
+    assert_eq!(
+        item(&files, file, "macros", "shapes_derive::Shape")["name"],
+        "Shape"
+    );
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use std::{env, fs, path::Path};
+
+use crate::common::{success, Fixture};
+
+/// Dumps the `markdown` fixture as Markdown with `args` and compares the report to
+/// `tests/snapshots/markdown/<snapshot>`. `USEDUMP_BLESS=1` rewrites the snapshot with the
+/// current report instead.
+fn check_snapshot(args: &[&str], snapshot: &str) {
+    let fixture = Fixture::new("markdown");
+    let output = fixture
+        .usedump()
+        .args(&["dump", "--no-sysroot", "--format", "markdown"])
+        .args(args)
+        .output()
+        .unwrap();
+    let report = success(output);
+
+    let snapshot = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("snapshots")
+        .join("markdown")
+        .join(snapshot);
+    if env::var_os("USEDUMP_BLESS").is_some() {
+        fs::write(&snapshot, &report).unwrap();
+    }
+    assert!(
+        fs::read_to_string(&snapshot).unwrap() == report,
+        "the report differs from {}, run with USEDUMP_BLESS=1 to update it:\n{}",
+        snapshot.display(),
+        report
+    );
+}
+
+#[test]
+fn report_matches_the_snapshot() {
+    check_snapshot(&[], "default.md");
+}
+
+#[test]
+fn collapsible_folds_the_long_lists() {
+    check_snapshot(&["--collapsible"], "collapsible.md");
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use std::path::Path;
+
+use crate::common::{paths, Fixture};
+
+#[test]
+fn members_sharing_a_layout_keep_their_files() {
+    let fixture = Fixture::new("members");
+    let files = fixture.dump(&["--no-sysroot"]);
+    assert_eq!(
+        paths(&files, "left/src/lib.rs", "structs"),
+        ["left::shapes::Square"]
+    );
+    assert_eq!(
+        paths(&files, "right/src/lib.rs", "structs"),
+        ["left::shapes::Square"]
+    );
+    assert_eq!(
+        paths(&files, "right/src/lib.rs", "fns"),
+        ["left::unit_square"]
+    );
+    assert_eq!(
+        paths(&files, "right/src/shapes.rs", "fns"),
+        ["left::unit_square"]
+    );
+    assert_eq!(files["src/lib.rs"], serde_json::Value::Null);
+}
+
+#[test]
+fn flat_paths_leave_the_package_out() {
+    let fixture = Fixture::new("members");
+    let files = fixture.dump(&["--no-sysroot", "--flat-paths"]);
+    let keys: Vec<&str> = files
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .filter(|key| key.ends_with(".rs"))
+        .collect();
+    // Each file shares its key with that of the other member, which only one of them keeps.
+    assert_eq!(keys, ["src/lib.rs", "src/shapes.rs"]);
+}
+
+#[test]
+fn absolute_paths_tell_the_members_apart() {
+    let fixture = Fixture::new("members");
+    let files = fixture.dump(&["--no-sysroot", "--absolute-paths"]);
+    let root = fixture.path().canonicalize().unwrap();
+    let mut libs = vec![];
+    for key in files.as_object().unwrap().keys() {
+        if key.ends_with("lib.rs") {
+            let path = Path::new(key).canonicalize().unwrap();
+            libs.push(path.strip_prefix(&root).unwrap().to_owned());
+        }
+    }
+    libs.sort();
+    assert_eq!(
+        libs,
+        [Path::new("left/src/lib.rs"), Path::new("right/src/lib.rs")]
+    );
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use serde_json::Value;
+
+use crate::common::{item, paths, Fixture};
+
+fn keys(files: &Value) -> Vec<&str> {
+    files
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .filter(|key| key.ends_with(".rs"))
+        .collect()
+}
+
+#[test]
+fn paths_are_listed_as_written() {
+    let fixture = Fixture::new("imports");
+    let files = fixture.dump(&["--no-sysroot", "--no-resolve"]);
+    let file = "imports/src/aliases.rs";
+    let map = item(&files, file, "structs", "std::collections::HashMap");
+    assert_eq!(map["alias"], "Map");
+    assert_eq!(map["origin"]["kind"], "std");
+    assert_eq!(map["unresolved"], true);
+    let geometry = item(&files, file, "modules", "crate::shapes");
+    assert_eq!(geometry["alias"], "geometry");
+    assert_eq!(geometry["origin"]["crate"], "imports");
+    // The alias is not followed without the analysis.
+    assert_eq!(
+        paths(&files, file, "structs"),
+        ["geometry::Circle", "std::collections::HashMap"]
+    );
+
+    let resolved = paths(&fixture.dump(&["--no-sysroot"]), file, "structs");
+    assert!(
+        resolved.contains(&"imports::shapes::Circle".to_owned()),
+        "{:?}",
+        resolved
+    );
+}
+
+#[test]
+fn globs_are_listed_as_globs() {
+    let fixture = Fixture::new("imports");
+    let files = fixture.dump(&["--no-sysroot", "--no-resolve"]);
+    let glob = item(&files, "imports/src/globs.rs", "others", "crate::shapes::*");
+    assert_eq!(glob["via_glob"], true);
+}
+
+#[test]
+fn every_file_of_the_package_is_listed() {
+    let fixture = Fixture::new("imports");
+    // Neither declared as a module nor part of the package.
+    fixture.write("src/stray.rs", "use crate::shapes::Circle;\n");
+    fixture.write("target/debug/build/out.rs", "use crate::shapes::Circle;\n");
+    let files = fixture.dump(&["--no-sysroot", "--no-resolve"]);
+    let keys = keys(&files);
+    assert!(keys.contains(&"imports/src/stray.rs"), "{:?}", keys);
+    assert!(keys.iter().all(|key| !key.contains("target")), "{:?}", keys);
+}
+
+#[test]
+fn what_needs_the_analysis_is_rejected() {
+    let fixture = Fixture::new("imports");
+    for args in &[
+        &["dump", "--no-resolve", "--keys", "module"][..],
+        &["exports", "--no-resolve"],
+    ] {
+        let output = fixture.usedump().args(*args).output().unwrap();
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("`--no-resolve`"), "{}", stderr);
+    }
+}
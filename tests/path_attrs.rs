@@ -0,0 +1,55 @@
+mod common;
+
+use serde_json::Value;
+
+use crate::common::{item, paths, Fixture};
+
+fn keys(files: &Value) -> Vec<&str> {
+    files
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .filter(|key| key.ends_with(".rs") || key.contains("::"))
+        .collect()
+}
+
+#[test]
+fn files_are_keyed_by_their_real_path() {
+    let fixture = Fixture::new("path_attrs");
+    let files = fixture.dump(&["--no-sysroot", "--definitions"]);
+    assert_eq!(
+        keys(&files),
+        [
+            "path_attrs/src/outer/handles.rs",
+            "path_attrs/src/sys/linux.rs"
+        ]
+    );
+    assert_eq!(
+        paths(&files, "path_attrs/src/sys/linux.rs", "structs"),
+        ["path_attrs::shapes::Square"]
+    );
+    // The item is named after the module declaring the file, and defined in the file.
+    let handle = item(
+        &files,
+        "path_attrs/src/outer/handles.rs",
+        "structs",
+        "path_attrs::imp::Handle",
+    );
+    let defined_in = handle["defined_in"]["file"].as_str().unwrap();
+    assert!(defined_in.ends_with("src/sys/linux.rs"), "{}", defined_in);
+}
+
+#[test]
+fn modules_are_named_after_their_declaration() {
+    let fixture = Fixture::new("path_attrs");
+    let files = fixture.dump(&["--no-sysroot", "--keys", "module"]);
+    assert_eq!(
+        keys(&files),
+        ["path_attrs::imp", "path_attrs::outer::helper"]
+    );
+    assert_eq!(
+        paths(&files, "path_attrs::outer::helper", "structs"),
+        ["path_attrs::imp::Handle"]
+    );
+}
@@ -0,0 +1,18 @@
+mod common;
+
+use crate::common::{names, paths, Fixture};
+
+#[test]
+fn same_names_of_two_dependencies_are_told_apart() {
+    let fixture = Fixture::new("same_names");
+    let files = fixture.dump(&["--no-sysroot", "app"]);
+    let file = "app/src/lib.rs";
+    assert_eq!(
+        paths(&files, file, "structs"),
+        ["alpha::Thing", "beta::things::Thing"]
+    );
+    assert_eq!(names(&files, file, "structs"), ["Thing", "Thing"]);
+    assert_eq!(files[file]["structs"][1]["alias"], "OtherThing");
+    assert_eq!(files[file]["structs"][0]["origin"]["crate"], "alpha");
+    assert_eq!(files[file]["structs"][1]["origin"]["crate"], "beta");
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use crate::common::{item, Fixture};
+
+const FILE: &str = "app/src/lib.rs";
+
+#[test]
+fn raw_identifiers_are_named_without_their_prefix() {
+    let fixture = Fixture::new("raw_idents");
+    let files = fixture.dump(&["--no-sysroot", "app"]);
+    // Paths keep the `r#`, which they could not be spelled without.
+    let module = item(&files, FILE, "modules", "keywords::r#type");
+    assert_eq!(module["name"], "type");
+    assert_eq!(module["raw"], true);
+    let match_fn = item(&files, FILE, "fns", "keywords::r#type::r#match");
+    assert_eq!(match_fn["name"], "match");
+    assert_eq!(match_fn["raw"], true);
+    assert!(match_fn["alias"].is_null());
+    // Only names which need the `r#` are flagged.
+    let kind = item(&files, FILE, "structs", "keywords::r#type::Kind");
+    assert_eq!(kind["name"], "Kind");
+    assert!(kind["raw"].is_null());
+}
+
+#[test]
+fn aliases_to_raw_identifiers_are_named_without_their_prefix() {
+    let fixture = Fixture::new("raw_idents");
+    let files = fixture.dump(&["--no-sysroot", "app"]);
+    let kind = item(&files, FILE, "structs", "keywords::r#type::Kind");
+    assert_eq!(kind["alias"], "struct");
+    let local = item(&files, FILE, "fns", "app::local::r#match");
+    assert_eq!(local["alias"], "loop");
+}
+
+#[test]
+fn raw_identifiers_of_different_paths_are_distinct() {
+    let fixture = Fixture::new("raw_idents");
+    let files = fixture.dump(&["--no-sysroot", "app"]);
+    let fns = files[FILE]["fns"].as_array().unwrap();
+    let matches: Vec<&str> = fns
+        .iter()
+        .filter(|item| item["name"] == "match")
+        .map(|item| item["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(matches.len(), 2, "{:?}", fns);
+    assert!(matches.contains(&"app::local::r#match"), "{:?}", matches);
+    assert!(
+        matches.contains(&"keywords::r#type::r#match"),
+        "{:?}",
+        matches
+    );
+}
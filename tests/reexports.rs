@@ -0,0 +1,71 @@
+mod common;
+
+use serde_json::Value;
+
+use crate::common::{paths, Fixture};
+
+const LIB: &str = "app/src/lib.rs";
+
+fn dump(fixture: &Fixture, resolve_reexports: &str) -> Value {
+    fixture.dump(&[
+        "--no-sysroot",
+        "--resolve-reexports",
+        resolve_reexports,
+        "app",
+    ])
+}
+
+#[test]
+fn facades_resolve_to_the_crate_they_reexport() {
+    let fixture = Fixture::new("reexports");
+    let files = dump(&fixture, "definition");
+    assert_eq!(paths(&files, LIB, "structs"), ["inner::shapes::Circle"]);
+    let circle = &files[LIB]["structs"][0];
+    // The origin is that of the definition, whichever path is listed.
+    assert_eq!(
+        circle["origin"],
+        serde_json::json!({"kind": "external", "crate": "inner"})
+    );
+    assert!(circle["surface_path"].is_null());
+
+    let files = dump(&fixture, "surface");
+    assert_eq!(paths(&files, LIB, "structs"), ["facade::Circle"]);
+    assert_eq!(files[LIB]["structs"][0]["origin"]["crate"], "inner");
+
+    let files = dump(&fixture, "both");
+    assert_eq!(paths(&files, LIB, "structs"), ["inner::shapes::Circle"]);
+    assert_eq!(files[LIB]["structs"][0]["surface_path"], "facade::Circle");
+}
+
+#[test]
+fn chained_reexports_resolve_at_every_hop() {
+    let fixture = Fixture::new("reexports");
+    // `facade` re-exports the struct of `middle`, which re-exports that of `inner`.
+    let hops = [
+        ("app/src/from_facade.rs", "facade::Square"),
+        ("app/src/from_middle.rs", "middle::Square"),
+        ("app/src/from_inner.rs", "inner::shapes::Square"),
+    ];
+    let definition = dump(&fixture, "definition");
+    let surface = dump(&fixture, "surface");
+    let both = dump(&fixture, "both");
+    for (file, imported_as) in &hops {
+        assert_eq!(
+            paths(&definition, file, "structs"),
+            ["inner::shapes::Square"]
+        );
+        assert_eq!(paths(&surface, file, "structs"), [*imported_as]);
+        assert_eq!(paths(&both, file, "structs"), ["inner::shapes::Square"]);
+        let surface_path = &both[file]["structs"][0]["surface_path"];
+        if *imported_as == "inner::shapes::Square" {
+            assert!(surface_path.is_null(), "{}", surface_path);
+        } else {
+            assert_eq!(surface_path, imported_as);
+        }
+        assert_eq!(
+            surface[file]["structs"][0]["origin"]["crate"], "inner",
+            "{}",
+            file
+        );
+    }
+}
@@ -0,0 +1,209 @@
+mod common;
+
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Output},
+};
+
+use crate::common::{paths, success, usedump, Fixture};
+
+/// A git repository made of the `remote` fixture, whose package lies in `crates/foo`. It
+/// is committed twice, the first commit being tagged `v1`, then cloned to `bare.git`.
+/// `tmp` is the temporary directory of `usedump`, which clones in it.
+struct Repository {
+    fixture: Fixture,
+    first_commit: String,
+}
+
+impl Repository {
+    fn new() -> Self {
+        let fixture = Fixture::new("remote");
+        git(fixture.path(), &["init", "--quiet"]);
+        git(fixture.path(), &["add", "-A"]);
+        git(fixture.path(), &["commit", "--quiet", "-m", "Add foo"]);
+        git(fixture.path(), &["tag", "v1"]);
+        let first_commit = git(fixture.path(), &["rev-parse", "HEAD"]);
+
+        let lib = fixture.read("crates/foo/src/lib.rs");
+        fixture.write(
+            "crates/foo/src/lib.rs",
+            &format!("pub mod circles;\n{}", lib),
+        );
+        fixture.write(
+            "crates/foo/src/circles.rs",
+            "use crate::shapes::Square;\n\npub struct Circle {\n    pub bounds: Square,\n}\n",
+        );
+        git(fixture.path(), &["add", "-A"]);
+        git(fixture.path(), &["commit", "--quiet", "-m", "Add circles"]);
+
+        git(
+            fixture.path(),
+            &["clone", "--quiet", "--bare", ".", "bare.git"],
+        );
+        fs::create_dir(fixture.join("tmp")).unwrap();
+        Repository {
+            fixture,
+            first_commit,
+        }
+    }
+
+    fn url(&self) -> String {
+        format!("file://{}", self.fixture.join("bare.git").display())
+    }
+
+    /// `usedump remote --offline` with `args`.
+    fn remote(&self, args: &[&str]) -> Output {
+        usedump()
+            .env("TMPDIR", self.fixture.join("tmp"))
+            .args(&["remote", "--offline"])
+            .args(args)
+            .output()
+            .unwrap()
+    }
+
+    /// Whether the clones have all been removed.
+    fn cleaned_up(&self) -> bool {
+        fs::read_dir(self.fixture.join("tmp"))
+            .unwrap()
+            .next()
+            .is_none()
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(&[
+            "-c",
+            "user.name=usedump",
+            "-c",
+            "user.email=usedump@example.com",
+            "-c",
+            "commit.gpgsign=false",
+        ])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap().trim().to_owned()
+}
+
+fn has_git() -> bool {
+    Command::new("git").arg("--version").output().is_ok()
+}
+
+#[test]
+fn the_default_branch_is_cloned_and_removed() {
+    if !has_git() {
+        return;
+    }
+    let repository = Repository::new();
+    let url = repository.url();
+    let output = repository.remote(&["--git", &url, "--subdir", "crates/foo"]);
+    let files: serde_json::Value = serde_json::from_str(&success(output)).unwrap();
+    assert_eq!(
+        paths(&files, "foo/src/lib.rs", "structs"),
+        ["foo::shapes::Square"]
+    );
+    assert_eq!(
+        paths(&files, "foo/src/circles.rs", "structs"),
+        ["foo::shapes::Square"]
+    );
+    assert!(repository.cleaned_up());
+}
+
+#[test]
+fn rev_checks_out_a_tag_or_a_commit() {
+    if !has_git() {
+        return;
+    }
+    let repository = Repository::new();
+    let url = repository.url();
+    let output = repository.remote(&["--git", &url, "--rev", "v1", "--subdir", "crates/foo"]);
+    let files: serde_json::Value = serde_json::from_str(&success(output)).unwrap();
+    assert_eq!(
+        paths(&files, "foo/src/lib.rs", "structs"),
+        ["foo::shapes::Square"]
+    );
+    assert_eq!(files["foo/src/circles.rs"], serde_json::Value::Null);
+
+    let output = repository.remote(&[
+        "--git",
+        &url,
+        "--rev",
+        &repository.first_commit,
+        "--subdir",
+        "crates/foo",
+    ]);
+    let by_commit: serde_json::Value = serde_json::from_str(&success(output)).unwrap();
+    assert_eq!(by_commit, files);
+    assert!(repository.cleaned_up());
+}
+
+#[test]
+fn a_missing_subdir_is_a_usage_error() {
+    if !has_git() {
+        return;
+    }
+    let repository = Repository::new();
+    let url = repository.url();
+    let output = repository.remote(&["--git", &url, "--subdir", "crates/bar"]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("there is no directory `crates/bar`"),
+        "{}",
+        stderr
+    );
+    assert!(repository.cleaned_up());
+}
+
+#[test]
+fn clone_failures_are_reported() {
+    if !has_git() {
+        return;
+    }
+    let repository = Repository::new();
+    let missing = format!(
+        "file://{}",
+        repository.fixture.join("missing.git").display()
+    );
+    let output = repository.remote(&["--git", &missing]);
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains(&format!("failed to fetch `{}`", missing)),
+        "{}",
+        stderr
+    );
+    assert!(repository.cleaned_up());
+
+    let url = repository.url();
+    let output = repository.remote(&["--git", &url, "--rev", "v2"]);
+    assert_eq!(output.status.code(), Some(3));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("no revision `v2`"), "{}", stderr);
+    assert!(repository.cleaned_up());
+}
+
+#[test]
+fn path_dumps_an_existing_checkout_and_keeps_it() {
+    if !has_git() {
+        return;
+    }
+    let repository = Repository::new();
+    let checkout = repository.fixture.path().to_str().unwrap();
+    let output = repository.remote(&["--path", checkout, "--subdir", "crates/foo"]);
+    let files: serde_json::Value = serde_json::from_str(&success(output)).unwrap();
+    assert_eq!(
+        paths(&files, "foo/src/circles.rs", "structs"),
+        ["foo::shapes::Square"]
+    );
+    assert!(repository.fixture.join("crates/foo/src/lib.rs").exists());
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use usedump::{output, CrateMap, UsedItem, UsedItemKind, UsedItemMap};
+
+use crate::common::Fixture;
+
+const COMMA: &str = "odd_paths/src/with, comma.rs";
+const UNICODE: &str = "odd_paths/src/with space/ünïcode.rs";
+const EMPTY: &str = "odd_paths/src/empty.rs";
+
+/// Dumps `fixture` to `file`, in the format of its extension, and reads the dump back.
+fn dump_and_read(fixture: &Fixture, file: &str) -> CrateMap {
+    let path = fixture.join(file);
+    let format = path.extension().unwrap().to_str().unwrap().to_owned();
+    let status = fixture
+        .usedump()
+        .args(&["dump", "--no-sysroot", "--include-empty", "--locations"])
+        .args(&["--definitions", "--format", &format, "--output"])
+        .arg(&path)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    output::read_crate_map(&path).unwrap()
+}
+
+fn item<'a>(map: &'a UsedItemMap, kind: UsedItemKind, name: &str) -> &'a UsedItem {
+    map.get(kind)
+        .iter()
+        .find(|item| item.name() == name)
+        .unwrap_or_else(|| panic!("no {} `{}`", kind.name(), name))
+}
+
+#[test]
+fn dumps_read_back_as_written() {
+    let fixture = Fixture::new("odd_paths");
+    let map = dump_and_read(&fixture, "dump.json");
+
+    let comma = map.get(COMMA).unwrap();
+    let disc = item(comma, UsedItemKind::Struct, "Circle");
+    assert_eq!(disc.alias(), Some("Disc"));
+    assert_eq!(disc.path(), "odd_paths::shapes::Circle");
+    let size = item(comma, UsedItemKind::Struct, "Größe");
+    assert_eq!(size.alias(), Some("Size"));
+    assert_eq!(size.path(), "odd_paths::unicode::Größe");
+    assert_eq!(size.kind(), UsedItemKind::Struct);
+    assert!(size.location().is_some());
+    assert!(map.get(UNICODE).is_some());
+    // `--include-empty` keeps the file importing nothing, with none of its sets.
+    let empty = map.get(EMPTY).unwrap();
+    assert!(empty.is_empty());
+
+    let json = serde_json::to_string(&map).unwrap();
+    let read: CrateMap = serde_json::from_str(&json).unwrap();
+    assert_eq!(read, map);
+    assert_eq!(serde_json::to_string(&read).unwrap(), json);
+}
+
+#[test]
+fn every_format_reads_back_the_same_map() {
+    let fixture = Fixture::new("odd_paths");
+    let json = dump_and_read(&fixture, "dump.json");
+    assert_eq!(dump_and_read(&fixture, "dump.yaml"), json);
+    assert_eq!(dump_and_read(&fixture, "dump.toml"), json);
+}
+
+#[test]
+fn maps_round_trip_through_every_format() {
+    let fixture = Fixture::new("odd_paths");
+    let map = dump_and_read(&fixture, "dump.json");
+    for file in map.files().map(|(file, _)| file) {
+        let used_item_map = map.get(file).unwrap();
+        let yaml = serde_yaml::to_string(used_item_map).unwrap();
+        let read: UsedItemMap = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(&read, used_item_map, "{}", file);
+        for item in used_item_map.iter() {
+            // Through a value, which puts the tables after the other fields like dumps do.
+            let toml = toml::Value::try_from(item).unwrap().to_string();
+            let read: UsedItem = toml::from_str(&toml).unwrap();
+            assert_eq!(&read, item, "{}", toml);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use std::fs;
+
+use serde_json::Value;
+
+use crate::common::{
+    schema::{assert_valid, Validator},
+    Fixture,
+};
+
+/// The SARIF 2.1.0 schema vendored under `tests/schemas`.
+fn validator() -> Validator {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/schemas/sarif-2.1.0.json"
+    );
+    Validator::new(serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap())
+}
+
+/// The SARIF log of `command` run on `fixture`, which exits with 1 on findings.
+fn sarif(fixture: &Fixture, command: &[&str]) -> Value {
+    let output = fixture
+        .usedump()
+        .args(command)
+        .args(&["--format", "sarif"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.code() == Some(0) || output.status.code() == Some(1),
+        "usedump failed with {}: {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+fn results(log: &Value) -> &[Value] {
+    log["runs"][0]["results"].as_array().unwrap()
+}
+
+#[test]
+fn unused_imports_match_the_schema() {
+    let fixture = Fixture::new("unused");
+    let log = sarif(&fixture, &["unused"]);
+    assert_valid(&validator(), &log);
+    let results = results(&log);
+    assert!(!results.is_empty());
+    for result in results {
+        assert_eq!(result["ruleId"], "unused-import");
+        let location = &result["locations"][0]["physicalLocation"];
+        assert!(location["artifactLocation"]["uri"]
+            .as_str()
+            .unwrap()
+            .ends_with(".rs"));
+        // The imports are dumped with their locations.
+        assert!(location["region"]["startLine"].as_u64().unwrap() >= 1);
+    }
+}
+
+#[test]
+fn dead_exports_match_the_schema() {
+    let fixture = Fixture::new("members");
+    let log = sarif(&fixture, &["dead-exports"]);
+    assert_valid(&validator(), &log);
+    assert!(results(&log)
+        .iter()
+        .all(|result| result["ruleId"] == "dead-export"));
+}
+
+#[test]
+fn deps_audit_matches_the_schema() {
+    let fixture = Fixture::new("deps");
+    let log = sarif(&fixture, &["deps-audit", "app"]);
+    assert_valid(&validator(), &log);
+    let mut rules: Vec<&str> = results(&log)
+        .iter()
+        .map(|result| result["ruleId"].as_str().unwrap())
+        .collect();
+    rules.sort();
+    assert_eq!(
+        rules,
+        [
+            "dev-dependency-outside-tests",
+            "non-build-dependency-in-build-script",
+            "test-only-dependency"
+        ]
+    );
+}
+
+#[test]
+fn the_schema_rejects_what_logs_never_have() {
+    let validator = validator();
+    let fixture = Fixture::new("unused");
+    let log = sarif(&fixture, &["unused"]);
+    assert_valid(&validator, &log);
+
+    let mut unknown_level = log.clone();
+    unknown_level["runs"][0]["results"][0]["level"] = "fatal".into();
+    assert!(!validator.errors(&unknown_level).is_empty());
+
+    let mut zero_line = log.clone();
+    zero_line["runs"][0]["results"][0]["locations"][0]["physicalLocation"]["region"]["startLine"] =
+        0.into();
+    assert!(!validator.errors(&zero_line).is_empty());
+
+    let mut no_message = log;
+    no_message["runs"][0]["results"][0]
+        .as_object_mut()
+        .unwrap()
+        .remove("message");
+    assert!(!validator.errors(&no_message).is_empty());
+}
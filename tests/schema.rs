@@ -0,0 +1,70 @@
+mod common;
+
+use serde_json::json;
+
+use crate::common::{
+    schema::{assert_valid, Validator},
+    success, Fixture,
+};
+
+fn validator() -> Validator {
+    let output = common::usedump().arg("schema").output().unwrap();
+    Validator::new(serde_json::from_str(&success(output)).unwrap())
+}
+
+#[test]
+fn dumps_match_the_schema() {
+    let validator = validator();
+    let cases: &[(&str, &[&str])] = &[
+        ("kinds", &[]),
+        ("kinds", &["--locations", "--definitions"]),
+        (
+            "kinds",
+            &["--no-sysroot", "--include-empty", "--no-envelope"],
+        ),
+        ("odd_paths", &["--no-sysroot", "--locations"]),
+        ("broken", &["--no-sysroot"]),
+        (
+            "broken",
+            &["--no-sysroot", "--no-cache", "--file-timeout", "0"],
+        ),
+        ("features", &["--no-sysroot", "--all-features", "app"]),
+        ("members", &["--no-sysroot", "--stats=json"]),
+    ];
+    for (name, args) in cases {
+        let fixture = Fixture::new(name);
+        let dump = fixture.dump_envelope(args);
+        assert_valid(&validator, &dump);
+    }
+}
+
+#[test]
+fn several_projects_match_the_schema() {
+    let fixture = Fixture::new("same_names");
+    let dump = fixture.dump_envelope(&["--no-sysroot", "app", "alpha"]);
+    assert_eq!(dump["files"].as_object().unwrap().len(), 2);
+    assert_valid(&validator(), &dump);
+}
+
+#[test]
+fn the_schema_rejects_what_dumps_never_have() {
+    let validator = validator();
+    let fixture = Fixture::new("names");
+    let dump = fixture.dump_envelope(&["--no-sysroot"]);
+    assert_valid(&validator, &dump);
+
+    let mut unknown_field = dump.clone();
+    unknown_field["files"]["names/src/lib.rs"]["structs"][0]["colour"] = json!("red");
+    assert!(!validator.errors(&unknown_field).is_empty());
+
+    let mut unknown_kind = dump.clone();
+    unknown_kind["files"]["names/src/lib.rs"]["structs"][0]["kind"] = json!("class");
+    assert!(!validator.errors(&unknown_kind).is_empty());
+
+    let mut missing_path = dump;
+    missing_path["files"]["names/src/lib.rs"]["structs"][0]
+        .as_object_mut()
+        .unwrap()
+        .remove("path");
+    assert!(!validator.errors(&missing_path).is_empty());
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    process::{Child, ChildStdin, ChildStdout, Stdio},
+};
+
+use serde_json::{json, Value};
+
+use crate::common::Fixture;
+
+/// `usedump serve` running in a fixture, talked to over its stdin and stdout.
+struct Server {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl Server {
+    fn start(fixture: &Fixture) -> Self {
+        let mut child = fixture
+            .usedump()
+            .args(&["serve", "--no-sysroot"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let stdin = child.stdin.take();
+        let stdout = BufReader::new(child.stdout.take().unwrap());
+        Server {
+            child,
+            stdin,
+            stdout,
+        }
+    }
+
+    fn send(&mut self, line: &str) {
+        let stdin = self.stdin.as_mut().unwrap();
+        writeln!(stdin, "{}", line).unwrap();
+        stdin.flush().unwrap();
+    }
+
+    /// Sends `line` and reads the response to it.
+    fn request(&mut self, line: &str) -> Value {
+        self.send(line);
+        let mut response = String::new();
+        self.stdout.read_line(&mut response).unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    fn call(&mut self, id: u64, method: &str, params: Value) -> Value {
+        let request = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        let response = self.request(&request.to_string());
+        assert_eq!(response["jsonrpc"], "2.0");
+        assert_eq!(response["id"], id);
+        assert_eq!(response["error"], Value::Null, "{}", response);
+        response["result"].clone()
+    }
+
+    /// Closes stdin, which the server stops on.
+    fn stop(mut self) {
+        self.stdin = None;
+        assert!(self.child.wait().unwrap().success());
+    }
+}
+
+fn importers(result: &Value) -> Vec<&str> {
+    result
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .collect()
+}
+
+#[test]
+fn serve_answers_over_stdio() {
+    let fixture = Fixture::new("members");
+    let mut server = Server::start(&fixture);
+
+    let square = json!({"item": "left::shapes::Square"});
+    let result = server.call(1, "who_uses", square.clone());
+    assert_eq!(importers(&result), ["left/src/lib.rs", "right/src/lib.rs"]);
+    assert_eq!(
+        result["right/src/lib.rs"],
+        json!([{"kind": "struct", "name": "Square", "path": "left::shapes::Square"}])
+    );
+
+    // A file changed since the workspace was loaded is resolved again.
+    fixture.write(
+        "right/src/shapes.rs",
+        "use left::shapes::Square;\n\npub fn side(square: &Square) -> f64 {\n    square.side\n}\n",
+    );
+    let result = server.call(2, "dump_file", json!({"path": "right/src/shapes.rs"}));
+    assert_eq!(
+        result["right/src/shapes.rs"]["structs"][0]["path"],
+        "left::shapes::Square"
+    );
+    let result = server.call(3, "who_uses", square.clone());
+    assert_eq!(
+        importers(&result),
+        ["left/src/lib.rs", "right/src/lib.rs", "right/src/shapes.rs"]
+    );
+    let result = server.call(4, "who_uses", json!({"item": "^left::.*", "regex": true}));
+    assert!(importers(&result).contains(&"right/src/shapes.rs"));
+
+    // A new file is only known after a reload.
+    fixture.write("right/src/extra.rs", "use left::shapes::Square;\n");
+    let mut lib = fixture.read("right/src/lib.rs");
+    lib.insert_str(0, "pub mod extra;\n");
+    fixture.write("right/src/lib.rs", &lib);
+    assert_eq!(server.call(5, "reload", Value::Null), Value::Null);
+    let result = server.call(6, "who_uses", square);
+    assert!(importers(&result).contains(&"right/src/extra.rs"));
+
+    server.stop();
+}
+
+#[test]
+fn serve_reports_errors() {
+    let fixture = Fixture::new("members");
+    let mut server = Server::start(&fixture);
+
+    // Notifications get no response, so the next one read is that of the next request.
+    server.send(r#"{"jsonrpc": "2.0", "method": "reload"}"#);
+    let response = server.request(r#"{"jsonrpc": "2.0", "id": 1, "method": "frobnicate"}"#);
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["error"]["code"], -32601);
+
+    let response = server.request("{not json");
+    assert_eq!(response["id"], Value::Null);
+    assert_eq!(response["error"]["code"], -32700);
+
+    let response = server.request(r#"{"jsonrpc": "1.0", "id": 2, "method": "reload"}"#);
+    assert_eq!(response["error"]["code"], -32600);
+
+    let response =
+        server.request(r#"{"jsonrpc": "2.0", "id": 3, "method": "who_uses", "params": {}}"#);
+    assert_eq!(response["error"]["code"], -32602);
+
+    let response = server.request(
+        r#"{"jsonrpc": "2.0", "id": 4, "method": "dump_file", "params": {"path": "nowhere.rs"}}"#,
+    );
+    assert_eq!(response["id"], 4);
+    assert_eq!(response["error"]["code"], -32000);
+    assert_eq!(response["result"], Value::Null);
+
+    // The server keeps answering after errors.
+    let result = server.call(5, "who_uses", json!({"item": "left::unit_square"}));
+    assert_eq!(
+        importers(&result),
+        ["right/src/lib.rs", "right/src/shapes.rs"]
+    );
+
+    server.stop();
+}
@@ -0,0 +1,7 @@
+use crate::shapes::{Circle, Circle as Round, Square};
+use std::{io, io as stdio};
+
+pub fn shapes(_: Circle, _: Round, _: Square) -> io::Result<()> {
+    stdio::stdout();
+    Ok(())
+}
@@ -0,0 +1,7 @@
+#[cfg(test)]
+use std::fmt::{self, Display};
+use std::{fmt::Write, rc::Weak};
+pub(crate) use std::io::{self, Read};
+#[cfg(all(unix, not(test)))]
+use std::env::{self, args};
+use std::{/* not merged */ mem, rc::Rc};
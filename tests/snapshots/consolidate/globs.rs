@@ -0,0 +1,2 @@
+use std::{fmt::Debug, io::{self, BufRead, prelude::*}};
+use ::std::fmt;
@@ -0,0 +1,13 @@
+use crate::shapes::{Circle, Square};
+
+pub struct Thing;
+
+mod inner {
+    use super::{Circle, Thing};
+    use crate::shapes;
+
+    fn body() {
+        use std::{mem, ptr};
+    }
+}
+
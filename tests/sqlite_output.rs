@@ -0,0 +1,95 @@
+mod common;
+
+use rusqlite::Connection;
+use usedump::SQLITE_SCHEMA_VERSION;
+
+use crate::common::{success, Fixture};
+
+/// The queries `usedump dump --help` gives as examples, so that they cannot go stale.
+fn example_queries() -> Vec<String> {
+    let output = common::usedump()
+        .args(&["dump", "--help"])
+        .output()
+        .unwrap();
+    let help = success(output);
+    let examples = &help[help.find("EXAMPLE QUERIES:").expect("no example queries")..];
+    let mut queries = vec![];
+    let mut query = String::new();
+    // The queries are indented deeper than the sentences introducing them.
+    for line in examples.lines().filter(|line| line.starts_with("        ")) {
+        query.push_str(line.trim());
+        query.push(' ');
+        if line.ends_with(';') {
+            queries.push(query.trim().to_owned());
+            query.clear();
+        }
+    }
+    queries
+}
+
+/// Dumps `fixture` into a database written with `--output` and opens it.
+fn dump_sqlite(fixture: &Fixture) -> Connection {
+    let path = fixture.join("usedump.db");
+    let output = fixture
+        .usedump()
+        .args(&["dump", "--no-sysroot", "--format", "sqlite", "--output"])
+        .arg(&path)
+        .output()
+        .unwrap();
+    success(output);
+    Connection::open(&path).unwrap()
+}
+
+#[test]
+fn the_database_has_its_schema_version() {
+    let fixture = Fixture::new("members");
+    let connection = dump_sqlite(&fixture);
+    let version: String = connection
+        .query_row(
+            "SELECT value FROM meta WHERE key = 'schema_version'",
+            rusqlite::NO_PARAMS,
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(version, SQLITE_SCHEMA_VERSION.to_string());
+    let files: i64 = connection
+        .query_row("SELECT COUNT(*) FROM files", rusqlite::NO_PARAMS, |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert_eq!(files, 4);
+}
+
+#[test]
+fn the_example_queries_run() {
+    let queries = example_queries();
+    assert_eq!(queries.len(), 2, "{:?}", queries);
+    let fixture = Fixture::new("members");
+    let connection = dump_sqlite(&fixture);
+
+    // No file of the fixture imports tokio, nor async-std.
+    let mut both_runtimes = connection.prepare(&queries[0]).unwrap();
+    let files: Vec<String> = both_runtimes
+        .query_map(rusqlite::NO_PARAMS, |row| row.get(0))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(files, Vec::<String>::new());
+
+    let mut most_imported = connection.prepare(&queries[1]).unwrap();
+    let items: Vec<(String, i64)> = most_imported
+        .query_map(rusqlite::NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    // `Square` is imported by both libraries, `unit_square` by both files of `right`.
+    assert_eq!(items[0].1, 2, "{:?}", items);
+    for path in &["left::shapes::Square", "left::unit_square"] {
+        assert!(
+            items.contains(&(path.to_string(), 2)),
+            "{} in {:?}",
+            path,
+            items
+        );
+    }
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use serde_json::Value;
+
+use crate::common::{paths, Fixture};
+
+/// The files of `files` with the kind of their target.
+fn targets(files: &Value) -> Vec<(&str, &str)> {
+    files
+        .as_object()
+        .unwrap()
+        .iter()
+        .filter(|(key, _)| key.ends_with(".rs"))
+        .map(|(key, file)| (key.as_str(), file["target"].as_str().unwrap()))
+        .collect()
+}
+
+#[test]
+fn libraries_and_binaries_are_analyzed_by_default() {
+    let fixture = Fixture::new("targets");
+    let files = fixture.dump(&["--no-sysroot", "app"]);
+    assert_eq!(
+        targets(&files),
+        [("app/src/lib.rs", "lib"), ("app/src/main.rs", "bin")]
+    );
+    assert_eq!(
+        paths(&files, "app/src/lib.rs", "structs"),
+        ["shapes::Square"]
+    );
+    assert_eq!(paths(&files, "app/src/main.rs", "structs"), ["colors::Red"]);
+}
+
+#[test]
+fn every_file_records_its_target() {
+    let fixture = Fixture::new("targets");
+    let files = fixture.dump(&["--no-sysroot", "--all-targets", "app"]);
+    assert_eq!(
+        targets(&files),
+        [
+            ("app/benches/colors.rs", "bench"),
+            ("app/examples/demo.rs", "example"),
+            ("app/src/lib.rs", "lib"),
+            ("app/src/main.rs", "bin"),
+            ("app/tests/checks.rs", "test"),
+            // A module belongs to the target whose root is closest to it.
+            ("app/tests/common/mod.rs", "test"),
+        ]
+    );
+    assert_eq!(
+        paths(&files, "app/tests/checks.rs", "structs"),
+        ["checks::Check"]
+    );
+    assert_eq!(
+        paths(&files, "app/tests/common/mod.rs", "structs"),
+        ["shapes::Square"]
+    );
+    assert_eq!(
+        paths(&files, "app/examples/demo.rs", "structs"),
+        ["demo::Demo"]
+    );
+    assert_eq!(
+        paths(&files, "app/benches/colors.rs", "structs"),
+        ["colors::Red"]
+    );
+}
+
+#[test]
+fn target_flags_add_their_kind_only() {
+    let fixture = Fixture::new("targets");
+    let only = |flag: &str| -> Vec<String> {
+        let files = fixture.dump(&["--no-sysroot", flag, "app"]);
+        targets(&files)
+            .into_iter()
+            .filter(|(_, target)| *target != "lib" && *target != "bin")
+            .map(|(file, _)| file.to_owned())
+            .collect()
+    };
+    assert_eq!(
+        only("--tests"),
+        ["app/tests/checks.rs", "app/tests/common/mod.rs"]
+    );
+    assert_eq!(only("--examples"), ["app/examples/demo.rs"]);
+    assert_eq!(only("--benches"), ["app/benches/colors.rs"]);
+}
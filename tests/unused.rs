@@ -0,0 +1,48 @@
+mod common;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde_json::json;
+
+use crate::common::Fixture;
+
+#[test]
+fn imports_never_referred_to_are_reported() {
+    let fixture = Fixture::new("unused");
+    let output = fixture.usedump().arg("unused").output().unwrap();
+    assert_eq!(
+        output.status.code(),
+        Some(1),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let unused: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        unused,
+        json!({
+            "unused/src/aliases.rs": ["one"],
+            "unused/src/imports.rs": ["Area", "Circle", "unit"],
+        })
+    );
+}
+
+#[test]
+fn traits_whose_methods_are_called_are_used() {
+    let fixture = Fixture::new("unused");
+    let unused = usedump::find_unused_imports_in_cargo(fixture.path()).unwrap();
+    // `Area` and `Named` are only named by the method calls they make possible.
+    assert_eq!(unused.get("unused/src/methods.rs"), None);
+    // `Area` is imported the same way in `imports.rs`, but none of its methods are called.
+    assert!(unused["unused/src/imports.rs"].contains("Area"));
+}
+
+#[test]
+fn aliases_and_underscore_imports() {
+    let fixture = Fixture::new("unused");
+    let unused: BTreeMap<String, BTreeSet<String>> =
+        usedump::find_unused_imports_in_cargo(fixture.path()).unwrap();
+    // Aliases are reported, and referred to, by their local name, and `_` imports, which
+    // bring no name, are never unused.
+    let expected: BTreeSet<String> = vec!["one".to_owned()].into_iter().collect();
+    assert_eq!(unused["unused/src/aliases.rs"], expected);
+}
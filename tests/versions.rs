@@ -0,0 +1,55 @@
+mod common;
+
+use serde_json::json;
+
+use crate::common::{paths, success, Fixture};
+
+const FILE: &str = "app/src/lib.rs";
+
+#[test]
+fn each_item_has_the_version_of_its_copy() {
+    let fixture = Fixture::new("versions");
+    let files = fixture.dump(&["--no-sysroot", "app"]);
+    assert_eq!(
+        paths(&files, FILE, "structs"),
+        ["foo_v1::Widget", "foo_v2::Widget"]
+    );
+    assert_eq!(files[FILE]["structs"][0]["alias"], "OldWidget");
+    assert_eq!(files[FILE]["structs"][0]["version"], "1.0.0");
+    assert_eq!(files[FILE]["structs"][1]["version"], "2.0.0");
+    assert_eq!(paths(&files, FILE, "fns"), ["foo_v2::make"]);
+    assert_eq!(files[FILE]["fns"][0]["version"], "2.0.0");
+}
+
+#[test]
+fn dependencies_list_every_version() {
+    let fixture = Fixture::new("versions");
+    let files = fixture.dump(&["--no-sysroot", "app"]);
+    assert_eq!(files["dependencies"], json!({"foo": ["1.0.0", "2.0.0"]}));
+}
+
+#[test]
+fn merged_dependencies_keep_the_versions_of_both() {
+    let fixture = Fixture::new("versions");
+    let output = fixture
+        .usedump()
+        .args(&["dump", "--no-sysroot", "app"])
+        .output()
+        .unwrap();
+    fixture.write("app.json", &success(output));
+    fixture.write(
+        "other.json",
+        r#"{"dependencies": {"foo": ["1.0.10", "2.0.0"], "bar": ["0.3.1"]}}"#,
+    );
+    let output = fixture
+        .usedump()
+        .args(&["merge", "app.json", "other.json"])
+        .output()
+        .unwrap();
+    let files = common::files(&success(output));
+    assert_eq!(
+        files["dependencies"],
+        json!({"bar": ["0.3.1"], "foo": ["1.0.0", "1.0.10", "2.0.0"]})
+    );
+    assert_eq!(files[FILE]["structs"][1]["version"], "2.0.0");
+}
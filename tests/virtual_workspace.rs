@@ -0,0 +1,73 @@
+mod common;
+
+use crate::common::{paths, success, Fixture};
+
+const WARNING: &str = "warning: found no member files to analyze";
+
+fn keys(files: &serde_json::Value) -> Vec<&str> {
+    files
+        .as_object()
+        .unwrap()
+        .keys()
+        .map(String::as_str)
+        .filter(|key| key.ends_with(".rs"))
+        .collect()
+}
+
+#[test]
+fn the_virtual_root_dumps_every_member() {
+    let fixture = Fixture::new("members");
+    let output = fixture
+        .usedump_with_warnings()
+        .args(&["dump", "--no-sysroot"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    let files = common::files(&success(output));
+    assert_eq!(
+        keys(&files),
+        ["left/src/lib.rs", "right/src/lib.rs", "right/src/shapes.rs"]
+    );
+    assert_eq!(
+        paths(&files, "right/src/lib.rs", "structs"),
+        ["left::shapes::Square"]
+    );
+    assert!(!stderr.contains(WARNING), "{}", stderr);
+
+    // The same through the manifest of the root.
+    let output = fixture
+        .usedump()
+        .args(&["--manifest-path", "Cargo.toml", "dump", "--no-sysroot"])
+        .output()
+        .unwrap();
+    assert_eq!(common::files(&success(output)), files);
+}
+
+#[test]
+fn package_selects_a_member_of_the_virtual_root() {
+    let fixture = Fixture::new("members");
+    let files = fixture.dump(&["--no-sysroot", "--package", "right"]);
+    assert_eq!(keys(&files), ["right/src/lib.rs", "right/src/shapes.rs"]);
+}
+
+#[test]
+fn dumps_without_files_are_warned_about() {
+    let fixture = Fixture::new("members");
+    let output = fixture
+        .usedump_with_warnings()
+        .args(&["dump", "--no-sysroot", "--include", "nowhere/**"])
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    let files = common::files(&success(output));
+    assert!(keys(&files).is_empty());
+    assert!(stderr.contains(WARNING), "{}", stderr);
+
+    // `--quiet` silences it like any other warning.
+    let output = fixture
+        .usedump()
+        .args(&["dump", "--no-sysroot", "--include", "nowhere/**"])
+        .output()
+        .unwrap();
+    assert!(output.stderr.is_empty());
+}